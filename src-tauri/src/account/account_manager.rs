@@ -1,23 +1,198 @@
 use anyhow::{anyhow, Result};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
-
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::cookies;
+use super::drafts::{self, DraftBrief, DraftCapture};
+use super::foreign::{self, ForeignMapping};
+use super::error::AccountError;
+use super::store_lock::StoreLock;
 use super::types::*;
-use crate::api::{TraeApiClient, UsageSummary, UsageQueryResponse};
+use crate::api::{classify_network_error, TraeApiClient, UsageSummary, UsageQueryResponse, UserTokenResult};
+use crate::backup;
+use crate::settings::{DEFAULT_REFRESH_CONCURRENCY, DEFAULT_REFRESH_TIMEOUT_SECS};
+
+/// 最多保留的快照数量
+const MAX_SNAPSHOTS: usize = 20;
+
+/// 手动设置账号状态后，自动校验不覆盖的保护时长（秒）
+const STATUS_OVERRIDE_GRACE_SECS: i64 = 24 * 3600;
+
+/// 已尝试过从 webview 会话导入账号的标记文件
+const SESSION_IMPORT_MARKER: &str = "session_imported";
+
+/// 无法解析出用户 ID 的账号归入的分组标识
+const UNPARSEABLE_USER_ID: &str = "__unparseable__";
+
+/// 导出浏览器会话时 Cookies 的作用域名（包含 www、api 等子域名）
+const BROWSER_COOKIE_DOMAIN: &str = ".trae.ai";
+
+/// 导出浏览器会话时写入的 Cookie 有效期（秒），原始过期时间在捕获时已无法获取
+const BROWSER_COOKIE_TTL_SECS: i64 = 30 * 24 * 3600;
 
 /// 账号管理器
 pub struct AccountManager {
     store: AccountStore,
     data_path: PathBuf,
+    /// 存储文件锁；为 None 表示其他实例正在使用或处于安全模式，当前以只读模式运行
+    store_lock: Option<StoreLock>,
+    /// 安全模式：只读加载，跳过无法解析的账号，用于数据损坏时导出恢复
+    safe_mode: bool,
+    /// 账号数量上限，None 表示不限制
+    max_accounts: Option<usize>,
+    /// 凭证状态的有效期（秒），超过后视为未知，None 表示不过期
+    status_ttl: Option<i64>,
+    /// 批量刷新 Token 的最大并发数
+    refresh_concurrency: usize,
+    /// 批量刷新时单个账号的请求超时
+    refresh_timeout: Duration,
+    /// 新账号默认使用的代理（创建时复制到账号上）
+    default_proxy: Option<String>,
+    /// 新账号默认所属的环境（由设置中的站点地址推断）
+    default_environment: Option<String>,
+    /// 本机时钟相对 trae.ai 服务器的偏差（秒），用于判断 Token 是否过期
+    clock_offset_secs: i64,
+    /// 取消进行中的批量校验；校验期间管理器被锁定，因此由调用方在锁外持有该标记的副本
+    validation_cancel: Arc<AtomicBool>,
+    /// 已解析的 Token 声明缓存：账号 ID -> (Token, 声明)，Token 变化后重新解析；无法解析时为 None
+    claims_cache: HashMap<String, (String, Option<serde_json::Value>)>,
 }
 
 impl AccountManager {
     /// 创建账号管理器
     pub fn new() -> Result<Self> {
         let data_path = Self::get_data_path()?;
+        // 先获取锁再读取数据，避免读到其他实例正在写入的内容
+        let store_lock = StoreLock::acquire(data_path.with_extension("lock"))?;
+        if store_lock.is_none() {
+            println!("[WARN] 另一个实例正在使用账号数据，当前以只读模式运行");
+        }
         let store = Self::load_store(&data_path)?;
 
-        Ok(Self { store, data_path })
+        Ok(Self::from_parts(store, data_path, store_lock, false))
+    }
+
+    /// 以安全模式创建账号管理器：不获取文件锁、不写入，尽量加载能解析的账号
+    pub fn new_safe_mode() -> Result<Self> {
+        let data_path = Self::get_data_path()?;
+        let store = Self::load_store_lenient(&data_path);
+        println!("[WARN] 安全模式：账号数据以只读方式加载，共 {} 个账号", store.accounts.len());
+
+        Ok(Self::from_parts(store, data_path, None, true))
+    }
+
+    /// 用已加载的数据构造管理器，其余配置取默认值（由调用方按设置再调整）
+    fn from_parts(store: AccountStore, data_path: PathBuf, store_lock: Option<StoreLock>, safe_mode: bool) -> Self {
+        Self {
+            store,
+            data_path,
+            store_lock,
+            safe_mode,
+            max_accounts: None,
+            status_ttl: None,
+            refresh_concurrency: DEFAULT_REFRESH_CONCURRENCY,
+            refresh_timeout: Duration::from_secs(DEFAULT_REFRESH_TIMEOUT_SECS),
+            default_proxy: None,
+            default_environment: None,
+            clock_offset_secs: 0,
+            validation_cancel: Arc::new(AtomicBool::new(false)),
+            claims_cache: HashMap::new(),
+        }
+    }
+
+    /// 只在内存中操作的管理器（不持有文件锁，不会写入磁盘），供测试使用
+    #[cfg(test)]
+    pub fn in_memory(accounts: Vec<Account>) -> Self {
+        Self::from_parts(
+            AccountStore { accounts, ..Default::default() },
+            std::env::temp_dir().join("trae-account-manager-test.json"),
+            None,
+            false,
+        )
+    }
+
+    /// 是否处于只读模式
+    pub fn is_read_only(&self) -> bool {
+        self.store_lock.is_none()
+    }
+
+    /// 设置账号数量上限（None 表示不限制）
+    pub fn set_max_accounts(&mut self, max_accounts: Option<usize>) {
+        self.max_accounts = max_accounts;
+    }
+
+    /// 设置新账号默认所属的环境（空值表示不标记）
+    pub fn set_default_environment(&mut self, environment: Option<String>) {
+        self.default_environment = environment.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+    }
+
+    /// 设置新账号默认使用的代理（空值表示直连）
+    pub fn set_default_proxy(&mut self, proxy: Option<String>) {
+        self.default_proxy = proxy.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    }
+
+    /// 新账号继承默认设置（已单独设置过的字段不覆盖），并保证账号 ID 不与已有账号重复
+    fn apply_new_account_defaults(&self, account: &mut Account) {
+        if account.proxy.is_none() {
+            account.proxy = self.default_proxy.clone();
+        }
+        if account.environment.is_none() {
+            account.environment = self.default_environment.clone();
+        }
+        // 允许同一用户存在多条记录时，派生的 ID 可能已被占用，此时改用随机 ID
+        if self.store.accounts.iter().any(|a| a.id == account.id) {
+            account.id = uuid_simple();
+        }
+    }
+
+    /// 取消批量校验的标记，置为 true 后进行中的校验停止发起新请求并返回已完成的结果
+    pub fn validation_canceller(&self) -> Arc<AtomicBool> {
+        self.validation_cancel.clone()
+    }
+
+    /// 设置凭证状态的有效期（秒）
+    pub fn set_status_ttl(&mut self, ttl_secs: Option<u64>) {
+        self.status_ttl = ttl_secs.map(|ttl| ttl as i64);
+    }
+
+    /// 设置批量刷新 Token 的并发数与单个请求超时（秒），0 视为 1
+    pub fn set_refresh_limits(&mut self, concurrency: usize, timeout_secs: u64) {
+        self.refresh_concurrency = concurrency.max(1);
+        self.refresh_timeout = Duration::from_secs(timeout_secs.max(1));
+    }
+
+    /// 新增账号前检查是否已达到数量上限（更新已有账号不受限制）
+    fn ensure_capacity(&self) -> Result<()> {
+        match self.max_accounts {
+            Some(max) if self.store.accounts.len() >= max => Err(AccountError::LimitReached(max).into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// 是否处于安全模式
+    pub fn is_safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
+    /// 释放存储文件锁（应用退出时调用）
+    pub fn release_lock(&mut self) {
+        if let Some(lock) = self.store_lock.take() {
+            lock.release();
+        }
+    }
+
+    /// 账号数据所在目录
+    pub fn data_dir(&self) -> Result<PathBuf> {
+        self.data_path
+            .parent()
+            .map(|dir| dir.to_path_buf())
+            .ok_or_else(|| anyhow!("无法获取应用数据目录"))
     }
 
     /// 获取数据存储路径
@@ -42,16 +217,287 @@ impl AccountManager {
         }
     }
 
+    /// 宽松加载账号存储：逐个解析账号，跳过并记录无法解析的条目
+    fn load_store_lenient(path: &PathBuf) -> AccountStore {
+        let value: serde_json::Value = match fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| serde_json::from_str(&content).map_err(Into::into))
+        {
+            Ok(value) => value,
+            Err(e) => {
+                println!("[WARN] 无法读取账号数据文件: {}", e);
+                return AccountStore::default();
+            }
+        };
+
+        let mut accounts = Vec::new();
+        for (index, item) in value["accounts"].as_array().into_iter().flatten().enumerate() {
+            match serde_json::from_value::<Account>(item.clone()) {
+                Ok(account) => accounts.push(account),
+                Err(e) => println!(
+                    "[WARN] 第 {} 个账号解析失败（{}）: {}",
+                    index + 1,
+                    item["email"].as_str().unwrap_or("未知邮箱"),
+                    e
+                ),
+            }
+        }
+
+        let text = |key: &str| value[key].as_str().map(|s| s.to_string());
+        AccountStore {
+            accounts,
+            active_account_id: text("active_account_id"),
+            current_account_id: text("current_account_id"),
+            primary_account_id: text("primary_account_id"),
+            groups: serde_json::from_value(value["groups"].clone()).unwrap_or_default(),
+        }
+    }
+
+    /// 检查当前是否允许写入数据文件
+    fn ensure_writable(&self) -> Result<()> {
+        if self.safe_mode {
+            return Err(AccountError::ReadOnly("安全模式下账号数据为只读，无法保存修改").into());
+        }
+        if self.is_read_only() {
+            return Err(AccountError::ReadOnly("另一个实例正在使用账号数据，当前为只读模式，无法保存修改").into());
+        }
+        Ok(())
+    }
+
+    /// 探测数据目录（及已有的数据文件）是否可写：写入并删除一个探测文件，不修改账号数据
+    pub fn check_storage_writable(&self) -> StorageCheck {
+        let dir = match self.data_dir() {
+            Ok(dir) => dir,
+            // 无法确定数据目录时不在当前工作目录下探测，直接报告为不可写
+            Err(e) => {
+                return StorageCheck {
+                    path: self.data_path.display().to_string(),
+                    writable: false,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+        let probe = || -> std::io::Result<()> {
+            let probe_path = dir.join(".write-probe");
+            fs::write(&probe_path, b"")?;
+            fs::remove_file(&probe_path)?;
+            if self.data_path.exists() {
+                fs::OpenOptions::new().append(true).open(&self.data_path)?;
+            }
+            Ok(())
+        };
+        let error = probe().err().map(|e| e.to_string());
+        StorageCheck {
+            path: dir.display().to_string(),
+            writable: error.is_none(),
+            error,
+        }
+    }
+
     /// 保存账号存储
     fn save_store(&self) -> Result<()> {
+        self.ensure_writable()?;
+        let content = serde_json::to_string_pretty(&self.store)?;
+        self.write_store_content(&content)
+    }
+
+    /// 数据文件旁记录其 SHA-256 的文件（accounts.json.sha256）
+    fn checksum_path(&self) -> PathBuf {
+        self.data_path.with_extension("json.sha256")
+    }
+
+    fn sha256_hex(bytes: &[u8]) -> String {
+        ring::digest::digest(&ring::digest::SHA256, bytes)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// 写入数据文件并更新校验和：两者都先写临时文件再替换，避免留下写了一半的文件
+    fn write_store_content(&self, content: &str) -> Result<()> {
+        let tmp_path = self.data_path.with_extension("json.tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, &self.data_path)?;
+
+        let checksum_path = self.checksum_path();
+        let tmp_checksum = checksum_path.with_extension("sha256.tmp");
+        fs::write(&tmp_checksum, Self::sha256_hex(content.as_bytes()))?;
+        fs::rename(&tmp_checksum, &checksum_path)?;
+        Ok(())
+    }
+
+    /// 用上次保存时记录的校验和检查数据文件是否被外部修改或只写了一部分
+    pub fn verify_store_checksum(&self) -> Result<ChecksumStatus> {
+        if !self.data_path.exists() {
+            return Ok(ChecksumStatus::Missing);
+        }
+        let expected = match fs::read_to_string(self.checksum_path()) {
+            Ok(expected) => expected.trim().to_lowercase(),
+            // 旧版本没有校验和文件，下次保存时生成
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ChecksumStatus::Missing),
+            Err(e) => return Err(e.into()),
+        };
+        let actual = Self::sha256_hex(&fs::read(&self.data_path)?);
+        Ok(if actual == expected { ChecksumStatus::Match } else { ChecksumStatus::Mismatch })
+    }
+
+    /// 重写账号数据文件：丢弃旧版本遗留的未知字段并规范格式，先写临时文件再替换
+    pub fn compact_store(&self) -> Result<CompactReport> {
+        self.ensure_writable()?;
+        let before = fs::metadata(&self.data_path).map(|m| m.len()).unwrap_or(0);
+        let content = serde_json::to_string_pretty(&self.store)?;
+        self.write_store_content(&content)?;
+
+        let after = content.len() as u64;
+        Ok(CompactReport {
+            before_bytes: before,
+            after_bytes: after,
+            reclaimed_bytes: before.saturating_sub(after),
+        })
+    }
+
+    /// 获取快照目录
+    fn snapshot_dir(&self) -> Result<PathBuf> {
+        let dir = self.data_path
+            .parent()
+            .ok_or_else(|| anyhow!("无法获取应用数据目录"))?
+            .join("snapshots");
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// 获取快照文件路径（校验 ID，防止路径穿越）
+    fn snapshot_path(&self, snapshot_id: &str) -> Result<PathBuf> {
+        if snapshot_id.is_empty() || !snapshot_id.chars().all(|c| c.is_ascii_digit() || c == '-') {
+            return Err(anyhow!("无效的快照 ID"));
+        }
+        Ok(self.snapshot_dir()?.join(format!("{}.json", snapshot_id)))
+    }
+
+    /// 创建当前账号存储的快照，返回快照 ID
+    pub fn snapshot(&self) -> Result<String> {
+        self.ensure_writable()?;
+        let snapshot_id = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
         let content = serde_json::to_string_pretty(&self.store)?;
-        fs::write(&self.data_path, content)?;
+        fs::write(self.snapshot_path(&snapshot_id)?, content)?;
+
+        // 超出保留数量时删除最旧的快照
+        let snapshots = self.list_snapshots()?;
+        for old in snapshots.iter().skip(MAX_SNAPSHOTS) {
+            if let Ok(path) = self.snapshot_path(&old.id) {
+                let _ = fs::remove_file(path);
+            }
+        }
+
+        println!("[INFO] 已创建快照: {}", snapshot_id);
+        Ok(snapshot_id)
+    }
+
+    /// 列出所有快照（最新的在前）
+    pub fn list_snapshots(&self) -> Result<Vec<SnapshotInfo>> {
+        let mut snapshots = Vec::new();
+
+        for entry in fs::read_dir(self.snapshot_dir()?)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()).map(|s| s.to_string()) else {
+                continue;
+            };
+            let metadata = fs::metadata(&path)?;
+            let created_at = metadata.modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or_default();
+            let account_count = Self::load_store(&path)
+                .map(|store| store.accounts.len())
+                .unwrap_or_default();
+
+            snapshots.push(SnapshotInfo { id, created_at, account_count, size: metadata.len() });
+        }
+
+        // 快照 ID 按时间格式化，字典序即时间顺序
+        snapshots.sort_by(|a, b| b.id.cmp(&a.id));
+        Ok(snapshots)
+    }
+
+    /// 从快照恢复账号存储（恢复前会自动为当前状态创建快照）
+    pub fn restore_snapshot(&mut self, snapshot_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let path = self.snapshot_path(snapshot_id)?;
+        if !path.exists() {
+            return Err(anyhow!("快照不存在"));
+        }
+        let store = Self::load_store(&path)
+            .map_err(|e| anyhow!("读取快照失败: {}", e))?;
+
+        self.snapshot()?;
+        // 写入成功后才替换内存中的数据，写入失败时保持恢复前的状态
+        self.write_store_content(&serde_json::to_string_pretty(&store)?)?;
+        self.store = store;
+
+        println!("[INFO] 已从快照恢复: {}", snapshot_id);
         Ok(())
     }
 
+    /// 若已有相同用户 ID 的账号，返回携带该账号信息的 Duplicate 错误
+    fn ensure_not_duplicate(&self, user_id: &str) -> Result<()> {
+        match self.store.accounts.iter().find(|a| a.user_id == user_id) {
+            Some(existing) => Err(AccountError::Duplicate {
+                id: existing.id.clone(),
+                email: existing.email.clone(),
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    /// 解析 Token 的用户 ID 并对照账号数据，预测登录会新增账号还是更新已有账号（不发起网络请求）
+    pub fn predict_login(&self, token: &str) -> Result<LoginPrediction> {
+        let payload = TraeApiClient::parse_jwt_token(token.trim())?;
+        Ok(match self.store.accounts.iter().find(|a| a.user_id == payload.user_id) {
+            Some(existing) => LoginPrediction::WouldUpdate {
+                existing_id: existing.id.clone(),
+                email: existing.email.clone(),
+            },
+            None => LoginPrediction::WouldCreate,
+        })
+    }
+
+    /// 离线回放一次记录下的 GetUserToken 响应：按正式登录的规则提取 Token、解析声明并检查重复，
+    /// 报告将会添加的账号，不发起网络请求也不修改账号数据（用于复现捕获逻辑的问题）
+    pub fn replay_capture(&self, body: &str) -> Result<CaptureReplay> {
+        let (token, refresh_token) = TraeApiClient::extract_token_from_body(body)?;
+        let payload = TraeApiClient::parse_jwt_token(&token)?;
+        let expires_at = TraeApiClient::decode_jwt_claims(&token)?["exp"].as_i64();
+
+        let (duplicate_of, duplicate_email) = match self.ensure_not_duplicate(&payload.user_id) {
+            Ok(()) => (None, None),
+            Err(e) => match e.downcast::<AccountError>()? {
+                AccountError::Duplicate { id, email } => (Some(id), Some(email)),
+                other => return Err(other.into()),
+            },
+        };
+
+        Ok(CaptureReplay {
+            user_id: payload.user_id,
+            tenant_id: payload.tenant_id,
+            token_length: token.len(),
+            has_refresh_token: refresh_token.is_some(),
+            issued_at: payload.issued_at,
+            expires_at,
+            duplicate_of,
+            duplicate_email,
+        })
+    }
+
     /// 添加账号（通过 cookies）
     pub async fn add_account(&mut self, cookies: String) -> Result<Account> {
-        let mut client = TraeApiClient::new(&cookies)?;
+        self.ensure_writable()?;
+        let mut client = TraeApiClient::new(&cookies)?.with_proxy(self.default_proxy.as_deref())?;
 
         // 获取 token
         let token_result = client.get_user_token().await?;
@@ -60,14 +506,8 @@ impl AccountManager {
         let user_info = client.get_user_info().await?;
 
         // 检查是否已存在
-        if self
-            .store
-            .accounts
-            .iter()
-            .any(|a| a.user_id == token_result.user_id)
-        {
-            return Err(anyhow!("该账号已存在"));
-        }
+        self.ensure_not_duplicate(&token_result.user_id)?;
+        self.ensure_capacity()?;
 
         let mut account = Account::new(
             user_info.screen_name.clone(),
@@ -81,6 +521,9 @@ impl AccountManager {
         account.region = user_info.region;
         account.jwt_token = Some(token_result.token);
         account.token_expired_at = Some(token_result.expired_at);
+        account.update_refresh_token(token_result.refresh_token);
+        account.record_cookie_diagnostic();
+        self.apply_new_account_defaults(&mut account);
 
         self.store.accounts.push(account.clone());
 
@@ -93,255 +536,1507 @@ impl AccountManager {
         Ok(account)
     }
 
+    /// 是否还未尝试过从 webview 会话导入账号
+    pub fn session_import_pending(&self) -> bool {
+        self.data_dir()
+            .map(|dir| !dir.join(SESSION_IMPORT_MARKER).exists())
+            .unwrap_or(false)
+    }
+
+    /// 首次运行时用 webview 中已有的 trae.ai 会话创建账号，无论结果如何只尝试一次
+    /// 没有会话或账号已存在时返回 None
+    pub async fn import_current_session(&mut self, cookies: String) -> Result<Option<Account>> {
+        if !self.session_import_pending() {
+            return Ok(None);
+        }
+
+        let result = if cookies.trim().is_empty() {
+            Ok(None)
+        } else {
+            match self.add_account(cookies).await {
+                Ok(account) => Ok(Some(account)),
+                Err(e) if matches!(e.downcast_ref::<AccountError>(), Some(AccountError::Duplicate { .. })) => Ok(None),
+                Err(e) => Err(e),
+            }
+        };
+
+        if !self.is_read_only() {
+            fs::write(self.data_dir()?.join(SESSION_IMPORT_MARKER), "")?;
+        }
+        result
+    }
+
     /// 添加账号（通过 Token，可选 Cookies）
     pub async fn add_account_by_token(&mut self, token: String, cookies: Option<String>) -> Result<Account> {
-        let client = TraeApiClient::new_with_token(&token)?;
+        self.ensure_writable()?;
+        let client = TraeApiClient::new_with_token(&token)?.with_proxy(self.default_proxy.as_deref())?;
 
         // 通过 Token 获取用户信息
         let user_info = client.get_user_info_by_token().await?;
 
+        // 如果提供了 Cookies，尝试获取更详细的用户信息，并确认与 Token 属于同一用户
+        let cookie_info = match cookies {
+            Some(ref cookies_str) => self.get_user_info_with_cookies(cookies_str).await.ok(),
+            None => None,
+        };
+        if let Some(info) = &cookie_info {
+            Self::ensure_same_user(&user_info.user_id, &info.user_id)?;
+        }
+
         // 检查是否已存在
-        if self
-            .store
-            .accounts
-            .iter()
-            .any(|a| a.user_id == user_info.user_id)
-        {
-            return Err(anyhow!("该账号已存在"));
+        self.ensure_not_duplicate(&user_info.user_id)?;
+        self.ensure_capacity()?;
+
+        let (name, email, avatar_url) = if let Some(info) = cookie_info {
+            (
+                info.screen_name,
+                info.non_plain_text_email.unwrap_or_default(),
+                info.avatar_url,
+            )
+        } else {
+            (
+                user_info.screen_name.unwrap_or_else(|| format!("User_{}", &user_info.user_id[..8.min(user_info.user_id.len())])),
+                user_info.email.unwrap_or_default(),
+                user_info.avatar_url.unwrap_or_default(),
+            )
+        };
+
+        let mut account = Account::new(
+            name,
+            email,
+            cookies.unwrap_or_default(),
+            user_info.user_id.clone(),
+            user_info.tenant_id.clone(),
+        );
+
+        account.avatar_url = avatar_url;
+        account.jwt_token = Some(token);
+        account.token_expired_at = None;
+        account.record_cookie_diagnostic();
+        self.apply_new_account_defaults(&mut account);
+
+        self.store.accounts.push(account.clone());
+
+        // 如果是第一个账号，设为活跃账号
+        if self.store.active_account_id.is_none() {
+            self.store.active_account_id = Some(account.id.clone());
+        }
+
+        self.save_store()?;
+        Ok(account)
+    }
+
+    /// 确认 Token 与 Cookies 对应同一用户
+    fn ensure_same_user(token_user_id: &str, cookie_user_id: &str) -> Result<()> {
+        if token_user_id.is_empty() || cookie_user_id.is_empty() || token_user_id == cookie_user_id {
+            return Ok(());
+        }
+        Err(AccountError::CredentialMismatch {
+            token_user_id: token_user_id.to_string(),
+            cookie_user_id: cookie_user_id.to_string(),
+        }
+        .into())
+    }
+
+    /// 使用 Cookies 获取用户信息
+    async fn get_user_info_with_cookies(&self, cookies: &str) -> Result<crate::api::UserInfoResult> {
+        let client = TraeApiClient::new(cookies)?.with_proxy(self.default_proxy.as_deref())?;
+        client.get_user_info().await
+    }
+
+    /// 预先创建所有账号（及新账号默认代理）用到的共享客户端，返回客户端数量；无效的代理只记录警告
+    pub fn prewarm_clients(&self) -> usize {
+        fn trimmed(value: Option<&str>) -> Option<&str> {
+            value.map(str::trim).filter(|v| !v.is_empty())
+        }
+        let mut targets: Vec<(Option<&str>, Option<&str>)> = vec![(None, None), (trimmed(self.default_proxy.as_deref()), None)];
+        targets.extend(self.store.accounts.iter().map(|a| (trimmed(a.proxy.as_deref()), trimmed(a.user_agent.as_deref()))));
+        targets.sort();
+        targets.dedup();
+        targets
+            .into_iter()
+            .filter(|(proxy, user_agent)| match crate::api::shared_client(*proxy, *user_agent) {
+                Ok(_) => true,
+                Err(e) => {
+                    println!("[WARN] 创建代理 {} 的客户端失败: {}", proxy.unwrap_or_default(), e);
+                    false
+                }
+            })
+            .count()
+    }
+
+    /// 为账号创建基于 Token 的 API 客户端（应用账号级别的请求配置）
+    fn token_client_for(account: &Account, token: &str) -> Result<TraeApiClient> {
+        TraeApiClient::new_with_token(token)?
+            .with_user_agent(account.user_agent.as_deref())?
+            .with_proxy(account.proxy.as_deref())?
+            .with_extra_headers(&account.extra_headers)
+    }
+
+    /// 为账号创建基于 Cookies 的 API 客户端（应用账号级别的请求配置）
+    fn cookie_client_for(account: &Account, cookies: &str) -> Result<TraeApiClient> {
+        TraeApiClient::new(cookies)?
+            .with_user_agent(account.user_agent.as_deref())?
+            .with_proxy(account.proxy.as_deref())?
+            .with_extra_headers(&account.extra_headers)
+    }
+
+    /// 刷新 Token 使用的客户端：在 Cookies 客户端的基础上复用登录时捕获的请求头
+    fn refresh_client_for(account: &Account) -> Result<TraeApiClient> {
+        let mut headers = account.captured_headers.clone();
+        headers.extend(account.extra_headers.clone());
+        TraeApiClient::new(&account.cookies)?
+            .with_user_agent(account.user_agent.as_deref())?
+            .with_proxy(account.proxy.as_deref())?
+            .with_extra_headers(&headers)
+    }
+
+    /// 是否为 Trae IDE 当前使用的账号
+    pub fn is_current_account(&self, account_id: &str) -> bool {
+        self.store.current_account_id.as_deref() == Some(account_id)
+    }
+
+    /// 用重新登录捕获的凭证原地更新已有账号（Token 已在添加流程中校验过用户）
+    pub fn refresh_captured_credentials(
+        &mut self,
+        account_id: &str,
+        token: String,
+        cookies: Option<String>,
+        refresh_token: Option<String>,
+    ) -> Result<Account> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        acc.jwt_token = Some(token);
+        acc.token_expired_at = None;
+        acc.update_refresh_token(refresh_token);
+        acc.updated_at = chrono::Utc::now().timestamp();
+        acc.status = AccountStatus::Valid;
+        acc.status_override_until = None;
+        acc.last_checked = Some(acc.updated_at);
+        acc.record_health(AccountStatus::Valid, acc.updated_at);
+
+        if let Some(cookies) = cookies.filter(|c| !c.trim().is_empty()) {
+            self.merge_cookies(account_id, &cookies)?;
+        }
+        self.save_store()?;
+        self.get_account(account_id)
+    }
+
+    /// Token 的过期时间：优先从 JWT 的 exp 读取，读不到时使用记录的过期时间
+    fn stored_token_expiry(account: &Account) -> Option<i64> {
+        account.jwt_token.as_deref()
+            .and_then(|token| TraeApiClient::decode_jwt_claims(token).ok())
+            .and_then(|claims| claims["exp"].as_i64())
+            .or_else(|| account.token_expiry())
+    }
+
+    /// 判断重新登录捕获到的凭证中哪些需要替换，返回 (替换 Token, 合并 Cookies)
+    ///
+    /// Token：原 Token 缺失、已失效或新 Token 过期时间更晚时才替换；
+    /// Cookies：原 Cookies 为空或会话已过期时合并；否则只在新 Cookies 带来变化、
+    /// 且没有缺少原 Cookies 中已有的关键 Cookie（如只读到页面脚本可见的部分）时合并
+    fn selective_update_plan(account: &Account, token: &str, cookies: Option<&str>, now: i64) -> (bool, bool) {
+        let stored_expiry = Self::stored_token_expiry(account);
+        let update_token = match account.jwt_token.as_deref() {
+            None => true,
+            Some(stored) if stored == token => false,
+            Some(_) => {
+                account.status == AccountStatus::Expired
+                    || stored_expiry.is_none_or(|expiry| expiry <= now)
+                    || TraeApiClient::decode_jwt_claims(token).ok()
+                        .and_then(|claims| claims["exp"].as_i64())
+                        .zip(stored_expiry)
+                        .is_some_and(|(fresh, stored)| fresh > stored)
+            }
+        };
+
+        let update_cookies = match cookies.map(str::trim).filter(|c| !c.is_empty()) {
+            None => false,
+            Some(_) if account.cookies.trim().is_empty() => true,
+            Some(_) if cookies::session_expiry(&account.cookies).is_some_and(|expiry| expiry <= now) => true,
+            Some(fresh) => {
+                let (stored_present, _) = cookies::critical_cookie_presence(&account.cookies);
+                let (fresh_present, _) = cookies::critical_cookie_presence(fresh);
+                let complete = stored_present.iter().all(|name| fresh_present.contains(name));
+                let merged = cookies::merge_cookie_strings(&account.cookies, fresh);
+                complete && cookies::parse_cookie_pairs(&merged) != cookies::parse_cookie_pairs(&account.cookies)
+            }
+        };
+        (update_token, update_cookies)
+    }
+
+    /// 原地重新登录：只替换已失效或有更新的凭证，仍然有效的 Token 和 Cookies 保持不变
+    pub fn refresh_selective(
+        &mut self,
+        account_id: &str,
+        token: String,
+        cookies: Option<String>,
+        refresh_token: Option<String>,
+    ) -> Result<(Account, SelectiveUpdate)> {
+        self.ensure_writable()?;
+        let now = chrono::Utc::now().timestamp();
+        let (update_token, update_cookies) =
+            Self::selective_update_plan(&self.get_account(account_id)?, &token, cookies.as_deref(), now);
+
+        if update_token {
+            let acc = self.store.accounts.iter_mut()
+                .find(|a| a.id == account_id)
+                .ok_or_else(|| anyhow!("账号不存在"))?;
+            acc.jwt_token = Some(token);
+            acc.token_expired_at = None;
+            acc.update_refresh_token(refresh_token);
+            acc.updated_at = now;
+            acc.status = AccountStatus::Valid;
+            acc.status_override_until = None;
+            acc.last_checked = Some(now);
+            acc.record_health(AccountStatus::Valid, now);
+        }
+        if let Some(cookies) = cookies.filter(|_| update_cookies) {
+            self.merge_cookies(account_id, &cookies)?;
         }
+        if update_token || update_cookies {
+            self.save_store()?;
+        }
+
+        let account = self.get_account(account_id)?;
+        let update = SelectiveUpdate {
+            account_id: account.id.clone(),
+            email: account.email.clone(),
+            token_updated: update_token,
+            cookies_updated: update_cookies,
+        };
+        Ok((account, update))
+    }
+
+    /// 草稿文件路径（加密保存）
+    fn drafts_path(&self) -> Result<PathBuf> {
+        Ok(self.data_dir()?.join("drafts.enc"))
+    }
+
+    /// 添加失败时保存捕获到的凭证，稍后可通过 commit_draft 重试
+    pub fn save_draft(
+        &self,
+        token: String,
+        cookies: Option<String>,
+        refresh_token: Option<String>,
+        error: String,
+    ) -> Result<DraftBrief> {
+        self.ensure_writable()?;
+        let path = self.drafts_path()?;
+        let passphrase = backup::session_passphrase();
+        let mut list = drafts::load(&path, passphrase.as_deref())?;
+        let draft = DraftCapture {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            token,
+            cookies,
+            refresh_token,
+            error,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        let brief = DraftBrief::from(&draft);
+        list.push(draft);
+        drafts::save(&path, &list, passphrase.as_deref())?;
+        Ok(brief)
+    }
+
+    /// 列出保存的登录捕获草稿（不含凭证）
+    pub fn list_draft_captures(&self) -> Result<Vec<DraftBrief>> {
+        let passphrase = backup::session_passphrase();
+        Ok(drafts::load(&self.drafts_path()?, passphrase.as_deref())?.iter().map(DraftBrief::from).collect())
+    }
+
+    /// 用草稿中的凭证重新添加账号，成功后删除该草稿
+    pub async fn commit_draft(&mut self, draft_id: &str) -> Result<Account> {
+        let path = self.drafts_path()?;
+        let passphrase = backup::session_passphrase();
+        let mut list = drafts::load(&path, passphrase.as_deref())?;
+        let index = list.iter()
+            .position(|d| d.id == draft_id)
+            .ok_or_else(|| anyhow!("草稿不存在"))?;
+        let draft = list[index].clone();
+
+        let account = self.add_account_by_token(draft.token, draft.cookies).await?;
+        self.set_refresh_token(&account.id, draft.refresh_token)?;
+
+        list.remove(index);
+        drafts::save(&path, &list, passphrase.as_deref())?;
+        self.get_account(&account.id)
+    }
+
+    /// 删除草稿
+    pub fn discard_draft(&self, draft_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let path = self.drafts_path()?;
+        let passphrase = backup::session_passphrase();
+        let mut list = drafts::load(&path, passphrase.as_deref())?;
+        list.retain(|d| d.id != draft_id);
+        drafts::save(&path, &list, passphrase.as_deref())
+    }
+
+    /// 保存登录时捕获的刷新凭证
+    pub fn set_refresh_token(&mut self, account_id: &str, refresh_token: Option<String>) -> Result<()> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        acc.update_refresh_token(refresh_token);
+        self.save_store()
+    }
+
+    /// 设置账号的自定义 User-Agent（传空值恢复默认）
+    pub fn set_account_user_agent(&mut self, account_id: &str, user_agent: Option<String>) -> Result<()> {
+        self.ensure_writable()?;
+        let account = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+
+        account.user_agent = user_agent
+            .map(|ua| ua.trim().to_string())
+            .filter(|ua| !ua.is_empty());
+        account.updated_at = chrono::Utc::now().timestamp();
+
+        self.save_store()?;
+        Ok(())
+    }
+
+    /// 设置账号使用的代理（传空值表示直连）
+    pub fn set_account_proxy(&mut self, account_id: &str, proxy: Option<String>) -> Result<()> {
+        self.ensure_writable()?;
+        let proxy = proxy.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+        if let Some(proxy) = &proxy {
+            crate::api::parse_proxy_url(proxy)?;
+        }
+        let account = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        account.proxy = proxy;
+        account.updated_at = chrono::Utc::now().timestamp();
+        self.save_store()
+    }
+
+    /// 设置账号请求 Trae API 时附加的请求头（校验失败时不做修改）
+    pub fn set_account_extra_headers(&mut self, account_id: &str, extra_headers: HashMap<String, String>) -> Result<()> {
+        self.ensure_writable()?;
+        TraeApiClient::parse_extra_headers(&extra_headers)?;
+        let account = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+
+        account.extra_headers = extra_headers
+            .into_iter()
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+        account.updated_at = chrono::Utc::now().timestamp();
+
+        self.save_store()?;
+        Ok(())
+    }
+
+    /// 删除账号
+    pub fn remove_account(&mut self, account_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let index = self
+            .store
+            .accounts
+            .iter()
+            .position(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+
+        self.store.accounts.remove(index);
+
+        // 如果删除的是活跃账号，重置活跃账号
+        if self.store.active_account_id.as_deref() == Some(account_id) {
+            self.store.active_account_id = self.store.accounts.first().map(|a| a.id.clone());
+        }
+        self.clear_primary_if_removed();
+
+        self.save_store()?;
+        Ok(())
+    }
+
+    /// 修改账号 ID（修复导入造成的 ID 冲突），同时更新活跃账号和当前账号的引用
+    /// 存在多个账号使用 old_id 时只修改第一个，引用保持指向剩下的那个
+    pub fn reassign_account_id(&mut self, old_id: &str, new_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let new_id = new_id.trim();
+        if new_id.is_empty() || !new_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(anyhow!("新 ID 只能包含字母、数字、- 和 _"));
+        }
+        if self.store.accounts.iter().any(|a| a.id == new_id) {
+            return Err(anyhow!("ID {} 已被其他账号使用", new_id));
+        }
+        let index = self.store.accounts.iter()
+            .position(|a| a.id == old_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+
+        self.store.accounts[index].id = new_id.to_string();
+        self.store.accounts[index].updated_at = chrono::Utc::now().timestamp();
+        self.claims_cache.remove(old_id);
+        for id in self.store.groups.iter_mut().flat_map(|g| g.account_ids.iter_mut()) {
+            if id == old_id {
+                *id = new_id.to_string();
+            }
+        }
+
+        if !self.store.accounts.iter().any(|a| a.id == old_id) {
+            for pointer in [
+                &mut self.store.active_account_id,
+                &mut self.store.current_account_id,
+                &mut self.store.primary_account_id,
+            ] {
+                if pointer.as_deref() == Some(old_id) {
+                    *pointer = Some(new_id.to_string());
+                }
+            }
+        }
+
+        self.save_store()?;
+        println!("[INFO] 已将账号 ID {} 修改为 {}", old_id, new_id);
+        Ok(())
+    }
+
+    /// 设置主账号（替换原来的主账号）；传入 None 表示取消
+    pub fn set_primary(&mut self, account_id: Option<&str>) -> Result<()> {
+        self.ensure_writable()?;
+        if let Some(id) = account_id {
+            if !self.store.accounts.iter().any(|a| a.id == id) {
+                return Err(anyhow!("账号不存在"));
+            }
+        }
+        self.store.primary_account_id = account_id.map(str::to_string);
+        self.save_store()
+    }
+
+    /// 主账号的摘要信息，未设置时为 None
+    pub fn get_primary(&self) -> Option<AccountBrief> {
+        let primary_id = self.store.primary_account_id.as_deref()?;
+        let account = self.store.accounts.iter().find(|a| a.id == primary_id)?;
+        let mut brief = AccountBrief::from_account(account, self.is_current_account(primary_id));
+        brief.status = account.effective_status(self.status_ttl);
+        brief.is_primary = true;
+        Some(brief)
+    }
+
+    /// 主账号已被删除时清除记录
+    fn clear_primary_if_removed(&mut self) {
+        let exists = self.store.primary_account_id.as_deref().is_some_and(|id| {
+            self.store.accounts.iter().any(|a| a.id == id)
+        });
+        if !exists {
+            self.store.primary_account_id = None;
+        }
+    }
+
+    /// 设置活跃账号
+    pub fn set_active_account(&mut self, account_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        if !self.store.accounts.iter().any(|a| a.id == account_id) {
+            return Err(anyhow!("账号不存在"));
+        }
+
+        self.store.active_account_id = Some(account_id.to_string());
+        self.save_store()?;
+        Ok(())
+    }
+
+    /// 切换账号（设置活跃账号并将登录信息写入 Trae IDE）
+    pub fn switch_account(&mut self, account_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        // 检查是否已经是当前使用的账号
+        if self.store.current_account_id.as_deref() == Some(account_id) {
+            return Err(anyhow!("该账号已经是当前使用的账号"));
+        }
+
+        let account = self.store.accounts.iter()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?
+            .clone();
+        if account.quarantined {
+            return Err(anyhow!("账号已被隔离，请先从隔离区恢复后再切换"));
+        }
+
+        // 检查账号是否有有效的 Token
+        let token = account.jwt_token.as_ref()
+            .ok_or_else(|| anyhow!("账号没有有效的 Token，无法切换"))?;
+
+        // 构建 Trae IDE 登录信息
+        let login_info = crate::machine::TraeLoginInfo {
+            token: token.clone(),
+            refresh_token: account.refresh_token.clone(),
+            user_id: account.user_id.clone(),
+            email: account.email.clone(),
+            username: account.name.clone(),
+            avatar_url: account.avatar_url.clone(),
+            host: String::new(), // 根据 region 自动选择
+            region: if account.region.is_empty() { "SG".to_string() } else { account.region.clone() },
+        };
+
+        // 切换 Trae IDE 到该账号（清除旧登录状态并写入新账号信息）
+        crate::machine::switch_trae_account(&login_info, account.machine_id.as_deref())?;
+
+        // 如果账号有绑定的机器码，也更新系统机器码
+        if let Some(machine_id) = &account.machine_id {
+            match crate::machine::set_machine_guid(machine_id) {
+                Ok(_) => println!("[INFO] 已切换系统机器码: {}", machine_id),
+                Err(e) => println!("[WARN] 切换系统机器码失败（可能需要管理员权限）: {}", e),
+            }
+        }
+
+        // 设置活跃账号和当前使用的账号
+        self.store.active_account_id = Some(account_id.to_string());
+        self.store.current_account_id = Some(account_id.to_string());
+        self.save_store()?;
+
+        println!("[INFO] 已切换到账号: {}", account.email);
+        Ok(())
+    }
+
+    /// 按顺序检查账号能否切换：存储可写、账号存在且未隔离、Token 存在且未过期
+    /// （切换只写入 Token，不检查 Cookies），返回第一个阻塞原因
+    pub fn activation_diagnosis(&self, account_id: &str) -> ActivationDiagnosis {
+        let blocker = self.activation_blocker(account_id);
+        ActivationDiagnosis::new(account_id, blocker)
+    }
+
+    fn activation_blocker(&self, account_id: &str) -> Option<ActivationBlocker> {
+        if self.is_read_only() {
+            return Some(ActivationBlocker::StoreReadOnly);
+        }
+        let Some(account) = self.store.accounts.iter().find(|a| a.id == account_id) else {
+            return Some(ActivationBlocker::NotFound);
+        };
+        if account.quarantined {
+            return Some(ActivationBlocker::Quarantined);
+        }
+        if account.jwt_token.as_deref().is_none_or(|t| t.trim().is_empty()) {
+            return Some(ActivationBlocker::NoToken);
+        }
+        if is_expired(account.token_expiry()) {
+            return Some(ActivationBlocker::TokenExpired);
+        }
+        None
+    }
+
+    /// 绑定当前系统机器码到账号
+    pub fn bind_machine_id(&mut self, account_id: &str) -> Result<String> {
+        self.ensure_writable()?;
+        // 获取当前系统机器码
+        let current_machine_id = crate::machine::get_machine_guid()?;
+
+        // 更新账号的机器码
+        let account = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+
+        account.machine_id = Some(current_machine_id.clone());
+        account.updated_at = chrono::Utc::now().timestamp();
+        let email = account.email.clone();
+
+        self.save_store()?;
+        println!("[INFO] 已绑定机器码 {} 到账号 {}", current_machine_id, email);
+
+        Ok(current_machine_id)
+    }
+
+    /// 获取账号列表；include_quarantined 为 false 时不包含已隔离的账号
+    pub fn get_accounts(&self, include_quarantined: bool) -> Vec<AccountBrief> {
+        let current_id = self.store.current_account_id.as_deref();
+        self.store.accounts.iter().filter(|a| include_quarantined || !a.quarantined).map(|account| {
+            let is_current = current_id == Some(account.id.as_str());
+            let mut brief = AccountBrief::from_account(account, is_current);
+            brief.status = account.effective_status(self.status_ttl);
+            brief.is_primary = self.store.primary_account_id.as_deref() == Some(account.id.as_str());
+            brief
+        }).collect()
+    }
+
+    /// 按分组返回账号列表：先按分组顺序列出各分组（组内按设置的顺序），最后是未分组的账号
+    pub fn grouped_accounts(&self, include_quarantined: bool) -> Vec<AccountSection> {
+        let mut remaining: Vec<Option<AccountBrief>> = self.get_accounts(include_quarantined).into_iter().map(Some).collect();
+        let mut take = |id: &str| {
+            remaining.iter_mut()
+                .find(|brief| brief.as_ref().is_some_and(|b| b.id == id))
+                .and_then(Option::take)
+        };
+
+        let mut sections: Vec<AccountSection> = self.store.groups.iter().map(|group| AccountSection {
+            group: Some(group.name.clone()),
+            collapsed: group.collapsed,
+            accounts: group.account_ids.iter().filter_map(|id| take(id)).collect(),
+        }).collect();
+        sections.push(AccountSection {
+            group: None,
+            collapsed: false,
+            accounts: remaining.into_iter().flatten().collect(),
+        });
+        sections
+    }
+
+    /// 规范化分组名称：去掉首尾空白，不能为空且不超过 50 个字符
+    fn normalize_group_name(name: &str) -> Result<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("分组名称不能为空"));
+        }
+        if name.chars().count() > 50 {
+            return Err(anyhow!("分组名称不能超过 50 个字符"));
+        }
+        Ok(name.to_string())
+    }
+
+    /// 清理分组：去掉已删除的账号，删除空分组
+    fn prune_groups(&mut self) {
+        let accounts = &self.store.accounts;
+        for group in &mut self.store.groups {
+            group.account_ids.retain(|id| accounts.iter().any(|a| &a.id == id));
+        }
+        self.store.groups.retain(|g| !g.account_ids.is_empty());
+    }
+
+    /// 把账号移到指定分组的末尾（分组不存在时新建在最后）；group 为 None 表示移出分组
+    pub fn set_account_group(&mut self, account_id: &str, group: Option<&str>) -> Result<()> {
+        self.ensure_writable()?;
+        if !self.store.accounts.iter().any(|a| a.id == account_id) {
+            return Err(anyhow!("账号不存在"));
+        }
+        let group = group.map(Self::normalize_group_name).transpose()?;
+        for g in &mut self.store.groups {
+            g.account_ids.retain(|id| id != account_id);
+        }
+        if let Some(name) = group {
+            match self.store.groups.iter_mut().find(|g| g.name == name) {
+                Some(g) => g.account_ids.push(account_id.to_string()),
+                None => self.store.groups.push(AccountGroup {
+                    name,
+                    account_ids: vec![account_id.to_string()],
+                    collapsed: false,
+                }),
+            }
+        }
+        self.prune_groups();
+        self.save_store()
+    }
+
+    /// 按 order 重新排列：order 中的项排在前面，未列出的保持原有相对顺序排在后面；order 中不能有未知的项
+    fn reorder<T>(items: &mut Vec<T>, order: &[String], key: impl Fn(&T) -> &str) -> Result<()> {
+        if let Some(unknown) = order.iter().find(|o| !items.iter().any(|item| key(item) == o.as_str())) {
+            return Err(anyhow!("{} 不在列表中", unknown));
+        }
+        let mut rest = std::mem::take(items);
+        for o in order {
+            if let Some(index) = rest.iter().position(|item| key(item) == o.as_str()) {
+                items.push(rest.remove(index));
+            }
+        }
+        items.extend(rest);
+        Ok(())
+    }
+
+    /// 设置分组内账号的顺序
+    pub fn reorder_group(&mut self, group: &str, account_ids: &[String]) -> Result<()> {
+        self.ensure_writable()?;
+        self.prune_groups();
+        let group = self.store.groups.iter_mut()
+            .find(|g| g.name == group.trim())
+            .ok_or_else(|| anyhow!("分组不存在"))?;
+        Self::reorder(&mut group.account_ids, account_ids, |id| id.as_str())?;
+        self.save_store()
+    }
+
+    /// 设置分组的显示顺序
+    pub fn reorder_groups(&mut self, names: &[String]) -> Result<()> {
+        self.ensure_writable()?;
+        self.prune_groups();
+        Self::reorder(&mut self.store.groups, names, |g| g.name.as_str())?;
+        self.save_store()
+    }
+
+    /// 设置分组在界面上是否折叠
+    pub fn set_group_collapsed(&mut self, group: &str, collapsed: bool) -> Result<()> {
+        self.ensure_writable()?;
+        let group = self.store.groups.iter_mut()
+            .find(|g| g.name == group.trim())
+            .ok_or_else(|| anyhow!("分组不存在"))?;
+        group.collapsed = collapsed;
+        self.save_store()
+    }
+
+    /// 从未校验过凭证的账号（如刚导入的账号），不含已隔离和手动设置了状态的账号
+    pub fn list_unvalidated(&self) -> Vec<AccountBrief> {
+        let current_id = self.store.current_account_id.as_deref();
+        self.store.accounts.iter()
+            .filter(|a| !a.quarantined && !a.is_status_manual())
+            .filter(|a| a.last_checked.is_none() && a.status == AccountStatus::Unknown)
+            .map(|account| AccountBrief::from_account(account, current_id == Some(account.id.as_str())))
+            .collect()
+    }
+
+    /// 解析账号对应的底层用户 ID（优先从 Token 中解析）
+    fn resolve_user_id(account: &Account) -> Option<String> {
+        match &account.jwt_token {
+            Some(token) => TraeApiClient::parse_jwt_token(token).ok().map(|p| p.user_id),
+            None if !account.user_id.is_empty() => Some(account.user_id.clone()),
+            None => None,
+        }
+    }
+
+    /// 查找底层用户 ID 相同的账号分组（不包含只有一个账号的分组）
+    /// Token 无法解析的账号单独归为一组
+    pub fn find_duplicate_groups(&self) -> Vec<Vec<String>> {
+        let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+
+        for account in &self.store.accounts {
+            let key = Self::resolve_user_id(account)
+                .unwrap_or_else(|| UNPARSEABLE_USER_ID.to_string());
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, ids)) => ids.push(account.id.clone()),
+                None => groups.push((key, vec![account.id.clone()])),
+            }
+        }
+
+        groups
+            .into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .map(|(_, ids)| ids)
+            .collect()
+    }
+
+    /// 查找底层用户 ID 相同但邮箱不同的账号分组（同一用户绑定了多个邮箱）
+    /// 只有邮箱相同的分组属于重复账号，由 find_duplicate_groups 处理；Token 无法解析的账号不参与分组
+    pub fn find_linked_accounts(&self) -> Vec<LinkedAccountGroup> {
+        let mut groups: Vec<LinkedAccountGroup> = Vec::new();
+
+        for account in &self.store.accounts {
+            let Some(user_id) = Self::resolve_user_id(account) else {
+                continue;
+            };
+            let index = match groups.iter().position(|g| g.user_id == user_id) {
+                Some(index) => index,
+                None => {
+                    groups.push(LinkedAccountGroup { user_id, account_ids: Vec::new(), emails: Vec::new() });
+                    groups.len() - 1
+                }
+            };
+            let group = &mut groups[index];
+            group.account_ids.push(account.id.clone());
+            if !group.emails.iter().any(|e| e.eq_ignore_ascii_case(&account.email)) {
+                group.emails.push(account.email.clone());
+            }
+        }
+
+        groups.into_iter().filter(|g| g.emails.len() > 1).collect()
+    }
+
+    /// 检查账号数据，列出缺少关键 Cookie 的账号（没有 Cookies 的账号不在此列）
+    pub fn check_store(&self) -> Vec<StoreIssue> {
+        self.store.accounts.iter().filter_map(|account| {
+            let missing = account.missing_critical_cookies();
+            if missing.is_empty() {
+                return None;
+            }
+            Some(StoreIssue {
+                account_id: account.id.clone(),
+                email: account.email.clone(),
+                missing_cookies: missing,
+            })
+        }).collect()
+    }
+
+    /// 汇总所有未隔离账号的问题（失效、缺少 Cookies、即将过期、状态未知），按严重程度排序
+    pub fn diagnose_all(&self) -> Vec<AccountProblem> {
+        let now = self.server_now();
+        let mut problems = Vec::new();
+        for account in self.store.accounts.iter().filter(|a| !a.quarantined) {
+            let mut push = |kind: ProblemKind, detail: String| problems.push(AccountProblem {
+                account_id: account.id.clone(),
+                email: account.email.clone(),
+                kind,
+                detail,
+            });
+            let status = account.effective_status(self.status_ttl);
+            let expired = status == AccountStatus::Expired || is_expired(account.earliest_expiry());
+            if expired {
+                push(ProblemKind::Expired, "凭证已失效，需要重新登录".to_string());
+            }
+            if account.cookies.trim().is_empty() {
+                push(ProblemKind::MissingCookies, "没有 Cookies".to_string());
+            } else {
+                let missing = account.missing_critical_cookies();
+                if !missing.is_empty() {
+                    push(ProblemKind::MissingCookies, format!("缺少关键 Cookie: {}", missing.join(", ")));
+                }
+            }
+            if let Some(divergence) = account.expiry_divergence().filter(|d| *d > EXPIRY_DIVERGENCE_THRESHOLD_SECS) {
+                push(
+                    ProblemKind::ExpiryMismatch,
+                    format!("Token 与会话 Cookie 的过期时间相差 {} 小时，建议重新登录", divergence / 3600),
+                );
+            }
+            if !expired && account.token_expired_at.is_some() && Self::is_token_expiring_soon(account, now) {
+                push(ProblemKind::ExpiringSoon, "Token 将在 1 小时内过期".to_string());
+            }
+            if !expired && status == AccountStatus::Unknown {
+                push(ProblemKind::Unknown, "尚未校验或校验结果已过时".to_string());
+            }
+        }
+        problems.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.email.cmp(&b.email)));
+        problems
+    }
+
+    /// 按 Token 声明查找账号；key 支持点号路径（如 data.tenant_id），值按字符串比较
+    /// Token 缺失或无法解析的账号直接跳过
+    pub fn find_by_claim(&mut self, key: &str, value: &str) -> Vec<AccountBrief> {
+        let key = key.trim();
+        let value = value.trim();
+        let current_id = self.store.current_account_id.clone();
+        let mut matches = Vec::new();
+
+        for account in &self.store.accounts {
+            let Some(token) = account.jwt_token.as_deref() else { continue };
+            let cached = self.claims_cache.get(&account.id).filter(|(t, _)| t == token);
+            let claims = match cached {
+                Some((_, claims)) => claims.clone(),
+                None => {
+                    let claims = TraeApiClient::decode_jwt_claims(token).ok();
+                    self.claims_cache.insert(account.id.clone(), (token.to_string(), claims.clone()));
+                    claims
+                }
+            };
+            let Some(claims) = claims else { continue };
+
+            let found = key.split('.').try_fold(&claims, |node, part| node.get(part));
+            let matched = match found {
+                Some(serde_json::Value::String(s)) => s == value,
+                Some(serde_json::Value::Number(n)) => n.to_string() == value,
+                Some(serde_json::Value::Bool(b)) => b.to_string() == value,
+                _ => false,
+            };
+            if matched {
+                let is_current = current_id.as_deref() == Some(account.id.as_str());
+                matches.push(AccountBrief::from_account(account, is_current));
+            }
+        }
+        matches
+    }
+
+    /// 获取活跃账号
+    pub fn get_active_account(&self) -> Option<&Account> {
+        self.store
+            .active_account_id
+            .as_ref()
+            .and_then(|id| self.store.accounts.iter().find(|a| &a.id == id))
+    }
+
+    /// 获取指定账号
+    pub fn get_account(&self, account_id: &str) -> Result<Account> {
+        self.store
+            .accounts
+            .iter()
+            .find(|a| a.id == account_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("账号不存在"))
+    }
+
+    /// 将所有账号的 Cookies 规范化为统一格式，返回被修改的账号数量（已规范的账号不受影响）
+    pub fn normalize_cookies(&mut self) -> Result<usize> {
+        self.ensure_writable()?;
+        let now = chrono::Utc::now().timestamp();
+        let mut changed = 0;
+        for account in self.store.accounts.iter_mut().filter(|a| !a.cookies.trim().is_empty()) {
+            let normalized = cookies::normalize_cookie_string(&account.cookies);
+            if normalized != account.cookies {
+                account.cookies = normalized;
+                account.record_cookie_diagnostic();
+                account.updated_at = now;
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            self.save_store()?;
+            println!("[INFO] 已规范化 {} 个账号的 Cookies", changed);
+        }
+        Ok(changed)
+    }
+
+    /// 去除账号 Cookies 中重复的同名 Cookie（保留最后一次出现的值），返回去掉的个数
+    pub fn dedup_account_cookies(&mut self, account_id: &str) -> Result<usize> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        let (deduped, removed) = cookies::dedup_cookie_string(&acc.cookies);
+        if removed > 0 {
+            acc.cookies = deduped;
+            acc.record_cookie_diagnostic();
+            acc.updated_at = chrono::Utc::now().timestamp();
+            self.save_store()?;
+            println!("[INFO] 已去除账号 {} 的 {} 个重复 Cookie", account_id, removed);
+        }
+        Ok(removed)
+    }
+
+    /// 记录浏览器登录捕获的 Cookie 分类
+    pub fn record_cookie_capture(&mut self, account_id: &str, capture: CookieCapture) -> Result<()> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        acc.cookie_capture = Some(capture);
+        self.save_store()
+    }
+
+    /// 账号 Cookies 中哪些是 HttpOnly、哪些脚本可读（按最近一次浏览器登录捕获时的记录）
+    pub fn cookie_capture_report(&self, account_id: &str) -> Result<CookieCaptureReport> {
+        let account = self.store.accounts.iter()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        let capture = account.cookie_capture.clone();
+        Ok(CookieCaptureReport {
+            account_id: account.id.clone(),
+            likely_incomplete: capture.as_ref().is_some_and(|c| c.http_only.is_empty()),
+            captured_at: capture.as_ref().map(|c| c.captured_at),
+            http_only: capture.as_ref().map(|c| c.http_only.clone()).unwrap_or_default(),
+            js_accessible: capture.map(|c| c.js_accessible).unwrap_or_default(),
+        })
+    }
+
+    /// 将账号的 Cookies 格式化为 `Cookie:` 请求头
+    pub fn cookie_header(&self, account_id: &str) -> Result<String> {
+        let account = self.get_account(account_id)?;
+        if account.cookies.trim().is_empty() {
+            return Err(anyhow!("该账号没有 Cookies"));
+        }
+        Ok(cookies::format_cookie_header(&account.cookies))
+    }
+
+    /// 列出账号 Cookies 中同名但值不同的 Cookie；这类 Cookie 写入浏览器时只有一个值会生效
+    pub fn report_cookie_conflicts(&self, account_id: &str) -> Result<Vec<CookieConflict>> {
+        Ok(cookies::find_conflicts(&self.get_account(account_id)?.cookies))
+    }
+
+    /// 站点迁移域名后，把写在旧域名下的 Cookie 改写到新域名（host-only Cookie 固定在旧域名），返回受影响的账号；
+    /// default_domain 为没有单独记录域名的 Cookie 所在的站点域名
+    pub fn remap_cookie_domain(&mut self, old_domain: &str, new_domain: &str, default_domain: &str) -> Result<Vec<CookieRemap>> {
+        self.ensure_writable()?;
+        let old = cookies::normalize_cookie_domain(old_domain).ok_or_else(|| anyhow!("旧域名无效: {}", old_domain))?;
+        let new = cookies::normalize_cookie_domain(new_domain).ok_or_else(|| anyhow!("新域名无效: {}", new_domain))?;
+        if old == new {
+            return Err(anyhow!("新旧域名相同"));
+        }
+        let default_domain = cookies::normalize_cookie_domain(default_domain).unwrap_or_default();
+
+        let now = chrono::Utc::now().timestamp();
+        let mut report = Vec::new();
+        for account in self.store.accounts.iter_mut() {
+            let mut remapped: Vec<String> = Vec::new();
+            let mut kept_host_only: Vec<String> = Vec::new();
+            for (name, _) in cookies::parse_cookie_pairs(&account.cookies) {
+                if remapped.contains(&name) || kept_host_only.contains(&name) {
+                    continue;
+                }
+                let current = account.cookie_domain(&name, &default_domain).to_string();
+                match cookies::remap_decision(&name, &current, &old) {
+                    cookies::RemapDecision::Unaffected => {}
+                    cookies::RemapDecision::Remap => {
+                        if new == default_domain {
+                            account.cookie_domains.remove(&name);
+                        } else {
+                            account.cookie_domains.insert(name.clone(), new.clone());
+                        }
+                        remapped.push(name);
+                    }
+                    cookies::RemapDecision::KeepHostOnly => {
+                        // 站点域名随后改为新域名时，未单独记录的 host-only Cookie 仍写在旧域名
+                        account.cookie_domains.insert(name.clone(), old.clone());
+                        kept_host_only.push(name);
+                    }
+                }
+            }
+            if remapped.is_empty() && kept_host_only.is_empty() {
+                continue;
+            }
+            account.updated_at = now;
+            report.push(CookieRemap {
+                account_id: account.id.clone(),
+                email: account.email.clone(),
+                remapped,
+                kept_host_only,
+            });
+        }
+        if !report.is_empty() {
+            self.save_store()?;
+            println!("[INFO] 已把 {} 个账号的 Cookies 从 {} 迁移到 {}", report.len(), old, new);
+        }
+        Ok(report)
+    }
+
+    /// 导出账号 Cookies 为浏览器可导入的格式（作用于 .trae.ai，按 30 天有效期导出）
+    pub fn export_browser_session(&self, account_id: &str, format: BrowserCookieFormat) -> Result<String> {
+        let account = self.get_account(account_id)?;
+        if account.cookies.trim().is_empty() {
+            return Err(anyhow!("该账号没有 Cookies"));
+        }
+        let expires_at = chrono::Utc::now().timestamp() + BROWSER_COOKIE_TTL_SECS;
+        // 按浏览器登录时记录的 HttpOnly Cookie 导出，没有记录时把关键 Cookie 视为 HttpOnly
+        let http_only = match &account.cookie_capture {
+            Some(capture) if !capture.http_only.is_empty() => capture.http_only.clone(),
+            _ => cookies::CRITICAL_COOKIES.iter().map(|name| name.to_string()).collect(),
+        };
+        Ok(match format {
+            BrowserCookieFormat::Netscape => {
+                cookies::format_netscape_cookies(&account.cookies, BROWSER_COOKIE_DOMAIN, expires_at, &http_only)
+            }
+            BrowserCookieFormat::Json => serde_json::to_string_pretty(&cookies::format_cookie_json(
+                &account.cookies,
+                BROWSER_COOKIE_DOMAIN,
+                expires_at,
+                &http_only,
+            ))?,
+        })
+    }
+
+    /// 获取账号使用量，并记录凭证状态
+    pub async fn get_account_usage(&mut self, account_id: &str) -> Result<UsageSummary> {
+        self.fetch_account_usage(account_id).await
+    }
+
+    /// 判断错误是否由凭证失效导致
+    fn is_auth_error(error: &anyhow::Error) -> bool {
+        let msg = error.to_string();
+        msg.contains("401") || msg.contains("403") || msg.contains("过期")
+    }
+
+    /// 记录自动校验得到的凭证状态（不保存）；手动设置的状态在保护期内不会被覆盖
+    fn record_status(&mut self, account_id: &str, status: AccountStatus) {
+        if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == account_id) {
+            let now = chrono::Utc::now().timestamp();
+            acc.last_checked = Some(now);
+            acc.record_health(status, now);
+            if !acc.is_status_manual() {
+                acc.status = status;
+                acc.status_override_until = None;
+            }
+        }
+    }
+
+    /// 账号最近几次校验的结果（从旧到新）
+    pub fn account_health_history(&self, account_id: &str) -> Result<Vec<HealthRecord>> {
+        self.store.accounts.iter()
+            .find(|a| a.id == account_id)
+            .map(|a| a.health_history.clone())
+            .ok_or_else(|| anyhow!("账号不存在"))
+    }
+
+    /// 启用或停用账号（停用的账号不参与批量刷新）
+    pub fn set_account_enabled(&mut self, account_id: &str, enabled: bool) -> Result<()> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        acc.is_active = enabled;
+        acc.updated_at = chrono::Utc::now().timestamp();
+        self.save_store()
+    }
+
+    /// 设置账号是否参与 Token 自动刷新
+    pub fn set_account_auto_refresh(&mut self, account_id: &str, auto_refresh: bool) -> Result<()> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        acc.auto_refresh = auto_refresh;
+        acc.updated_at = chrono::Utc::now().timestamp();
+        self.save_store()
+    }
 
-        // 如果提供了 Cookies，尝试获取更详细的用户信息
-        let (name, email, avatar_url) = if let Some(ref cookies_str) = cookies {
-            match self.get_user_info_with_cookies(cookies_str).await {
-                Ok(info) => (
-                    info.screen_name,
-                    info.non_plain_text_email.unwrap_or_default(),
-                    info.avatar_url,
-                ),
-                Err(_) => (
-                    user_info.screen_name.unwrap_or_else(|| format!("User_{}", &user_info.user_id[..8.min(user_info.user_id.len())])),
-                    user_info.email.unwrap_or_default(),
-                    user_info.avatar_url.unwrap_or_default(),
-                ),
-            }
-        } else {
-            (
-                user_info.screen_name.unwrap_or_else(|| format!("User_{}", &user_info.user_id[..8.min(user_info.user_id.len())])),
-                user_info.email.unwrap_or_default(),
-                user_info.avatar_url.unwrap_or_default(),
-            )
+    /// 设置账号的颜色标记（#rgb 或 #rrggbb，统一保存为小写 #rrggbb）；传入 None 或空字符串表示清除
+    pub fn set_account_color(&mut self, account_id: &str, color: Option<String>) -> Result<()> {
+        self.ensure_writable()?;
+        let color = match color.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+            Some(c) => Some(Self::normalize_color(c).ok_or_else(|| anyhow!("无效的颜色: {}，请使用 #rrggbb 格式", c))?),
+            None => None,
         };
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        acc.color = color;
+        acc.updated_at = chrono::Utc::now().timestamp();
+        self.save_store()
+    }
 
-        let mut account = Account::new(
-            name,
-            email,
-            cookies.unwrap_or_default(),
-            user_info.user_id.clone(),
-            user_info.tenant_id.clone(),
-        );
-
-        account.avatar_url = avatar_url;
-        account.jwt_token = Some(token);
-        account.token_expired_at = None;
+    /// 保存登录时捕获的请求头（只保留可复用的部分），返回保存的请求头名称
+    pub fn set_captured_headers(&mut self, account_id: &str, headers: &HashMap<String, String>) -> Result<Vec<String>> {
+        self.ensure_writable()?;
+        let headers = TraeApiClient::replayable_request_headers(headers);
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        let mut names: Vec<String> = headers.keys().cloned().collect();
+        names.sort();
+        acc.captured_headers = headers;
+        acc.updated_at = chrono::Utc::now().timestamp();
+        self.save_store()?;
+        Ok(names)
+    }
 
-        self.store.accounts.push(account.clone());
+    /// 账号保存的捕获请求头名称（不含值）
+    pub fn captured_header_names(&self, account_id: &str) -> Result<Vec<String>> {
+        let account = self.get_account(account_id)?;
+        let mut names: Vec<String> = account.captured_headers.keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
 
-        // 如果是第一个账号，设为活跃账号
-        if self.store.active_account_id.is_none() {
-            self.store.active_account_id = Some(account.id.clone());
-        }
+    /// 清除账号保存的捕获请求头
+    pub fn clear_captured_headers(&mut self, account_id: &str) -> Result<()> {
+        self.set_captured_headers(account_id, &HashMap::new()).map(|_| ())
+    }
 
-        self.save_store()?;
-        Ok(account)
+    /// 设置账号所属的环境（如 prod/staging），空值表示清除
+    pub fn set_account_environment(&mut self, account_id: &str, environment: Option<String>) -> Result<()> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        acc.environment = environment.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+        acc.updated_at = chrono::Utc::now().timestamp();
+        self.save_store()
     }
 
-    /// 使用 Cookies 获取用户信息
-    async fn get_user_info_with_cookies(&self, cookies: &str) -> Result<crate::api::UserInfoResult> {
-        let client = TraeApiClient::new(cookies)?;
-        client.get_user_info().await
+    /// 校验并规范化十六进制颜色
+    fn normalize_color(color: &str) -> Option<String> {
+        let hex = color.strip_prefix('#')?;
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let hex = match hex.len() {
+            3 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 => hex.to_string(),
+            _ => return None,
+        };
+        Some(format!("#{}", hex.to_ascii_lowercase()))
     }
 
-    /// 删除账号
-    pub fn remove_account(&mut self, account_id: &str) -> Result<()> {
-        let index = self
-            .store
-            .accounts
-            .iter()
-            .position(|a| a.id == account_id)
+    /// 手动设置账号状态；设为 Unknown 表示取消手动设置
+    pub fn set_account_status(&mut self, account_id: &str, status: AccountStatus) -> Result<()> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
             .ok_or_else(|| anyhow!("账号不存在"))?;
+        acc.status = status;
+        acc.status_override_until = match status {
+            AccountStatus::Unknown => None,
+            _ => Some(chrono::Utc::now().timestamp() + STATUS_OVERRIDE_GRACE_SECS),
+        };
+        self.save_store()
+    }
 
-        self.store.accounts.remove(index);
+    /// 把所有账号的状态重置为未知并清除校验时间和手动设置（不修改凭证），返回有变化的账号数量
+    pub fn reset_statuses(&mut self) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut reset = 0;
+        for acc in self.store.accounts.iter_mut() {
+            if acc.status == AccountStatus::Unknown && acc.last_checked.is_none() && acc.status_override_until.is_none() {
+                continue;
+            }
+            acc.status = AccountStatus::Unknown;
+            acc.status_override_until = None;
+            acc.last_checked = None;
+            reset += 1;
+        }
+        if reset > 0 {
+            self.save_store()?;
+        }
+        Ok(reset)
+    }
 
-        // 如果删除的是活跃账号，重置活跃账号
-        if self.store.active_account_id.as_deref() == Some(account_id) {
-            self.store.active_account_id = self.store.accounts.first().map(|a| a.id.clone());
+    /// 清理凭证已失效的账号，返回处理的账号数量；quarantine 为 true 时只隔离不删除
+    /// 当前 Trae IDE 正在使用的账号和手动设置了状态的账号不处理
+    pub fn purge_expired(&mut self, quarantine: bool) -> Result<usize> {
+        self.ensure_writable()?;
+        let current_id = self.store.current_account_id.clone();
+        let expired: Vec<String> = self.store.accounts.iter()
+            .filter(|a| !a.quarantined)
+            .filter(|a| current_id.as_deref() != Some(a.id.as_str()))
+            .filter(|a| !a.is_status_manual())
+            .filter(|a| a.effective_status(self.status_ttl) == AccountStatus::Expired)
+            .map(|a| a.id.clone())
+            .collect();
+        if expired.is_empty() {
+            return Ok(0);
         }
 
+        if quarantine {
+            let now = chrono::Utc::now().timestamp();
+            for acc in self.store.accounts.iter_mut().filter(|a| expired.contains(&a.id)) {
+                acc.quarantined = true;
+                acc.updated_at = now;
+            }
+            println!("[INFO] 已隔离 {} 个失效账号", expired.len());
+        } else {
+            self.store.accounts.retain(|a| !expired.contains(&a.id));
+            self.clear_primary_if_removed();
+            println!("[INFO] 已删除 {} 个失效账号", expired.len());
+        }
+        self.reset_active_if_hidden();
         self.save_store()?;
-        Ok(())
+        Ok(expired.len())
     }
 
-    /// 设置活跃账号
-    pub fn set_active_account(&mut self, account_id: &str) -> Result<()> {
-        if !self.store.accounts.iter().any(|a| a.id == account_id) {
-            return Err(anyhow!("账号不存在"));
+    /// 将账号移出隔离区
+    pub fn restore_from_quarantine(&mut self, account_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+        if !acc.quarantined {
+            return Err(anyhow!("该账号未被隔离"));
         }
+        acc.quarantined = false;
+        acc.updated_at = chrono::Utc::now().timestamp();
+        self.save_store()
+    }
 
-        self.store.active_account_id = Some(account_id.to_string());
-        self.save_store()?;
-        Ok(())
+    /// 删除所有已隔离的账号，返回删除的数量
+    pub fn empty_quarantine(&mut self) -> Result<usize> {
+        self.ensure_writable()?;
+        let before = self.store.accounts.len();
+        self.store.accounts.retain(|a| !a.quarantined);
+        let removed = before - self.store.accounts.len();
+        if removed > 0 {
+            self.reset_active_if_hidden();
+            self.clear_primary_if_removed();
+            self.save_store()?;
+        }
+        Ok(removed)
     }
 
-    /// 切换账号（设置活跃账号并将登录信息写入 Trae IDE）
-    pub fn switch_account(&mut self, account_id: &str) -> Result<()> {
-        // 检查是否已经是当前使用的账号
-        if self.store.current_account_id.as_deref() == Some(account_id) {
-            return Err(anyhow!("该账号已经是当前使用的账号"));
+    /// 活跃账号被删除或隔离时，重置为第一个未隔离的账号
+    fn reset_active_if_hidden(&mut self) {
+        let active_visible = self.store.active_account_id.as_deref().is_some_and(|id| {
+            self.store.accounts.iter().any(|a| a.id == id && !a.quarantined)
+        });
+        if !active_visible {
+            self.store.active_account_id = self.store.accounts.iter()
+                .find(|a| !a.quarantined)
+                .map(|a| a.id.clone());
         }
+    }
 
-        let account = self.store.accounts.iter()
-            .find(|a| a.id == account_id)
-            .ok_or_else(|| anyhow!("账号不存在"))?
-            .clone();
+    /// 给所有符合条件的账号添加标签，返回匹配的账号数量（已有该标签的账号不重复添加）
+    pub fn tag_where(&mut self, filter: &AccountFilter, tag: &str) -> Result<usize> {
+        self.ensure_writable()?;
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(anyhow!("标签不能为空"));
+        }
 
-        // 检查账号是否有有效的 Token
-        let token = account.jwt_token.as_ref()
-            .ok_or_else(|| anyhow!("账号没有有效的 Token，无法切换"))?;
+        let mut matched = 0;
+        let mut changed = false;
+        for account in self.store.accounts.iter_mut().filter(|a| filter.matches(a)) {
+            matched += 1;
+            if !account.tags.iter().any(|t| t == tag) {
+                account.tags.push(tag.to_string());
+                changed = true;
+            }
+        }
 
-        // 构建 Trae IDE 登录信息
-        let login_info = crate::machine::TraeLoginInfo {
-            token: token.clone(),
-            refresh_token: None, // 如果有 refresh token 可以在这里设置
-            user_id: account.user_id.clone(),
-            email: account.email.clone(),
-            username: account.name.clone(),
-            avatar_url: account.avatar_url.clone(),
-            host: String::new(), // 根据 region 自动选择
-            region: if account.region.is_empty() { "SG".to_string() } else { account.region.clone() },
-        };
+        if changed {
+            self.save_store()?;
+        }
+        Ok(matched)
+    }
 
-        // 切换 Trae IDE 到该账号（清除旧登录状态并写入新账号信息）
-        crate::machine::switch_trae_account(&login_info, account.machine_id.as_deref())?;
+    /// 为所有符合条件的账号设置代理（None 或空字符串表示清除），返回匹配的账号数量；代理地址只校验一次
+    pub fn set_proxy_where(&mut self, filter: &AccountFilter, proxy: Option<String>) -> Result<usize> {
+        self.ensure_writable()?;
+        let proxy = proxy.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+        if let Some(proxy) = &proxy {
+            crate::api::parse_proxy_url(proxy)?;
+        }
 
-        // 如果账号有绑定的机器码，也更新系统机器码
-        if let Some(machine_id) = &account.machine_id {
-            match crate::machine::set_machine_guid(machine_id) {
-                Ok(_) => println!("[INFO] 已切换系统机器码: {}", machine_id),
-                Err(e) => println!("[WARN] 切换系统机器码失败（可能需要管理员权限）: {}", e),
+        let now = chrono::Utc::now().timestamp();
+        let mut matched = 0;
+        let mut changed = false;
+        for account in self.store.accounts.iter_mut().filter(|a| filter.matches(a)) {
+            matched += 1;
+            if account.proxy != proxy {
+                account.proxy = proxy.clone();
+                account.updated_at = now;
+                changed = true;
             }
         }
 
-        // 设置活跃账号和当前使用的账号
-        self.store.active_account_id = Some(account_id.to_string());
-        self.store.current_account_id = Some(account_id.to_string());
-        self.save_store()?;
-
-        println!("[INFO] 已切换到账号: {}", account.email);
-        Ok(())
+        if changed {
+            self.save_store()?;
+        }
+        Ok(matched)
     }
 
-    /// 绑定当前系统机器码到账号
-    pub fn bind_machine_id(&mut self, account_id: &str) -> Result<String> {
-        // 获取当前系统机器码
-        let current_machine_id = crate::machine::get_machine_guid()?;
+    /// 给每个账号添加其邮箱域名标签，返回新添加标签的账号数量
+    ///
+    /// 可重复执行：已自动添加过的域名记录在 auto_tags 中，用户删除该标签后不会再被加回；
+    /// 邮箱无法解析出域名的账号跳过
+    pub fn auto_tag_by_domain(&mut self) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut tagged = 0;
+        let mut changed = false;
+        for account in self.store.accounts.iter_mut() {
+            let Some(domain) = account.email_domain() else { continue };
+            if account.auto_tags.contains(&domain) {
+                continue;
+            }
+            if !account.tags.contains(&domain) {
+                account.tags.push(domain.clone());
+                tagged += 1;
+            }
+            account.auto_tags.push(domain);
+            account.updated_at = chrono::Utc::now().timestamp();
+            changed = true;
+        }
+        if changed {
+            self.save_store()?;
+        }
+        Ok(tagged)
+    }
 
-        // 更新账号的机器码
+    /// 删除账号的一个标签（自动添加的标签删除后不会再被自动加回）
+    pub fn remove_tag(&mut self, account_id: &str, tag: &str) -> Result<()> {
+        self.ensure_writable()?;
         let account = self.store.accounts.iter_mut()
             .find(|a| a.id == account_id)
             .ok_or_else(|| anyhow!("账号不存在"))?;
-
-        account.machine_id = Some(current_machine_id.clone());
+        let before = account.tags.len();
+        account.tags.retain(|t| t != tag);
+        if account.tags.len() == before {
+            return Ok(());
+        }
         account.updated_at = chrono::Utc::now().timestamp();
-        let email = account.email.clone();
-
-        self.save_store()?;
-        println!("[INFO] 已绑定机器码 {} 到账号 {}", current_machine_id, email);
-
-        Ok(current_machine_id)
+        self.save_store()
     }
 
-    /// 获取所有账号列表
-    pub fn get_accounts(&self) -> Vec<AccountBrief> {
-        let current_id = self.store.current_account_id.as_deref();
-        self.store.accounts.iter().map(|account| {
-            let is_current = current_id == Some(account.id.as_str());
-            AccountBrief::from_account(account, is_current)
-        }).collect()
+    /// 估算每个账号在数据文件中的占用（与 save_store 相同的格式序列化），按占用从大到小排列
+    pub fn account_sizes(&self) -> Result<Vec<AccountSize>> {
+        let mut sizes = Vec::with_capacity(self.store.accounts.len());
+        for account in &self.store.accounts {
+            let total = serde_json::to_string_pretty(account)?.len();
+            let token = account.jwt_token.as_deref().map_or(0, str::len)
+                + account.refresh_token.as_deref().map_or(0, str::len);
+            sizes.push(AccountSize {
+                account_id: account.id.clone(),
+                email: account.email.clone(),
+                total_bytes: total as u64,
+                token_bytes: token as u64,
+                cookies_bytes: account.cookies.len() as u64,
+            });
+        }
+        sizes.sort_by_key(|size| std::cmp::Reverse(size.total_bytes));
+        Ok(sizes)
     }
 
-    /// 获取活跃账号
-    pub fn get_active_account(&self) -> Option<&Account> {
-        self.store
-            .active_account_id
-            .as_ref()
-            .and_then(|id| self.store.accounts.iter().find(|a| &a.id == id))
+    /// 按缓存的状态统计账号数量
+    pub fn account_stats(&self) -> AccountStats {
+        let mut stats = AccountStats {
+            total: self.store.accounts.len(),
+            ..Default::default()
+        };
+        for account in &self.store.accounts {
+            match account.effective_status(self.status_ttl) {
+                AccountStatus::Valid => stats.valid += 1,
+                AccountStatus::Expired => stats.expired += 1,
+                AccountStatus::Unknown => stats.unknown += 1,
+            }
+            if account.needs_login() {
+                stats.needs_login += 1;
+            }
+        }
+        stats
     }
 
-    /// 获取指定账号
-    pub fn get_account(&self, account_id: &str) -> Result<Account> {
-        self.store
-            .accounts
-            .iter()
-            .find(|a| a.id == account_id)
-            .cloned()
-            .ok_or_else(|| anyhow!("账号不存在"))
+    async fn fetch_account_usage(&mut self, account_id: &str) -> Result<UsageSummary> {
+        let account = self.usage_target(account_id)?;
+        let fetched = Self::fetch_usage_for(&account).await;
+        self.record_fetched_usage(account_id, fetched)
     }
 
-    /// 获取账号使用量
-    pub async fn get_account_usage(&mut self, account_id: &str) -> Result<UsageSummary> {
-        let account = self
-            .store
-            .accounts
-            .iter()
-            .find(|a| a.id == account_id)
-            .ok_or_else(|| anyhow!("账号不存在"))?
-            .clone();
+    /// 查询使用量前的准备：确认结果可以保存并复制账号，调用方可在释放锁后再发起请求
+    pub fn usage_target(&self, account_id: &str) -> Result<Account> {
+        self.ensure_writable()?;
+        self.get_account(account_id)
+    }
 
+    /// 查询账号使用量，不访问账号存储；Token 过期时用 Cookies 换取新 Token，新 Token 随结果返回
+    pub async fn fetch_usage_for(account: &Account) -> Result<(UsageSummary, Option<UserTokenResult>)> {
         // 根据账号类型选择不同的方式获取使用量
-        let summary = if let Some(token) = &account.jwt_token {
+        if let Some(token) = &account.jwt_token {
             // 优先使用 Token
-            let client = TraeApiClient::new_with_token(token)?;
+            let client = Self::token_client_for(account, token)?;
             match client.get_usage_summary_by_token().await {
-                Ok(summary) => summary,
+                Ok(summary) => Ok((summary, None)),
                 Err(e) => {
                     let error_msg = e.to_string();
                     // 如果是 401 错误且有 Cookies，尝试刷新 Token
                     if error_msg.contains("401") && !account.cookies.is_empty() {
                         println!("[INFO] Token 已过期，尝试使用 Cookies 刷新...");
                         // 使用 Cookies 刷新 Token
-                        let mut cookie_client = TraeApiClient::new(&account.cookies)?;
+                        let mut cookie_client = Self::cookie_client_for(account, &account.cookies)?;
                         let token_result = cookie_client.get_user_token().await?;
 
-                        // 更新存储的 Token
-                        if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == account_id) {
-                            acc.jwt_token = Some(token_result.token.clone());
-                            acc.token_expired_at = Some(token_result.expired_at.clone());
-                        }
-                        self.save_store()?;
-
                         // 使用新 Token 重新获取使用量
-                        let new_client = TraeApiClient::new_with_token(&token_result.token)?;
-                        new_client.get_usage_summary_by_token().await?
+                        let new_client = Self::token_client_for(account, &token_result.token)?;
+                        let summary = new_client.get_usage_summary_by_token().await?;
+                        Ok((summary, Some(token_result)))
                     } else if error_msg.contains("401") {
-                        return Err(anyhow!("Token 已过期，请更新 Token 或 Cookies"));
+                        Err(anyhow!("Token 已过期，请更新 Token 或 Cookies"))
                     } else {
-                        return Err(e);
+                        Err(e)
                     }
                 }
             }
         } else if !account.cookies.is_empty() {
             // 使用 Cookies
-            let mut client = TraeApiClient::new(&account.cookies)?;
-            client.get_usage_summary().await?
+            let mut client = Self::cookie_client_for(account, &account.cookies)?;
+            Ok((client.get_usage_summary().await?, None))
         } else {
-            return Err(anyhow!("账号没有有效的 Token 或 Cookies"));
+            Err(anyhow!("账号没有有效的 Token 或 Cookies"))
+        }
+    }
+
+    /// 保存 fetch_usage_for 的结果：成功时更新新 Token、套餐类型并标记有效，凭证失效时标记过期
+    pub fn record_fetched_usage(
+        &mut self,
+        account_id: &str,
+        fetched: Result<(UsageSummary, Option<UserTokenResult>)>,
+    ) -> Result<UsageSummary> {
+        self.ensure_writable()?;
+        let (summary, token_result) = match fetched {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                // 网络等其他错误无法判断凭证状态，不做记录
+                if Self::is_auth_error(&e) {
+                    self.record_status(account_id, AccountStatus::Expired);
+                    if let Err(save_err) = self.save_store() {
+                        println!("[WARN] 保存账号状态失败: {}", save_err);
+                    }
+                }
+                return Err(e);
+            }
         };
 
-        // 更新账号的 plan_type
         if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == account_id) {
+            // 更新存储的 Token
+            if let Some(token_result) = token_result {
+                acc.jwt_token = Some(token_result.token);
+                acc.token_expired_at = Some(token_result.expired_at);
+                acc.update_refresh_token(token_result.refresh_token);
+            }
+            // 更新账号的 plan_type
             acc.plan_type = summary.plan_type.clone();
             acc.updated_at = chrono::Utc::now().timestamp();
         }
+        self.record_status(account_id, AccountStatus::Valid);
         self.save_store()?;
 
         Ok(summary)
@@ -349,6 +2044,7 @@ impl AccountManager {
 
     /// 刷新账号 Token
     pub async fn refresh_token(&mut self, account_id: &str) -> Result<()> {
+        self.ensure_writable()?;
         let account = self
             .store
             .accounts
@@ -356,15 +2052,20 @@ impl AccountManager {
             .find(|a| a.id == account_id)
             .ok_or_else(|| anyhow!("账号不存在"))?
             .clone();
+        if account.token_only {
+            return Err(anyhow!("该账号只含分享的 Token，无法刷新，Token 过期后需要重新获取"));
+        }
 
-        let mut client = TraeApiClient::new(&account.cookies)?;
+        let mut client = Self::refresh_client_for(&account)?;
         let token_result = client.get_user_token().await?;
 
         if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == account_id) {
             acc.jwt_token = Some(token_result.token);
             acc.token_expired_at = Some(token_result.expired_at);
+            acc.update_refresh_token(token_result.refresh_token);
             acc.updated_at = chrono::Utc::now().timestamp();
         }
+        self.record_status(account_id, AccountStatus::Valid);
 
         self.save_store()?;
         Ok(())
@@ -372,7 +2073,8 @@ impl AccountManager {
 
     /// 更新账号 Token
     pub async fn update_account_token(&mut self, account_id: &str, token: String) -> Result<UsageSummary> {
-        let client = TraeApiClient::new_with_token(&token)?;
+        self.ensure_writable()?;
+        let client = Self::token_client_for(&self.get_account(account_id)?, &token)?;
 
         // 验证 Token 并获取用户信息
         let user_info = client.get_user_info_by_token().await?;
@@ -394,6 +2096,10 @@ impl AccountManager {
         // 获取最新使用量
         let summary = client.get_usage_summary_by_token().await?;
         acc.plan_type = summary.plan_type.clone();
+        acc.status = AccountStatus::Valid;
+        acc.status_override_until = None;
+        acc.last_checked = Some(acc.updated_at);
+        acc.record_health(AccountStatus::Valid, acc.updated_at);
 
         self.save_store()?;
         Ok(summary)
@@ -401,8 +2107,9 @@ impl AccountManager {
 
     /// 更新账号 Cookies
     pub async fn update_cookies(&mut self, account_id: &str, cookies: String) -> Result<()> {
+        self.ensure_writable()?;
         // 验证新 cookies 是否有效
-        let mut client = TraeApiClient::new(&cookies)?;
+        let mut client = Self::cookie_client_for(&self.get_account(account_id)?, &cookies)?;
         let token_result = client.get_user_token().await?;
 
         if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == account_id) {
@@ -412,9 +2119,15 @@ impl AccountManager {
             }
 
             acc.cookies = cookies;
+            acc.record_cookie_diagnostic();
             acc.jwt_token = Some(token_result.token);
             acc.token_expired_at = Some(token_result.expired_at);
+            acc.update_refresh_token(token_result.refresh_token);
             acc.updated_at = chrono::Utc::now().timestamp();
+            acc.status = AccountStatus::Valid;
+            acc.status_override_until = None;
+            acc.last_checked = Some(acc.updated_at);
+            acc.record_health(AccountStatus::Valid, acc.updated_at);
         } else {
             return Err(anyhow!("账号不存在"));
         }
@@ -423,10 +2136,116 @@ impl AccountManager {
         Ok(())
     }
 
+    /// 从 trae.ai 重新获取账号邮箱，有变化时更新并返回新邮箱
+    pub async fn refresh_account_email(&mut self, account_id: &str) -> Result<Option<String>> {
+        self.ensure_writable()?;
+        let account = self.get_account(account_id)?;
+
+        let (user_id, email) = if !account.cookies.is_empty() {
+            let info = Self::cookie_client_for(&account, &account.cookies)?.get_user_info().await?;
+            (info.user_id, info.non_plain_text_email.unwrap_or_default())
+        } else if let Some(token) = &account.jwt_token {
+            let info = Self::token_client_for(&account, token)?.get_user_info_by_token().await?;
+            (info.user_id, info.email.unwrap_or_default())
+        } else {
+            return Err(anyhow!("账号没有有效的 Token 或 Cookies"));
+        };
+
+        // 账号以用户 ID 去重，邮箱变化不影响归属；但返回的用户不同则不能更新
+        if user_id != account.user_id {
+            return Err(anyhow!("凭证对应的用户与当前账号不匹配"));
+        }
+        let email = email.trim().to_string();
+        if email.is_empty() || email == account.email {
+            return Ok(None);
+        }
+
+        if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == account_id) {
+            println!("[INFO] 账号邮箱已更新: {} -> {}", acc.email, email);
+            acc.email = email.clone();
+            acc.updated_at = chrono::Utc::now().timestamp();
+        }
+        self.save_store()?;
+        Ok(Some(email))
+    }
+
+    /// 将新的 Cookies 合并到账号已有的 Cookies 中（同名覆盖，其余保留）
+    pub fn merge_cookies(&mut self, account_id: &str, cookies: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let acc = self.store.accounts.iter_mut()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| anyhow!("账号不存在"))?;
+
+        let mut pairs = cookies::parse_cookie_pairs(&acc.cookies);
+        let mut changed = false;
+        for (name, value) in cookies::parse_cookie_pairs(cookies) {
+            match pairs.iter_mut().find(|(n, _)| *n == name) {
+                Some(existing) if existing.1 == value => {}
+                Some(existing) => {
+                    existing.1 = value;
+                    changed = true;
+                }
+                None => {
+                    pairs.push((name, value));
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            acc.cookies = cookies::join_cookie_pairs(&pairs);
+            acc.record_cookie_diagnostic();
+            acc.updated_at = chrono::Utc::now().timestamp();
+            self.save_store()?;
+        }
+        Ok(())
+    }
+
     /// 导出账号数据
     pub fn export_accounts(&self) -> Result<String> {
-        let export_data: Vec<serde_json::Value> = self.store.accounts.iter().map(|acc| {
+        Self::export_entries(self.store.accounts.iter())
+    }
+
+    /// 只导出账号的访问 Token（不含 Cookies 和刷新凭证），用备份密码加密，供他人在 Token 过期前临时使用
+    ///
+    /// 内容与 export_accounts 格式相同，条目带有 token_only 标记，import_accounts 据此导入为不可刷新的账号
+    pub fn export_token_only(&self, account_id: &str, passphrase: &str) -> Result<Vec<u8>> {
+        if passphrase.is_empty() {
+            return Err(anyhow!("密码不能为空"));
+        }
+        let account = self.get_account(account_id)?;
+        let token = account.jwt_token.as_deref().ok_or_else(|| anyhow!("该账号没有 Token"))?;
+        let data = serde_json::to_string_pretty(&serde_json::json!([{
+            "token_only": true,
+            "name": account.name,
+            "email": account.email,
+            "user_id": account.user_id,
+            "jwt_token": token,
+            "token_expired_at": account.token_expired_at,
+        }]))?;
+        crate::backup::encrypt(data.as_bytes(), passphrase)
+    }
+
+    /// 只导出带有指定标签的账号（格式与 export_accounts 相同），没有匹配的账号时报错
+    pub fn export_by_tag(&self, tag: &str) -> Result<String> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(anyhow!("标签不能为空"));
+        }
+        let accounts: Vec<&Account> = self.store.accounts.iter()
+            .filter(|a| a.tags.iter().any(|t| t == tag))
+            .collect();
+        if accounts.is_empty() {
+            return Err(anyhow!("没有带有标签「{}」的账号", tag));
+        }
+        Self::export_entries(accounts.into_iter())
+    }
+
+    /// 将账号序列化为可通过 import_accounts 导入的 JSON
+    fn export_entries<'a>(accounts: impl Iterator<Item = &'a Account>) -> Result<String> {
+        let export_data: Vec<serde_json::Value> = accounts.map(|acc| {
             serde_json::json!({
+                "id": acc.id,
                 "name": acc.name,
                 "email": acc.email,
                 "cookies": acc.cookies,
@@ -438,44 +2257,313 @@ impl AccountManager {
                 "jwt_token": acc.jwt_token,
                 "machine_id": acc.machine_id,
             })
-        }).collect();
+        }).collect();
+
+        serde_json::to_string_pretty(&export_data)
+            .map_err(|e| anyhow!("导出失败: {}", e))
+    }
+
+    /// 导入条目凭证（Token 与 Cookies）的内容哈希，用于识别未变化的重复导入
+    fn import_hash(jwt_token: Option<&str>, cookies: &str) -> String {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        jwt_token.unwrap_or("").hash(&mut hasher);
+        cookies.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// 导入条目对应的用户 ID：优先取 user_id 字段，否则从 Token 中解析；都没有时为空
+    fn import_user_id(item: &serde_json::Value) -> String {
+        match item.get("user_id").and_then(|v| v.as_str()) {
+            Some(id) if !id.is_empty() => id.to_string(),
+            _ => item.get("jwt_token").and_then(|v| v.as_str())
+                .and_then(|t| TraeApiClient::parse_jwt_token(t).ok())
+                .map(|p| p.user_id)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// 找出导入数据中指向同一用户的多个条目
+    fn import_duplicates(items: &[serde_json::Value]) -> Vec<ImportDuplicate> {
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let user_id = Self::import_user_id(item);
+            if user_id.is_empty() {
+                continue;
+            }
+            match groups.iter_mut().find(|(id, _)| *id == user_id) {
+                Some((_, indices)) => indices.push(index),
+                None => groups.push((user_id, vec![index])),
+            }
+        }
+
+        groups.into_iter()
+            .filter(|(_, indices)| indices.len() > 1)
+            .map(|(user_id, indices)| {
+                let issued_at = |index: usize| items[index].get("jwt_token").and_then(|v| v.as_str())
+                    .and_then(|t| TraeApiClient::parse_jwt_token(t).ok())
+                    .map(|p| p.issued_at);
+                let kept = indices.iter().copied().max_by_key(|&i| (issued_at(i), i)).unwrap_or(indices[0]);
+                let emails = indices.iter()
+                    .map(|&i| items[i].get("email").and_then(|v| v.as_str()).unwrap_or("").to_string())
+                    .collect();
+                ImportDuplicate { user_id, indices, kept, emails }
+            })
+            .collect()
+    }
 
-        serde_json::to_string_pretty(&export_data)
-            .map_err(|e| anyhow!("导出失败: {}", e))
+    /// 导入前预检：列出导入数据中指向同一用户的重复条目，不修改本地数据
+    pub fn find_import_duplicates(&self, data: &str) -> Result<Vec<ImportDuplicate>> {
+        let import_data: Vec<serde_json::Value> = serde_json::from_str(data)
+            .map_err(|e| anyhow!("JSON 解析失败: {}", e))?;
+        Ok(Self::import_duplicates(&import_data))
     }
 
-    /// 导入账号数据
-    pub async fn import_accounts(&mut self, data: &str) -> Result<usize> {
+    /// 导入账号数据（导入文件中同一用户的多个条目先合并为一个，避免一次导入中重复更新）
+    ///
+    /// preserve_ids 为 true 时新增的账号沿用条目中的 id；该 id 无效或已被占用时改用派生 ID，并在结果中记录
+    pub async fn import_accounts(&mut self, data: &str, strategy: ImportStrategy, preserve_ids: bool) -> Result<Vec<ImportRecord>> {
+        self.ensure_writable()?;
         let import_data: Vec<serde_json::Value> = serde_json::from_str(data)
             .map_err(|e| anyhow!("JSON 解析失败: {}", e))?;
 
-        let mut imported_count = 0;
+        let merged: HashMap<usize, String> = Self::import_duplicates(&import_data)
+            .into_iter()
+            .flat_map(|dup| {
+                let kept = dup.kept;
+                dup.indices.into_iter().filter(move |&i| i != kept).map(move |i| (i, dup.user_id.clone()))
+            })
+            .collect();
+        if !merged.is_empty() {
+            println!("[INFO] 导入文件中有 {} 个重复条目，已合并", merged.len());
+        }
+
+        let mut records = Vec::new();
+
+        for (index, item) in import_data.into_iter().enumerate() {
+            if let Some(user_id) = merged.get(&index) {
+                records.push(ImportRecord {
+                    user_id: user_id.clone(),
+                    email: item.get("email").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                    action: ImportAction::Duplicate,
+                    message: Some("导入文件中有该用户的其他条目，已合并".to_string()),
+                    id_remap: None,
+                });
+                continue;
+            }
+
+            let field = |key: &str| item.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+            let cookies = field("cookies");
+            let email = field("email");
+            let jwt_token = item.get("jwt_token").and_then(|v| v.as_str()).map(|s| s.to_string());
+            let token_only = item.get("token_only").and_then(|v| v.as_bool()).unwrap_or(false);
+
+            // 凭证与上次导入完全相同的条目直接跳过，不解析 Token、不写入
+            let import_hash = Self::import_hash(jwt_token.as_deref(), &cookies);
+            if let Some(acc) = self.store.accounts.iter().find(|a| a.last_import_hash.as_deref() == Some(import_hash.as_str())) {
+                records.push(ImportRecord {
+                    user_id: acc.user_id.clone(),
+                    email,
+                    action: ImportAction::Skipped,
+                    message: Some("与上次导入的内容相同".to_string()),
+                    id_remap: None,
+                });
+                continue;
+            }
 
-        for item in import_data {
-            let cookies = item.get("cookies")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
+            let incoming_jwt = jwt_token.as_deref().and_then(|t| TraeApiClient::parse_jwt_token(t).ok());
+            let user_id = Self::import_user_id(&item);
 
-            if cookies.is_empty() {
+            let existing_index = if user_id.is_empty() {
+                None
+            } else {
+                self.store.accounts.iter().position(|a| a.user_id == user_id)
+            };
+
+            // 只含 Token 的分享条目不能覆盖本地已有的完整账号
+            if let (true, Some(_)) = (token_only, existing_index) {
+                records.push(ImportRecord {
+                    user_id,
+                    email,
+                    action: ImportAction::Skipped,
+                    message: Some("本地已有该账号，不导入只含 Token 的副本".to_string()),
+                    id_remap: None,
+                });
                 continue;
             }
 
-            // 尝试通过 cookies 添加账号
-            match self.add_account(cookies).await {
-                Ok(_) => {
-                    imported_count += 1;
+            if let Some(index) = existing_index {
+                let existing_iat = self.store.accounts[index].jwt_token.as_deref()
+                    .and_then(|t| TraeApiClient::parse_jwt_token(t).ok())
+                    .map(|p| p.issued_at);
+                if let Err(reason) = strategy.resolve(incoming_jwt.as_ref().map(|p| p.issued_at), existing_iat) {
+                    records.push(ImportRecord {
+                        user_id,
+                        email,
+                        action: ImportAction::Skipped,
+                        message: Some(reason.to_string()),
+                        id_remap: None,
+                    });
+                    continue;
                 }
-                Err(e) => {
-                    // 如果是"账号已存在"错误，跳过
-                    if !e.to_string().contains("已存在") {
-                        println!("[WARN] 导入账号失败: {}", e);
+
+                let acc = &mut self.store.accounts[index];
+                for (key, target) in [
+                    ("name", &mut acc.name),
+                    ("email", &mut acc.email),
+                    ("avatar_url", &mut acc.avatar_url),
+                    ("region", &mut acc.region),
+                    ("plan_type", &mut acc.plan_type),
+                ] {
+                    let value = field(key);
+                    if !value.is_empty() {
+                        *target = value;
+                    }
+                }
+                acc.cookies = cookies;
+                acc.record_cookie_diagnostic();
+                if jwt_token.is_some() {
+                    acc.jwt_token = jwt_token;
+                    acc.token_expired_at = None;
+                }
+                acc.update_refresh_token(Some(field("refresh_token")));
+                if let Some(machine_id) = item.get("machine_id").and_then(|v| v.as_str()) {
+                    acc.machine_id = Some(machine_id.to_string());
+                }
+                acc.last_import_hash = Some(import_hash);
+                acc.updated_at = chrono::Utc::now().timestamp();
+                self.save_store()?;
+
+                records.push(ImportRecord { user_id, email, action: ImportAction::Overwritten, message: None, id_remap: None });
+                continue;
+            }
+
+            // 优先通过 cookies 添加账号，只有 Token 时通过 Token 添加
+            let added = match (cookies.is_empty(), jwt_token) {
+                (false, _) => self.add_account(cookies).await,
+                (true, Some(token)) => self.add_account_by_token(token, None).await,
+                (true, None) => {
+                    records.push(ImportRecord {
+                        user_id,
+                        email,
+                        action: ImportAction::Skipped,
+                        message: Some("缺少 Cookies 或 Token".to_string()),
+                        id_remap: None,
+                    });
+                    continue;
+                }
+            };
+
+            match added {
+                Ok(account) => {
+                    if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == account.id) {
+                        acc.last_import_hash = Some(import_hash);
+                        if token_only {
+                            acc.token_only = true;
+                            acc.auto_refresh = false;
+                            if let Some(expired_at) = item.get("token_expired_at").and_then(|v| v.as_str()) {
+                                acc.token_expired_at = Some(expired_at.to_string());
+                            }
+                        }
+                        self.save_store()?;
                     }
+                    let id_remap = match field("id") {
+                        source_id if preserve_ids && !source_id.is_empty() && source_id != account.id => {
+                            self.preserve_source_id(&account.id, &source_id)
+                        }
+                        _ => None,
+                    };
+                    records.push(ImportRecord {
+                        user_id: account.user_id,
+                        email: account.email,
+                        action: ImportAction::Added,
+                        message: id_remap.as_ref().map(|remap| format!("源 ID {} 已被占用或无效，改用 {}", remap.source_id, remap.new_id)),
+                        id_remap,
+                    });
+                }
+                Err(e) => {
+                    println!("[WARN] 导入账号失败: {}", e);
+                    records.push(ImportRecord {
+                        user_id,
+                        email,
+                        action: ImportAction::Failed,
+                        message: Some(e.to_string()),
+                        id_remap: None,
+                    });
                 }
             }
         }
 
-        Ok(imported_count)
+        Ok(records)
+    }
+
+    /// 把刚导入账号的 ID 改为源 ID；无法沿用时返回改用派生 ID 的记录
+    fn preserve_source_id(&mut self, new_id: &str, source_id: &str) -> Option<IdRemap> {
+        match self.reassign_account_id(new_id, source_id) {
+            Ok(()) => None,
+            Err(e) => {
+                println!("[INFO] 无法沿用源 ID {}（{}），保留派生 ID {}", source_id, e, new_id);
+                Some(IdRemap { source_id: source_id.to_string(), new_id: new_id.to_string() })
+            }
+        }
+    }
+
+    /// 从其他工具导出的 JSON 文件导入账号，按字段映射转换后沿用常规导入逻辑（已存在的账号跳过）
+    pub async fn import_foreign_json(&mut self, path: &str, mapping: Option<ForeignMapping>) -> Result<Vec<ImportRecord>> {
+        let content = fs::read_to_string(path).map_err(|e| anyhow!("读取文件失败: {}", e))?;
+        let items = foreign::normalize(&content, &mapping.unwrap_or_default())?;
+        let data = serde_json::to_string(&items)?;
+        self.import_accounts(&data, ImportStrategy::Skip, false).await
+    }
+
+    /// 账号清单（仅元数据）
+    pub fn manifest(&self) -> Vec<ManifestEntry> {
+        self.store.accounts.iter().map(ManifestEntry::from).collect()
+    }
+
+    /// 导出账号清单（仅元数据，可安全分享）
+    pub fn export_manifest(&self) -> Result<String> {
+        serde_json::to_string_pretty(&self.manifest())
+            .map_err(|e| anyhow!("导出清单失败: {}", e))
+    }
+
+    /// 导入账号清单，为尚不存在的账号创建需要登录的占位条目
+    pub fn import_manifest(&mut self, data: &str) -> Result<usize> {
+        self.ensure_writable()?;
+        let manifest: Vec<ManifestEntry> = serde_json::from_str(data)
+            .map_err(|e| anyhow!("清单解析失败: {}", e))?;
+
+        let mut created = 0;
+        for entry in manifest {
+            let exists = self.store.accounts.iter().any(|a| {
+                a.id == entry.id || (!entry.user_id.is_empty() && a.user_id == entry.user_id)
+            });
+            if exists {
+                continue;
+            }
+            if let Err(e) = self.ensure_capacity() {
+                println!("[WARN] 清单导入提前结束: {}", e);
+                break;
+            }
+
+            let mut account = Account::new(
+                entry.name,
+                entry.email,
+                String::new(),
+                entry.user_id,
+                String::new(),
+            );
+            account.id = entry.id;
+            account.plan_type = entry.plan_type;
+            account.created_at = entry.created_at;
+            self.store.accounts.push(account);
+            created += 1;
+        }
+
+        if created > 0 {
+            self.save_store()?;
+        }
+        Ok(created)
     }
 
     /// 获取使用事件
@@ -498,7 +2586,7 @@ impl AccountManager {
         // 根据账号类型选择不同的方式调用 API
         if let Some(token) = &account.jwt_token {
             // 优先使用 Token
-            let client = TraeApiClient::new_with_token(token)?;
+            let client = Self::token_client_for(&account, token)?;
             match client.query_usage(start_time, end_time, page_size, page_num).await {
                 Ok(response) => Ok(response),
                 Err(e) => {
@@ -507,18 +2595,20 @@ impl AccountManager {
                     if error_msg.contains("401") && !account.cookies.is_empty() {
                         println!("[INFO] Token 已过期，尝试使用 Cookies 刷新...");
                         // 使用 Cookies 刷新 Token
-                        let mut cookie_client = TraeApiClient::new(&account.cookies)?;
+                        let mut cookie_client = Self::cookie_client_for(&account, &account.cookies)?;
                         let token_result = cookie_client.get_user_token().await?;
 
-                        // 更新存储的 Token
+                        // 更新存储的 Token（只读模式下无法保存，不修改内存中的数据）
+                        self.ensure_writable()?;
                         if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == account_id) {
                             acc.jwt_token = Some(token_result.token.clone());
                             acc.token_expired_at = Some(token_result.expired_at.clone());
+                            acc.update_refresh_token(token_result.refresh_token.clone());
                         }
                         self.save_store()?;
 
                         // 使用新 Token 重新查询
-                        let new_client = TraeApiClient::new_with_token(&token_result.token)?;
+                        let new_client = Self::token_client_for(&account, &token_result.token)?;
                         new_client.query_usage(start_time, end_time, page_size, page_num).await
                     } else if error_msg.contains("401") {
                         Err(anyhow!("Token 已过期，请更新 Token 或 Cookies"))
@@ -529,7 +2619,7 @@ impl AccountManager {
             }
         } else if !account.cookies.is_empty() {
             // 使用 Cookies
-            let mut client = TraeApiClient::new(&account.cookies)?;
+            let mut client = Self::cookie_client_for(&account, &account.cookies)?;
             // 先获取 token
             client.get_user_token().await?;
             client.query_usage(start_time, end_time, page_size, page_num).await
@@ -540,6 +2630,7 @@ impl AccountManager {
 
     /// 从 Trae IDE 读取当前登录账号
     pub async fn read_trae_ide_account(&mut self) -> Result<Option<Account>> {
+        self.ensure_writable()?;
         // 获取 Trae IDE 配置文件路径（跨平台支持）
         #[cfg(target_os = "windows")]
         let trae_data_path = {
@@ -598,6 +2689,11 @@ impl AccountManager {
             .ok_or_else(|| anyhow!("未找到 Token"))?
             .to_string();
 
+        let refresh_token = auth_info
+            .get("refreshToken")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
         let user_id = auth_info
             .get("userId")
             .and_then(|v| v.as_str())
@@ -630,6 +2726,7 @@ impl AccountManager {
             println!("[INFO] Trae IDE 账号已存在于账号管理中");
             return Ok(None);
         }
+        self.ensure_capacity()?;
 
         // 使用 Token 获取完整的用户信息
         let client = TraeApiClient::new_with_token(&token)?;
@@ -658,6 +2755,8 @@ impl AccountManager {
             avatar_url
         };
         account.jwt_token = Some(token);
+        account.update_refresh_token(refresh_token);
+        self.apply_new_account_defaults(&mut account);
 
         // 添加到账号列表
         self.store.accounts.push(account.clone());
@@ -673,22 +2772,30 @@ impl AccountManager {
         Ok(Some(account))
     }
 
-    /// 判断账号的 Token 是否即将过期（< 1小时）或已过期
-    fn is_token_expiring_soon(account: &Account) -> bool {
+    /// 校正本机时钟偏差后的当前时间（以 trae.ai 服务器时间为准）
+    fn server_now(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc::now() + chrono::Duration::seconds(self.clock_offset_secs)
+    }
+
+    /// 设置本机时钟相对服务器的偏差（秒，服务器时间 - 本机时间）
+    pub fn set_clock_offset(&mut self, offset_secs: i64) {
+        self.clock_offset_secs = offset_secs;
+    }
+
+    /// 判断账号的 Token 在 now 时刻是否即将过期（< 1小时）或已过期
+    fn is_token_expiring_soon(account: &Account, now: chrono::DateTime<chrono::Utc>) -> bool {
         match &account.token_expired_at {
             None => true, // 无过期时间信息，需要刷新
             Some(expired_at) => {
                 match chrono::DateTime::parse_from_rfc3339(expired_at) {
                     Ok(expiry) => {
-                        let now = chrono::Utc::now();
                         let one_hour = chrono::Duration::hours(1);
                         expiry.with_timezone(&chrono::Utc) < now + one_hour
                     }
                     Err(_) => {
                         // 尝试解析为时间戳（秒）
                         if let Ok(ts) = expired_at.parse::<i64>() {
-                            let now = chrono::Utc::now().timestamp();
-                            ts < now + 3600
+                            ts < now.timestamp() + 3600
                         } else {
                             true // 无法解析，需要刷新
                         }
@@ -698,18 +2805,45 @@ impl AccountManager {
         }
     }
 
-    /// 批量刷新所有即将过期的 Token
+    /// 批量刷新所有即将过期的 Token（跳过关闭了自动刷新的账号）
     pub async fn refresh_all_tokens(&mut self) -> Result<Vec<String>> {
-        let mut refreshed = Vec::new();
-        let account_ids: Vec<String> = self.store.accounts.iter()
+        self.ensure_writable()?;
+        let now = self.server_now();
+        let accounts: Vec<Account> = self.store.accounts.iter()
+            .filter(|a| a.is_active && a.auto_refresh && !a.quarantined)
             .filter(|a| !a.cookies.is_empty())
-            .filter(|a| Self::is_token_expiring_soon(a))
-            .map(|a| a.id.clone())
+            .filter(|a| Self::is_token_expiring_soon(a, now))
+            .cloned()
             .collect();
 
-        for id in account_ids {
-            match self.refresh_token(&id).await {
-                Ok(_) => {
+        // 并发请求新 Token，超时的账号按失败处理，不阻塞整批刷新
+        let semaphore = Arc::new(Semaphore::new(self.refresh_concurrency));
+        let timeout = self.refresh_timeout;
+        let mut tasks = JoinSet::new();
+        for account in accounts {
+            let semaphore = semaphore.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let result = match tokio::time::timeout(timeout, Self::fetch_user_token(&account)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(anyhow!("请求超时（{} 秒）", timeout.as_secs())),
+                };
+                (account.id, result)
+            });
+        }
+
+        let mut refreshed = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            let Ok((id, result)) = joined else { continue };
+            match result {
+                Ok(token_result) => {
+                    if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == id) {
+                        acc.jwt_token = Some(token_result.token);
+                        acc.token_expired_at = Some(token_result.expired_at);
+                        acc.update_refresh_token(token_result.refresh_token);
+                        acc.updated_at = chrono::Utc::now().timestamp();
+                    }
+                    self.record_status(&id, AccountStatus::Valid);
                     println!("[INFO] 自动刷新 Token 成功: {}", id);
                     refreshed.push(id);
                 }
@@ -718,9 +2852,186 @@ impl AccountManager {
                 }
             }
         }
+
+        if !refreshed.is_empty() {
+            self.save_store()?;
+        }
         Ok(refreshed)
     }
 
+    /// 只校验超过 ttl_secs 未校验的账号（按批量刷新的并发和超时限制），最近校验过的账号跳过
+    pub async fn validate_stale(&mut self, ttl_secs: u64) -> Result<ValidationReport> {
+        let now = chrono::Utc::now().timestamp();
+        let ttl = ttl_secs as i64;
+        let mut skipped = 0;
+        let mut stale = Vec::new();
+        for account in self.store.accounts.iter().filter(|a| a.is_active && !a.quarantined) {
+            let fresh = account.last_checked.is_some_and(|checked| now - checked <= ttl);
+            if fresh || account.needs_login() {
+                skipped += 1;
+            } else {
+                stale.push(account.clone());
+            }
+        }
+
+        self.validate_batch(stale, skipped, |_, _| {}).await
+    }
+
+    /// 校验所有启用、未隔离且有凭证的账号，每校验完一个调用一次 on_progress(已完成, 总数)
+    pub async fn validate_all(&mut self, on_progress: impl FnMut(usize, usize)) -> Result<ValidationReport> {
+        let batch = self.prepare_validate_all()?;
+        let outcome = batch.run(on_progress).await;
+        self.record_validation(outcome)
+    }
+
+    /// 选出 validate_all 要校验的账号；返回的批次可在释放管理器的锁后执行网络请求，
+    /// 再用 record_validation 记录结果
+    pub fn prepare_validate_all(&self) -> Result<ValidationBatch> {
+        let mut skipped = 0;
+        let mut accounts = Vec::new();
+        for account in self.store.accounts.iter().filter(|a| a.is_active && !a.quarantined) {
+            if account.needs_login() {
+                skipped += 1;
+            } else {
+                accounts.push(account.clone());
+            }
+        }
+        self.prepare_batch(accounts, skipped)
+    }
+
+    /// 校验刚导入（新增或覆盖）的账号，每校验完一个调用一次 on_progress(已完成, 总数)
+    pub async fn validate_imported(
+        &mut self,
+        records: &[ImportRecord],
+        on_progress: impl FnMut(usize, usize),
+    ) -> Result<ValidationReport> {
+        let imported: Vec<&str> = records.iter()
+            .filter(|r| matches!(r.action, ImportAction::Added | ImportAction::Overwritten))
+            .map(|r| r.user_id.as_str())
+            .collect();
+        let mut skipped = 0;
+        let mut accounts = Vec::new();
+        for account in self.store.accounts.iter().filter(|a| imported.contains(&a.user_id.as_str())) {
+            if account.needs_login() {
+                skipped += 1;
+            } else {
+                accounts.push(account.clone());
+            }
+        }
+
+        self.validate_batch(accounts, skipped, on_progress).await
+    }
+
+    /// 按批量刷新的并发和超时限制校验一组账号，记录各账号的状态
+    async fn validate_batch(
+        &mut self,
+        accounts: Vec<Account>,
+        skipped: usize,
+        on_progress: impl FnMut(usize, usize),
+    ) -> Result<ValidationReport> {
+        let batch = self.prepare_batch(accounts, skipped)?;
+        let outcome = batch.run(on_progress).await;
+        self.record_validation(outcome)
+    }
+
+    /// 生成批量校验的批次，并清除上一次的取消请求
+    fn prepare_batch(&self, accounts: Vec<Account>, skipped: usize) -> Result<ValidationBatch> {
+        self.ensure_writable()?;
+        self.validation_cancel.store(false, Ordering::SeqCst);
+        Ok(ValidationBatch {
+            accounts,
+            skipped,
+            concurrency: self.refresh_concurrency,
+            timeout: self.refresh_timeout,
+            cancel: self.validation_cancel.clone(),
+        })
+    }
+
+    /// 记录批量校验的结果并保存，返回校验报告
+    pub fn record_validation(&mut self, outcome: ValidationOutcome) -> Result<ValidationReport> {
+        let mut report = ValidationReport {
+            skipped: outcome.skipped,
+            cancelled: outcome.cancelled,
+            failed: outcome.panicked,
+            ..Default::default()
+        };
+        for (id, email, result) in outcome.results {
+            let status = match result {
+                Ok(summary) => {
+                    if let Some(acc) = self.store.accounts.iter_mut().find(|a| a.id == id) {
+                        acc.plan_type = summary.plan_type;
+                    }
+                    self.record_status(&id, AccountStatus::Valid);
+                    report.valid += 1;
+                    Some(AccountStatus::Valid)
+                }
+                Err(e) if Self::is_auth_error(&e) => {
+                    self.record_status(&id, AccountStatus::Expired);
+                    report.expired += 1;
+                    Some(AccountStatus::Expired)
+                }
+                Err(e) => {
+                    println!("[WARN] 校验账号失败 {}: {}", id, e);
+                    report.failed += 1;
+                    report.failures.push(ValidationFailure {
+                        account_id: id.clone(),
+                        message: e.to_string(),
+                        network_error: classify_network_error(&e),
+                    });
+                    None
+                }
+            };
+            report.results.push(ValidationResult { account_id: id, email, status });
+        }
+
+        if report.valid + report.expired > 0 {
+            self.save_store()?;
+        }
+        Ok(report)
+    }
+
+    /// 通过指定代理校验账号（使用账号的副本，不保存代理、不记录状态），用于在分配代理前测试
+    pub async fn validate_account_via(account: &Account, proxy: &str) -> Result<ProxyValidation> {
+        let proxy = proxy.trim();
+        crate::api::parse_proxy_url(proxy)?;
+        if account.needs_login() {
+            return Err(anyhow!("账号没有有效的 Token 或 Cookies"));
+        }
+        let mut account = account.clone();
+        account.proxy = Some(proxy.to_string());
+
+        let started = std::time::Instant::now();
+        let result = Self::fetch_usage_summary(&account).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let (status, message, network_error) = match result {
+            Ok(_) => (Some(AccountStatus::Valid), None, None),
+            Err(e) if Self::is_auth_error(&e) => (Some(AccountStatus::Expired), Some(e.to_string()), None),
+            Err(e) => (None, Some(e.to_string()), classify_network_error(&e)),
+        };
+        Ok(ProxyValidation {
+            account_id: account.id,
+            proxy: proxy.to_string(),
+            status,
+            latency_ms,
+            message,
+            network_error,
+        })
+    }
+
+    /// 用账号现有的凭证获取使用量（优先 Token，不刷新、不修改账号）
+    async fn fetch_usage_summary(account: &Account) -> Result<UsageSummary> {
+        match &account.jwt_token {
+            Some(token) => Self::token_client_for(account, token)?.get_usage_summary_by_token().await,
+            None => Self::cookie_client_for(account, &account.cookies)?.get_usage_summary().await,
+        }
+    }
+
+    /// 使用账号的 Cookies 获取新 Token
+    async fn fetch_user_token(account: &Account) -> Result<crate::api::UserTokenResult> {
+        let mut client = Self::cookie_client_for(account, &account.cookies)?;
+        client.get_user_token().await
+    }
+
     /// 领取生日礼包
     pub async fn claim_birthday_bonus(&mut self, account_id: &str) -> Result<()> {
         let account = self.store.accounts.iter()
@@ -730,7 +3041,7 @@ impl AccountManager {
         let token = account.jwt_token.as_ref()
             .ok_or_else(|| anyhow!("账号没有 Token"))?;
 
-        let client = TraeApiClient::new_with_token(token)?;
+        let client = Self::token_client_for(account, token)?;
 
         // 先查询是否已领取
         let claimed = client.query_birthday_bonus().await?;
@@ -745,3 +3056,661 @@ impl AccountManager {
         Ok(())
     }
 }
+
+/// 一次批量校验要请求的账号及并发、超时设置；由管理器在锁内生成，
+/// 网络请求阶段不访问管理器，调用方可以先释放锁再执行 run
+pub struct ValidationBatch {
+    accounts: Vec<Account>,
+    skipped: usize,
+    concurrency: usize,
+    timeout: Duration,
+    cancel: Arc<AtomicBool>,
+}
+
+/// 批量校验的网络请求结果，交给 AccountManager::record_validation 记录
+pub struct ValidationOutcome {
+    /// 已完成的请求：(账号 ID, 邮箱, 结果)
+    results: Vec<(String, String, Result<UsageSummary>)>,
+    /// 异常退出的请求数
+    panicked: usize,
+    skipped: usize,
+    cancelled: bool,
+}
+
+impl ValidationBatch {
+    /// 按并发和超时限制请求各账号的使用量，每完成一个调用一次 on_progress(已完成, 总数)；
+    /// 取消后中止排队中和进行中的请求，只返回已完成的结果
+    pub async fn run(self, on_progress: impl FnMut(usize, usize)) -> ValidationOutcome {
+        self.run_with(on_progress, |account| async move { AccountManager::fetch_usage_summary(&account).await })
+            .await
+    }
+
+    async fn run_with<F, Fut>(self, mut on_progress: impl FnMut(usize, usize), fetch: F) -> ValidationOutcome
+    where
+        F: Fn(Account) -> Fut,
+        Fut: std::future::Future<Output = Result<UsageSummary>> + Send + 'static,
+    {
+        let total = self.accounts.len();
+        let mut outcome = ValidationOutcome {
+            results: Vec::new(),
+            panicked: 0,
+            skipped: self.skipped,
+            cancelled: false,
+        };
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let timeout = self.timeout;
+        let mut tasks = JoinSet::new();
+        for account in self.accounts {
+            let semaphore = semaphore.clone();
+            let request = fetch(account.clone());
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let result = match tokio::time::timeout(timeout, request).await {
+                    Ok(result) => result,
+                    Err(_) => Err(anyhow!("请求超时（{} 秒）", timeout.as_secs())),
+                };
+                (account.id, account.email, result)
+            });
+        }
+
+        let mut done = 0;
+        loop {
+            let joined = tokio::select! {
+                joined = tasks.join_next() => match joined {
+                    Some(joined) => joined,
+                    None => break,
+                },
+                _ = wait_cancelled(&self.cancel) => {
+                    tasks.abort_all();
+                    outcome.cancelled = true;
+                    println!("[INFO] 批量校验已取消，已完成 {}/{}", done, total);
+                    break;
+                }
+            };
+            done += 1;
+            on_progress(done, total);
+            match joined {
+                Ok(result) => outcome.results.push(result),
+                Err(_) => outcome.panicked += 1,
+            }
+        }
+        outcome
+    }
+}
+
+/// 等待取消标记被置位
+async fn wait_cancelled(cancel: &AtomicBool) {
+    while !cancel.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn jwt(exp: i64) -> String {
+        jwt_for("1001", exp)
+    }
+
+    fn jwt_for(user_id: &str, exp: i64) -> String {
+        let claims = serde_json::json!({
+            "data": { "id": user_id, "source": "", "source_id": "", "tenant_id": "", "type": "" },
+            "exp": exp,
+            "iat": exp - 3600,
+        });
+        format!("e30.{}.sig", URL_SAFE_NO_PAD.encode(claims.to_string()))
+    }
+
+    fn manager(accounts: Vec<Account>) -> AccountManager {
+        AccountManager::in_memory(accounts)
+    }
+
+    /// 数据写入独立临时目录并持有文件锁的管理器，用于测试会保存数据的操作
+    fn writable_manager(accounts: Vec<Account>) -> AccountManager {
+        let dir = std::env::temp_dir().join(format!("trae-manager-test-{}", uuid_simple()));
+        fs::create_dir_all(&dir).unwrap();
+        let lock = StoreLock::acquire(dir.join("accounts.lock")).unwrap();
+        assert!(lock.is_some());
+        AccountManager::from_parts(AccountStore { accounts, ..Default::default() }, dir.join("accounts.json"), lock, false)
+    }
+
+    fn account_with_id(id: &str, user_id: &str, token: Option<String>) -> Account {
+        let mut account = Account::new(
+            id.to_string(),
+            format!("{}@example.com", id),
+            String::new(),
+            user_id.to_string(),
+            String::new(),
+        );
+        account.id = id.to_string();
+        account.jwt_token = token;
+        account
+    }
+
+    /// sid_guard 在 `expires_at` 过期的完整会话 Cookies
+    fn session_cookies(expires_at: i64) -> String {
+        let issued_at = expires_at - 86_400;
+        format!("sessionid=s1; sid_tt=s1; uid_tt=u1; sid_guard=s1%7C{}%7C86400%7Cdate", issued_at)
+    }
+
+    fn account(token_exp: i64, cookies: &str) -> Account {
+        let mut account = Account::new(
+            "test".to_string(),
+            "test@example.com".to_string(),
+            cookies.to_string(),
+            "1001".to_string(),
+            String::new(),
+        );
+        account.jwt_token = Some(jwt(token_exp));
+        account
+    }
+
+    #[test]
+    fn stale_token_with_fresh_cookies_replaces_only_token() {
+        let cookies = session_cookies(NOW + 86_400);
+        let account = account(NOW - 60, &cookies);
+        let plan = AccountManager::selective_update_plan(&account, &jwt(NOW + 3600), Some(&cookies), NOW);
+        assert_eq!(plan, (true, false));
+    }
+
+    #[test]
+    fn fresh_token_with_stale_cookies_replaces_only_cookies() {
+        let account = account(NOW + 3600, &session_cookies(NOW - 60));
+        let fresh_cookies = session_cookies(NOW + 86_400);
+        // 新 Token 的过期时间不比原 Token 晚，保留原 Token
+        let plan = AccountManager::selective_update_plan(&account, &jwt(NOW + 1800), Some(&fresh_cookies), NOW);
+        assert_eq!(plan, (false, true));
+    }
+
+    #[test]
+    fn both_stale_replaces_both() {
+        let account = account(NOW - 60, &session_cookies(NOW - 60));
+        let plan = AccountManager::selective_update_plan(
+            &account,
+            &jwt(NOW + 3600),
+            Some(&session_cookies(NOW + 86_400)),
+            NOW,
+        );
+        assert_eq!(plan, (true, true));
+    }
+
+    #[test]
+    fn missing_cookies_never_replace_stored_cookies() {
+        let account = account(NOW - 60, &session_cookies(NOW - 60));
+        assert_eq!(AccountManager::selective_update_plan(&account, &jwt(NOW + 3600), None, NOW), (true, false));
+        assert_eq!(AccountManager::selective_update_plan(&account, &jwt(NOW + 3600), Some("  "), NOW), (true, false));
+    }
+
+    #[test]
+    fn incomplete_fresh_cookies_do_not_replace_valid_session() {
+        let account = account(NOW + 3600, &session_cookies(NOW + 86_400));
+        // 只读到页面脚本可见的部分，缺少 sessionid 等关键 Cookie
+        let plan = AccountManager::selective_update_plan(&account, &jwt(NOW + 3600), Some("uid_tt=u2"), NOW);
+        assert_eq!(plan, (false, false));
+    }
+
+    #[test]
+    fn duplicate_groups_use_token_user_id() {
+        let manager = manager(vec![
+            account_with_id("a", "stale", Some(jwt_for("u1", NOW))),
+            account_with_id("b", "u2", None),
+            account_with_id("c", "u1", Some(jwt_for("u1", NOW + 60))),
+            account_with_id("d", "u2", Some(jwt_for("u3", NOW))),
+            account_with_id("e", "u2", None),
+        ]);
+        // Token 中的用户 ID 优先于记录的 user_id；只有一个账号的用户不返回
+        assert_eq!(manager.find_duplicate_groups(), vec![vec!["a", "c"], vec!["b", "e"]]);
+    }
+
+    #[test]
+    fn unparseable_accounts_form_one_group() {
+        let manager = manager(vec![
+            account_with_id("a", "", Some("not-a-jwt".to_string())),
+            account_with_id("b", "", None),
+            account_with_id("c", "u1", Some(jwt_for("u1", NOW))),
+        ]);
+        assert_eq!(manager.find_duplicate_groups(), vec![vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn single_account_has_no_duplicates() {
+        assert!(manager(vec![account_with_id("a", "u1", None)]).find_duplicate_groups().is_empty());
+    }
+
+    #[test]
+    fn storage_check_reports_missing_data_dir() {
+        let mut manager = manager(Vec::new());
+        manager.data_path = PathBuf::from("/");
+        let check = manager.check_storage_writable();
+        assert!(!check.writable);
+        assert_eq!(check.error.as_deref(), Some("无法获取应用数据目录"));
+        assert_eq!(check.path, "/");
+    }
+
+    #[test]
+    fn storage_check_probes_data_dir() {
+        let dir = std::env::temp_dir().join(format!("trae-storage-test-{}", uuid_simple()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut manager = manager(Vec::new());
+        manager.data_path = dir.join("accounts.json");
+        let check = manager.check_storage_writable();
+        assert!(check.writable, "{:?}", check.error);
+        assert!(!dir.join(".write-probe").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn safe_mode_refuses_mutations_but_lists_accounts() {
+        let mut manager = AccountManager::from_parts(
+            AccountStore { accounts: vec![account_with_id("a", "u1", None)], ..Default::default() },
+            std::env::temp_dir().join("trae-account-manager-test.json"),
+            None,
+            true,
+        );
+        let err = manager.remove_account("a").unwrap_err();
+        assert!(matches!(err.downcast_ref::<AccountError>(), Some(AccountError::ReadOnly(_))));
+        assert!(manager.set_primary(Some("a")).is_err());
+        assert_eq!(manager.get_accounts(false).len(), 1);
+        assert!(manager.export_accounts().is_ok());
+    }
+
+    #[tokio::test]
+    async fn import_strategies_against_colliding_account() {
+        let entry = |exp: i64| serde_json::json!([{ "email": "new@example.com", "user_id": "1001", "cookies": "", "jwt_token": jwt(exp) }]).to_string();
+        for (strategy, incoming_exp, overwritten) in [
+            (ImportStrategy::Skip, NOW + 100, false),
+            (ImportStrategy::Overwrite, NOW - 100, true),
+            (ImportStrategy::KeepNewest, NOW + 100, true),
+            (ImportStrategy::KeepNewest, NOW - 100, false),
+        ] {
+            let mut manager = writable_manager(vec![account_with_id("a", "1001", Some(jwt(NOW)))]);
+            let records = manager.import_accounts(&entry(incoming_exp), strategy, false).await.unwrap();
+            let expected = if overwritten { ImportAction::Overwritten } else { ImportAction::Skipped };
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].action, expected, "{:?}", strategy);
+            assert_eq!(records[0].message.is_some(), !overwritten);
+            assert_eq!(manager.store.accounts.len(), 1);
+            assert_eq!(manager.store.accounts[0].email == "new@example.com", overwritten);
+        }
+    }
+
+    #[test]
+    fn snapshot_restore_round_trip() {
+        let mut manager = writable_manager(vec![account_with_id("a", "u1", None), account_with_id("b", "u2", None)]);
+        let snapshot_id = manager.snapshot().unwrap();
+        manager.remove_account("a").unwrap();
+        assert_eq!(manager.store.accounts.len(), 1);
+
+        // 快照 ID 精确到毫秒，避免恢复前的自动快照与上一个同名
+        std::thread::sleep(Duration::from_millis(5));
+        manager.restore_snapshot(&snapshot_id).unwrap();
+        let ids: Vec<&str> = manager.store.accounts.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert_eq!(AccountManager::load_store(&manager.data_path).unwrap().accounts.len(), 2);
+
+        // 恢复前为删除后的状态创建了自动快照
+        let snapshots = manager.list_snapshots().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].account_count, 1);
+        assert_eq!(snapshots[1].id, snapshot_id);
+    }
+
+    #[test]
+    fn snapshot_rejects_invalid_ids() {
+        let mut manager = writable_manager(Vec::new());
+        assert!(manager.restore_snapshot("../accounts").is_err());
+        assert!(manager.restore_snapshot("").is_err());
+        assert!(manager.restore_snapshot("20200101-000000-000").is_err());
+    }
+
+    #[test]
+    fn snapshot_evicts_oldest_beyond_cap() {
+        let manager = writable_manager(vec![account_with_id("a", "u1", None)]);
+        let dir = manager.snapshot_dir().unwrap();
+        for i in 0..MAX_SNAPSHOTS {
+            fs::write(dir.join(format!("20200101-0000{:02}-000.json", i)), "{\"accounts\":[]}").unwrap();
+        }
+        let newest = manager.snapshot().unwrap();
+
+        let snapshots = manager.list_snapshots().unwrap();
+        assert_eq!(snapshots.len(), MAX_SNAPSHOTS);
+        assert_eq!(snapshots[0].id, newest);
+        assert!(!dir.join("20200101-000000-000.json").exists());
+        assert!(dir.join("20200101-000001-000.json").exists());
+    }
+
+    #[test]
+    fn capacity_rejects_new_accounts_at_cap() {
+        let mut manager = manager(vec![account_with_id("a", "u1", None)]);
+        assert!(manager.ensure_capacity().is_ok());
+
+        manager.set_max_accounts(Some(2));
+        assert!(manager.ensure_capacity().is_ok());
+
+        manager.set_max_accounts(Some(1));
+        let err = manager.ensure_capacity().unwrap_err();
+        assert!(matches!(err.downcast_ref::<AccountError>(), Some(AccountError::LimitReached(1))));
+    }
+
+    #[tokio::test]
+    async fn updating_existing_account_at_cap_is_allowed() {
+        let mut manager = writable_manager(vec![account_with_id("a", "1001", Some(jwt(NOW)))]);
+        manager.set_max_accounts(Some(1));
+        let data = serde_json::json!([{ "email": "new@example.com", "user_id": "1001", "cookies": "", "jwt_token": jwt(NOW + 100) }]);
+        let records = manager.import_accounts(&data.to_string(), ImportStrategy::Overwrite, false).await.unwrap();
+        assert_eq!(records[0].action, ImportAction::Overwritten);
+        assert_eq!(manager.store.accounts[0].email, "new@example.com");
+    }
+
+    #[test]
+    fn manifest_import_stops_at_cap() {
+        let mut manager = writable_manager(vec![account_with_id("a", "u1", None)]);
+        manager.set_max_accounts(Some(2));
+        let manifest = serde_json::to_string(&vec![
+            ManifestEntry::from(&account_with_id("b", "u2", None)),
+            ManifestEntry::from(&account_with_id("c", "u3", None)),
+        ])
+        .unwrap();
+        assert_eq!(manager.import_manifest(&manifest).unwrap(), 1);
+        let ids: Vec<&str> = manager.store.accounts.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    fn expired_account(id: &str) -> Account {
+        let mut account = account_with_id(id, id, Some(jwt(NOW)));
+        account.status = AccountStatus::Expired;
+        account
+    }
+
+    #[test]
+    fn purge_in_quarantine_mode_flags_instead_of_deleting() {
+        let mut manager = writable_manager(vec![expired_account("a"), account_with_id("b", "b", Some(jwt(NOW)))]);
+        manager.store.active_account_id = Some("a".to_string());
+        assert_eq!(manager.purge_expired(true).unwrap(), 1);
+
+        assert_eq!(manager.store.accounts.len(), 2);
+        assert!(manager.store.accounts[0].quarantined);
+        assert_eq!(manager.store.active_account_id.as_deref(), Some("b"));
+        let listed: Vec<String> = manager.get_accounts(false).into_iter().map(|a| a.id).collect();
+        assert_eq!(listed, vec!["b"]);
+        assert_eq!(manager.get_accounts(true).len(), 2);
+        // 已隔离的账号不会再次计入
+        assert_eq!(manager.purge_expired(true).unwrap(), 0);
+    }
+
+    #[test]
+    fn restore_from_quarantine_lists_account_again() {
+        let mut manager = writable_manager(vec![expired_account("a")]);
+        manager.purge_expired(true).unwrap();
+        manager.restore_from_quarantine("a").unwrap();
+        assert!(!manager.store.accounts[0].quarantined);
+        assert_eq!(manager.get_accounts(false).len(), 1);
+        assert!(manager.restore_from_quarantine("a").is_err());
+        assert!(manager.restore_from_quarantine("missing").is_err());
+    }
+
+    #[test]
+    fn empty_quarantine_removes_only_quarantined() {
+        let mut manager = writable_manager(vec![expired_account("a"), account_with_id("b", "b", None)]);
+        manager.store.primary_account_id = Some("a".to_string());
+        manager.purge_expired(true).unwrap();
+        assert_eq!(manager.empty_quarantine().unwrap(), 1);
+        let ids: Vec<&str> = manager.store.accounts.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["b"]);
+        assert!(manager.store.primary_account_id.is_none());
+        assert_eq!(manager.empty_quarantine().unwrap(), 0);
+    }
+
+    #[test]
+    fn set_replace_and_clear_primary() {
+        let mut manager = writable_manager(vec![account_with_id("a", "u1", None), account_with_id("b", "u2", None)]);
+        assert!(manager.get_primary().is_none());
+
+        manager.set_primary(Some("a")).unwrap();
+        assert_eq!(manager.get_primary().unwrap().id, "a");
+
+        // 设置新的主账号替换旧的，列表中始终只有一个主账号
+        manager.set_primary(Some("b")).unwrap();
+        assert_eq!(manager.get_primary().unwrap().id, "b");
+        let primaries: Vec<String> = manager.get_accounts(false).into_iter().filter(|a| a.is_primary).map(|a| a.id).collect();
+        assert_eq!(primaries, vec!["b"]);
+
+        manager.set_primary(None).unwrap();
+        assert!(manager.get_primary().is_none());
+        assert!(manager.get_accounts(false).iter().all(|a| !a.is_primary));
+    }
+
+    #[test]
+    fn primary_must_exist_and_is_cleared_on_removal() {
+        let mut manager = writable_manager(vec![account_with_id("a", "u1", None)]);
+        assert!(manager.set_primary(Some("missing")).is_err());
+        manager.set_primary(Some("a")).unwrap();
+        manager.remove_account("a").unwrap();
+        assert!(manager.store.primary_account_id.is_none());
+        assert!(manager.get_primary().is_none());
+    }
+
+    #[test]
+    fn validation_status_is_recorded_in_health_history() {
+        let mut manager = manager(vec![account_with_id("a", "u1", None)]);
+        manager.record_status("a", AccountStatus::Valid);
+        manager.record_status("a", AccountStatus::Expired);
+        let statuses: Vec<AccountStatus> = manager.account_health_history("a").unwrap().iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![AccountStatus::Valid, AccountStatus::Expired]);
+        assert!(manager.account_health_history("missing").is_err());
+    }
+
+    #[test]
+    fn import_duplicates_keep_newest_entry_per_user() {
+        let items = vec![
+            serde_json::json!({ "email": "old@example.com", "jwt_token": jwt_for("1001", NOW) }),
+            serde_json::json!({ "email": "other@example.com", "user_id": "2002" }),
+            serde_json::json!({ "email": "new@example.com", "user_id": "1001", "jwt_token": jwt_for("1001", NOW + 100) }),
+        ];
+        let duplicates = AccountManager::import_duplicates(&items);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].user_id, "1001");
+        assert_eq!(duplicates[0].indices, vec![0, 2]);
+        assert_eq!(duplicates[0].kept, 2);
+        assert_eq!(duplicates[0].emails, vec!["old@example.com", "new@example.com"]);
+    }
+
+    #[test]
+    fn import_duplicates_ignore_entries_without_user_id() {
+        let items = vec![serde_json::json!({ "email": "a@example.com" }), serde_json::json!({ "email": "b@example.com" })];
+        assert!(AccountManager::import_duplicates(&items).is_empty());
+    }
+
+    #[tokio::test]
+    async fn import_collapses_same_user_entries_before_merging() {
+        let mut manager = writable_manager(vec![account_with_id("a", "1001", Some(jwt(NOW - 100)))]);
+        let data = serde_json::json!([
+            { "email": "first@example.com", "user_id": "1001", "cookies": "", "jwt_token": jwt(NOW) },
+            { "email": "second@example.com", "user_id": "1001", "cookies": "", "jwt_token": jwt(NOW + 100) },
+        ]);
+        let records = manager.import_accounts(&data.to_string(), ImportStrategy::KeepNewest, false).await.unwrap();
+        let actions: Vec<ImportAction> = records.iter().map(|r| r.action).collect();
+        // 文件内的重复条目单独报告，只有保留的条目与本地账号合并一次
+        assert_eq!(actions, vec![ImportAction::Duplicate, ImportAction::Overwritten]);
+        assert_eq!(manager.store.accounts.len(), 1);
+        assert_eq!(manager.store.accounts[0].email, "second@example.com");
+    }
+
+    #[test]
+    fn preserve_source_id_keeps_non_colliding_id() {
+        let mut manager = writable_manager(vec![account_with_id("derived", "u1", None)]);
+        manager.store.active_account_id = Some("derived".to_string());
+        assert!(manager.preserve_source_id("derived", "source-1").is_none());
+        assert_eq!(manager.store.accounts[0].id, "source-1");
+        assert_eq!(manager.store.active_account_id.as_deref(), Some("source-1"));
+    }
+
+    #[test]
+    fn preserve_source_id_reports_remap_on_collision() {
+        let mut manager = writable_manager(vec![account_with_id("source-1", "u1", None), account_with_id("derived", "u2", None)]);
+        let remap = manager.preserve_source_id("derived", "source-1").unwrap();
+        assert_eq!(remap.source_id, "source-1");
+        assert_eq!(remap.new_id, "derived");
+        let ids: Vec<&str> = manager.store.accounts.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["source-1", "derived"]);
+
+        // 无效的源 ID 同样改用派生 ID
+        assert!(manager.preserve_source_id("derived", "../etc").is_some());
+        assert_eq!(manager.store.accounts[1].id, "derived");
+    }
+
+    #[test]
+    fn taken_derived_id_falls_back_to_random_id() {
+        let manager = manager(vec![account_with_id(&derive_account_id("1001"), "1001", None)]);
+        let mut account = Account::new("b".to_string(), String::new(), String::new(), "1001".to_string(), String::new());
+        manager.apply_new_account_defaults(&mut account);
+        assert_ne!(account.id, derive_account_id("1001"));
+    }
+
+    fn validation_batch(accounts: Vec<Account>, cancel: Arc<AtomicBool>) -> ValidationBatch {
+        ValidationBatch { accounts, skipped: 0, concurrency: 1, timeout: Duration::from_secs(30), cancel }
+    }
+
+    #[test]
+    fn new_batch_clears_previous_cancellation() {
+        let manager = writable_manager(vec![account_with_id("a", "u1", Some(jwt(NOW)))]);
+        let canceller = manager.validation_canceller();
+        assert!(!canceller.load(Ordering::SeqCst));
+
+        canceller.store(true, Ordering::SeqCst);
+        let batch = manager.prepare_validate_all().unwrap();
+        assert!(!canceller.load(Ordering::SeqCst));
+        // 批次与管理器共用同一个取消标记
+        canceller.store(true, Ordering::SeqCst);
+        assert!(batch.cancel.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn cancellation_stops_new_requests_and_returns_partials() {
+        let accounts: Vec<Account> = ["a", "b", "c"].iter().map(|id| account_with_id(id, id, Some(jwt(NOW)))).collect();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let started = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let batch = validation_batch(accounts, cancel.clone());
+
+        let (fetch_started, fetch_cancel) = (started.clone(), cancel.clone());
+        let mut progress = Vec::new();
+        let outcome = batch
+            .run_with(
+                |done, total| progress.push((done, total)),
+                move |account| {
+                    let (started, cancel) = (fetch_started.clone(), fetch_cancel.clone());
+                    async move {
+                        started.fetch_add(1, Ordering::SeqCst);
+                        if account.id == "a" {
+                            // 第一个请求完成时用户取消
+                            cancel.store(true, Ordering::SeqCst);
+                        } else {
+                            tokio::time::sleep(Duration::from_secs(30)).await;
+                        }
+                        Err(anyhow!("network down"))
+                    }
+                },
+            )
+            .await;
+
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.results.len(), 1);
+        assert_eq!(outcome.results[0].0, "a");
+        assert_eq!(progress, vec![(1, 3)]);
+        // 并发为 1：取消时第二个请求在进行中被中止，第三个没有发出
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+
+        let report = AccountManager::in_memory(Vec::new()).record_validation(outcome).unwrap();
+        assert!(report.cancelled);
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn uncancelled_batch_returns_every_result() {
+        let accounts: Vec<Account> = ["a", "b"].iter().map(|id| account_with_id(id, id, Some(jwt(NOW)))).collect();
+        let outcome = validation_batch(accounts, Arc::new(AtomicBool::new(false)))
+            .run_with(|_, _| {}, |_| async { Err(anyhow!("network down")) })
+            .await;
+        assert!(!outcome.cancelled);
+        assert_eq!(outcome.results.len(), 2);
+    }
+
+
+    #[test]
+    fn store_checksum_tracks_saves_and_outside_edits() {
+        let manager = writable_manager(vec![account_with_id("a", "u1", Some(jwt(NOW)))]);
+        assert_eq!(manager.verify_store_checksum().unwrap(), ChecksumStatus::Missing);
+
+        manager.save_store().unwrap();
+        assert_eq!(manager.verify_store_checksum().unwrap(), ChecksumStatus::Match);
+
+        let mut content = fs::read_to_string(&manager.data_path).unwrap();
+        content.push(' ');
+        fs::write(&manager.data_path, &content).unwrap();
+        assert_eq!(manager.verify_store_checksum().unwrap(), ChecksumStatus::Mismatch);
+
+        // 旧版本没有写校验和文件
+        fs::remove_file(manager.checksum_path()).unwrap();
+        assert_eq!(manager.verify_store_checksum().unwrap(), ChecksumStatus::Missing);
+        let _ = fs::remove_dir_all(manager.data_path.parent().unwrap());
+    }
+
+    #[test]
+    fn truncated_store_is_a_mismatch() {
+        let manager = writable_manager(vec![account_with_id("a", "u1", Some(jwt(NOW)))]);
+        manager.save_store().unwrap();
+        let content = fs::read(&manager.data_path).unwrap();
+        fs::write(&manager.data_path, &content[..content.len() / 2]).unwrap();
+        assert_eq!(manager.verify_store_checksum().unwrap(), ChecksumStatus::Mismatch);
+        let _ = fs::remove_dir_all(manager.data_path.parent().unwrap());
+    }
+
+
+    #[test]
+    fn remap_cookie_domain_moves_cookies_and_pins_host_only() {
+        let mut a = account_with_id("a", "u1", Some(jwt(NOW)));
+        a.cookies = "sessionid=s1; __Host-csrf=c1; theme=dark".to_string();
+        a.cookie_domains.insert("theme".to_string(), "static.trae.ai".to_string());
+        let mut b = account_with_id("b", "u2", Some(jwt(NOW)));
+        b.cookies = "sessionid=s2".to_string();
+        b.cookie_domains.insert("sessionid".to_string(), "marscode.com".to_string());
+        let mut manager = writable_manager(vec![a, b]);
+
+        let report = manager.remap_cookie_domain(".trae.ai", "Trae.COM", "trae.ai").unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].account_id, "a");
+        assert_eq!(report[0].remapped, vec!["sessionid".to_string()]);
+        assert_eq!(report[0].kept_host_only, vec!["__Host-csrf".to_string()]);
+
+        let a = manager.get_account("a").unwrap();
+        assert_eq!(a.cookie_domain("sessionid", "trae.com"), "trae.com");
+        // 站点地址改为新域名后，host-only Cookie 仍写在旧域名
+        assert_eq!(a.cookie_domain("__Host-csrf", "trae.com"), "trae.ai");
+        assert_eq!(a.cookie_domain("theme", "trae.com"), "static.trae.ai");
+        let b = manager.get_account("b").unwrap();
+        assert_eq!(b.cookie_domain("sessionid", "trae.ai"), "marscode.com");
+        let _ = fs::remove_dir_all(manager.data_path.parent().unwrap());
+    }
+
+    #[test]
+    fn remap_back_to_site_domain_drops_override() {
+        let mut a = account_with_id("a", "u1", Some(jwt(NOW)));
+        a.cookies = "sessionid=s1".to_string();
+        a.cookie_domains.insert("sessionid".to_string(), "old.trae.ai".to_string());
+        let mut manager = writable_manager(vec![a]);
+
+        let report = manager.remap_cookie_domain("old.trae.ai", "trae.ai", "trae.ai").unwrap();
+        assert_eq!(report[0].remapped, vec!["sessionid".to_string()]);
+        assert!(manager.get_account("a").unwrap().cookie_domains.is_empty());
+
+        assert!(manager.remap_cookie_domain("trae.ai", "TRAE.ai", "trae.ai").is_err());
+        assert!(manager.remap_cookie_domain("trae.ai", "not a domain", "trae.ai").is_err());
+        assert!(manager.remap_cookie_domain("nowhere.example", "trae.com", "trae.ai").unwrap().is_empty());
+        let _ = fs::remove_dir_all(manager.data_path.parent().unwrap());
+    }
+}
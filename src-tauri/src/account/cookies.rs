@@ -0,0 +1,367 @@
+use super::types::CookieConflict;
+
+/// 登录态依赖的关键 Cookie（缺少时切换账号可能失败）
+pub const CRITICAL_COOKIES: &[&str] = &["sessionid", "sid_tt", "sid_guard", "uid_tt"];
+
+/// 将 Cookie 字符串解析为 (name, value) 列表（保持原有顺序，跳过无效项）
+pub fn parse_cookie_pairs(cookies: &str) -> Vec<(String, String)> {
+    cookies
+        .split(';')
+        .filter_map(|part| {
+            let (name, value) = part.split_once('=')?;
+            let name = name.trim();
+            if name.is_empty() {
+                return None;
+            }
+            Some((name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// 将 (name, value) 列表格式化为 Cookie 字符串
+pub fn join_cookie_pairs(pairs: &[(String, String)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// 将 overlay 中的 Cookies 合并到 base 中（同名覆盖，其余保留原有顺序）
+pub fn merge_cookie_strings(base: &str, overlay: &str) -> String {
+    let mut pairs = parse_cookie_pairs(base);
+    for (name, value) in parse_cookie_pairs(overlay) {
+        match pairs.iter_mut().find(|(n, _)| *n == name) {
+            Some(existing) => existing.1 = value,
+            None => pairs.push((name, value)),
+        }
+    }
+    join_cookie_pairs(&pairs)
+}
+
+/// 去除重复的同名 Cookie：保留最后一次出现的值（位置取首次出现处），返回去重后的字符串和去掉的个数
+pub fn dedup_cookie_string(cookies: &str) -> (String, usize) {
+    let original = parse_cookie_pairs(cookies);
+    let total = original.len();
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    for (name, value) in original {
+        match pairs.iter_mut().find(|(n, _)| *n == name) {
+            Some(existing) => existing.1 = value,
+            None => pairs.push((name, value)),
+        }
+    }
+    let removed = total - pairs.len();
+    (join_cookie_pairs(&pairs), removed)
+}
+
+/// 规范化 Cookie 字符串：同名只保留最后一个，值中 RFC 6265 不允许的字符按 UTF-8 百分号编码，
+/// 已有的 %XX 编码保持不变，因此对已规范的字符串不做任何修改
+pub fn normalize_cookie_string(cookies: &str) -> String {
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    for (name, value) in parse_cookie_pairs(cookies) {
+        let value = encode_cookie_value(&value);
+        match pairs.iter_mut().find(|(n, _)| *n == name) {
+            Some(existing) => existing.1 = value,
+            None => pairs.push((name, value)),
+        }
+    }
+    join_cookie_pairs(&pairs)
+}
+
+/// 对 Cookie 值中不合法的字节做百分号编码（保留首尾成对的双引号）
+fn encode_cookie_value(value: &str) -> String {
+    let (quoted, inner) = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => (true, inner),
+        None => (false, value),
+    };
+    let mut encoded = String::with_capacity(inner.len());
+    for byte in inner.bytes() {
+        // cookie-octet = %x21 / %x23-2B / %x2D-3A / %x3C-5B / %x5D-7E
+        let allowed = matches!(byte, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E);
+        if allowed {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    if quoted {
+        format!("\"{}\"", encoded)
+    } else {
+        encoded
+    }
+}
+
+/// 格式化为完整的 `Cookie:` 请求头（去除换行等会破坏请求头的字符）
+pub fn format_cookie_header(cookies: &str) -> String {
+    let pairs: Vec<(String, String)> = parse_cookie_pairs(cookies)
+        .into_iter()
+        .map(|(name, value)| {
+            let value: String = value.chars().filter(|c| !c.is_control()).collect();
+            (name, value)
+        })
+        .collect();
+    format!("Cookie: {}", join_cookie_pairs(&pairs))
+}
+
+/// 按名称列出 Cookie 字符串中存在与缺失的关键 Cookie，不保留任何值
+pub fn critical_cookie_presence(cookies: &str) -> (Vec<String>, Vec<String>) {
+    let names: Vec<String> = parse_cookie_pairs(cookies)
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, _)| name)
+        .collect();
+    CRITICAL_COOKIES
+        .iter()
+        .map(|name| name.to_string())
+        .partition(|name| names.contains(name))
+}
+
+/// 由 sid_guard 推算会话 Cookie 的过期时间（Unix 秒）
+///
+/// sid_guard 的值形如 `<sessionid>|<签发时间>|<有效秒数>|<过期日期>`，分隔符可能被编码为 `%7C`
+pub fn session_expiry(cookies: &str) -> Option<i64> {
+    let (_, value) = parse_cookie_pairs(cookies).into_iter().find(|(name, _)| name == "sid_guard")?;
+    let value = value.replace("%7C", "|").replace("%7c", "|");
+    let mut parts = value.split('|');
+    let issued_at: i64 = parts.nth(1)?.trim().parse().ok()?;
+    let max_age: i64 = parts.next()?.trim().parse().ok()?;
+    issued_at.checked_add(max_age)
+}
+
+/// 对比写入 webview 的 Cookies 与读回的 Cookies：返回未能写入的名称和读回值不一致的名称（均不含值）
+pub fn diff_cookie_sets(applied: &str, readback: &[(String, String)]) -> (Vec<String>, Vec<String>) {
+    let mut missing = Vec::new();
+    let mut mismatched = Vec::new();
+    for (name, value) in parse_cookie_pairs(applied) {
+        if value.is_empty() {
+            continue;
+        }
+        match readback.iter().find(|(n, _)| *n == name) {
+            None => missing.push(name),
+            Some((_, live)) if *live != value => mismatched.push(name),
+            Some(_) => {}
+        }
+    }
+    (missing, mismatched)
+}
+
+/// Cookie 字符串中出现多次且值不同的 Cookie 名称（通常来自不同域名下的同名 Cookie），按首次出现的顺序返回
+pub fn find_conflicts(cookies: &str) -> Vec<CookieConflict> {
+    let mut conflicts: Vec<CookieConflict> = Vec::new();
+    let mut seen: Vec<(String, Vec<String>)> = Vec::new();
+    for (name, value) in parse_cookie_pairs(cookies) {
+        match seen.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, values)) => {
+                if !values.contains(&value) {
+                    values.push(value);
+                }
+            }
+            None => seen.push((name, vec![value])),
+        }
+    }
+    for (name, values) in seen {
+        if values.len() > 1 {
+            conflicts.push(CookieConflict { name, distinct_values: values.len() });
+        }
+    }
+    conflicts
+}
+
+/// 只属于设置它的主机的 Cookie（`__Host-` 前缀，浏览器要求其不带 Domain 属性），不能改写到其他域名
+pub fn is_host_only(name: &str) -> bool {
+    name.get(..7).is_some_and(|prefix| prefix.eq_ignore_ascii_case("__Host-"))
+}
+
+/// 规范化 Cookie 域名：去掉空白和开头的点并转为小写；不是合法域名时返回 None
+pub fn normalize_cookie_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_start_matches('.').to_ascii_lowercase();
+    let valid = domain.contains('.')
+        && !domain.ends_with('.')
+        && domain.split('.').all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-'))
+        && domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    valid.then_some(domain)
+}
+
+/// 域名迁移时对单个 Cookie 的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemapDecision {
+    /// 改写到新域名
+    Remap,
+    /// 不在旧域名下，不处理
+    Unaffected,
+    /// host-only Cookie，保留在旧域名
+    KeepHostOnly,
+}
+
+/// 判断写在 current_domain 下的 Cookie 在旧域名迁移时是否改写（域名均为规范化后的形式）
+pub fn remap_decision(name: &str, current_domain: &str, old_domain: &str) -> RemapDecision {
+    if current_domain != old_domain {
+        RemapDecision::Unaffected
+    } else if is_host_only(name) {
+        RemapDecision::KeepHostOnly
+    } else {
+        RemapDecision::Remap
+    }
+}
+
+/// 导出为 Netscape cookies.txt（curl、wget 及多数浏览器 cookies.txt 导入扩展通用）；
+/// http_only 中的 Cookie 按 curl 的约定在域名前加 `#HttpOnly_`
+pub fn format_netscape_cookies(cookies: &str, domain: &str, expires_at: i64, http_only: &[String]) -> String {
+    let mut lines = vec!["# Netscape HTTP Cookie File".to_string(), String::new()];
+    for (name, value) in parse_cookie_pairs(cookies) {
+        let value: String = value.chars().filter(|c| !c.is_control()).collect();
+        let prefix = if http_only.contains(&name) { "#HttpOnly_" } else { "" };
+        // domain / 包含子域名 / path / 仅 https / 过期时间 / name / value
+        lines.push(format!("{}{}\tTRUE\t/\tTRUE\t{}\t{}\t{}", prefix, domain, expires_at, name, value));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// 导出为 Chrome Cookie 管理扩展（EditThisCookie 等）可导入的 JSON 数组
+pub fn format_cookie_json(cookies: &str, domain: &str, expires_at: i64, http_only: &[String]) -> serde_json::Value {
+    let entries: Vec<serde_json::Value> = parse_cookie_pairs(cookies)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, value))| {
+            serde_json::json!({
+                "domain": domain,
+                "expirationDate": expires_at,
+                "hostOnly": false,
+                "httpOnly": http_only.contains(&name),
+                "name": name,
+                "path": "/",
+                "sameSite": "unspecified",
+                "secure": true,
+                "session": false,
+                "storeId": "0",
+                "value": value,
+                "id": index + 1,
+            })
+        })
+        .collect();
+    serde_json::Value::Array(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn netscape_export_marks_http_only_cookies() {
+        let text = format_netscape_cookies("sessionid=s1; theme=dark", ".trae.ai", 1_700_000_000, &names(&["sessionid"]));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "# Netscape HTTP Cookie File");
+        assert_eq!(lines[2], "#HttpOnly_.trae.ai\tTRUE\t/\tTRUE\t1700000000\tsessionid\ts1");
+        assert_eq!(lines[3], ".trae.ai\tTRUE\t/\tTRUE\t1700000000\ttheme\tdark");
+    }
+
+    #[test]
+    fn netscape_export_strips_control_characters() {
+        let text = format_netscape_cookies("a=x\ny", ".trae.ai", 1, &[]);
+        assert!(text.contains("\ta\txy\n"));
+    }
+
+    #[test]
+    fn json_export_sets_http_only_flag() {
+        let json = format_cookie_json("sessionid=s1; theme=dark", ".trae.ai", 1_700_000_000, &names(&["sessionid"]));
+        let entries = json.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["name"], "sessionid");
+        assert_eq!(entries[0]["httpOnly"], true);
+        assert_eq!(entries[1]["httpOnly"], false);
+        assert_eq!(entries[1]["domain"], ".trae.ai");
+        assert_eq!(entries[1]["expirationDate"], 1_700_000_000);
+        assert_eq!(entries[1]["id"], 2);
+    }
+
+    #[test]
+    fn normalize_encodes_invalid_bytes_and_keeps_last_duplicate() {
+        assert_eq!(
+            normalize_cookie_string("a=1; b=hello world; a=2; c=\"x,y\""),
+            "a=2; b=hello%20world; c=\"x%2Cy\""
+        );
+        assert_eq!(normalize_cookie_string("name=值"), "name=%E5%80%BC");
+    }
+
+    #[test]
+    fn normalize_is_idempotent() {
+        let normalized = normalize_cookie_string("sid_guard=abc|1700000000|86400|date; x=a%7Cb; y=1 2");
+        assert_eq!(normalize_cookie_string(&normalized), normalized);
+        assert!(normalized.contains("x=a%7Cb"));
+    }
+
+    #[test]
+    fn dedup_keeps_first_position_and_last_value() {
+        assert_eq!(dedup_cookie_string("a=1; b=2; a=3; c=4; b=5"), ("a=3; b=5; c=4".to_string(), 2));
+        assert_eq!(dedup_cookie_string("a=1; b=2"), ("a=1; b=2".to_string(), 0));
+        assert_eq!(dedup_cookie_string(""), (String::new(), 0));
+    }
+
+    #[test]
+    fn diff_reports_missing_and_mismatched_names() {
+        let readback = vec![
+            ("sessionid".to_string(), "s1".to_string()),
+            ("sid_tt".to_string(), "other".to_string()),
+        ];
+        let (missing, mismatched) = diff_cookie_sets("sessionid=s1; sid_tt=s1; uid_tt=u1; empty=", &readback);
+        assert_eq!(missing, vec!["uid_tt"]);
+        assert_eq!(mismatched, vec!["sid_tt"]);
+        assert_eq!(diff_cookie_sets("a=1", &[("a".to_string(), "1".to_string())]), (vec![], vec![]));
+    }
+
+    #[test]
+    fn conflicts_only_count_distinct_values() {
+        let conflicts = find_conflicts("a=1; b=2; a=1; c=3; c=4; a=5; c=3");
+        let summary: Vec<(String, usize)> = conflicts.into_iter().map(|c| (c.name, c.distinct_values)).collect();
+        assert_eq!(summary, vec![("a".to_string(), 2), ("c".to_string(), 2)]);
+        assert!(find_conflicts("a=1; a=1; b=2").is_empty());
+    }
+
+    #[test]
+    fn session_expiry_reads_sid_guard() {
+        assert_eq!(session_expiry("a=1; sid_guard=s1%7C1700000000%7C86400%7Cdate"), Some(1_700_086_400));
+        assert_eq!(session_expiry("sid_guard=s1|1700000000|60|date"), Some(1_700_000_060));
+        assert_eq!(session_expiry("sid_guard=s1%7c1700000000%7c60"), Some(1_700_000_060));
+        assert_eq!(session_expiry("sessionid=s1"), None);
+        assert_eq!(session_expiry("sid_guard=s1|later|60"), None);
+        assert_eq!(session_expiry("sid_guard=s1|1700000000"), None);
+    }
+
+
+    #[test]
+    fn remap_decision_per_cookie() {
+        assert_eq!(remap_decision("sessionid", "trae.ai", "trae.ai"), RemapDecision::Remap);
+        assert_eq!(remap_decision("sessionid", "api.trae.ai", "trae.ai"), RemapDecision::Unaffected);
+        assert_eq!(remap_decision("sessionid", "marscode.com", "trae.ai"), RemapDecision::Unaffected);
+        assert_eq!(remap_decision("__Host-csrf", "trae.ai", "trae.ai"), RemapDecision::KeepHostOnly);
+        assert_eq!(remap_decision("__host-csrf", "trae.ai", "trae.ai"), RemapDecision::KeepHostOnly);
+        // __Secure- 只要求 https，可以跨域名改写
+        assert_eq!(remap_decision("__Secure-sid", "trae.ai", "trae.ai"), RemapDecision::Remap);
+        assert_eq!(remap_decision("__Host-csrf", "other.ai", "trae.ai"), RemapDecision::Unaffected);
+    }
+
+    #[test]
+    fn host_only_detection() {
+        assert!(is_host_only("__Host-session"));
+        assert!(!is_host_only("__Host"));
+        assert!(!is_host_only("Host-session"));
+        assert!(!is_host_only("值值值"));
+    }
+
+    #[test]
+    fn cookie_domains_are_normalized() {
+        assert_eq!(normalize_cookie_domain(" .Trae.AI ").as_deref(), Some("trae.ai"));
+        assert_eq!(normalize_cookie_domain("new-trae.example.com").as_deref(), Some("new-trae.example.com"));
+        assert_eq!(normalize_cookie_domain("localhost"), None);
+        assert_eq!(normalize_cookie_domain("trae.ai."), None);
+        assert_eq!(normalize_cookie_domain("trae..ai"), None);
+        assert_eq!(normalize_cookie_domain("-trae.ai"), None);
+        assert_eq!(normalize_cookie_domain("https://trae.ai"), None);
+        assert_eq!(normalize_cookie_domain(""), None);
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::api::TraeApiClient;
+use crate::backup;
+
+/// 添加失败时保存的登录捕获（用本次运行的备份密码加密后保存在数据目录）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DraftCapture {
+    pub id: String,
+    pub token: String,
+    #[serde(default)]
+    pub cookies: Option<String>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// 添加失败的原因
+    pub error: String,
+    pub created_at: i64,
+}
+
+/// 草稿简要信息（用于列表展示，不含凭证）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DraftBrief {
+    pub id: String,
+    /// 从 Token 中解析出的用户 ID，无法解析时为空
+    pub user_id: String,
+    pub has_cookies: bool,
+    pub error: String,
+    pub created_at: i64,
+}
+
+impl From<&DraftCapture> for DraftBrief {
+    fn from(draft: &DraftCapture) -> Self {
+        Self {
+            id: draft.id.clone(),
+            user_id: TraeApiClient::parse_jwt_token(&draft.token)
+                .map(|payload| payload.user_id)
+                .unwrap_or_default(),
+            has_cookies: draft.cookies.as_deref().is_some_and(|c| !c.trim().is_empty()),
+            error: draft.error.clone(),
+            created_at: draft.created_at,
+        }
+    }
+}
+
+/// 草稿和备份使用同一个密码，未输入时无法读写草稿
+fn require_passphrase(passphrase: Option<&str>) -> Result<&str> {
+    passphrase
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| anyhow!("本次运行尚未输入备份密码，草稿需要用它加密，请在设置中输入后重试"))
+}
+
+/// 读取并解密草稿列表，文件不存在时为空
+pub fn load(path: &Path, passphrase: Option<&str>) -> Result<Vec<DraftCapture>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let plaintext = backup::decrypt(&fs::read(path)?, require_passphrase(passphrase)?)
+        .map_err(|_| anyhow!("无法解密草稿，备份密码与保存草稿时不一致或草稿文件已损坏"))?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// 加密写入草稿列表，列表为空时删除文件
+pub fn save(path: &Path, drafts: &[DraftCapture], passphrase: Option<&str>) -> Result<()> {
+    if drafts.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    let plaintext = serde_json::to_vec(drafts)?;
+    fs::write(path, backup::encrypt(&plaintext, require_passphrase(passphrase)?)?)?;
+    Ok(())
+}
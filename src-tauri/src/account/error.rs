@@ -0,0 +1,18 @@
+use thiserror::Error;
+
+/// 账号操作中需要调用方区分处理的错误
+#[derive(Debug, Error)]
+pub enum AccountError {
+    /// 账号已存在，携带已有账号的 ID 和邮箱
+    #[error("该账号已存在")]
+    Duplicate { id: String, email: String },
+    /// 已达到账号数量上限
+    #[error("账号数量已达上限（{0} 个）")]
+    LimitReached(usize),
+    /// 存储处于只读模式（其他实例占用或安全模式），拒绝写入
+    #[error("{0}")]
+    ReadOnly(&'static str),
+    /// Token 与 Cookies 对应的用户不一致（如两次登录的捕获交错）
+    #[error("Token 与 Cookies 不属于同一账号（{token_user_id} / {cookie_user_id}）")]
+    CredentialMismatch { token_user_id: String, cookie_user_id: String },
+}
@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 其他账号管理工具导出 JSON 的字段映射
+///
+/// 每个字段是候选路径列表（用 `.` 访问嵌套字段），按顺序取第一个非空的字符串值。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForeignMapping {
+    /// 账号数组所在路径，为空时使用根数组或根对象下的 `accounts`
+    pub accounts: Option<String>,
+    pub token: Vec<String>,
+    pub cookies: Vec<String>,
+    pub email: Vec<String>,
+}
+
+impl Default for ForeignMapping {
+    /// 内置映射：兼容常见的 `token/accessToken`、`cookie/cookies`、`email/account` 字段写法
+    fn default() -> Self {
+        let paths = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect();
+        Self {
+            accounts: None,
+            token: paths(&["token", "jwt_token", "accessToken", "access_token", "Result.Token"]),
+            cookies: paths(&["cookies", "cookie", "Cookie"]),
+            email: paths(&["email", "account", "username"]),
+        }
+    }
+}
+
+/// 按 `a.b.c` 路径取值
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(value, |current, key| current.get(key))
+}
+
+/// 取第一个非空的字符串字段
+fn first_text(item: &Value, paths: &[String]) -> String {
+    paths
+        .iter()
+        .filter_map(|path| lookup(item, path).and_then(|v| v.as_str()))
+        .map(str::trim)
+        .find(|text| !text.is_empty())
+        .unwrap_or("")
+        .to_string()
+}
+
+/// 将外部 JSON 转换为 `import_accounts` 使用的账号数组
+pub fn normalize(data: &str, mapping: &ForeignMapping) -> Result<Vec<Value>> {
+    let root: Value = serde_json::from_str(data).map_err(|e| anyhow!("JSON 解析失败: {}", e))?;
+
+    let list = match mapping.accounts.as_deref() {
+        Some(path) => lookup(&root, path),
+        None if root.is_array() => Some(&root),
+        None => root.get("accounts"),
+    }
+    .and_then(|v| v.as_array())
+    .ok_or_else(|| anyhow!("未找到账号列表"))?;
+
+    Ok(list
+        .iter()
+        .map(|item| {
+            let token = first_text(item, &mapping.token);
+            serde_json::json!({
+                "email": first_text(item, &mapping.email),
+                "cookies": first_text(item, &mapping.cookies),
+                "jwt_token": if token.is_empty() { Value::Null } else { Value::from(token) },
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_mapping_reads_root_array_with_aliases() {
+        let data = r#"[
+            {"accessToken": " t1 ", "cookie": "a=1", "account": "a@example.com"},
+            {"token": "", "Result": {"Token": "t2"}, "email": "b@example.com"},
+            {"email": "c@example.com"}
+        ]"#;
+        let accounts = normalize(data, &ForeignMapping::default()).unwrap();
+        assert_eq!(accounts.len(), 3);
+        assert_eq!(accounts[0]["jwt_token"], "t1");
+        assert_eq!(accounts[0]["cookies"], "a=1");
+        assert_eq!(accounts[0]["email"], "a@example.com");
+        // 空字符串跳过，继续尝试下一个候选路径
+        assert_eq!(accounts[1]["jwt_token"], "t2");
+        assert_eq!(accounts[2]["jwt_token"], Value::Null);
+        assert_eq!(accounts[2]["cookies"], "");
+    }
+
+    #[test]
+    fn default_mapping_reads_accounts_field() {
+        let accounts = normalize(r#"{"accounts": [{"token": "t1"}]}"#, &ForeignMapping::default()).unwrap();
+        assert_eq!(accounts[0]["jwt_token"], "t1");
+    }
+
+    #[test]
+    fn custom_mapping_uses_nested_paths() {
+        let mapping = ForeignMapping {
+            accounts: Some("data.items".to_string()),
+            token: vec!["auth.jwt".to_string()],
+            cookies: vec!["auth.cookies".to_string()],
+            email: vec!["profile.mail".to_string()],
+        };
+        let data = r#"{"data": {"items": [{"auth": {"jwt": "t1", "cookies": "a=1"}, "profile": {"mail": "a@example.com"}}]}}"#;
+        let accounts = normalize(data, &mapping).unwrap();
+        assert_eq!(accounts[0]["jwt_token"], "t1");
+        assert_eq!(accounts[0]["cookies"], "a=1");
+        assert_eq!(accounts[0]["email"], "a@example.com");
+    }
+
+    #[test]
+    fn missing_list_or_invalid_json_fails() {
+        assert!(normalize(r#"{"items": []}"#, &ForeignMapping::default()).is_err());
+        assert!(normalize(r#"{"accounts": {}}"#, &ForeignMapping::default()).is_err());
+        assert!(normalize("not json", &ForeignMapping::default()).is_err());
+    }
+}
@@ -1,5 +1,13 @@
 pub mod account_manager;
+pub mod cookies;
+pub mod drafts;
+pub mod error;
+pub mod foreign;
+pub mod store_lock;
 pub mod types;
 
 pub use account_manager::AccountManager;
+pub use drafts::DraftBrief;
+pub use error::AccountError;
+pub use foreign::ForeignMapping;
 pub use types::*;
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::path::PathBuf;
+
+/// 账号存储文件锁（防止多个应用实例同时写入同一份数据）
+///
+/// 锁在 accounts.lock 上加操作系统的排他建议锁，而不是加在数据文件上：
+/// 数据文件保存时会被临时文件替换，锁会随旧文件一起失效。
+/// 进程退出（包括崩溃）时操作系统自动释放锁，残留的锁文件不会阻止下次启动
+pub struct StoreLock {
+    file: File,
+}
+
+impl StoreLock {
+    /// 尝试获取锁；锁已被其他实例持有时返回 None
+    pub fn acquire(path: PathBuf) -> Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(Self { file })),
+            Err(e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 释放锁（drop 时关闭文件也会释放）
+    pub fn release(&self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("trae-store-lock-{}-{}.lock", name, std::process::id()))
+    }
+
+    #[test]
+    fn second_acquire_fails_until_release() {
+        let path = lock_path("double");
+        let first = StoreLock::acquire(path.clone()).unwrap();
+        assert!(first.is_some());
+        assert!(StoreLock::acquire(path.clone()).unwrap().is_none());
+
+        drop(first);
+        let again = StoreLock::acquire(path.clone()).unwrap();
+        assert!(again.is_some());
+        again.unwrap().release();
+        assert!(StoreLock::acquire(path.clone()).unwrap().is_some());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn leftover_lock_file_is_not_held() {
+        // 崩溃后残留的锁文件（旧版本在其中写入 PID）不持有系统锁，可以直接获取
+        let path = lock_path("stale");
+        std::fs::write(&path, "4242").unwrap();
+        assert!(StoreLock::acquire(path.clone()).unwrap().is_some());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// 账号信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,16 +11,134 @@ pub struct Account {
     pub cookies: String,
     pub jwt_token: Option<String>,
     pub token_expired_at: Option<String>,
+    /// 刷新凭证，与短期的 jwt_token 分开存储
+    #[serde(default)]
+    pub refresh_token: Option<String>,
     pub user_id: String,
     pub tenant_id: String,
     pub region: String,
     pub plan_type: String,
     pub created_at: i64,
     pub updated_at: i64,
+    /// 是否启用；停用的账号保留在列表中，但不参与批量刷新
     pub is_active: bool,
     /// 账号关联的机器码
     #[serde(default)]
     pub machine_id: Option<String>,
+    /// 请求 Trae API 时使用的自定义 User-Agent
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// 请求 Trae API 时附加的请求头（如区域、客户端 ID）
+    #[serde(default)]
+    pub extra_headers: HashMap<String, String>,
+    /// 登录时 GetUserToken 请求携带的请求头（客户端版本等），刷新 Token 时复用；手动设置的 extra_headers 优先
+    #[serde(default)]
+    pub captured_headers: HashMap<String, String>,
+    /// 最近一次校验得到的凭证状态
+    #[serde(default)]
+    pub status: AccountStatus,
+    /// 最近一次校验凭证的时间
+    #[serde(default)]
+    pub last_checked: Option<i64>,
+    /// 手动设置状态的保护截止时间，在此之前自动校验不会覆盖状态
+    #[serde(default)]
+    pub status_override_until: Option<i64>,
+    /// 账号标签
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 最近一次写入 Cookies 时的关键 Cookie 诊断
+    #[serde(default)]
+    pub cookie_diagnostic: Option<CookieDiagnostic>,
+    /// 最近一次导入时凭证的内容哈希
+    #[serde(default)]
+    pub last_import_hash: Option<String>,
+    /// 是否已隔离；隔离的账号默认不在列表中显示，也不参与批量刷新
+    #[serde(default)]
+    pub quarantined: bool,
+    /// 界面上用于分组的颜色标记（#rrggbb）
+    #[serde(default)]
+    pub color: Option<String>,
+    /// 账号所属的 trae.ai 环境（如 prod/staging），新账号默认取自设置中的站点地址
+    #[serde(default)]
+    pub environment: Option<String>,
+    /// 请求 Trae API 时使用的代理（http/https），None 表示直连
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// 是否参与 Token 自动刷新；共享账号可关闭，避免刷新后轮换掉他人正在使用的凭证
+    #[serde(default = "default_auto_refresh")]
+    pub auto_refresh: bool,
+    /// 曾经自动添加过的标签；用户删除后不再自动加回
+    #[serde(default)]
+    pub auto_tags: Vec<String>,
+    /// 从只含 Token 的分享文件导入：没有 Cookies 和刷新凭证，Token 过期后即失效，不能刷新
+    #[serde(default)]
+    pub token_only: bool,
+    /// 最近几次校验的结果（从旧到新，最多 HEALTH_HISTORY_LEN 条），用于查看账号是否时好时坏
+    #[serde(default)]
+    pub health_history: Vec<HealthRecord>,
+    /// 最近一次浏览器登录捕获到的 Cookie 分类（只记录名称）
+    #[serde(default)]
+    pub cookie_capture: Option<CookieCapture>,
+    /// 写入 webview 时不使用站点域名的 Cookie（名称 -> 域名），由域名迁移产生
+    #[serde(default)]
+    pub cookie_domains: HashMap<String, String>,
+}
+
+/// 浏览器登录捕获的 Cookie 按来源分类（只记录名称，不记录值）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CookieCapture {
+    /// HttpOnly Cookie，脚本读不到，只能由应用从 webview 中读取
+    pub http_only: Vec<String>,
+    /// 页面脚本可以读到的 Cookie
+    pub js_accessible: Vec<String>,
+    pub captured_at: i64,
+}
+
+/// 每个账号最多保留的校验记录数
+pub const HEALTH_HISTORY_LEN: usize = 20;
+
+/// 一次校验的结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HealthRecord {
+    pub checked_at: i64,
+    pub status: AccountStatus,
+}
+
+/// 旧数据没有 auto_refresh 字段时默认参与自动刷新
+fn default_auto_refresh() -> bool {
+    true
+}
+
+/// Cookies 写入时关键 Cookie 的齐全情况（只记录名称，不记录值）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CookieDiagnostic {
+    pub present: Vec<String>,
+    pub missing: Vec<String>,
+    pub captured_at: i64,
+}
+
+impl CookieDiagnostic {
+    pub fn from_cookies(cookies: &str) -> Self {
+        let (present, missing) = super::cookies::critical_cookie_presence(cookies);
+        Self {
+            present,
+            missing,
+            captured_at: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+/// 账号凭证状态（缓存自最近一次接口调用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountStatus {
+    /// 从未校验过
+    #[default]
+    Unknown,
+    /// 凭证有效
+    Valid,
+    /// 凭证已过期或失效
+    Expired,
 }
 
 impl Account {
@@ -32,13 +151,14 @@ impl Account {
     ) -> Self {
         let now = chrono::Utc::now().timestamp();
         Self {
-            id: uuid_simple(),
+            id: derive_account_id(&user_id),
             name,
             email,
             avatar_url: String::new(),
             cookies,
             jwt_token: None,
             token_expired_at: None,
+            refresh_token: None,
             user_id,
             tenant_id,
             region: String::new(),
@@ -47,8 +167,121 @@ impl Account {
             updated_at: now,
             is_active: true,
             machine_id: None,
+            user_agent: None,
+            extra_headers: HashMap::new(),
+            captured_headers: HashMap::new(),
+            status: AccountStatus::Unknown,
+            last_checked: None,
+            status_override_until: None,
+            tags: Vec::new(),
+            cookie_diagnostic: None,
+            last_import_hash: None,
+            quarantined: false,
+            color: None,
+            environment: None,
+            proxy: None,
+            auto_refresh: true,
+            auto_tags: Vec::new(),
+            token_only: false,
+            health_history: Vec::new(),
+            cookie_capture: None,
+            cookie_domains: HashMap::new(),
+        }
+    }
+
+    /// Cookie 写入 webview 时使用的域名；没有单独记录时为站点域名 default_domain
+    pub fn cookie_domain<'a>(&'a self, name: &str, default_domain: &'a str) -> &'a str {
+        self.cookie_domains.get(name).map(String::as_str).unwrap_or(default_domain)
+    }
+
+    /// 按当前 Cookies 重新记录关键 Cookie 诊断（没有 Cookies 时清除）
+    pub fn record_cookie_diagnostic(&mut self) {
+        self.cookie_diagnostic = if self.cookies.trim().is_empty() {
+            None
+        } else {
+            Some(CookieDiagnostic::from_cookies(&self.cookies))
+        };
+    }
+
+    /// 缺失的关键 Cookie；没有诊断记录时按当前 Cookies 计算
+    pub fn missing_critical_cookies(&self) -> Vec<String> {
+        match &self.cookie_diagnostic {
+            Some(diagnostic) => diagnostic.missing.clone(),
+            None if self.cookies.trim().is_empty() => Vec::new(),
+            None => super::cookies::critical_cookie_presence(&self.cookies).1,
+        }
+    }
+
+    /// 更新刷新凭证；新值为空时保留原有的刷新凭证
+    pub fn update_refresh_token(&mut self, refresh_token: Option<String>) {
+        if let Some(refresh_token) = refresh_token.filter(|t| !t.trim().is_empty()) {
+            self.refresh_token = Some(refresh_token);
+        }
+    }
+
+    /// 追加一条校验记录，超过 HEALTH_HISTORY_LEN 条时丢弃最旧的记录
+    pub fn record_health(&mut self, status: AccountStatus, checked_at: i64) {
+        self.health_history.push(HealthRecord { checked_at, status });
+        let excess = self.health_history.len().saturating_sub(HEALTH_HISTORY_LEN);
+        self.health_history.drain(..excess);
+    }
+
+    /// 状态是否为手动设置且仍在保护期内
+    pub fn is_status_manual(&self) -> bool {
+        self.status_override_until
+            .is_some_and(|until| until > chrono::Utc::now().timestamp())
+    }
+
+    /// 考虑时效后的状态：超过 ttl 秒未校验的自动状态视为 Unknown（手动设置的状态不受影响）
+    pub fn effective_status(&self, ttl: Option<i64>) -> AccountStatus {
+        if self.is_status_manual() {
+            return self.status;
+        }
+        match (ttl, self.last_checked) {
+            (Some(ttl), Some(checked)) if chrono::Utc::now().timestamp() - checked > ttl => AccountStatus::Unknown,
+            _ => self.status,
+        }
+    }
+
+    /// 是否缺少凭证（既没有 Token 也没有 Cookies）
+    pub fn needs_login(&self) -> bool {
+        self.jwt_token.is_none() && self.cookies.is_empty()
+    }
+
+    /// Token 过期时间（Unix 秒）；支持 RFC 3339 和秒级时间戳，无法解析时为 None
+    pub fn token_expiry(&self) -> Option<i64> {
+        let expired_at = self.token_expired_at.as_deref()?.trim();
+        chrono::DateTime::parse_from_rfc3339(expired_at)
+            .map(|expiry| expiry.timestamp())
+            .ok()
+            .or_else(|| expired_at.parse().ok())
+    }
+
+    /// Token 与会话 Cookie 中较早的过期时间（Unix 秒），两者都未知时为 None
+    pub fn earliest_expiry(&self) -> Option<i64> {
+        let cookie_expiry = super::cookies::session_expiry(&self.cookies);
+        match (self.token_expiry(), cookie_expiry) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
         }
     }
+
+    /// Token 与会话 Cookie 过期时间之差（秒）；任一方未知时为 None
+    pub fn expiry_divergence(&self) -> Option<i64> {
+        let cookie_expiry = super::cookies::session_expiry(&self.cookies)?;
+        Some((self.token_expiry()? - cookie_expiry).abs())
+    }
+
+    /// 邮箱域名（小写）；邮箱为空或格式不正确时为 None
+    pub fn email_domain(&self) -> Option<String> {
+        let (local, domain) = self.email.trim().rsplit_once('@')?;
+        let valid = !local.is_empty()
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+        valid.then(|| domain.to_ascii_lowercase())
+    }
 }
 
 /// 账号列表存储结构
@@ -59,10 +292,45 @@ pub struct AccountStore {
     /// 当前 Trae IDE 正在使用的账号 ID
     #[serde(default)]
     pub current_account_id: Option<String>,
+    /// 用户指定的主账号 ID（用于快速切换），同一时间只有一个
+    #[serde(default)]
+    pub primary_account_id: Option<String>,
+    /// 账号分组及其显示顺序
+    #[serde(default)]
+    pub groups: Vec<AccountGroup>,
+}
+
+/// 账号分组：分组在列表中的位置即显示顺序，account_ids 的顺序即组内顺序
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountGroup {
+    pub name: String,
+    pub account_ids: Vec<String>,
+    /// 界面上是否折叠该分组
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+/// 分组显示的账号列表中的一节；group 为 None 的一节是未分组的账号，排在最后
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountSection {
+    pub group: Option<String>,
+    pub collapsed: bool,
+    pub accounts: Vec<AccountBrief>,
+}
+
+/// 由 Token 中的用户 ID 确定性地生成账号 ID，重新导入、重新登录以及在其他设备上都得到同一个 ID；
+/// 没有用户 ID 时退回随机 ID
+pub fn derive_account_id(user_id: &str) -> String {
+    let user_id = user_id.trim();
+    if user_id.is_empty() {
+        return uuid_simple();
+    }
+    let digest = ring::digest::digest(&ring::digest::SHA256, format!("trae-account:{}", user_id).as_bytes());
+    digest.as_ref()[..16].iter().map(|b| format!("{:02x}", b)).collect()
 }
 
-/// 简单的 UUID 生成
-fn uuid_simple() -> String {
+/// 随机账号 ID（时间戳）
+pub(crate) fn uuid_simple() -> String {
     use std::time::{SystemTime, UNIX_EPOCH};
     let duration = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -86,6 +354,30 @@ pub struct AccountBrief {
     pub is_current: bool,
     /// Token 过期时间
     pub token_expired_at: Option<String>,
+    /// 没有任何凭证（如从清单导入的占位账号），需要重新登录
+    pub needs_login: bool,
+    /// 凭证状态
+    pub status: AccountStatus,
+    /// 状态是否为手动设置
+    pub status_manual: bool,
+    /// 最近一次校验凭证的时间
+    pub last_checked: Option<i64>,
+    /// 账号标签
+    pub tags: Vec<String>,
+    /// 是否已隔离
+    pub quarantined: bool,
+    /// 颜色标记
+    pub color: Option<String>,
+    /// 所属环境
+    pub environment: Option<String>,
+    /// 是否参与 Token 自动刷新
+    pub auto_refresh: bool,
+    /// Token 与会话 Cookie 中较早的过期时间（Unix 秒）
+    pub expires_at: Option<i64>,
+    /// 是否已过期
+    pub expired: bool,
+    /// 是否为主账号（由 AccountManager 设置）
+    pub is_primary: bool,
 }
 
 impl From<&Account> for AccountBrief {
@@ -101,6 +393,18 @@ impl From<&Account> for AccountBrief {
             machine_id: account.machine_id.clone(),
             is_current: false, // 默认为 false，由 AccountManager 设置
             token_expired_at: account.token_expired_at.clone(),
+            needs_login: account.needs_login(),
+            status: account.status,
+            status_manual: account.is_status_manual(),
+            last_checked: account.last_checked,
+            tags: account.tags.clone(),
+            quarantined: account.quarantined,
+            color: account.color.clone(),
+            environment: account.environment.clone(),
+            auto_refresh: account.auto_refresh,
+            expires_at: account.earliest_expiry(),
+            expired: is_expired(account.earliest_expiry()),
+            is_primary: false,
         }
     }
 }
@@ -119,6 +423,538 @@ impl AccountBrief {
             machine_id: account.machine_id.clone(),
             is_current,
             token_expired_at: account.token_expired_at.clone(),
+            needs_login: account.needs_login(),
+            status: account.status,
+            status_manual: account.is_status_manual(),
+            last_checked: account.last_checked,
+            tags: account.tags.clone(),
+            quarantined: account.quarantined,
+            color: account.color.clone(),
+            environment: account.environment.clone(),
+            auto_refresh: account.auto_refresh,
+            expires_at: account.earliest_expiry(),
+            expired: is_expired(account.earliest_expiry()),
+            is_primary: false,
+        }
+    }
+}
+
+/// 账号列表的排序方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountSort {
+    /// 保持存储顺序
+    #[default]
+    Default,
+    /// 按最早过期时间升序，已过期的在最前，过期时间未知的在最后
+    ByExpiry,
+}
+
+/// 比较两个过期时间：越早越靠前，未知的排在最后
+pub fn compare_expiry(a: Option<i64>, b: Option<i64>) -> std::cmp::Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+}
+
+pub(super) fn is_expired(expires_at: Option<i64>) -> bool {
+    expires_at.is_some_and(|ts| ts <= chrono::Utc::now().timestamp())
+}
+
+/// 导出浏览器会话时的 Cookies 格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrowserCookieFormat {
+    /// Netscape cookies.txt
+    Netscape,
+    /// Chrome Cookie 管理扩展使用的 JSON
+    Json,
+}
+
+/// Token 与会话 Cookie 过期时间相差超过该值（秒）时视为不一致
+pub const EXPIRY_DIVERGENCE_THRESHOLD_SECS: i64 = 24 * 3600;
+
+/// 账号环境的建议取值（也可填写其他名称）
+pub const SUGGESTED_ENVIRONMENTS: &[&str] = &["prod", "staging", "test"];
+
+/// 批量操作时筛选账号的条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum AccountFilter {
+    /// 按凭证状态
+    Status(AccountStatus),
+    /// 按邮箱域名（不区分大小写）
+    EmailDomain(String),
+    /// 没有 Cookies 的账号
+    MissingCookies,
+    /// 带有指定标签的账号
+    Tag(String),
+    /// 属于指定环境的账号（不区分大小写）
+    Environment(String),
+}
+
+impl AccountFilter {
+    pub fn matches(&self, account: &Account) -> bool {
+        match self {
+            AccountFilter::Status(status) => account.status == *status,
+            AccountFilter::EmailDomain(domain) => {
+                let domain = domain.trim().trim_start_matches('@');
+                account
+                    .email
+                    .rsplit_once('@')
+                    .is_some_and(|(_, d)| d.eq_ignore_ascii_case(domain))
+            }
+            AccountFilter::MissingCookies => account.cookies.trim().is_empty(),
+            AccountFilter::Tag(tag) => account.tags.iter().any(|t| t == tag.trim()),
+            AccountFilter::Environment(env) => account
+                .environment
+                .as_deref()
+                .is_some_and(|e| e.eq_ignore_ascii_case(env.trim())),
+        }
+    }
+}
+
+/// 账号 Cookies 的捕获情况：哪些是 HttpOnly、哪些脚本可读（只含名称）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CookieCaptureReport {
+    pub account_id: String,
+    pub http_only: Vec<String>,
+    pub js_accessible: Vec<String>,
+    /// 最近一次浏览器登录捕获的时间，从未通过浏览器登录捕获时为 None
+    pub captured_at: Option<i64>,
+    /// 捕获时没有读到任何 HttpOnly Cookie，登录态可能不完整，建议重新登录捕获
+    pub likely_incomplete: bool,
+}
+
+/// 数据文件与上次保存时记录的校验和的比对结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumStatus {
+    Match,
+    /// 文件在应用之外被修改，或上次保存只写了一部分
+    Mismatch,
+    /// 没有数据文件或校验和文件（旧版本数据，下次保存时生成）
+    Missing,
+}
+
+/// 重写账号数据文件的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactReport {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    /// 节省的字节数（文件变大时为 0）
+    pub reclaimed_bytes: u64,
+}
+
+/// 单个账号在数据文件中占用的空间（按 JSON 序列化后的字节数估算）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountSize {
+    pub account_id: String,
+    pub email: String,
+    /// 整个账号记录的字节数
+    pub total_bytes: u64,
+    /// 其中访问 Token 和刷新凭证的字节数
+    pub token_bytes: u64,
+    /// 其中 Cookies 的字节数
+    pub cookies_bytes: u64,
+}
+
+/// 批量校验的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// 凭证有效的账号数量
+    pub valid: usize,
+    /// 凭证失效的账号数量
+    pub expired: usize,
+    /// 因网络等原因无法判断的账号数量
+    pub failed: usize,
+    /// 最近已校验过、或没有凭证而跳过的账号数量
+    pub skipped: usize,
+    /// 无法判断的账号及原因
+    pub failures: Vec<ValidationFailure>,
+    /// 每个已校验账号的结果
+    pub results: Vec<ValidationResult>,
+    /// 是否被 cancel_validation 中途取消（此时结果只包含已完成的账号）
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// 单个账号的校验结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationResult {
+    pub account_id: String,
+    pub email: String,
+    /// 校验得到的状态，无法判断时为空（原因见 failures）
+    pub status: Option<AccountStatus>,
+}
+
+/// 账号无法切换或打开时的阻塞原因，按检查顺序排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivationBlocker {
+    /// 存储处于只读模式（其他实例占用或安全模式）
+    StoreReadOnly,
+    NotFound,
+    /// 账号已被隔离
+    Quarantined,
+    NoToken,
+    TokenExpired,
+}
+
+impl ActivationBlocker {
+    /// 给用户的处理建议
+    pub fn guidance(&self) -> &'static str {
+        match self {
+            ActivationBlocker::StoreReadOnly => "账号数据处于只读模式，请关闭其他正在运行的实例或退出安全模式后重试",
+            ActivationBlocker::NotFound => "账号不存在，可能已被删除，请刷新账号列表",
+            ActivationBlocker::Quarantined => "账号已被隔离，请先从隔离区恢复后再切换",
+            ActivationBlocker::NoToken => "账号没有 Token，请刷新 Token 或重新登录",
+            ActivationBlocker::TokenExpired => "账号的 Token 已过期，请刷新 Token 或重新登录",
         }
     }
 }
+
+/// 账号能否切换的诊断结果：只报告第一个阻塞原因，没有阻塞时 blocker 为 None
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivationDiagnosis {
+    pub account_id: String,
+    pub blocker: Option<ActivationBlocker>,
+    pub guidance: Option<String>,
+}
+
+impl ActivationDiagnosis {
+    pub fn new(account_id: &str, blocker: Option<ActivationBlocker>) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            blocker,
+            guidance: blocker.map(|b| b.guidance().to_string()),
+        }
+    }
+}
+
+/// 用一个 Token 登录时的预期结果：新增账号，或更新已有账号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LoginPrediction {
+    WouldCreate,
+    WouldUpdate { existing_id: String, email: String },
+}
+
+/// 回放一次记录下的 GetUserToken 响应的结果：将会添加的账号信息（不含任何凭证值）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureReplay {
+    pub user_id: String,
+    pub tenant_id: String,
+    pub token_length: usize,
+    pub has_refresh_token: bool,
+    /// Token 签发和过期时间（Unix 秒）
+    pub issued_at: i64,
+    pub expires_at: Option<i64>,
+    /// 与已有账号重复时为该账号的 ID 和邮箱，正式登录时会走重复账号的处理流程
+    pub duplicate_of: Option<String>,
+    pub duplicate_email: Option<String>,
+}
+
+/// 底层用户 ID 相同但邮箱不同的账号分组（同一用户绑定了多个邮箱），仅供用户核对，不会自动合并
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedAccountGroup {
+    pub user_id: String,
+    pub account_ids: Vec<String>,
+    /// 组内出现的不同邮箱（按首次出现顺序）
+    pub emails: Vec<String>,
+}
+
+/// 同名但值不同的 Cookie（只含名称和不同值的数量，不含值）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CookieConflict {
+    pub name: String,
+    pub distinct_values: usize,
+}
+
+/// 域名迁移时受影响的账号（只含 Cookie 名称）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CookieRemap {
+    pub account_id: String,
+    pub email: String,
+    /// 改写到新域名的 Cookie
+    pub remapped: Vec<String>,
+    /// 因 host-only 保留在旧域名的 Cookie，需要重新登录才能在新域名下获得
+    pub kept_host_only: Vec<String>,
+}
+
+/// 通过临时代理校验账号的结果（代理不会保存到账号上）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyValidation {
+    pub account_id: String,
+    pub proxy: String,
+    /// 校验得到的状态，请求失败时为空
+    pub status: Option<AccountStatus>,
+    /// 请求耗时（毫秒）
+    pub latency_ms: u64,
+    /// 请求失败时的错误信息
+    pub message: Option<String>,
+    /// 网络错误的具体类型，不是网络错误时为空
+    pub network_error: Option<crate::api::NetworkErrorKind>,
+}
+
+/// 单个账号校验失败的原因
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationFailure {
+    pub account_id: String,
+    pub message: String,
+    /// 网络错误的具体类型，不是网络错误时为空
+    pub network_error: Option<crate::api::NetworkErrorKind>,
+}
+
+/// 数据目录可写性检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageCheck {
+    /// 数据目录路径
+    pub path: String,
+    pub writable: bool,
+    /// 不可写的原因
+    pub error: Option<String>,
+}
+
+/// 按状态统计的账号数量
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountStats {
+    pub total: usize,
+    pub valid: usize,
+    pub expired: usize,
+    /// 从未校验过的账号
+    pub unknown: usize,
+    /// 缺少凭证、需要重新登录的账号
+    pub needs_login: usize,
+}
+
+/// 账号数据检查发现的问题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreIssue {
+    pub account_id: String,
+    pub email: String,
+    /// 缺失的关键 Cookie 名称
+    pub missing_cookies: Vec<String>,
+}
+
+/// 账号问题的类型，按严重程度从高到低排列（排序时直接比较）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    /// 凭证已失效或已过期
+    Expired,
+    /// 没有 Cookies 或缺少关键 Cookie
+    MissingCookies,
+    /// Token 与会话 Cookie 的过期时间相差过大
+    ExpiryMismatch,
+    /// Token 即将过期
+    ExpiringSoon,
+    /// 尚未校验或校验结果已过时
+    Unknown,
+}
+
+/// 原地重新登录时各项凭证是否被替换（未替换的保留原值）
+#[derive(Debug, Clone, Serialize)]
+pub struct SelectiveUpdate {
+    pub account_id: String,
+    pub email: String,
+    pub token_updated: bool,
+    pub cookies_updated: bool,
+}
+
+/// 一键检查得到的单个账号问题
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountProblem {
+    pub account_id: String,
+    pub email: String,
+    pub kind: ProblemKind,
+    /// 具体说明（如缺失的 Cookie 名称）
+    pub detail: String,
+}
+
+/// 导入时遇到已存在账号的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStrategy {
+    /// 跳过已存在的账号
+    Skip,
+    /// 用导入的数据覆盖已存在的账号
+    Overwrite,
+    /// 保留 Token 签发时间（iat）较新的一方
+    #[default]
+    KeepNewest,
+}
+
+impl ImportStrategy {
+    /// 导入条目与已存在账号冲突时，按 Token 签发时间决定是否覆盖；
+    /// 不覆盖时返回写入导入记录的原因
+    pub fn resolve(self, incoming_iat: Option<i64>, existing_iat: Option<i64>) -> Result<(), &'static str> {
+        match self {
+            ImportStrategy::Skip => Err("本地已有该账号，按导入策略跳过"),
+            ImportStrategy::Overwrite => Ok(()),
+            ImportStrategy::KeepNewest => match (incoming_iat, existing_iat) {
+                (Some(incoming), Some(existing)) if incoming > existing => Ok(()),
+                (Some(_), Some(_)) => Err("本地账号的 Token 不比导入的旧，保留本地账号"),
+                (Some(_), None) => Ok(()),
+                (None, _) => Err("导入的条目没有可解析的 Token，无法判断新旧，保留本地账号"),
+            },
+        }
+    }
+}
+
+/// 单个账号的导入动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Added,
+    Skipped,
+    Overwritten,
+    Failed,
+    /// 导入文件中同一用户的其他条目已被采用，本条目被合并
+    Duplicate,
+}
+
+/// 单个账号的导入结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRecord {
+    pub user_id: String,
+    pub email: String,
+    pub action: ImportAction,
+    pub message: Option<String>,
+    /// 保留源 ID 导入时，源 ID 已被占用而改用派生 ID 的记录
+    #[serde(default)]
+    pub id_remap: Option<IdRemap>,
+}
+
+/// 导入时源 ID 与实际使用的 ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdRemap {
+    pub source_id: String,
+    pub new_id: String,
+}
+
+/// 导入文件内指向同一用户的多个条目（与本地已有账号的冲突分开报告）
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportDuplicate {
+    pub user_id: String,
+    /// 这些条目在导入文件中的下标（从 0 开始）
+    pub indices: Vec<usize>,
+    /// 实际导入的条目下标：Token 签发时间最新的条目，相同时取文件中靠后的条目
+    pub kept: usize,
+    pub emails: Vec<String>,
+}
+
+/// 账号清单条目（仅包含元数据，不含 Token / Cookies 等敏感信息）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub user_id: String,
+    pub plan_type: String,
+    pub created_at: i64,
+}
+
+impl From<&Account> for ManifestEntry {
+    fn from(account: &Account) -> Self {
+        Self {
+            id: account.id.clone(),
+            name: account.name.clone(),
+            email: account.email.clone(),
+            user_id: account.user_id.clone(),
+            plan_type: account.plan_type.clone(),
+            created_at: account.created_at,
+        }
+    }
+}
+
+/// 账号存储快照信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub created_at: i64,
+    pub account_count: usize,
+    pub size: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_never_overwrites() {
+        assert!(ImportStrategy::Skip.resolve(Some(200), Some(100)).is_err());
+        assert!(ImportStrategy::Skip.resolve(Some(200), None).is_err());
+    }
+
+    #[test]
+    fn overwrite_always_overwrites() {
+        assert!(ImportStrategy::Overwrite.resolve(Some(100), Some(200)).is_ok());
+        assert!(ImportStrategy::Overwrite.resolve(None, Some(200)).is_ok());
+    }
+
+    #[test]
+    fn keep_newest_compares_issued_at() {
+        let strategy = ImportStrategy::KeepNewest;
+        assert!(strategy.resolve(Some(200), Some(100)).is_ok());
+        assert!(strategy.resolve(Some(100), Some(200)).is_err());
+        // 签发时间相同时保留本地账号
+        assert!(strategy.resolve(Some(100), Some(100)).is_err());
+        // 本地账号没有可解析的 Token 时导入的更新
+        assert!(strategy.resolve(Some(100), None).is_ok());
+        assert!(strategy.resolve(None, None).is_err());
+    }
+
+    #[test]
+    fn keep_newest_is_default() {
+        assert_eq!(ImportStrategy::default(), ImportStrategy::KeepNewest);
+    }
+
+    fn account() -> Account {
+        Account::new("test".to_string(), "test@example.com".to_string(), String::new(), "1001".to_string(), String::new())
+    }
+
+    #[test]
+    fn health_history_appends_in_order() {
+        let mut account = account();
+        account.record_health(AccountStatus::Valid, 1);
+        account.record_health(AccountStatus::Expired, 2);
+        let history: Vec<(i64, AccountStatus)> = account.health_history.iter().map(|r| (r.checked_at, r.status)).collect();
+        assert_eq!(history, vec![(1, AccountStatus::Valid), (2, AccountStatus::Expired)]);
+    }
+
+    #[test]
+    fn health_history_evicts_oldest_beyond_cap() {
+        let mut account = account();
+        for checked_at in 0..(HEALTH_HISTORY_LEN as i64 + 5) {
+            account.record_health(AccountStatus::Valid, checked_at);
+        }
+        assert_eq!(account.health_history.len(), HEALTH_HISTORY_LEN);
+        assert_eq!(account.health_history.first().unwrap().checked_at, 5);
+        assert_eq!(account.health_history.last().unwrap().checked_at, HEALTH_HISTORY_LEN as i64 + 4);
+    }
+
+    #[test]
+    fn derived_id_is_stable_per_user() {
+        assert_eq!(derive_account_id("1001"), derive_account_id("1001"));
+        assert_eq!(derive_account_id(" 1001 "), derive_account_id("1001"));
+        assert_eq!(derive_account_id("1001").len(), 32);
+        assert_ne!(derive_account_id("1001"), derive_account_id("1002"));
+    }
+
+    #[test]
+    fn accounts_for_the_same_user_share_an_id() {
+        assert_eq!(account().id, Account::new("other".to_string(), String::new(), String::new(), "1001".to_string(), String::new()).id);
+        assert_eq!(account().id, derive_account_id("1001"));
+    }
+
+    #[test]
+    fn missing_user_id_falls_back_to_random_id() {
+        // 随机 ID 由时间戳生成，比派生的 32 位十六进制 ID 短
+        let id = derive_account_id("  ");
+        assert!(!id.is_empty() && id.len() < 32, "{}", id);
+    }
+}
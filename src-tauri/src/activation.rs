@@ -0,0 +1,118 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::account::AccountManager;
+
+/// 切换账号的执行队列：同一时间只执行一个切换，按请求顺序依次执行（tokio Mutex 按等待顺序唤醒）
+struct ActivationQueue {
+    turn: Mutex<()>,
+    /// 最近一次切换请求的序号，用于合并连续的请求
+    latest: AtomicU64,
+}
+
+impl ActivationQueue {
+    fn new() -> Self {
+        Self { turn: Mutex::new(()), latest: AtomicU64::new(0) }
+    }
+
+    /// 登记一个切换请求，返回其序号
+    fn enqueue(&self) -> u64 {
+        self.latest.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// 等待轮到该请求执行；coalesce 为 true 且排队期间有更新的请求时返回 None
+    async fn turn(&self, ticket: u64, coalesce: bool) -> Option<MutexGuard<'_, ()>> {
+        let turn = self.turn.lock().await;
+        if coalesce && self.latest.load(Ordering::SeqCst) != ticket {
+            return None;
+        }
+        Some(turn)
+    }
+}
+
+static QUEUE: Lazy<ActivationQueue> = Lazy::new(ActivationQueue::new);
+
+/// 排队切换账号，返回是否实际执行；coalesce 为 true 时，排队期间有更新的请求则跳过本次
+pub async fn switch_account(
+    app: Option<&AppHandle>,
+    manager: &Mutex<AccountManager>,
+    account_id: &str,
+    coalesce: bool,
+) -> Result<bool> {
+    let ticket = QUEUE.enqueue();
+    if let Some(app) = app {
+        let _ = app.emit("activation-queued", serde_json::json!({ "id": account_id, "ticket": ticket }));
+    }
+
+    let Some(_turn) = QUEUE.turn(ticket, coalesce).await else {
+        println!("[INFO] 切换到账号 {} 的请求已被之后的请求取代", account_id);
+        return Ok(false);
+    };
+    manager.lock().await.switch_account(account_id)?;
+
+    if let Some(app) = app {
+        let _ = app.emit("activation-applied", serde_json::json!({ "id": account_id, "ticket": ticket }));
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn requests_run_one_at_a_time_in_order() {
+        let queue = Arc::new(ActivationQueue::new());
+        let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        // 先占住队列，使之后的请求按登记顺序排队等待
+        let first = queue.enqueue();
+        let held = queue.turn(first, false).await.unwrap();
+        let mut tasks = Vec::new();
+        for id in 0..4 {
+            let (queue, log) = (queue.clone(), log.clone());
+            tasks.push(tokio::spawn(async move {
+                let ticket = queue.enqueue();
+                let _turn = queue.turn(ticket, false).await.unwrap();
+                log.lock().unwrap().push(("start", id));
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                log.lock().unwrap().push(("end", id));
+            }));
+            tokio::task::yield_now().await;
+        }
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let expected: Vec<_> = (0..4).flat_map(|id| [("start", id), ("end", id)]).collect();
+        assert_eq!(*log.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn coalescing_skips_superseded_requests() {
+        let queue = ActivationQueue::new();
+        let first = queue.enqueue();
+        let held = queue.turn(first, true).await.unwrap();
+        let second = queue.enqueue();
+        let third = queue.enqueue();
+        drop(held);
+
+        assert!(queue.turn(second, true).await.is_none());
+        assert!(queue.turn(third, true).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn without_coalescing_every_request_runs() {
+        let queue = ActivationQueue::new();
+        let first = queue.enqueue();
+        let second = queue.enqueue();
+        assert!(queue.turn(first, false).await.is_some());
+        assert!(queue.turn(second, false).await.is_some());
+    }
+}
@@ -0,0 +1,183 @@
+use ::age::secrecy::SecretString;
+use ::age::{scrypt, x25519, Decryptor, Encryptor};
+use anyhow::{anyhow, Result};
+use std::io::{Read, Write};
+use std::iter;
+
+/// 本程序生成的 scrypt 工作因子（log2 N），与 age 命令行工具默认值一致
+const SCRYPT_LOG_N: u8 = 18;
+
+/// 解密时接受的最大 scrypt 工作因子：log2 N = 20 时 scrypt（r = 8）约需 1 GiB 内存，
+/// 更高的值会被拒绝，避免恶意文件耗尽内存
+const MAX_SCRYPT_LOG_N: u8 = 20;
+
+/// age 文件的接收方
+pub enum Recipient {
+    /// 用密码加密
+    Passphrase(String),
+    /// 用 X25519 公钥（age1 开头）加密
+    X25519(x25519::Recipient),
+}
+
+impl Recipient {
+    /// 解析 age1 开头的公钥，其他输入视为密码
+    pub fn parse(value: &str) -> Result<Recipient> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(anyhow!("接收方不能为空"));
+        }
+        if value.to_ascii_lowercase().starts_with("age1") {
+            let recipient = value
+                .parse::<x25519::Recipient>()
+                .map_err(|_| anyhow!("age 公钥格式无效"))?;
+            return Ok(Recipient::X25519(recipient));
+        }
+        Ok(Recipient::Passphrase(value.to_string()))
+    }
+}
+
+/// 加密为标准 age 文件，可用 age 工具解密
+pub fn encrypt(plaintext: &[u8], recipient: &Recipient) -> Result<Vec<u8>> {
+    encrypt_with_work_factor(plaintext, recipient, SCRYPT_LOG_N)
+}
+
+fn encrypt_with_work_factor(plaintext: &[u8], recipient: &Recipient, log_n: u8) -> Result<Vec<u8>> {
+    let encryptor = match recipient {
+        Recipient::Passphrase(passphrase) => {
+            let mut recipient = scrypt::Recipient::new(SecretString::from(passphrase.clone()));
+            recipient.set_work_factor(log_n);
+            Encryptor::with_recipients(iter::once(&recipient as &dyn ::age::Recipient))
+        }
+        Recipient::X25519(recipient) => Encryptor::with_recipients(iter::once(recipient as &dyn ::age::Recipient)),
+    }
+    .map_err(|e| anyhow!("age 加密失败: {}", e))?;
+
+    let mut output = Vec::new();
+    let mut writer = encryptor
+        .wrap_output(&mut output)
+        .map_err(|e| anyhow!("age 加密失败: {}", e))?;
+    writer.write_all(plaintext).map_err(|e| anyhow!("age 加密失败: {}", e))?;
+    writer.finish().map_err(|e| anyhow!("age 加密失败: {}", e))?;
+    Ok(output)
+}
+
+/// 解密 age 文件：secret 为 AGE-SECRET-KEY-1 开头的 X25519 身份密钥时按身份解密，否则视为密码
+pub fn decrypt(data: &[u8], secret: &str) -> Result<Vec<u8>> {
+    let secret = secret.trim();
+    let decryptor = Decryptor::new(data).map_err(|e| anyhow!("age 文件无效: {}", e))?;
+    let reader = if secret.to_ascii_uppercase().starts_with("AGE-SECRET-KEY-1") {
+        let identity = secret
+            .parse::<x25519::Identity>()
+            .map_err(|_| anyhow!("age 身份密钥格式无效"))?;
+        decryptor.decrypt(iter::once(&identity as &dyn ::age::Identity))
+    } else {
+        if !decryptor.is_scrypt() {
+            return Err(anyhow!("该 age 文件不是用密码加密的，请输入对应的 AGE-SECRET-KEY-1 身份密钥"));
+        }
+        let mut identity = scrypt::Identity::new(SecretString::from(secret.to_string()));
+        identity.set_max_work_factor(MAX_SCRYPT_LOG_N);
+        decryptor.decrypt(iter::once(&identity as &dyn ::age::Identity))
+    };
+    let mut reader = reader.map_err(|e| anyhow!("age 文件解密失败: {}", e))?;
+
+    let mut plaintext = Vec::new();
+    reader
+        .read_to_end(&mut plaintext)
+        .map_err(|_| anyhow!("age 文件已损坏或已被修改"))?;
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::age::secrecy::ExposeSecret;
+    use ring::digest;
+
+    // 以下文件来自 age 官方测试集（C2SP CCTV age testkit），由 age 参考实现生成；
+    // 明文的 SHA-256 记在测试集中
+    const TESTKIT_SCRYPT: &[u8] = include_bytes!("../testdata/age/scrypt.age");
+    const TESTKIT_X25519: &[u8] = include_bytes!("../testdata/age/x25519.age");
+    const TESTKIT_SCRYPT_WORK_FACTOR_23: &[u8] = include_bytes!("../testdata/age/scrypt_work_factor_23.age");
+    const TESTKIT_PAYLOAD_SHA256: &str = "013f54400c82da08037759ada907a8b864e97de81c088a182062c4b5622fd2ab";
+    const TESTKIT_IDENTITY: &str = "AGE-SECRET-KEY-1EGTZVFFV20835NWYV6270LXYVK2VKNX2MMDKWYKLMGR48UAWX40Q2P2LM0";
+
+    fn sha256_hex(bytes: &[u8]) -> String {
+        digest::digest(&digest::SHA256, bytes)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    #[test]
+    fn decrypts_reference_files() {
+        let plaintext = decrypt(TESTKIT_SCRYPT, "password").unwrap();
+        assert_eq!(sha256_hex(&plaintext), TESTKIT_PAYLOAD_SHA256);
+
+        let plaintext = decrypt(TESTKIT_X25519, TESTKIT_IDENTITY).unwrap();
+        assert_eq!(sha256_hex(&plaintext), TESTKIT_PAYLOAD_SHA256);
+    }
+
+    #[test]
+    fn rejects_work_factor_above_cap() {
+        assert!(decrypt(TESTKIT_SCRYPT_WORK_FACTOR_23, "password").is_err());
+    }
+
+    #[test]
+    fn passphrase_round_trip() {
+        let recipient = Recipient::Passphrase("correct horse".to_string());
+        for len in [0, 5, 64 * 1024 + 1] {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let sealed = encrypt_with_work_factor(&plaintext, &recipient, 10).unwrap();
+            assert!(sealed.starts_with(b"age-encryption.org/v1\n-> scrypt "));
+            assert_eq!(decrypt(&sealed, "correct horse").unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn wrong_passphrase_fails() {
+        let sealed = encrypt_with_work_factor(b"accounts", &Recipient::Passphrase("right".to_string()), 10).unwrap();
+        assert!(decrypt(&sealed, "wrong").is_err());
+    }
+
+    #[test]
+    fn x25519_round_trip_and_wrong_key() {
+        let identity = x25519::Identity::generate();
+        let recipient = Recipient::parse(&identity.to_public().to_string()).unwrap();
+        let sealed = encrypt(b"accounts", &recipient).unwrap();
+        assert!(sealed.starts_with(b"age-encryption.org/v1\n-> X25519 "));
+
+        let key = identity.to_string();
+        assert_eq!(decrypt(&sealed, key.expose_secret()).unwrap(), b"accounts");
+
+        let other = x25519::Identity::generate().to_string();
+        assert!(decrypt(&sealed, other.expose_secret()).is_err());
+        // 没有密码接收方时不能用密码解密
+        assert!(decrypt(&sealed, "password").is_err());
+    }
+
+    #[test]
+    fn tampered_payload_fails() {
+        let sealed = encrypt_with_work_factor(b"accounts", &Recipient::Passphrase("pw".to_string()), 10).unwrap();
+        let mut tampered = sealed.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(decrypt(&tampered, "pw").is_err());
+
+        let mut truncated = sealed.clone();
+        truncated.truncate(sealed.len() - 1);
+        assert!(decrypt(&truncated, "pw").is_err());
+    }
+
+    #[test]
+    fn parse_recipient() {
+        assert!(matches!(Recipient::parse(" secret ").unwrap(), Recipient::Passphrase(p) if p == "secret"));
+        assert!(Recipient::parse("  ").is_err());
+        assert!(matches!(
+            Recipient::parse("age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p").unwrap(),
+            Recipient::X25519(_)
+        ));
+        // 校验和错误
+        assert!(Recipient::parse("age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8q").is_err());
+    }
+}
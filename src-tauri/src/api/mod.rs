@@ -1,5 +1,5 @@
 pub mod trae_api;
 pub mod types;
 
-pub use trae_api::TraeApiClient;
+pub use trae_api::{classify_network_error, fetch_server_time, parse_proxy_url, shared_client, test_proxy, TraeApiClient};
 pub use types::*;
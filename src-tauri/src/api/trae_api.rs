@@ -1,5 +1,8 @@
 use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
 use reqwest::{header, Client};
+use std::collections::HashMap;
+use std::sync::Mutex;
 use serde_json::json;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
@@ -9,19 +12,75 @@ const API_BASE_US: &str = "https://api-us-east.trae.ai";
 const API_BASE_SG: &str = "https://api-sg-central.trae.ai";
 const API_BASE_UG: &str = "https://ug-normal.trae.ai";
 
+/// 默认 User-Agent（账号未单独配置时使用）
+pub const DEFAULT_USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36";
+
+/// 不允许通过账号自定义请求头覆盖的请求头（由客户端自身管理）
+const FORBIDDEN_EXTRA_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "host",
+    "content-length",
+    "content-type",
+    "connection",
+    "transfer-encoding",
+    "user-agent",
+];
+
+/// 登录时捕获的请求头最多保留的数量，以及单个值的最大长度
+const MAX_CAPTURED_HEADERS: usize = 16;
+const MAX_CAPTURED_HEADER_LEN: usize = 1024;
+
+/// 共享客户端的默认请求超时，避免无响应的连接让批量操作一直挂起
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// 按 (代理地址, User-Agent) 缓存的 reqwest 客户端（代理为 None 表示直连），批量校验和刷新时复用连接池
+///
+/// User-Agent 设置在客户端上，不同 User-Agent 的账号不共用连接，避免服务端把它们关联到同一连接上
+static CLIENT_POOL: Lazy<Mutex<HashMap<ClientKey, Client>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 共享客户端的缓存键：(代理地址, User-Agent)
+type ClientKey = (Option<String>, String);
+
+/// 获取指定代理和 User-Agent（为空时使用默认值）的共享客户端，不存在时创建并缓存（Client 内部为 Arc，克隆开销很小）
+pub fn shared_client(proxy: Option<&str>, user_agent: Option<&str>) -> Result<Client> {
+    crate::offline::ensure_online()?;
+    let proxy = proxy.map(str::trim).filter(|p| !p.is_empty()).map(str::to_string);
+    let user_agent = user_agent.map(str::trim).filter(|ua| !ua.is_empty()).unwrap_or(DEFAULT_USER_AGENT);
+    let key = (proxy, user_agent.to_string());
+    let mut pool = CLIENT_POOL.lock().map_err(|_| anyhow!("客户端缓存不可用"))?;
+    if let Some(client) = pool.get(&key) {
+        return Ok(client.clone());
+    }
+    let mut builder = Client::builder()
+        .user_agent(user_agent)
+        .timeout(std::time::Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS));
+    if let Some(proxy) = key.0.as_deref() {
+        builder = builder.proxy(reqwest::Proxy::all(parse_proxy_url(proxy)?)?);
+    }
+    let client = builder.build()?;
+    pool.insert(key, client.clone());
+    Ok(client)
+}
+
 /// Trae API 客户端
 pub struct TraeApiClient {
     client: Client,
     cookies: String,
     jwt_token: Option<String>,
     api_base: String,  // 动态 API 端点
+    user_agent: String,
+    /// 使用的代理（None 为直连），与 user_agent 一起决定使用哪个共享客户端
+    proxy: Option<String>,
+    /// 账号级别的附加请求头
+    extra_headers: header::HeaderMap,
 }
 
 impl TraeApiClient {
     /// 创建新的 API 客户端（使用 Cookies）
     pub fn new(cookies: &str) -> Result<Self> {
-        let client = Client::builder()
-            .build()?;
+        let client = shared_client(None, None)?;
 
         // 清理 Cookie 字符串：移除换行符、多余空格
         let cleaned_cookies = cookies
@@ -39,13 +98,15 @@ impl TraeApiClient {
             cookies: cleaned_cookies,
             jwt_token: None,
             api_base,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            proxy: None,
+            extra_headers: header::HeaderMap::new(),
         })
     }
 
     /// 创建新的 API 客户端（使用 Token）
     pub fn new_with_token(token: &str) -> Result<Self> {
-        let client = Client::builder()
-            .build()?;
+        let client = shared_client(None, None)?;
 
         // 从 Token 中解析区域信息，默认尝试多个端点
         let api_base = API_BASE_SG.to_string(); // 默认使用新加坡，因为大多数亚洲用户
@@ -55,9 +116,72 @@ impl TraeApiClient {
             cookies: String::new(),
             jwt_token: Some(token.to_string()),
             api_base,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            proxy: None,
+            extra_headers: header::HeaderMap::new(),
         })
     }
 
+    /// 使用自定义 User-Agent（为空时保留默认值）
+    pub fn with_user_agent(mut self, user_agent: Option<&str>) -> Result<Self> {
+        if let Some(ua) = user_agent.map(str::trim).filter(|ua| !ua.is_empty()) {
+            self.user_agent = ua.to_string();
+            self.client = shared_client(self.proxy.as_deref(), Some(&self.user_agent))?;
+        }
+        Ok(self)
+    }
+
+    /// 通过代理发送请求（为空时直连）
+    pub fn with_proxy(mut self, proxy: Option<&str>) -> Result<Self> {
+        if let Some(proxy) = proxy.map(str::trim).filter(|p| !p.is_empty()) {
+            self.proxy = Some(proxy.to_string());
+            self.client = shared_client(self.proxy.as_deref(), Some(&self.user_agent))?;
+        }
+        Ok(self)
+    }
+
+    /// 附加账号自定义的请求头
+    pub fn with_extra_headers(mut self, extra_headers: &HashMap<String, String>) -> Result<Self> {
+        self.extra_headers = Self::parse_extra_headers(extra_headers)?;
+        Ok(self)
+    }
+
+    /// 校验并解析自定义请求头：名称和值必须合法，且不能覆盖客户端管理的请求头
+    pub fn parse_extra_headers(extra_headers: &HashMap<String, String>) -> Result<header::HeaderMap> {
+        let mut headers = header::HeaderMap::new();
+        for (name, value) in extra_headers {
+            let name = header::HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| anyhow!("请求头名称无效: {}", name))?;
+            if FORBIDDEN_EXTRA_HEADERS.contains(&name.as_str()) {
+                return Err(anyhow!("不允许自定义请求头: {}", name));
+            }
+            let value = header::HeaderValue::from_str(value.trim())
+                .map_err(|_| anyhow!("请求头 {} 的值无效", name))?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+
+    /// 从登录时 GetUserToken 请求的请求头中挑出可在刷新时复用的部分：只保留合法的 x- 自定义请求头，
+    /// 不含客户端自身管理的请求头，名称统一为小写
+    pub fn replayable_request_headers(headers: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut replayable: Vec<(String, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .filter(|(name, value)| {
+                name.starts_with("x-")
+                    && !FORBIDDEN_EXTRA_HEADERS.contains(&name.as_str())
+                    && !value.is_empty()
+                    && value.len() <= MAX_CAPTURED_HEADER_LEN
+                    && header::HeaderName::from_bytes(name.as_bytes()).is_ok()
+                    && header::HeaderValue::from_str(value).is_ok()
+            })
+            .collect();
+        replayable.sort();
+        replayable.truncate(MAX_CAPTURED_HEADERS);
+        replayable.into_iter().collect()
+    }
+
     /// 从 Cookies 中检测 API 端点
     fn detect_api_base_from_cookies(cookies: &str) -> String {
         // 检查 store-idc 或 trae-target-idc
@@ -102,10 +226,9 @@ impl TraeApiClient {
         headers.insert(header::ACCEPT, "application/json, text/plain, */*".parse()?);
         headers.insert(header::ORIGIN, "https://www.trae.ai".parse()?);
         headers.insert(header::REFERER, "https://www.trae.ai/".parse()?);
-        headers.insert(
-            header::USER_AGENT,
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36".parse()?,
-        );
+        let ua_value = header::HeaderValue::from_str(&self.user_agent)
+            .map_err(|e| anyhow!("User-Agent 格式错误: {}", e))?;
+        headers.insert(header::USER_AGENT, ua_value);
 
         if let Some(token) = &self.jwt_token {
             let auth_value = header::HeaderValue::from_bytes(
@@ -113,6 +236,7 @@ impl TraeApiClient {
             ).map_err(|e| anyhow!("Token 格式错误: {}", e))?;
             headers.insert(header::AUTHORIZATION, auth_value);
         }
+        headers.extend(self.extra_headers.clone());
 
         Ok(headers)
     }
@@ -197,8 +321,46 @@ impl TraeApiClient {
         Ok(data.result)
     }
 
+    /// 从 GetUserToken 响应体或 Web Storage 中的值提取访问 Token 和刷新凭证（与登录页注入脚本的 tokenFromStorageValue 规则一致）
+    ///
+    /// 支持 `Result.Token` 响应、带 `Token`/`token` 字段的对象（以及 JSON 字符串包裹的裸 JWT）
+    pub fn extract_token_from_body(body: &str) -> Result<(String, Option<String>)> {
+        let data: serde_json::Value = match serde_json::from_str(body.trim()) {
+            Ok(data) => data,
+            // Web Storage 中也可能直接存放未经 JSON 编码的 JWT
+            Err(_) if body.trim().split('.').count() == 3 => serde_json::Value::String(body.trim().to_string()),
+            Err(e) => return Err(anyhow!("响应体不是有效的 JSON: {}", e)),
+        };
+        let non_empty = |value: &serde_json::Value| value.as_str().filter(|t| !t.is_empty()).map(str::to_string);
+
+        if let Some(token) = non_empty(&data["Result"]["Token"]) {
+            return Ok((token, non_empty(&data["Result"]["RefreshToken"])));
+        }
+        if let Some(token) = non_empty(&data["Token"]).or_else(|| non_empty(&data["token"])) {
+            let refresh_token = non_empty(&data["RefreshToken"]).or_else(|| non_empty(&data["refreshToken"]));
+            return Ok((token, refresh_token));
+        }
+        if let Some(token) = non_empty(&data).filter(|t| t.split('.').count() == 3) {
+            return Ok((token, None));
+        }
+        Err(anyhow!("响应体中没有找到 Token"))
+    }
+
     /// 解析 JWT Token 获取用户信息
-    fn parse_jwt_token(token: &str) -> Result<JwtPayload> {
+    pub fn parse_jwt_token(token: &str) -> Result<JwtPayload> {
+        let claims = Self::decode_jwt_claims(token)?;
+        let payload: JwtPayloadRaw = serde_json::from_value(claims)
+            .map_err(|e| anyhow!("解析 JWT payload 失败: {}", e))?;
+
+        Ok(JwtPayload {
+            user_id: payload.data.id,
+            tenant_id: payload.data.tenant_id,
+            issued_at: payload.iat,
+        })
+    }
+
+    /// 解码 JWT Token 的 payload，返回全部声明（不校验签名）
+    pub fn decode_jwt_claims(token: &str) -> Result<serde_json::Value> {
         // JWT 格式: header.payload.signature
         let parts: Vec<&str> = token.split('.').collect();
         if parts.len() != 3 {
@@ -219,13 +381,8 @@ impl TraeApiClient {
         let payload_str = String::from_utf8(payload_bytes)
             .map_err(|e| anyhow!("JWT payload 不是有效的 UTF-8: {}", e))?;
 
-        let payload: JwtPayloadRaw = serde_json::from_str(&payload_str)
-            .map_err(|e| anyhow!("解析 JWT payload 失败: {}", e))?;
-
-        Ok(JwtPayload {
-            user_id: payload.data.id,
-            tenant_id: payload.data.tenant_id,
-        })
+        serde_json::from_str(&payload_str)
+            .map_err(|e| anyhow!("解析 JWT payload 失败: {}", e))
     }
 
     /// 构建请求头
@@ -241,10 +398,9 @@ impl TraeApiClient {
 
         headers.insert(header::ORIGIN, "https://www.trae.ai".parse()?);
         headers.insert(header::REFERER, "https://www.trae.ai/".parse()?);
-        headers.insert(
-            header::USER_AGENT,
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36".parse()?,
-        );
+        let ua_value = header::HeaderValue::from_str(&self.user_agent)
+            .map_err(|e| anyhow!("User-Agent 格式错误: {}", e))?;
+        headers.insert(header::USER_AGENT, ua_value);
 
         if with_auth {
             if let Some(token) = &self.jwt_token {
@@ -254,6 +410,7 @@ impl TraeApiClient {
                 headers.insert(header::AUTHORIZATION, auth_value);
             }
         }
+        headers.extend(self.extra_headers.clone());
 
         Ok(headers)
     }
@@ -521,3 +678,117 @@ impl TraeApiClient {
         Ok(())
     }
 }
+
+/// 校验代理地址格式（仅支持 http/https 代理）
+pub fn parse_proxy_url(proxy: &str) -> Result<reqwest::Url> {
+    let url = reqwest::Url::parse(proxy.trim()).map_err(|e| anyhow!("代理地址格式错误: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("不支持的代理协议: {}（仅支持 http/https）", url.scheme()));
+    }
+    if url.host_str().is_none_or(|h| h.is_empty()) {
+        return Err(anyhow!("代理地址缺少主机名"));
+    }
+    Ok(url)
+}
+
+/// 将请求错误归类为具体的网络错误类型；不是网络错误时返回 None
+pub fn classify_network_error(error: &anyhow::Error) -> Option<NetworkErrorKind> {
+    if error.downcast_ref::<crate::offline::OfflineMode>().is_some() {
+        return Some(NetworkErrorKind::Offline);
+    }
+    let mut is_connect = false;
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() {
+                return Some(NetworkErrorKind::Timeout);
+            }
+            is_connect |= e.is_connect() || e.is_request();
+        }
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            match e.kind() {
+                std::io::ErrorKind::ConnectionRefused => return Some(NetworkErrorKind::ConnectionRefused),
+                std::io::ErrorKind::TimedOut => return Some(NetworkErrorKind::Timeout),
+                _ => {}
+            }
+        }
+    }
+
+    // hyper 等底层错误类型不公开，且部分错误已被转换为文本，按错误信息判断
+    let text = format!("{:#}", error).to_lowercase();
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|p| text.contains(p));
+    if contains_any(&["dns error", "failed to lookup address", "name or service not known", "no such host"]) {
+        Some(NetworkErrorKind::Dns)
+    } else if contains_any(&["certificate", "tls", "ssl", "handshake"]) {
+        Some(NetworkErrorKind::Tls)
+    } else if contains_any(&["timed out", "超时"]) {
+        Some(NetworkErrorKind::Timeout)
+    } else if contains_any(&["connection refused", "actively refused"]) {
+        Some(NetworkErrorKind::ConnectionRefused)
+    } else if is_connect {
+        Some(NetworkErrorKind::Other)
+    } else {
+        None
+    }
+}
+
+/// 从 trae.ai 响应的 Date 头获取服务器时间，用于检测本机时钟偏差
+pub async fn fetch_server_time() -> Result<chrono::DateTime<chrono::Utc>> {
+    crate::offline::ensure_online()?;
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()?;
+    let response = client
+        .head(API_BASE_SG)
+        .header(header::USER_AGENT, DEFAULT_USER_AGENT)
+        .send()
+        .await?;
+    let date = response
+        .headers()
+        .get(header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| anyhow!("服务器响应中没有 Date 头"))?;
+    let time = chrono::DateTime::parse_from_rfc2822(date).map_err(|e| anyhow!("无法解析服务器时间: {}", e))?;
+    Ok(time.with_timezone(&chrono::Utc))
+}
+
+/// 通过指定代理访问 trae.ai，测试代理是否可用
+pub async fn test_proxy(proxy: &str) -> Result<ProxyTestResult> {
+    crate::offline::ensure_online()?;
+    let proxy_url = parse_proxy_url(proxy)?;
+    let client = Client::builder()
+        .proxy(reqwest::Proxy::all(proxy_url)?)
+        .timeout(std::time::Duration::from_secs(10))
+        .build()?;
+
+    let started = std::time::Instant::now();
+    let result = client
+        .get(API_BASE_SG)
+        .header(header::USER_AGENT, DEFAULT_USER_AGENT)
+        .send()
+        .await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    Ok(match result {
+        Ok(resp) if resp.status() == reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED => ProxyTestResult {
+            status: ProxyStatus::AuthRequired,
+            latency_ms: Some(latency_ms),
+            message: Some("代理需要认证".to_string()),
+        },
+        // 只要收到 trae.ai 的响应（即使是 404）即代表代理可用
+        Ok(_) => ProxyTestResult {
+            status: ProxyStatus::Reachable,
+            latency_ms: Some(latency_ms),
+            message: None,
+        },
+        Err(e) => {
+            let msg = e.to_string();
+            // HTTPS 隧道建立失败时 407 体现在错误信息中
+            let status = if msg.contains("407") {
+                ProxyStatus::AuthRequired
+            } else {
+                ProxyStatus::Failed
+            };
+            ProxyTestResult { status, latency_ms: None, message: Some(msg) }
+        }
+    })
+}
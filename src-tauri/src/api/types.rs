@@ -23,6 +23,8 @@ pub struct JwtData {
 pub struct JwtPayload {
     pub user_id: String,
     pub tenant_id: String,
+    /// 签发时间（秒级时间戳）
+    pub issued_at: i64,
 }
 
 /// 通过 Token 获取的用户信息
@@ -62,6 +64,9 @@ pub struct UserTokenResult {
     pub user_id: String,
     #[serde(rename = "TenantID")]
     pub tenant_id: String,
+    /// 长期有效的刷新凭证（接口未返回时为空）
+    #[serde(rename = "RefreshToken", default)]
+    pub refresh_token: Option<String>,
 }
 
 /// 用户信息响应
@@ -271,3 +276,42 @@ impl Default for UsageSummary {
         }
     }
 }
+
+/// 网络错误的具体类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkErrorKind {
+    /// 域名解析失败（通常是断网或 DNS 配置问题）
+    Dns,
+    /// TLS 握手或证书校验失败（通常是代理或抓包软件导致）
+    Tls,
+    /// 请求超时
+    Timeout,
+    /// 连接被拒绝（通常是代理未启动）
+    ConnectionRefused,
+    /// 其他连接错误
+    Other,
+    /// 已开启离线模式，请求未发出
+    Offline,
+}
+
+/// 代理测试结论
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyStatus {
+    /// 通过代理可以访问 trae.ai
+    Reachable,
+    /// 代理要求认证（407）
+    AuthRequired,
+    /// 无法通过代理访问
+    Failed,
+}
+
+/// 代理测试结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyTestResult {
+    pub status: ProxyStatus,
+    /// 请求耗时（毫秒），失败时为空
+    pub latency_ms: Option<u64>,
+    pub message: Option<String>,
+}
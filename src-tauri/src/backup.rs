@@ -0,0 +1,271 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+
+use crate::account::AccountManager;
+use crate::settings::AppSettings;
+use crate::tasks;
+
+/// 加密备份文件头，后接盐值、随机数和密文
+const MAGIC: &[u8] = b"TRAEBAK1";
+
+const SALT_LEN: usize = 16;
+
+/// 由备份密码派生密钥的 PBKDF2 迭代次数
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// 备份文件名前缀和扩展名，轮换时只处理符合该格式的文件
+const FILE_PREFIX: &str = "trae-backup-";
+const FILE_EXT: &str = "trbak";
+
+/// 本次运行输入的备份密码：只保存在内存中，不写入设置文件，应用重启后需要重新输入
+static SESSION_PASSPHRASE: Lazy<std::sync::Mutex<Option<String>>> = Lazy::new(|| std::sync::Mutex::new(None));
+
+/// 设置本次运行使用的备份密码（空值表示清除）
+pub fn set_session_passphrase(passphrase: Option<String>) {
+    if let Ok(mut current) = SESSION_PASSPHRASE.lock() {
+        *current = passphrase.filter(|p| !p.is_empty());
+    }
+}
+
+/// 本次运行输入的备份密码，尚未输入时为 None
+pub fn session_passphrase() -> Option<String> {
+    SESSION_PASSPHRASE.lock().ok().and_then(|p| p.clone())
+}
+
+/// 由备份密码和盐值派生 AES-256-GCM 密钥
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<LessSafeKey> {
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).expect("迭代次数不能为 0");
+    let mut key = [0u8; 32];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut key);
+    let key = UnboundKey::new(&aead::AES_256_GCM, &key).map_err(|_| anyhow!("创建备份密钥失败"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// 用备份密码加密数据（每个文件使用随机盐值和随机数）
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; aead::NONCE_LEN];
+    rng.fill(&mut salt).map_err(|_| anyhow!("生成随机数失败"))?;
+    rng.fill(&mut nonce).map_err(|_| anyhow!("生成随机数失败"))?;
+
+    let mut sealed = plaintext.to_vec();
+    derive_key(passphrase, &salt)?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(MAGIC), &mut sealed)
+        .map_err(|_| anyhow!("加密备份失败"))?;
+
+    let mut output = Vec::with_capacity(MAGIC.len() + SALT_LEN + nonce.len() + sealed.len());
+    output.extend_from_slice(MAGIC);
+    output.extend_from_slice(&salt);
+    output.extend_from_slice(&nonce);
+    output.extend_from_slice(&sealed);
+    Ok(output)
+}
+
+/// 解密备份文件内容；密码错误或文件被修改时报错
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let body = data.strip_prefix(MAGIC).ok_or_else(|| anyhow!("不是有效的备份文件"))?;
+    if body.len() < SALT_LEN + aead::NONCE_LEN {
+        return Err(anyhow!("备份文件已损坏"));
+    }
+    let (salt, rest) = body.split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(aead::NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("备份文件已损坏"))?;
+
+    let mut buffer = sealed.to_vec();
+    let plaintext = derive_key(passphrase, salt)?
+        .open_in_place(nonce, Aad::from(MAGIC), &mut buffer)
+        .map_err(|_| anyhow!("备份密码错误或备份文件已损坏"))?;
+    Ok(plaintext.to_vec())
+}
+
+/// 用旧密码解密后以新密码重新加密，不导入本地账号数据（用于把备份分享给不知道原密码的人）
+pub fn reencrypt(data: &[u8], old_passphrase: &str, new_passphrase: &str) -> Result<Vec<u8>> {
+    if new_passphrase.is_empty() {
+        return Err(anyhow!("新密码不能为空"));
+    }
+    let plaintext = decrypt(data, old_passphrase)?;
+    encrypt(&plaintext, new_passphrase)
+}
+
+/// 目录中由本应用生成的备份文件，按时间从旧到新排列（文件名中的时间戳可直接排序）
+fn list_backups(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path.extension().is_some_and(|ext| ext == FILE_EXT)
+                && path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(FILE_PREFIX))
+        })
+        .collect();
+    backups.sort();
+    Ok(backups)
+}
+
+/// 只保留最新的 keep 个备份，返回被删除的文件
+pub fn rotate(dir: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    let backups = list_backups(dir)?;
+    let excess = backups.len().saturating_sub(keep.max(1));
+    let mut removed = Vec::new();
+    for path in backups.into_iter().take(excess) {
+        match fs::remove_file(&path) {
+            Ok(()) => removed.push(path),
+            Err(e) => println!("[WARN] 删除旧备份失败 {}: {}", path.display(), e),
+        }
+    }
+    Ok(removed)
+}
+
+/// 写入一个加密备份并按保留数量轮换，返回新备份路径
+pub fn write_backup(dir: &Path, data: &str, passphrase: &str, retention: usize) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let name = format!("{}{}.{}", FILE_PREFIX, chrono::Local::now().format("%Y%m%d-%H%M%S"), FILE_EXT);
+    let path = dir.join(name);
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, encrypt(data.as_bytes(), passphrase)?)?;
+    fs::rename(&tmp_path, &path)?;
+    rotate(dir, retention)?;
+    Ok(path)
+}
+
+/// 备份设置是否完整：启用时必须填写目录（备份密码每次运行时输入，不在设置中）
+pub fn validate_settings(settings: &AppSettings) -> Result<()> {
+    if !settings.backup_enabled {
+        return Ok(());
+    }
+    if settings.backup_dir.as_deref().is_none_or(|dir| dir.trim().is_empty()) {
+        return Err(anyhow!("启用定时备份时必须设置备份目录"));
+    }
+    if settings.backup_interval_hours == 0 {
+        return Err(anyhow!("备份间隔必须大于 0"));
+    }
+    Ok(())
+}
+
+/// 导出全部账号并用本次运行输入的备份密码写入一个加密备份（复用 export_accounts 的格式）
+pub async fn run_once(manager: &Mutex<AccountManager>, settings: &AppSettings) -> Result<PathBuf> {
+    validate_settings(settings)?;
+    let passphrase = session_passphrase().ok_or_else(|| anyhow!("本次运行尚未输入备份密码，请在设置中输入后重试"))?;
+    let dir = settings
+        .backup_dir
+        .as_deref()
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+        .ok_or_else(|| anyhow!("未设置备份目录"))?;
+    let data = manager.lock().await.export_accounts()?;
+    write_backup(Path::new(dir), &data, &passphrase, settings.backup_retention)
+}
+
+/// 按设置重启定时备份任务；未启用时只停止旧任务
+pub fn start(app: AppHandle, manager: Arc<Mutex<AccountManager>>, settings: &AppSettings) -> Result<()> {
+    tasks::abort(tasks::BACKUP);
+    if !settings.backup_enabled {
+        return Ok(());
+    }
+    validate_settings(settings)?;
+    if crate::offline::is_enabled() {
+        println!("[INFO] 离线模式已开启，定时备份暂停");
+        return Ok(());
+    }
+
+    println!("[INFO] 定时备份已启用，每 {} 小时一次", settings.backup_interval_hours);
+    let settings = settings.clone();
+    let period = Duration::from_secs(settings.backup_interval_hours * 3600);
+    tasks::spawn(tasks::BACKUP, async move {
+        let mut interval = tokio::time::interval(period);
+        // 第一次 tick 立即完成，跳过它，避免每次启动都生成一个备份
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match run_once(&manager, &settings).await {
+                Ok(path) => {
+                    println!("[INFO] 已创建备份: {}", path.display());
+                    let _ = app.emit("backup-created", path.display().to_string());
+                }
+                Err(e) => {
+                    println!("[WARN] 创建备份失败: {}", e);
+                    let _ = app.emit("backup-failed", e.to_string());
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_round_trip() {
+        let sealed = encrypt(b"accounts", "pass").unwrap();
+        assert!(sealed.starts_with(MAGIC));
+        assert_eq!(decrypt(&sealed, "pass").unwrap(), b"accounts");
+        // 每次加密使用新的盐值和随机数
+        assert_ne!(encrypt(b"accounts", "pass").unwrap(), sealed);
+    }
+
+    #[test]
+    fn wrong_passphrase_or_tampering_fails() {
+        let sealed = encrypt(b"accounts", "pass").unwrap();
+        assert!(decrypt(&sealed, "wrong").is_err());
+
+        let mut tampered = sealed.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(decrypt(&tampered, "pass").is_err());
+
+        assert!(decrypt(&sealed[..MAGIC.len() + SALT_LEN], "pass").is_err());
+        assert!(decrypt(b"not a backup", "pass").is_err());
+    }
+
+    #[test]
+    fn session_passphrase_ignores_empty_value() {
+        set_session_passphrase(Some("pass".to_string()));
+        assert_eq!(session_passphrase().as_deref(), Some("pass"));
+        set_session_passphrase(Some(String::new()));
+        assert_eq!(session_passphrase(), None);
+    }
+
+    #[test]
+    fn reencrypt_switches_passphrase() {
+        let sealed = encrypt(b"accounts", "old").unwrap();
+        let shared = reencrypt(&sealed, "old", "new").unwrap();
+        assert_eq!(decrypt(&shared, "new").unwrap(), b"accounts");
+        assert!(decrypt(&shared, "old").is_err());
+        assert!(reencrypt(&sealed, "wrong", "new").is_err());
+        assert!(reencrypt(&sealed, "old", "").is_err());
+    }
+
+    #[test]
+    fn rotate_keeps_newest_backups() {
+        let dir = std::env::temp_dir().join(format!("trae-backup-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        for stamp in ["20240101-000000", "20240102-000000", "20240103-000000", "20240104-000000"] {
+            fs::write(dir.join(format!("{}{}.{}", FILE_PREFIX, stamp, FILE_EXT)), b"x").unwrap();
+        }
+        // 不符合备份文件名格式的文件不受影响
+        fs::write(dir.join("notes.trbak"), b"x").unwrap();
+
+        let removed = rotate(&dir, 2).unwrap();
+        let removed: Vec<String> = removed.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(removed, vec!["trae-backup-20240101-000000.trbak", "trae-backup-20240102-000000.trbak"]);
+        assert_eq!(list_backups(&dir).unwrap().len(), 2);
+        assert!(dir.join("notes.trbak").exists());
+
+        // 保留数量为 0 时仍保留最新的一个
+        assert_eq!(rotate(&dir, 0).unwrap().len(), 1);
+        assert_eq!(list_backups(&dir).unwrap().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+use serde::Serialize;
+use tauri::webview::Cookie;
+use tauri::{AppHandle, Manager, Url, WebviewUrl, WebviewWindowBuilder};
+
+use crate::account::{cookies, Account};
+use crate::window_accounts;
+
+/// 用量/账单页面窗口标签
+const BILLING_WINDOW_LABEL: &str = "trae-billing";
+
+/// 用量页面相对于 trae.ai 站点地址的路径
+const BILLING_PATH: &str = "/account-setting#usage";
+
+/// 用量窗口中账号 Cookies 的读回校验结果（只含 Cookie 名称）
+#[derive(Debug, Clone, Serialize)]
+pub struct CookieVerification {
+    pub account_id: String,
+    /// 写入的 Cookie 数量
+    pub applied: usize,
+    /// 写入后读不回来的 Cookie（通常是因 Secure/SameSite 等属性被 webview 丢弃）
+    pub missing: Vec<String>,
+    /// 读回值与写入值不一致的 Cookie
+    pub mismatched: Vec<String>,
+}
+
+/// 写入用量窗口前对某个 Cookie 的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CookieAction {
+    /// 写入当前会话中没有的 Cookie
+    Set,
+    /// 覆盖当前会话中值不同的同名 Cookie
+    Overwrite,
+    /// 当前会话中已有相同的值
+    Unchanged,
+    /// 当前会话中账号没有的 Cookie，打开时随会话一起清除
+    Clear,
+}
+
+/// 写入 webview 的单个 Cookie 操作（值不随预览返回）
+#[derive(Debug, Clone, Serialize)]
+pub struct CookieOperation {
+    pub name: String,
+    pub domain: String,
+    pub action: CookieAction,
+    #[serde(skip)]
+    pub value: String,
+}
+
+/// 以账号打开用量窗口前的预览（只含 Cookie 名称和域名）
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivationPreview {
+    pub account_id: String,
+    pub operations: Vec<CookieOperation>,
+    /// 是否会覆盖或清除当前会话中的 Cookie
+    pub overwrites_existing: bool,
+}
+
+/// 由站点地址拼出用量页面地址
+pub fn billing_url(web_base_url: &str) -> Result<Url, String> {
+    let base = web_base_url.trim().trim_end_matches('/');
+    let url = Url::parse(&format!("{}{}", base, BILLING_PATH))
+        .map_err(|e| format!("站点地址无效: {}", e))?;
+    if url.scheme() != "https" || url.host_str().is_none() {
+        return Err(format!("站点地址必须是 https 地址: {}", web_base_url));
+    }
+    Ok(url)
+}
+
+/// 账号 Cookies 写入 webview 时默认使用的域名：站点地址的主机名，去掉 www.
+pub fn session_cookie_domain(web_base_url: &str) -> Result<String, String> {
+    let url = billing_url(web_base_url)?;
+    Ok(url.host_str().unwrap_or_default().trim_start_matches("www.").to_string())
+}
+
+/// 按当前会话中的 Cookies 生成写入账号 Cookies 的操作：先列出账号的每个 Cookie，再列出会被清除的 Cookie
+pub fn cookie_operations(account: &Account, default_domain: &str, current: &[Cookie<'_>]) -> Vec<CookieOperation> {
+    let pairs = cookies::parse_cookie_pairs(&account.cookies);
+    let mut operations: Vec<CookieOperation> = pairs
+        .iter()
+        .map(|(name, value)| {
+            let live: Vec<&str> = current.iter().filter(|c| c.name() == name).map(|c| c.value()).collect();
+            let action = if live.is_empty() {
+                CookieAction::Set
+            } else if live.iter().all(|v| v == value) {
+                CookieAction::Unchanged
+            } else {
+                CookieAction::Overwrite
+            };
+            CookieOperation {
+                name: name.clone(),
+                domain: account.cookie_domain(name, default_domain).to_string(),
+                action,
+                value: value.clone(),
+            }
+        })
+        .collect();
+    for cookie in current.iter().filter(|c| !pairs.iter().any(|(name, _)| name == c.name())) {
+        operations.push(CookieOperation {
+            name: cookie.name().to_string(),
+            domain: cookie.domain().unwrap_or(default_domain).to_string(),
+            action: CookieAction::Clear,
+            value: String::new(),
+        });
+    }
+    operations
+}
+
+/// 预览以账号打开用量窗口时对 webview Cookies 的操作，不做任何写入；窗口未打开时没有当前会话
+pub fn preview_activation(app: &AppHandle, account: &Account, web_base_url: &str) -> Result<ActivationPreview, String> {
+    if account.cookies.trim().is_empty() {
+        return Err("该账号没有 Cookies，无法打开用量页面".to_string());
+    }
+    let domain = session_cookie_domain(web_base_url)?;
+    let current = match app.get_webview_window(BILLING_WINDOW_LABEL) {
+        Some(window) => window
+            .cookies_for_url(billing_url(web_base_url)?)
+            .map_err(|e| format!("读取用量页面 Cookies 失败: {}", e))?,
+        None => Vec::new(),
+    };
+    Ok(build_preview(account, &domain, &current))
+}
+
+fn build_preview(account: &Account, default_domain: &str, current: &[Cookie<'_>]) -> ActivationPreview {
+    let operations = cookie_operations(account, default_domain, current);
+    let overwrites_existing = operations
+        .iter()
+        .any(|op| matches!(op.action, CookieAction::Overwrite | CookieAction::Clear));
+    ActivationPreview { account_id: account.id.clone(), operations, overwrites_existing }
+}
+
+/// 以指定账号的 Cookies 打开 trae.ai 用量页面（无痕窗口，不影响登录窗口的会话）
+pub fn open_billing(app: &AppHandle, account: &Account, web_base_url: &str) -> Result<(), String> {
+    crate::offline::ensure_online().map_err(|e| e.to_string())?;
+    if account.cookies.trim().is_empty() {
+        return Err("该账号没有 Cookies，无法打开用量页面".to_string());
+    }
+    let url = billing_url(web_base_url)?;
+    let domain = session_cookie_domain(web_base_url)?;
+
+    // 已打开时复用窗口，先清空上一个账号的会话
+    let window = match app.get_webview_window(BILLING_WINDOW_LABEL) {
+        Some(window) => {
+            window.clear_all_browsing_data().map_err(|e| e.to_string())?;
+            let _ = window.set_title(&format!("用量 - {}", account.email));
+            let _ = window.set_focus();
+            window
+        }
+        None => {
+            let blank = Url::parse("about:blank").map_err(|e| e.to_string())?;
+            let window = WebviewWindowBuilder::new(app, BILLING_WINDOW_LABEL, WebviewUrl::External(blank))
+                .title(format!("用量 - {}", account.email))
+                .inner_size(1000.0, 750.0)
+                .center()
+                .incognito(true)
+                .build()
+                .map_err(|e| e.to_string())?;
+            window.on_window_event(|event| {
+                if let tauri::WindowEvent::Destroyed = event {
+                    window_accounts::release(BILLING_WINDOW_LABEL);
+                }
+            });
+            window
+        }
+    };
+    window_accounts::assign(BILLING_WINDOW_LABEL, &account.id);
+
+    // 先写入 Cookies 再跳转，保证页面首次加载即为该账号的登录态（上一个会话已清空）
+    for operation in cookie_operations(account, &domain, &[]) {
+        let cookie = Cookie::build((operation.name, operation.value))
+            .domain(operation.domain)
+            .path("/")
+            .secure(true)
+            .build();
+        if let Err(e) = window.set_cookie(cookie) {
+            println!("[WARN] 写入用量页面 Cookie 失败: {}", e);
+        }
+    }
+
+    window.navigate(url).map_err(|e| e.to_string())
+}
+
+/// 读回用量窗口中的 Cookies，与账号写入的 Cookies 对比，报告未生效的 Cookie
+pub fn verify_billing_session(app: &AppHandle, account: &Account, web_base_url: &str) -> Result<CookieVerification, String> {
+    let window = app
+        .get_webview_window(BILLING_WINDOW_LABEL)
+        .ok_or_else(|| "用量页面未打开".to_string())?;
+    if window_accounts::account_of(BILLING_WINDOW_LABEL).as_deref() != Some(account.id.as_str()) {
+        return Err(format!("用量页面当前不是 {} 的会话", account.email));
+    }
+    let readback: Vec<(String, String)> = window
+        .cookies_for_url(billing_url(web_base_url)?)
+        .map_err(|e| format!("读取用量页面 Cookies 失败: {}", e))?
+        .iter()
+        .map(|cookie| (cookie.name().to_string(), cookie.value().to_string()))
+        .collect();
+    let (missing, mismatched) = cookies::diff_cookie_sets(&account.cookies, &readback);
+    Ok(CookieVerification {
+        account_id: account.id.clone(),
+        applied: cookies::parse_cookie_pairs(&account.cookies).len(),
+        missing,
+        mismatched,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(cookies: &str) -> Account {
+        Account::new("n".into(), "a@example.com".into(), cookies.into(), "u1".into(), String::new())
+    }
+
+    fn live(name: &str, value: &str, domain: &str) -> Cookie<'static> {
+        Cookie::build((name.to_string(), value.to_string())).domain(domain.to_string()).build()
+    }
+
+    fn actions(preview: &ActivationPreview) -> Vec<(&str, &str, CookieAction)> {
+        preview
+            .operations
+            .iter()
+            .map(|op| (op.name.as_str(), op.domain.as_str(), op.action))
+            .collect()
+    }
+
+    #[test]
+    fn preview_without_session_only_sets_cookies() {
+        let preview = build_preview(&account("sessionid=s1; theme=dark"), "trae.ai", &[]);
+        assert_eq!(
+            actions(&preview),
+            vec![("sessionid", "trae.ai", CookieAction::Set), ("theme", "trae.ai", CookieAction::Set)]
+        );
+        assert!(!preview.overwrites_existing);
+    }
+
+    #[test]
+    fn preview_against_current_session() {
+        let mut target = account("sessionid=s1; theme=dark; region=us");
+        target.cookie_domains.insert("region".to_string(), "api.trae.ai".to_string());
+        let current = [live("sessionid", "other", "trae.ai"), live("theme", "dark", "trae.ai"), live("lang", "zh", "www.trae.ai")];
+
+        let preview = build_preview(&target, "trae.ai", &current);
+        assert_eq!(
+            actions(&preview),
+            vec![
+                ("sessionid", "trae.ai", CookieAction::Overwrite),
+                ("theme", "trae.ai", CookieAction::Unchanged),
+                ("region", "api.trae.ai", CookieAction::Set),
+                ("lang", "www.trae.ai", CookieAction::Clear),
+            ]
+        );
+        assert!(preview.overwrites_existing);
+    }
+
+    #[test]
+    fn identical_session_is_not_an_overwrite() {
+        let current = [live("sessionid", "s1", "trae.ai")];
+        assert!(!build_preview(&account("sessionid=s1"), "trae.ai", &current).overwrites_existing);
+        let current = [live("sessionid", "s1", "trae.ai"), live("extra", "x", "trae.ai")];
+        assert!(build_preview(&account("sessionid=s1"), "trae.ai", &current).overwrites_existing);
+    }
+
+    #[test]
+    fn preview_redacts_cookie_values() {
+        let current = [live("sessionid", "old-secret", "trae.ai")];
+        let json = serde_json::to_string(&build_preview(&account("sessionid=new-secret"), "trae.ai", &current)).unwrap();
+        assert!(json.contains("\"name\":\"sessionid\""));
+        assert!(json.contains("\"action\":\"overwrite\""));
+        assert!(!json.contains("secret"));
+    }
+
+    #[test]
+    fn cookie_domain_comes_from_site_host() {
+        assert_eq!(session_cookie_domain("https://www.trae.ai/").unwrap(), "trae.ai");
+        assert_eq!(session_cookie_domain("https://trae.com").unwrap(), "trae.com");
+        assert!(session_cookie_domain("http://www.trae.ai").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use ring::hmac;
+use ring::rand::SystemRandom;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// 回调令牌的有效期（秒）；令牌只能使用一次，每个通过校验的回调响应都会下发新的令牌
+pub const TOKEN_TTL_SECS: i64 = 600;
+
+/// 注入脚本携带回调令牌的请求头
+pub const HEADER: &str = "x-login-auth";
+
+/// 回调令牌校验失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenError {
+    /// 请求没有携带令牌
+    Missing,
+    /// 令牌格式错误
+    Malformed,
+    /// 签名不匹配（被篡改，或是之前某次登录签发的令牌）
+    BadSignature,
+    /// 令牌已过期
+    Expired,
+    /// 令牌已经使用过（重放的请求）
+    Replayed,
+}
+
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            TokenError::Missing => "缺少回调令牌",
+            TokenError::Malformed => "回调令牌格式错误",
+            TokenError::BadSignature => "回调令牌签名无效",
+            TokenError::Expired => "回调令牌已过期",
+            TokenError::Replayed => "回调令牌已使用过",
+        };
+        f.write_str(message)
+    }
+}
+
+/// 为单次登录签发和校验回调令牌（`过期时间.随机数.HMAC-SHA256 签名`）
+///
+/// 密钥在每次打开登录窗口时随机生成、只保存在内存中，
+/// 因此之前登录时泄露的令牌在新的登录流程中签名校验必然失败；
+/// 每个令牌的随机数在通过校验后记录下来，同一令牌再次出现时按重放拒绝
+pub struct CallbackSigner {
+    key: hmac::Key,
+    /// 已使用的随机数 -> 令牌过期时间；过期后不再需要记录（令牌本身已无法通过校验）
+    consumed: Mutex<HashMap<String, i64>>,
+}
+
+impl CallbackSigner {
+    pub fn new() -> Result<Self, String> {
+        let key = hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+            .map_err(|_| "生成登录回调密钥失败".to_string())?;
+        Ok(Self { key, consumed: Mutex::new(HashMap::new()) })
+    }
+
+    /// 签发一个从 now 起 TOKEN_TTL_SECS 秒内有效、只能使用一次的令牌
+    pub fn issue(&self, now: i64) -> String {
+        let expires_at = now + TOKEN_TTL_SECS;
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        let tag = hmac::sign(&self.key, &signed_message(expires_at, &nonce));
+        format!("{}.{}.{}", expires_at, nonce, URL_SAFE_NO_PAD.encode(tag.as_ref()))
+    }
+
+    /// 校验令牌的签名、过期时间和是否已使用过，通过后记为已使用
+    /// （先校验签名，过期时间或随机数被篡改时按签名无效处理）
+    pub fn verify(&self, token: Option<&str>, now: i64) -> Result<(), TokenError> {
+        let token = token.map(str::trim).filter(|t| !t.is_empty()).ok_or(TokenError::Missing)?;
+        let mut parts = token.splitn(3, '.');
+        let (Some(expires_at), Some(nonce), Some(signature)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(TokenError::Malformed);
+        };
+        let expires_at: i64 = expires_at.parse().map_err(|_| TokenError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| TokenError::Malformed)?;
+        hmac::verify(&self.key, &signed_message(expires_at, nonce), &signature)
+            .map_err(|_| TokenError::BadSignature)?;
+        if now > expires_at {
+            return Err(TokenError::Expired);
+        }
+
+        let mut consumed = self.consumed.lock().map_err(|_| TokenError::Replayed)?;
+        consumed.retain(|_, expiry| *expiry >= now);
+        if consumed.insert(nonce.to_string(), expires_at).is_some() {
+            return Err(TokenError::Replayed);
+        }
+        Ok(())
+    }
+}
+
+/// 参与签名的内容：固定前缀加过期时间和随机数，避免与其他用途的签名混用
+fn signed_message(expires_at: i64, nonce: &str) -> Vec<u8> {
+    format!("trae-login-callback:{}:{}", expires_at, nonce).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn issued_token_verifies_once() {
+        let signer = CallbackSigner::new().unwrap();
+        let token = signer.issue(NOW);
+        assert_eq!(signer.verify(Some(&token), NOW), Ok(()));
+        assert_eq!(signer.verify(Some(&token), NOW + 1), Err(TokenError::Replayed));
+    }
+
+    #[test]
+    fn each_issued_token_is_distinct() {
+        let signer = CallbackSigner::new().unwrap();
+        let first = signer.issue(NOW);
+        let second = signer.issue(NOW);
+        assert_ne!(first, second);
+        assert_eq!(signer.verify(Some(&first), NOW), Ok(()));
+        assert_eq!(signer.verify(Some(&second), NOW), Ok(()));
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let signer = CallbackSigner::new().unwrap();
+        let token = signer.issue(NOW);
+        assert_eq!(signer.verify(Some(&token), NOW + TOKEN_TTL_SECS), Ok(()));
+        let token = signer.issue(NOW);
+        assert_eq!(signer.verify(Some(&token), NOW + TOKEN_TTL_SECS + 1), Err(TokenError::Expired));
+    }
+
+    #[test]
+    fn tampered_token_is_rejected() {
+        let signer = CallbackSigner::new().unwrap();
+        let token = signer.issue(NOW);
+        let (expires_at, rest) = token.split_once('.').unwrap();
+        let (nonce, signature) = rest.split_once('.').unwrap();
+
+        // 延长过期时间
+        let extended = format!("{}.{}.{}", expires_at.parse::<i64>().unwrap() + 3600, nonce, signature);
+        assert_eq!(signer.verify(Some(&extended), NOW), Err(TokenError::BadSignature));
+        // 换一个随机数以绕过重放检查
+        let renonced = format!("{}.{}.{}", expires_at, "0".repeat(nonce.len()), signature);
+        assert_eq!(signer.verify(Some(&renonced), NOW), Err(TokenError::BadSignature));
+        // 修改签名
+        let first = if signature.starts_with('A') { 'B' } else { 'A' };
+        let forged = format!("{}.{}.{}{}", expires_at, nonce, first, &signature[1..]);
+        assert_eq!(signer.verify(Some(&forged), NOW), Err(TokenError::BadSignature));
+        // 被拒绝的篡改不会消耗原令牌
+        assert_eq!(signer.verify(Some(&token), NOW), Ok(()));
+    }
+
+    #[test]
+    fn token_from_another_signer_is_rejected() {
+        let earlier = CallbackSigner::new().unwrap();
+        let current = CallbackSigner::new().unwrap();
+        let token = earlier.issue(NOW);
+        assert_eq!(current.verify(Some(&token), NOW), Err(TokenError::BadSignature));
+    }
+
+    #[test]
+    fn missing_and_malformed_tokens_are_rejected() {
+        let signer = CallbackSigner::new().unwrap();
+        assert_eq!(signer.verify(None, NOW), Err(TokenError::Missing));
+        assert_eq!(signer.verify(Some("  "), NOW), Err(TokenError::Missing));
+        assert_eq!(signer.verify(Some("not-a-token"), NOW), Err(TokenError::Malformed));
+        assert_eq!(signer.verify(Some("abc.nonce.sig"), NOW), Err(TokenError::Malformed));
+        assert_eq!(signer.verify(Some("1700000600.nonce.!!!"), NOW), Err(TokenError::Malformed));
+    }
+}
@@ -0,0 +1,226 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use ring::rand::SystemRandom;
+use ring::{digest, hmac};
+use std::sync::Arc;
+use tokio::sync::{oneshot, Mutex};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+use crate::account::AccountManager;
+use crate::activation;
+use crate::settings::AppSettings;
+use crate::tasks;
+
+/// 运行中的本地控制 API，drop 时停止服务
+pub struct ControlApiHandle {
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl Drop for ControlApiHandle {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// 比较令牌摘要用的进程内随机密钥：比较两者的 HMAC，由 hmac::verify 以常量时间完成
+static COMPARE_KEY: Lazy<hmac::Key> =
+    Lazy::new(|| hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new()).expect("生成随机密钥失败"));
+
+/// 访问令牌缺失或错误
+#[derive(Debug)]
+struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+/// 计算访问令牌的 SHA-256 摘要（十六进制），设置中只保存摘要
+pub fn token_hash(token: &str) -> String {
+    digest::digest(&digest::SHA256, token.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 比较请求中的令牌与保存的摘要；比较的是摘要的 HMAC，耗时不随匹配的字节数变化
+fn token_matches(provided: &str, expected_hash: &str) -> bool {
+    let expected = hmac::sign(&COMPARE_KEY, expected_hash.as_bytes());
+    hmac::verify(&COMPARE_KEY, token_hash(provided).as_bytes(), expected.as_ref()).is_ok()
+}
+
+/// 校验 `Authorization: Bearer <token>` 请求头
+fn with_auth(token_hash: Arc<String>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |header: Option<String>| {
+            let token_hash = token_hash.clone();
+            async move {
+                let provided = header.as_deref().and_then(|h| h.strip_prefix("Bearer "));
+                match provided {
+                    Some(provided) if token_matches(provided, &token_hash) => Ok(()),
+                    _ => Err(warp::reject::custom(Unauthorized)),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+fn error_reply(status: StatusCode, message: &str) -> warp::reply::Response {
+    warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({ "message": message })),
+        status,
+    )
+    .into_response()
+}
+
+fn result_reply<T: serde::Serialize>(result: Result<T>) -> warp::reply::Response {
+    match result {
+        Ok(value) => warp::reply::json(&value).into_response(),
+        Err(e) => error_reply(StatusCode::BAD_REQUEST, &e.to_string()),
+    }
+}
+
+async fn handle_rejection(rejection: Rejection) -> Result<warp::reply::Response, std::convert::Infallible> {
+    if rejection.find::<Unauthorized>().is_some() {
+        Ok(error_reply(StatusCode::UNAUTHORIZED, "访问令牌无效"))
+    } else if rejection.is_not_found() {
+        Ok(error_reply(StatusCode::NOT_FOUND, "接口不存在"))
+    } else {
+        Ok(error_reply(StatusCode::BAD_REQUEST, "请求无效"))
+    }
+}
+
+/// 控制 API 路由：账号列表、切换账号、校验账号（复用 AccountManager 的实现）
+fn routes(
+    manager: Arc<Mutex<AccountManager>>,
+    token_hash: Arc<String>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible> + Clone {
+    let with_manager = warp::any().map(move || manager.clone());
+
+    // GET /accounts
+    let list = warp::get()
+        .and(warp::path!("accounts"))
+        .and(with_manager.clone())
+        .then(|manager: Arc<Mutex<AccountManager>>| async move {
+            warp::reply::json(&manager.lock().await.get_accounts(false)).into_response()
+        });
+
+    // POST /accounts/{id}/activate
+    let activate = warp::post()
+        .and(warp::path!("accounts" / String / "activate"))
+        .and(with_manager.clone())
+        .then(|id: String, manager: Arc<Mutex<AccountManager>>| async move {
+            result_reply(activation::switch_account(None, &manager, &id, false).await)
+        });
+
+    // POST /accounts/{id}/validate
+    let validate = warp::post()
+        .and(warp::path!("accounts" / String / "validate"))
+        .and(with_manager)
+        .then(|id: String, manager: Arc<Mutex<AccountManager>>| async move {
+            // 只在读取账号和保存结果时持有锁，网络请求期间不阻塞其他操作
+            let account = match manager.lock().await.usage_target(&id) {
+                Ok(account) => account,
+                Err(e) => return result_reply::<()>(Err(e)),
+            };
+            let fetched = AccountManager::fetch_usage_for(&account).await;
+            result_reply(manager.lock().await.record_fetched_usage(&id, fetched))
+        });
+
+    with_auth(token_hash)
+        .and(list.or(activate).unify().or(validate).unify())
+        .recover(handle_rejection)
+        .unify()
+}
+
+/// 按设置启动本地控制 API；未启用时返回 None
+pub fn start(manager: Arc<Mutex<AccountManager>>, settings: &AppSettings) -> Result<Option<ControlApiHandle>> {
+    // 旧服务的优雅关闭尚未完成时直接中止，关闭功能后不留后台任务
+    tasks::abort(tasks::CONTROL_API);
+    if !settings.control_api_enabled {
+        return Ok(None);
+    }
+    let token_hash = settings.control_api_token_hash.trim();
+    if token_hash.is_empty() {
+        return Err(anyhow!("未设置本地控制 API 的访问令牌，请先生成令牌"));
+    }
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let (addr, server) = warp::serve(routes(manager, Arc::new(token_hash.to_string())))
+        .try_bind_with_graceful_shutdown(([127, 0, 0, 1], settings.control_api_port), async {
+            let _ = shutdown_rx.await;
+        })
+        .map_err(|e| anyhow!("本地控制 API 启动失败: {}", e))?;
+
+    tasks::spawn(tasks::CONTROL_API, server);
+    println!("[INFO] 本地控制 API 已启动: http://{}", addr);
+
+    Ok(Some(ControlApiHandle { shutdown: Some(shutdown_tx) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::Account;
+
+    const TOKEN: &str = "test-token";
+
+    fn test_routes() -> impl Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible> + Clone {
+        let account = Account::new(
+            "a".to_string(),
+            "a@example.com".to_string(),
+            String::new(),
+            "1001".to_string(),
+            String::new(),
+        );
+        let manager = Arc::new(Mutex::new(AccountManager::in_memory(vec![account])));
+        routes(manager, Arc::new(token_hash(TOKEN)))
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_bearer_token() {
+        let response = warp::test::request().path("/accounts").reply(&test_routes()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["message"], "访问令牌无效");
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_bearer_token() {
+        let routes = test_routes();
+        for header in ["Bearer wrong-token", "Bearer ", TOKEN, "Basic test-token"] {
+            let response = warp::test::request()
+                .path("/accounts")
+                .header("authorization", header)
+                .reply(&routes)
+                .await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", header);
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_accounts_with_valid_token() {
+        let response = warp::test::request()
+            .path("/accounts")
+            .header("authorization", format!("Bearer {}", TOKEN))
+            .reply(&test_routes())
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        let accounts = body.as_array().unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0]["email"], "a@example.com");
+        for key in ["id", "name", "plan_type", "is_active", "status", "token_expired_at"] {
+            assert!(accounts[0].get(key).is_some(), "缺少字段 {}", key);
+        }
+    }
+
+    #[test]
+    fn token_comparison() {
+        let hash = token_hash(TOKEN);
+        assert!(token_matches(TOKEN, &hash));
+        assert!(!token_matches("test-tokeN", &hash));
+        assert!(!token_matches("", &hash));
+    }
+}
@@ -0,0 +1,46 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::account::{AccountManager, AccountStats, ManifestEntry, StoreIssue};
+use crate::metrics::{self, LoginMetrics};
+use crate::settings::AppSettings;
+use crate::tasks;
+
+/// 用于问题反馈的诊断信息（只包含元数据，不含任何 Token 或 Cookies）
+#[derive(Debug, Serialize)]
+pub struct DiagnosticsBundle {
+    pub app_version: &'static str,
+    pub os: &'static str,
+    pub generated_at: String,
+    pub safe_mode: bool,
+    pub read_only: bool,
+    pub settings: AppSettings,
+    pub accounts: Vec<ManifestEntry>,
+    pub stats: AccountStats,
+    pub store_issues: Vec<StoreIssue>,
+    pub login_metrics: LoginMetrics,
+    /// 各用途下仍在运行的后台任务数量
+    pub background_tasks: HashMap<&'static str, usize>,
+}
+
+/// 收集诊断信息；设置中的控制 API 令牌会被替换
+pub fn collect(manager: &AccountManager, settings: &AppSettings) -> DiagnosticsBundle {
+    let mut settings = settings.clone();
+    if !settings.control_api_token_hash.is_empty() {
+        settings.control_api_token_hash = "<redacted>".to_string();
+    }
+
+    DiagnosticsBundle {
+        app_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        generated_at: chrono::Local::now().to_rfc3339(),
+        safe_mode: manager.is_safe_mode(),
+        read_only: manager.is_read_only(),
+        settings,
+        accounts: manager.manifest(),
+        stats: manager.account_stats(),
+        store_issues: manager.check_store(),
+        login_metrics: metrics::snapshot(),
+        background_tasks: tasks::running(),
+    }
+}
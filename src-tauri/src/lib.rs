@@ -2,28 +2,52 @@ mod api;
 mod account;
 mod machine;
 mod login;
+mod metrics;
+mod settings;
+mod control_api;
+mod diagnostics;
+mod billing;
+mod tasks;
+mod callback_token;
+mod backup;
+mod window_accounts;
+mod pending_events;
+mod offline;
+mod activation;
+mod age;
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tauri::State;
+use tauri::{Emitter, Manager, RunEvent, State};
 
-use account::{AccountBrief, AccountManager, Account};
-use api::{UsageSummary, UsageQueryResponse};
+use account::{AccountBrief, AccountSection, ActivationDiagnosis, AccountManager, Account, AccountSize, AccountSort, DraftBrief, AccountFilter, AccountStats, AccountStatus, BrowserCookieFormat, CaptureReplay, ChecksumStatus, CookieCaptureReport, CookieConflict, CookieRemap, CompactReport, ForeignMapping, AccountProblem, HealthRecord, ImportDuplicate, ImportRecord, ImportStrategy, LinkedAccountGroup, LoginPrediction, ProxyValidation, SnapshotInfo, StorageCheck, StoreIssue, ValidationReport};
+use api::{NetworkErrorKind, ProxyTestResult, UsageSummary, UsageQueryResponse};
+use settings::{AppSettings, ConfigSource, ConfigValue};
 
 /// 应用状态
 pub struct AppState {
     pub account_manager: Arc<Mutex<AccountManager>>,
+    pub settings: Arc<Mutex<AppSettings>>,
+    /// 运行中的本地控制 API
+    pub control_api: Arc<Mutex<Option<control_api::ControlApiHandle>>>,
+    /// 取消进行中的批量校验（校验期间账号管理器被锁定，因此在锁外持有）
+    pub validation_cancel: Arc<std::sync::atomic::AtomicBool>,
 }
 
 /// 错误类型
 #[derive(Debug, serde::Serialize)]
 pub struct ApiError {
     pub message: String,
+    /// 网络错误的具体类型（DNS、TLS、超时等），用于提示修复方法
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_error: Option<NetworkErrorKind>,
 }
 
 impl From<anyhow::Error> for ApiError {
     fn from(err: anyhow::Error) -> Self {
         Self {
+            network_error: api::classify_network_error(&err),
             message: err.to_string(),
         }
     }
@@ -31,8 +55,53 @@ impl From<anyhow::Error> for ApiError {
 
 type Result<T> = std::result::Result<T, ApiError>;
 
+/// 本机时钟与 trae.ai 服务器相差超过该秒数时提醒用户
+const CLOCK_SKEW_WARN_SECS: i64 = 120;
+
+/// 对比 trae.ai 服务器时间测量本机时钟偏差，记录到账号管理器用于过期判断，偏差较大时发送 clock-skew-detected
+async fn measure_clock_skew(app: &tauri::AppHandle, manager: &Mutex<AccountManager>) -> anyhow::Result<i64> {
+    let server_time = api::fetch_server_time().await?;
+    let offset_secs = (server_time - chrono::Utc::now()).num_seconds();
+    manager.lock().await.set_clock_offset(offset_secs);
+    if offset_secs.abs() > CLOCK_SKEW_WARN_SECS {
+        println!("[WARN] 本机时钟与服务器相差 {} 秒", offset_secs);
+        let _ = app.emit("clock-skew-detected", serde_json::json!({ "offset_secs": offset_secs }));
+    }
+    Ok(offset_secs)
+}
+
 // ============ Tauri 命令 ============
 
+/// 账号数据是否处于只读模式（另一个实例正在使用）
+#[tauri::command]
+async fn is_store_read_only(state: State<'_, AppState>) -> Result<bool> {
+    let manager = state.account_manager.lock().await;
+    Ok(manager.is_read_only())
+}
+
+/// 检查数据目录是否可写，不可写时发送 storage-readonly 事件
+#[tauri::command]
+async fn check_storage_writable(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<StorageCheck> {
+    let check = state.account_manager.lock().await.check_storage_writable();
+    if !check.writable {
+        let _ = app.emit("storage-readonly", &check);
+    }
+    Ok(check)
+}
+
+/// 检测本机时钟偏差（秒，服务器时间 - 本机时间）
+#[tauri::command]
+async fn check_clock_skew(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<i64> {
+    measure_clock_skew(&app, &state.account_manager).await.map_err(Into::into)
+}
+
+/// 是否以安全模式运行
+#[tauri::command]
+async fn is_safe_mode(state: State<'_, AppState>) -> Result<bool> {
+    let manager = state.account_manager.lock().await;
+    Ok(manager.is_safe_mode())
+}
+
 /// 添加账号（通过 Token，可选 Cookies）
 #[tauri::command]
 async fn add_account_by_token(token: String, cookies: Option<String>, state: State<'_, AppState>) -> Result<Account> {
@@ -47,11 +116,353 @@ async fn remove_account(account_id: String, state: State<'_, AppState>) -> Resul
     manager.remove_account(&account_id).map_err(Into::into)
 }
 
-/// 获取所有账号
+/// 获取所有账号；sort 为 by_expiry 时按最早过期时间排序，最需要续期的在前
+#[tauri::command]
+async fn get_accounts(
+    include_quarantined: Option<bool>,
+    sort: Option<AccountSort>,
+    environment: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<AccountBrief>> {
+    let manager = state.account_manager.lock().await;
+    let mut accounts = manager.get_accounts(include_quarantined.unwrap_or(false));
+    if let Some(environment) = environment.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+        accounts.retain(|a| a.environment.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(environment)));
+    }
+    if let Some(AccountSort::ByExpiry) = sort {
+        accounts.sort_by(|a, b| account::compare_expiry(a.expires_at, b.expires_at));
+    }
+    Ok(accounts)
+}
+
+/// 修改账号 ID（修复导入造成的 ID 冲突）
+#[tauri::command]
+async fn reassign_account_id(old_id: String, new_id: String, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.reassign_account_id(&old_id, &new_id).map_err(Into::into)
+}
+
+/// 只校验超过 ttl_secs 未校验的账号，返回各状态的数量
+#[tauri::command]
+async fn validate_stale(app: tauri::AppHandle, ttl_secs: u64, state: State<'_, AppState>) -> Result<ValidationReport> {
+    let mut manager = state.account_manager.lock().await;
+    let report = manager.validate_stale(ttl_secs).await?;
+    emit_if_cancelled(&app, &report);
+    Ok(report)
+}
+
+/// 取消进行中的批量校验：停止发起新请求，校验命令返回已完成的部分结果
+#[tauri::command]
+async fn cancel_validation(state: State<'_, AppState>) -> Result<()> {
+    state.validation_cancel.store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+/// 批量校验被取消时发送 validation-cancelled 事件，附带已完成的数量
+fn emit_if_cancelled(app: &tauri::AppHandle, report: &ValidationReport) {
+    if report.cancelled {
+        let _ = app.emit("validation-cancelled", serde_json::json!({
+            "completed": report.valid + report.expired + report.failed,
+        }));
+    }
+}
+
+/// 列出从未校验过凭证的账号
+#[tauri::command]
+async fn list_unvalidated(state: State<'_, AppState>) -> Result<Vec<AccountBrief>> {
+    let manager = state.account_manager.lock().await;
+    Ok(manager.list_unvalidated())
+}
+
+/// 查找底层用户 ID 相同的重复账号分组
+#[tauri::command]
+async fn find_duplicate_groups(state: State<'_, AppState>) -> Result<Vec<Vec<String>>> {
+    let manager = state.account_manager.lock().await;
+    Ok(manager.find_duplicate_groups())
+}
+
+/// 预测用该 Token 登录会新增账号还是更新已有账号
+#[tauri::command]
+async fn predict_login(token: String, state: State<'_, AppState>) -> Result<LoginPrediction> {
+    let manager = state.account_manager.lock().await;
+    manager.predict_login(&token).map_err(Into::into)
+}
+
+/// 离线回放一次记录下的 GetUserToken 响应体，报告将会添加的账号（不修改账号数据）
+#[tauri::command]
+async fn replay_capture(body: String, state: State<'_, AppState>) -> Result<CaptureReplay> {
+    let manager = state.account_manager.lock().await;
+    manager.replay_capture(&body).map_err(Into::into)
+}
+
+/// 查找同一用户绑定不同邮箱的账号分组，供用户核对
+#[tauri::command]
+async fn find_linked_accounts(state: State<'_, AppState>) -> Result<Vec<LinkedAccountGroup>> {
+    let manager = state.account_manager.lock().await;
+    Ok(manager.find_linked_accounts())
+}
+
+/// 获取账号最近几次校验的结果，用于显示可靠性趋势
+#[tauri::command]
+async fn account_health_history(account_id: String, state: State<'_, AppState>) -> Result<Vec<HealthRecord>> {
+    let manager = state.account_manager.lock().await;
+    manager.account_health_history(&account_id).map_err(Into::into)
+}
+
+/// 启用或停用账号
+#[tauri::command]
+async fn set_account_enabled(account_id: String, enabled: bool, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_account_enabled(&account_id, enabled).map_err(Into::into)
+}
+
+/// 设置账号是否参与 Token 自动刷新
+#[tauri::command]
+async fn set_account_auto_refresh(account_id: String, auto_refresh: bool, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_account_auto_refresh(&account_id, auto_refresh).map_err(Into::into)
+}
+
+/// 设置账号使用的代理，None 表示直连
+#[tauri::command]
+async fn set_account_proxy(account_id: String, proxy: Option<String>, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_account_proxy(&account_id, proxy).map_err(Into::into)
+}
+
+/// 设置账号的颜色标记，None 表示清除
+#[tauri::command]
+async fn set_account_color(account_id: String, color: Option<String>, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_account_color(&account_id, color).map_err(Into::into)
+}
+
+/// 账号保存的登录请求头名称（不含值），刷新 Token 时会复用这些请求头
+#[tauri::command]
+async fn captured_header_names(account_id: String, state: State<'_, AppState>) -> Result<Vec<String>> {
+    let manager = state.account_manager.lock().await;
+    manager.captured_header_names(&account_id).map_err(Into::into)
+}
+
+/// 清除账号保存的登录请求头
+#[tauri::command]
+async fn clear_captured_headers(account_id: String, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.clear_captured_headers(&account_id).map_err(Into::into)
+}
+
+/// 设置账号所属的环境（如 prod/staging），None 表示清除
 #[tauri::command]
-async fn get_accounts(state: State<'_, AppState>) -> Result<Vec<AccountBrief>> {
+async fn set_account_environment(account_id: String, environment: Option<String>, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_account_environment(&account_id, environment).map_err(Into::into)
+}
+
+/// 按分组获取账号列表（未分组的账号在最后一节）
+#[tauri::command]
+async fn get_grouped_accounts(include_quarantined: Option<bool>, state: State<'_, AppState>) -> Result<Vec<AccountSection>> {
     let manager = state.account_manager.lock().await;
-    Ok(manager.get_accounts())
+    Ok(manager.grouped_accounts(include_quarantined.unwrap_or(false)))
+}
+
+/// 把账号移到指定分组（group 为空表示移出分组）
+#[tauri::command]
+async fn set_account_group(account_id: String, group: Option<String>, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_account_group(&account_id, group.as_deref()).map_err(Into::into)
+}
+
+/// 设置分组内账号的顺序
+#[tauri::command]
+async fn reorder_group(group: String, account_ids: Vec<String>, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.reorder_group(&group, &account_ids).map_err(Into::into)
+}
+
+/// 设置分组的显示顺序
+#[tauri::command]
+async fn reorder_groups(groups: Vec<String>, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.reorder_groups(&groups).map_err(Into::into)
+}
+
+/// 折叠或展开分组
+#[tauri::command]
+async fn set_group_collapsed(group: String, collapsed: bool, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_group_collapsed(&group, collapsed).map_err(Into::into)
+}
+
+/// 账号环境的建议取值
+#[tauri::command]
+async fn suggested_environments() -> Result<Vec<&'static str>> {
+    Ok(account::SUGGESTED_ENVIRONMENTS.to_vec())
+}
+
+/// 手动设置账号状态（自动校验在保护期内不会覆盖）
+#[tauri::command]
+async fn set_account_status(account_id: String, status: AccountStatus, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_account_status(&account_id, status).map_err(Into::into)
+}
+
+/// 预先创建账号用到的 HTTP 客户端，批量校验时复用连接，返回客户端数量
+#[tauri::command]
+async fn prewarm_clients(state: State<'_, AppState>) -> Result<usize> {
+    Ok(state.account_manager.lock().await.prewarm_clients())
+}
+
+/// 设置主账号（替换原来的主账号）；account_id 为空时取消
+#[tauri::command]
+async fn set_primary(account_id: Option<String>, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_primary(account_id.as_deref()).map_err(Into::into)
+}
+
+/// 获取主账号，未设置时返回 null
+#[tauri::command]
+async fn get_primary(state: State<'_, AppState>) -> Result<Option<AccountBrief>> {
+    Ok(state.account_manager.lock().await.get_primary())
+}
+
+/// 取出上次运行时主窗口关闭期间未送达的重要事件（如登录成功），取出后清空
+#[tauri::command]
+async fn drain_pending_events() -> Result<Vec<pending_events::PendingEvent>> {
+    Ok(pending_events::drain())
+}
+
+/// 把所有账号的状态重置为未知（不修改凭证），下次校验重新判断
+#[tauri::command]
+async fn reset_statuses(state: State<'_, AppState>) -> Result<usize> {
+    let mut manager = state.account_manager.lock().await;
+    manager.reset_statuses().map_err(Into::into)
+}
+
+/// 清理凭证已失效的账号；quarantine 为 true 时只隔离不删除
+#[tauri::command]
+async fn purge_expired(quarantine: bool, state: State<'_, AppState>) -> Result<usize> {
+    let mut manager = state.account_manager.lock().await;
+    manager.purge_expired(quarantine).map_err(Into::into)
+}
+
+/// 将账号移出隔离区
+#[tauri::command]
+async fn restore_from_quarantine(account_id: String, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.restore_from_quarantine(&account_id).map_err(Into::into)
+}
+
+/// 删除所有已隔离的账号
+#[tauri::command]
+async fn empty_quarantine(state: State<'_, AppState>) -> Result<usize> {
+    let mut manager = state.account_manager.lock().await;
+    manager.empty_quarantine().map_err(Into::into)
+}
+
+/// 按 Token 声明查找账号（如组织、团队 ID）
+#[tauri::command]
+async fn find_by_claim(key: String, value: String, state: State<'_, AppState>) -> Result<Vec<AccountBrief>> {
+    let mut manager = state.account_manager.lock().await;
+    Ok(manager.find_by_claim(&key, &value))
+}
+
+/// 给符合条件的账号批量添加标签
+#[tauri::command]
+async fn tag_where(filter: AccountFilter, tag: String, state: State<'_, AppState>) -> Result<usize> {
+    let mut manager = state.account_manager.lock().await;
+    manager.tag_where(&filter, &tag).map_err(Into::into)
+}
+
+/// 为符合条件的账号批量设置代理（proxy 为空时清除），返回匹配的账号数量
+#[tauri::command]
+async fn set_proxy_where(filter: AccountFilter, proxy: Option<String>, state: State<'_, AppState>) -> Result<usize> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_proxy_where(&filter, proxy).map_err(Into::into)
+}
+
+/// 给每个账号添加其邮箱域名标签，返回新添加标签的账号数量
+#[tauri::command]
+async fn auto_tag_by_domain(state: State<'_, AppState>) -> Result<usize> {
+    let mut manager = state.account_manager.lock().await;
+    manager.auto_tag_by_domain().map_err(Into::into)
+}
+
+/// 删除账号的一个标签
+#[tauri::command]
+async fn remove_tag(account_id: String, tag: String, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.remove_tag(&account_id, &tag).map_err(Into::into)
+}
+
+/// 按凭证状态统计账号数量
+#[tauri::command]
+async fn account_stats(state: State<'_, AppState>) -> Result<AccountStats> {
+    let manager = state.account_manager.lock().await;
+    Ok(manager.account_stats())
+}
+
+/// 检查账号数据，列出缺少关键 Cookie 的账号
+#[tauri::command]
+async fn check_store(state: State<'_, AppState>) -> Result<Vec<StoreIssue>> {
+    let manager = state.account_manager.lock().await;
+    Ok(manager.check_store())
+}
+
+/// 一次检查所有账号的问题（失效、缺少 Cookies、即将过期、状态未知），按严重程度排序
+#[tauri::command]
+async fn diagnose_all(state: State<'_, AppState>) -> Result<Vec<AccountProblem>> {
+    let manager = state.account_manager.lock().await;
+    Ok(manager.diagnose_all())
+}
+
+/// 导出用于问题反馈的诊断信息（JSON，不含 Token 与 Cookies）
+#[tauri::command]
+async fn export_diagnostics(state: State<'_, AppState>) -> Result<String> {
+    let settings = state.settings.lock().await.clone();
+    let manager = state.account_manager.lock().await;
+    let bundle = diagnostics::collect(&manager, &settings);
+    serde_json::to_string_pretty(&bundle).map_err(|e| ApiError {
+        message: format!("导出诊断信息失败: {}", e),
+        network_error: None,
+    })
+}
+
+/// 用上次保存时记录的校验和检查账号数据文件的完整性
+#[tauri::command]
+async fn verify_store_checksum(state: State<'_, AppState>) -> Result<ChecksumStatus> {
+    let manager = state.account_manager.lock().await;
+    manager.verify_store_checksum().map_err(Into::into)
+}
+
+/// 重写账号数据文件，清理旧版本遗留的字段
+#[tauri::command]
+async fn compact_store(state: State<'_, AppState>) -> Result<CompactReport> {
+    let manager = state.account_manager.lock().await;
+    manager.compact_store().map_err(Into::into)
+}
+
+/// 估算每个账号在数据文件中的占用，按占用从大到小排列
+#[tauri::command]
+async fn account_sizes(state: State<'_, AppState>) -> Result<Vec<AccountSize>> {
+    let manager = state.account_manager.lock().await;
+    manager.account_sizes().map_err(Into::into)
+}
+
+/// 将所有账号的 Cookies 规范化为统一格式，返回被修改的账号数量
+#[tauri::command]
+async fn normalize_cookies(state: State<'_, AppState>) -> Result<usize> {
+    let mut manager = state.account_manager.lock().await;
+    manager.normalize_cookies().map_err(Into::into)
+}
+
+/// 在系统文件管理器中打开账号数据目录
+#[tauri::command]
+async fn reveal_store_in_explorer(state: State<'_, AppState>) -> Result<()> {
+    let dir = state.account_manager.lock().await.data_dir()?;
+    open::that(&dir).map_err(|e| ApiError {
+        message: format!("无法打开数据目录 {}: {}", dir.display(), e),
+        network_error: None,
+    })
 }
 
 /// 获取单个账号详情
@@ -61,11 +472,87 @@ async fn get_account(account_id: String, state: State<'_, AppState>) -> Result<A
     manager.get_account(&account_id).map_err(Into::into)
 }
 
-/// 切换账号（设置活跃账号并更新机器码）
+/// 以指定账号打开 trae.ai 用量页面
+#[tauri::command]
+async fn open_billing(app: tauri::AppHandle, account_id: String, state: State<'_, AppState>) -> Result<()> {
+    let web_base_url = state.settings.lock().await.web_base_url.clone();
+    let account = state.account_manager.lock().await.get_account(&account_id)?;
+    billing::open_billing(&app, &account, &web_base_url).map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 预览以账号打开用量页面时将写入、覆盖或清除的 Cookies（只含名称和域名），不做任何修改
 #[tauri::command]
-async fn switch_account(account_id: String, state: State<'_, AppState>) -> Result<()> {
+async fn preview_activation(app: tauri::AppHandle, account_id: String, state: State<'_, AppState>) -> Result<billing::ActivationPreview> {
+    let web_base_url = state.settings.lock().await.web_base_url.clone();
+    let account = state.account_manager.lock().await.get_account(&account_id)?;
+    billing::preview_activation(&app, &account, &web_base_url).map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 校验以账号打开的用量页面：读回 webview 中的 Cookies，报告写入后未生效的 Cookie
+#[tauri::command]
+async fn verify_active(app: tauri::AppHandle, account_id: String, state: State<'_, AppState>) -> Result<billing::CookieVerification> {
+    let web_base_url = state.settings.lock().await.web_base_url.clone();
+    let account = state.account_manager.lock().await.get_account(&account_id)?;
+    billing::verify_billing_session(&app, &account, &web_base_url).map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// trae.ai 迁移域名后，把账号 Cookies 从旧域名改写到新域名（host-only Cookie 不改写），返回受影响的账号
+#[tauri::command]
+async fn remap_cookie_domain(old_domain: String, new_domain: String, state: State<'_, AppState>) -> Result<Vec<CookieRemap>> {
+    let web_base_url = state.settings.lock().await.web_base_url.clone();
+    let default_domain = billing::session_cookie_domain(&web_base_url).map_err(|e| ApiError { message: e, network_error: None })?;
     let mut manager = state.account_manager.lock().await;
-    manager.switch_account(&account_id).map_err(Into::into)
+    manager.remap_cookie_domain(&old_domain, &new_domain, &default_domain).map_err(Into::into)
+}
+
+/// 列出账号 Cookies 中同名但值不同的 Cookie（通常来自不同域名）
+#[tauri::command]
+async fn report_cookie_conflicts(account_id: String, state: State<'_, AppState>) -> Result<Vec<CookieConflict>> {
+    let manager = state.account_manager.lock().await;
+    manager.report_cookie_conflicts(&account_id).map_err(Into::into)
+}
+
+/// 列出账号 Cookies 中哪些是 HttpOnly、哪些脚本可读（只含名称）
+#[tauri::command]
+async fn cookie_capture_report(account_id: String, state: State<'_, AppState>) -> Result<CookieCaptureReport> {
+    let manager = state.account_manager.lock().await;
+    manager.cookie_capture_report(&account_id).map_err(Into::into)
+}
+
+/// 去除账号 Cookies 中重复的同名 Cookie，返回去掉的个数
+#[tauri::command]
+async fn dedup_account_cookies(account_id: String, state: State<'_, AppState>) -> Result<usize> {
+    let mut manager = state.account_manager.lock().await;
+    manager.dedup_account_cookies(&account_id).map_err(Into::into)
+}
+
+/// 获取账号 Cookies 格式化后的 `Cookie:` 请求头
+#[tauri::command]
+async fn copy_cookie_header(account_id: String, state: State<'_, AppState>) -> Result<String> {
+    let manager = state.account_manager.lock().await;
+    manager.cookie_header(&account_id).map_err(Into::into)
+}
+
+/// 导出账号 Cookies 为浏览器可导入的格式（netscape 或 json）
+#[tauri::command]
+async fn export_browser_session(account_id: String, format: BrowserCookieFormat, state: State<'_, AppState>) -> Result<String> {
+    let manager = state.account_manager.lock().await;
+    manager.export_browser_session(&account_id, format).map_err(Into::into)
+}
+
+/// 切换账号（设置活跃账号并更新机器码）；多个请求排队依次执行，返回本次请求是否实际执行
+#[tauri::command]
+async fn switch_account(app: tauri::AppHandle, account_id: String, state: State<'_, AppState>) -> Result<bool> {
+    let coalesce = state.settings.lock().await.coalesce_activations;
+    activation::switch_account(Some(&app), &state.account_manager, &account_id, coalesce)
+        .await
+        .map_err(Into::into)
+}
+
+/// 诊断账号为什么无法切换，返回第一个阻塞原因及处理建议
+#[tauri::command]
+async fn activation_diagnosis(account_id: String, state: State<'_, AppState>) -> Result<ActivationDiagnosis> {
+    Ok(state.account_manager.lock().await.activation_diagnosis(&account_id))
 }
 
 /// 获取账号使用量
@@ -75,6 +562,49 @@ async fn get_account_usage(account_id: String, state: State<'_, AppState>) -> Re
     manager.get_account_usage(&account_id).await.map_err(Into::into)
 }
 
+/// 从 trae.ai 重新获取账号邮箱，有变化时更新并通知前端
+#[tauri::command]
+async fn refresh_account_email(app: tauri::AppHandle, account_id: String, state: State<'_, AppState>) -> Result<Option<String>> {
+    let mut manager = state.account_manager.lock().await;
+    let email = manager.refresh_account_email(&account_id).await?;
+    if let Some(email) = &email {
+        let _ = app.emit("account-email-updated", serde_json::json!({ "id": account_id, "email": email }));
+    }
+    Ok(email)
+}
+
+/// 列出浏览器登录添加失败时保存的草稿
+#[tauri::command]
+async fn list_draft_captures(state: State<'_, AppState>) -> Result<Vec<DraftBrief>> {
+    let manager = state.account_manager.lock().await;
+    manager.list_draft_captures().map_err(Into::into)
+}
+
+/// 用草稿中的凭证重新添加账号
+#[tauri::command]
+async fn commit_draft(draft_id: String, state: State<'_, AppState>) -> Result<Account> {
+    let mut manager = state.account_manager.lock().await;
+    manager.commit_draft(&draft_id).await.map_err(Into::into)
+}
+
+/// 删除草稿
+#[tauri::command]
+async fn discard_draft(draft_id: String, state: State<'_, AppState>) -> Result<()> {
+    let manager = state.account_manager.lock().await;
+    manager.discard_draft(&draft_id).map_err(Into::into)
+}
+
+/// 首次运行时从主窗口已有的 trae.ai 会话导入账号（只执行一次）
+#[tauri::command]
+async fn import_current_session(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<Option<Account>> {
+    if !state.account_manager.lock().await.session_import_pending() {
+        return Ok(None);
+    }
+    let cookies = login::main_session_cookies(&app).map_err(|e| ApiError { message: e, network_error: None })?;
+    let mut manager = state.account_manager.lock().await;
+    manager.import_current_session(cookies).await.map_err(Into::into)
+}
+
 /// 更新账号 Token
 #[tauri::command]
 async fn update_account_token(account_id: String, token: String, state: State<'_, AppState>) -> Result<UsageSummary> {
@@ -82,6 +612,24 @@ async fn update_account_token(account_id: String, token: String, state: State<'_
     manager.update_account_token(&account_id, token).await.map_err(Into::into)
 }
 
+/// 设置账号的自定义 User-Agent
+#[tauri::command]
+async fn set_account_user_agent(account_id: String, user_agent: Option<String>, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_account_user_agent(&account_id, user_agent).map_err(Into::into)
+}
+
+/// 设置账号请求 Trae API 时附加的请求头
+#[tauri::command]
+async fn set_account_extra_headers(
+    account_id: String,
+    extra_headers: std::collections::HashMap<String, String>,
+    state: State<'_, AppState>,
+) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.set_account_extra_headers(&account_id, extra_headers).map_err(Into::into)
+}
+
 /// 导出账号
 #[tauri::command]
 async fn export_accounts(state: State<'_, AppState>) -> Result<String> {
@@ -89,11 +637,123 @@ async fn export_accounts(state: State<'_, AppState>) -> Result<String> {
     manager.export_accounts().map_err(Into::into)
 }
 
-/// 导入账号
+/// 只导出带有指定标签的账号
 #[tauri::command]
-async fn import_accounts(data: String, state: State<'_, AppState>) -> Result<usize> {
+async fn export_by_tag(tag: String, state: State<'_, AppState>) -> Result<String> {
+    let manager = state.account_manager.lock().await;
+    manager.export_by_tag(&tag).map_err(Into::into)
+}
+
+/// 导入账号（strategy 为空时默认保留较新的 Token；preserve_ids 为 true 时新增账号沿用源数据中的 ID）
+#[tauri::command]
+async fn import_accounts(
+    data: String,
+    strategy: Option<ImportStrategy>,
+    preserve_ids: Option<bool>,
+    state: State<'_, AppState>
+) -> Result<Vec<ImportRecord>> {
+    let mut manager = state.account_manager.lock().await;
+    manager
+        .import_accounts(&data, strategy.unwrap_or_default(), preserve_ids.unwrap_or(false))
+        .await
+        .map_err(Into::into)
+}
+
+/// 导入前预检：列出导入数据中指向同一用户的重复条目（导入时会合并为一个）
+#[tauri::command]
+async fn find_import_duplicates(data: String, state: State<'_, AppState>) -> Result<Vec<ImportDuplicate>> {
+    let manager = state.account_manager.lock().await;
+    manager.find_import_duplicates(&data).map_err(Into::into)
+}
+
+/// 校验刚导入的账号，校验过程中发送 import-validate-progress 事件
+#[tauri::command]
+async fn validate_imported(
+    app: tauri::AppHandle,
+    records: Vec<ImportRecord>,
+    state: State<'_, AppState>,
+) -> Result<ValidationReport> {
     let mut manager = state.account_manager.lock().await;
-    manager.import_accounts(&data).await.map_err(Into::into)
+    let report = manager
+        .validate_imported(&records, |done, total| {
+            let _ = app.emit("import-validate-progress", serde_json::json!({ "done": done, "total": total }));
+        })
+        .await?;
+    emit_if_cancelled(&app, &report);
+    Ok(report)
+}
+
+/// 启动时的后台校验：发送 startup-validate-progress 进度事件，完成后发送 startup-validate-finished；
+/// 只在选出账号和记录结果时持有锁，网络请求期间不阻塞其他命令
+async fn run_startup_validation(app: &tauri::AppHandle, manager: &Mutex<AccountManager>) {
+    println!("[INFO] 开始启动校验");
+    let batch = match manager.lock().await.prepare_validate_all() {
+        Ok(batch) => batch,
+        Err(e) => {
+            println!("[WARN] 启动校验失败: {}", e);
+            return;
+        }
+    };
+    let outcome = batch
+        .run(|done, total| {
+            let _ = app.emit("startup-validate-progress", serde_json::json!({ "done": done, "total": total }));
+        })
+        .await;
+    let result = manager.lock().await.record_validation(outcome);
+    match result {
+        Ok(report) => {
+            emit_if_cancelled(app, &report);
+            println!("[INFO] 启动校验完成: 有效 {}，失效 {}，失败 {}", report.valid, report.expired, report.failed);
+            let _ = app.emit("startup-validate-finished", &report);
+        }
+        Err(e) => println!("[WARN] 启动校验失败: {}", e),
+    }
+}
+
+/// 从其他工具导出的 JSON 文件导入账号（mapping 为空时使用内置映射）
+#[tauri::command]
+async fn import_foreign_json(
+    path: String,
+    mapping: Option<ForeignMapping>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ImportRecord>> {
+    let mut manager = state.account_manager.lock().await;
+    manager.import_foreign_json(&path, mapping).await.map_err(Into::into)
+}
+
+/// 导出账号清单（不含敏感信息）
+#[tauri::command]
+async fn export_manifest(state: State<'_, AppState>) -> Result<String> {
+    let manager = state.account_manager.lock().await;
+    manager.export_manifest().map_err(Into::into)
+}
+
+/// 导入账号清单（为缺失的账号创建占位条目）
+#[tauri::command]
+async fn import_manifest(data: String, state: State<'_, AppState>) -> Result<usize> {
+    let mut manager = state.account_manager.lock().await;
+    manager.import_manifest(&data).map_err(Into::into)
+}
+
+/// 创建账号存储快照
+#[tauri::command]
+async fn snapshot(state: State<'_, AppState>) -> Result<String> {
+    let manager = state.account_manager.lock().await;
+    manager.snapshot().map_err(Into::into)
+}
+
+/// 列出所有快照
+#[tauri::command]
+async fn list_snapshots(state: State<'_, AppState>) -> Result<Vec<SnapshotInfo>> {
+    let manager = state.account_manager.lock().await;
+    manager.list_snapshots().map_err(Into::into)
+}
+
+/// 从快照恢复账号存储
+#[tauri::command]
+async fn restore_snapshot(snapshot_id: String, state: State<'_, AppState>) -> Result<()> {
+    let mut manager = state.account_manager.lock().await;
+    manager.restore_snapshot(&snapshot_id).map_err(Into::into)
 }
 
 /// 获取使用事件
@@ -201,34 +861,568 @@ async fn claim_gift(account_id: String, state: State<'_, AppState>) -> Result<()
     manager.claim_birthday_bonus(&account_id).await.map_err(Into::into)
 }
 
-/// 浏览器登录
+/// 浏览器登录（可选预填邮箱；multi_add 为 true 时登录成功后不关闭窗口，可连续添加；account_id 为重新登录的目标账号；
+/// keep_session 为 true 时沿用登录窗口已有的会话，默认先清空；verbose 为 true 时本次登录发送 login-debug 详细日志）
+#[tauri::command]
+async fn start_browser_login(
+    app: tauri::AppHandle,
+    email_hint: Option<String>,
+    multi_add: Option<bool>,
+    account_id: Option<String>,
+    keep_session: Option<bool>,
+    verbose: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<()> {
+    let manager = state.account_manager.clone();
+    // 重新登录已有账号时默认预填其邮箱
+    let email_hint = match (&email_hint, &account_id) {
+        (None, Some(id)) => manager.lock().await.get_account(id).ok().map(|a| a.email),
+        _ => email_hint,
+    };
+    let options = login::LoginOptions {
+        email_hint,
+        multi_add: multi_add.unwrap_or(false),
+        target_account_id: account_id,
+        keep_session: keep_session.unwrap_or(false),
+        verbose: verbose.unwrap_or(false),
+        ..login_options(&*state.settings.lock().await)
+    };
+    login::start_login_flow(app, manager, options).await.map_err(|e| ApiError { message: e, network_error: None })?;
+    Ok(())
+}
+
+/// 只刷新账号的 Cookies：打开登录窗口复用已有会话，捕获完整 Cookies 后合并到账号，Token 保持不变
+#[tauri::command]
+async fn refresh_account_cookies(app: tauri::AppHandle, account_id: String, state: State<'_, AppState>) -> Result<()> {
+    let email_hint = state.account_manager.lock().await.get_account(&account_id)?.email;
+    let options = login::LoginOptions {
+        email_hint: Some(email_hint),
+        target_account_id: Some(account_id),
+        cookies_only: true,
+        ..login_options(&*state.settings.lock().await)
+    };
+    login::start_login_flow(app, state.account_manager.clone(), options)
+        .await
+        .map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 原地重新登录：打开登录窗口登录该账号，只替换已失效或有更新的 Token / Cookies，仍然有效的保持不变
 #[tauri::command]
-async fn start_browser_login(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<()> {
+async fn smart_relogin_account(app: tauri::AppHandle, account_id: String, state: State<'_, AppState>) -> Result<()> {
+    let email_hint = state.account_manager.lock().await.get_account(&account_id)?.email;
+    let options = login::LoginOptions {
+        email_hint: Some(email_hint),
+        target_account_id: Some(account_id),
+        selective: true,
+        ..login_options(&*state.settings.lock().await)
+    };
+    login::start_login_flow(app, state.account_manager.clone(), options)
+        .await
+        .map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 测试登录捕获：打开登录窗口，捕获后只上报 capture-test-result 并关闭窗口，不保存任何数据
+#[tauri::command]
+async fn test_capture(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<()> {
+    let options = login::LoginOptions {
+        dry_run: true,
+        ..login_options(&*state.settings.lock().await)
+    };
+    login::start_login_flow(app, state.account_manager.clone(), options)
+        .await
+        .map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 重新打开登录窗口，从上次失败的页面继续
+#[tauri::command]
+async fn retry_login(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<()> {
     let manager = state.account_manager.clone();
-    login::start_login_flow(app, manager).await.map_err(|e| ApiError { message: e })?;
+    let options = login_options(&*state.settings.lock().await);
+    login::retry_login_flow(app, manager, options).await.map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 按应用设置生成登录窗口选项
+fn login_options(settings: &AppSettings) -> login::LoginOptions {
+    login::LoginOptions {
+        notify_success: settings.login_notify_enabled,
+        save_drafts: settings.save_failed_captures,
+        incognito: settings.login_incognito,
+        min_token_length: Some(settings.min_token_length),
+        success_page: settings.login_success_page,
+        always_on_top: settings.login_always_on_top,
+        logout_patterns: Some(settings.login_logout_patterns.clone()),
+        callback_path: Some(settings.login_callback_path.clone()),
+        skip_cookies: !settings.capture_cookies,
+        ..Default::default()
+    }
+}
+
+/// 登录捕获统计（附带成功率）
+#[derive(Debug, serde::Serialize)]
+struct MetricsReport {
+    #[serde(flatten)]
+    metrics: metrics::LoginMetrics,
+    success_rate: Option<f64>,
+}
+
+/// 获取浏览器登录捕获统计
+#[tauri::command]
+async fn get_metrics() -> Result<MetricsReport> {
+    let metrics = metrics::snapshot();
+    let success_rate = metrics.success_rate();
+    Ok(MetricsReport { metrics, success_rate })
+}
+
+/// 最近一次成功捕获时各阶段的耗时（打开窗口、页面加载、收到 Token、收集 Cookies、保存账号）
+#[tauri::command]
+async fn last_capture_timing() -> Result<Option<metrics::CaptureTiming>> {
+    Ok(metrics::last_capture_timing())
+}
+
+/// 获取注入登录页的脚本，供用户审阅
+#[tauri::command]
+async fn get_injection_script(port_placeholder: Option<String>) -> Result<String> {
+    Ok(login::injection_script(port_placeholder.as_deref()))
+}
+
+/// 按当前设置渲染指定端口下实际会注入的脚本（回调令牌以占位符显示），callback_path 为空时使用设置中的路径
+#[tauri::command]
+async fn render_injection_for_port(
+    port: u16,
+    callback_path: Option<String>,
+    nonce_placeholder: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String> {
+    let mut options = login_options(&*state.settings.lock().await);
+    if callback_path.is_some() {
+        options.callback_path = callback_path;
+    }
+    login::render_injection_for_port(port, &options, nonce_placeholder.as_deref())
+        .map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 在登录窗口中手动触发 Token 捕获
+#[tauri::command]
+async fn request_manual_capture(app: tauri::AppHandle) -> Result<()> {
+    login::request_manual_capture(&app).map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 为登录窗口打开开发者工具（需在设置中开启）
+#[tauri::command]
+async fn open_login_devtools(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<()> {
+    if !state.settings.lock().await.login_devtools_enabled {
+        return Err(ApiError {
+            message: "未在设置中允许打开登录窗口的开发者工具".to_string(),
+            network_error: None,
+        });
+    }
+    login::open_login_devtools(&app).map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 结束连续添加并关闭登录窗口
+#[tauri::command]
+async fn finish_browser_login(app: tauri::AppHandle) -> Result<()> {
+    login::finish_login_flow(&app).map_err(|e| ApiError { message: e, network_error: None })
+}
+
+/// 测试代理是否可以访问 trae.ai
+#[tauri::command]
+async fn test_proxy(proxy: String) -> Result<ProxyTestResult> {
+    api::test_proxy(&proxy).await.map_err(Into::into)
+}
+
+/// 通过指定代理校验账号，不保存代理到账号上，返回状态和耗时
+#[tauri::command]
+async fn validate_account_via(account_id: String, proxy: String, state: State<'_, AppState>) -> Result<ProxyValidation> {
+    // 只在读取账号时持有锁，网络请求期间不阻塞其他操作
+    let account = state.account_manager.lock().await.get_account(&account_id)?;
+    AccountManager::validate_account_via(&account, &proxy).await.map_err(Into::into)
+}
+
+/// 获取应用设置
+#[tauri::command]
+async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings> {
+    Ok(state.settings.lock().await.clone())
+}
+
+/// 获取实际生效的配置及每一项的来源（用于排查问题）
+#[tauri::command]
+async fn get_effective_config(state: State<'_, AppState>) -> Result<BTreeMap<String, ConfigValue>> {
+    let mut config = settings::effective_config(&state.settings.lock().await.clone());
+    // 设置中的安全模式下次启动才生效，这里报告本次运行实际使用的值
+    let safe_mode = state.account_manager.lock().await.is_safe_mode();
+    if let Some(entry) = config.get_mut("safe_mode") {
+        entry.value = serde_json::Value::Bool(safe_mode);
+        if safe_mode && std::env::args().any(|arg| arg == "--safe-mode") {
+            entry.source = ConfigSource::CommandLine;
+        }
+    }
+    Ok(config)
+}
+
+/// 更新应用设置，并按新设置重启本地控制 API
+#[tauri::command]
+async fn update_settings(app: tauri::AppHandle, mut settings: AppSettings, state: State<'_, AppState>) -> Result<AppSettings> {
+    // 令牌摘要只能通过 regenerate_control_api_token 修改
+    settings.control_api_token_hash = state.settings.lock().await.control_api_token_hash.clone();
+    settings.default_proxy = settings.default_proxy.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(proxy) = &settings.default_proxy {
+        api::parse_proxy_url(proxy)?;
+    }
+    settings.backup_dir = settings.backup_dir.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+    settings.login_callback_path = login::normalize_callback_path(&settings.login_callback_path)
+        .map_err(|e| ApiError { message: e, network_error: None })?;
+    backup::validate_settings(&settings)?;
+    settings::save_settings(&settings)?;
+    offline::set_enabled(settings.offline_mode);
+    *state.settings.lock().await = settings.clone();
+    {
+        let mut manager = state.account_manager.lock().await;
+        manager.set_max_accounts(settings.max_accounts);
+        manager.set_status_ttl(settings.status_ttl_secs);
+        manager.set_refresh_limits(settings.refresh_concurrency, settings.refresh_timeout_secs);
+        manager.set_default_proxy(settings.default_proxy.clone());
+        manager.set_default_environment(settings::environment_for_base_url(&settings.web_base_url));
+    }
+
+    let mut control_api = state.control_api.lock().await;
+    // 先停止旧服务再按新设置启动，避免端口占用
+    control_api.take();
+    *control_api = control_api::start(state.account_manager.clone(), &settings)?;
+    backup::start(app, state.account_manager.clone(), &settings)?;
+    Ok(settings)
+}
+
+/// 生成新的本地控制 API 访问令牌并按新令牌重启服务；设置中只保存摘要，返回的令牌只显示这一次
+#[tauri::command]
+async fn regenerate_control_api_token(state: State<'_, AppState>) -> Result<String> {
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    let settings = {
+        let mut settings = state.settings.lock().await;
+        settings.control_api_token_hash = control_api::token_hash(&token);
+        settings::save_settings(&settings)?;
+        settings.clone()
+    };
+
+    let mut control_api = state.control_api.lock().await;
+    control_api.take();
+    *control_api = control_api::start(state.account_manager.clone(), &settings)?;
+    Ok(token)
+}
+
+/// 开启或关闭离线模式：开启时停止定时备份和启动校验，之后的网络请求直接返回离线错误；关闭时按设置恢复定时备份
+#[tauri::command]
+async fn set_offline_mode(app: tauri::AppHandle, enabled: bool, state: State<'_, AppState>) -> Result<AppSettings> {
+    let mut settings = state.settings.lock().await;
+    settings.offline_mode = enabled;
+    settings::save_settings(&settings)?;
+    offline::set_enabled(enabled);
+    if enabled {
+        let stopped = tasks::abort(tasks::BACKUP) + tasks::abort(tasks::STARTUP_VALIDATION);
+        println!("[INFO] 已开启离线模式，停止了 {} 个后台任务", stopped);
+    } else {
+        println!("[INFO] 已关闭离线模式");
+        backup::start(app.clone(), state.account_manager.clone(), &settings)?;
+    }
+    let _ = app.emit("offline-mode-changed", enabled);
+    Ok(settings.clone())
+}
+
+/// 立即按当前设置创建一个加密备份，返回备份文件路径
+#[tauri::command]
+async fn run_backup(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<String> {
+    let settings = state.settings.lock().await.clone();
+    match backup::run_once(&state.account_manager, &settings).await {
+        Ok(path) => {
+            let path = path.display().to_string();
+            let _ = app.emit("backup-created", &path);
+            Ok(path)
+        }
+        Err(e) => {
+            let _ = app.emit("backup-failed", e.to_string());
+            Err(e.into())
+        }
+    }
+}
+
+/// 设置本次运行使用的备份密码（只保存在内存中，空值表示清除）
+#[tauri::command]
+async fn set_backup_passphrase(passphrase: Option<String>) -> Result<()> {
+    backup::set_session_passphrase(passphrase);
+    Ok(())
+}
+
+/// 本次运行是否已输入备份密码
+#[tauri::command]
+async fn has_backup_passphrase() -> Result<bool> {
+    Ok(backup::session_passphrase().is_some())
+}
+
+/// 把账号的访问 Token 加密导出到 path（不含 Cookies 和刷新凭证），可通过「从备份恢复」导入
+#[tauri::command]
+async fn export_token_only(account_id: String, passphrase: String, path: String, state: State<'_, AppState>) -> Result<()> {
+    let data = state.account_manager.lock().await.export_token_only(&account_id, &passphrase)?;
+    std::fs::write(&path, data).map_err(anyhow::Error::from)?;
     Ok(())
 }
 
+/// 用新密码重新加密备份文件，写入同目录下的 <原文件名>-shared.trbak 并返回其路径（不导入账号）
+#[tauri::command]
+async fn reencrypt_backup(path: String, old_passphrase: String, new_passphrase: String) -> Result<String> {
+    let source = std::path::PathBuf::from(&path);
+    let data = std::fs::read(&source).map_err(anyhow::Error::from)?;
+    let output = backup::reencrypt(&data, &old_passphrase, &new_passphrase)?;
+    let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("trae-backup");
+    let target = source.with_file_name(format!("{}-shared.trbak", stem));
+    std::fs::write(&target, output).map_err(anyhow::Error::from)?;
+    Ok(target.display().to_string())
+}
+
+/// 用备份密码解密备份文件并导入其中的账号（preserve_ids 同 import_accounts）
+#[tauri::command]
+async fn import_backup(
+    path: String,
+    passphrase: String,
+    strategy: Option<ImportStrategy>,
+    preserve_ids: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ImportRecord>> {
+    let data = std::fs::read(&path).map_err(anyhow::Error::from)?;
+    let plaintext = backup::decrypt(&data, &passphrase)?;
+    let text = String::from_utf8(plaintext).map_err(|_| anyhow::anyhow!("备份内容不是有效的文本"))?;
+    let mut manager = state.account_manager.lock().await;
+    manager
+        .import_accounts(&text, strategy.unwrap_or_default(), preserve_ids.unwrap_or(false))
+        .await
+        .map_err(Into::into)
+}
+
+/// 把全部账号导出为标准 age 文件；recipient 为 age1 开头的公钥或密码
+#[tauri::command]
+async fn export_age(path: String, recipient: String, state: State<'_, AppState>) -> Result<()> {
+    let recipient = age::Recipient::parse(&recipient)?;
+    let data = state.account_manager.lock().await.export_accounts()?;
+    let sealed = age::encrypt(data.as_bytes(), &recipient)?;
+    std::fs::write(&path, sealed).map_err(anyhow::Error::from)?;
+    Ok(())
+}
+
+/// 解密 age 文件并导入其中的账号；secret 为密码或 AGE-SECRET-KEY-1 身份密钥（preserve_ids 同 import_accounts）
+#[tauri::command]
+async fn import_age(
+    path: String,
+    secret: String,
+    strategy: Option<ImportStrategy>,
+    preserve_ids: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ImportRecord>> {
+    let data = std::fs::read(&path).map_err(anyhow::Error::from)?;
+    let plaintext = age::decrypt(&data, &secret)?;
+    let text = String::from_utf8(plaintext).map_err(|_| anyhow::anyhow!("age 文件内容不是有效的文本"))?;
+    let mut manager = state.account_manager.lock().await;
+    manager
+        .import_accounts(&text, strategy.unwrap_or_default(), preserve_ids.unwrap_or(false))
+        .await
+        .map_err(Into::into)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let account_manager = AccountManager::new().expect("无法初始化账号管理器");
+    let settings = settings::load_settings();
+    // 安全模式：命令行 --safe-mode 或设置中开启
+    let safe_mode = settings.safe_mode || std::env::args().any(|arg| arg == "--safe-mode");
+    let account_manager = if safe_mode {
+        AccountManager::new_safe_mode()
+    } else {
+        AccountManager::new()
+    };
+    let mut account_manager = account_manager.expect("无法初始化账号管理器");
+    account_manager.set_max_accounts(settings.max_accounts);
+    account_manager.set_status_ttl(settings.status_ttl_secs);
+    account_manager.set_refresh_limits(settings.refresh_concurrency, settings.refresh_timeout_secs);
+    account_manager.set_default_proxy(settings.default_proxy.clone());
+    account_manager.set_default_environment(settings::environment_for_base_url(&settings.web_base_url));
+    let validation_cancel = account_manager.validation_canceller();
+    let account_manager = Arc::new(Mutex::new(account_manager));
+    let control_api = Arc::new(Mutex::new(None));
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(AppState {
-            account_manager: Arc::new(Mutex::new(account_manager)),
+            account_manager: account_manager.clone(),
+            settings: Arc::new(Mutex::new(settings.clone())),
+            control_api: control_api.clone(),
+            validation_cancel,
+        })
+        .setup(move |app| {
+            offline::set_enabled(settings.offline_mode);
+            if settings.offline_mode {
+                println!("[INFO] 离线模式已开启，跳过启动时的网络请求");
+            }
+
+            // 数据目录不可写时提前提醒，避免修改在保存时静默丢失
+            let app_handle = app.handle().clone();
+            let manager_for_check = account_manager.clone();
+            tauri::async_runtime::spawn(async move {
+                let check = manager_for_check.lock().await.check_storage_writable();
+                if !check.writable {
+                    println!("[WARN] 数据目录不可写: {} ({})", check.path, check.error.as_deref().unwrap_or_default());
+                    let _ = app_handle.emit("storage-readonly", &check);
+                }
+                // 数据文件在应用之外被修改或上次保存不完整时提醒，比解析失败更早发现损坏
+                match manager_for_check.lock().await.verify_store_checksum() {
+                    Ok(ChecksumStatus::Mismatch) => {
+                        println!("[WARN] 账号数据文件与记录的校验和不一致");
+                        let _ = app_handle.emit("store-checksum-mismatch", ());
+                    }
+                    Ok(_) => {}
+                    Err(e) => println!("[WARN] 校验账号数据文件失败: {}", e),
+                }
+            });
+
+            // 预先创建 HTTP 客户端，首次批量校验时不必逐个建立
+            let manager_for_clients = account_manager.clone();
+            tauri::async_runtime::spawn(async move {
+                let count = manager_for_clients.lock().await.prewarm_clients();
+                println!("[INFO] 已预先创建 {} 个 HTTP 客户端", count);
+            });
+
+            // 检测本机时钟偏差，校正 Token 过期判断
+            if !settings.offline_mode {
+                let app_handle = app.handle().clone();
+                let manager_for_clock = account_manager.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = measure_clock_skew(&app_handle, &manager_for_clock).await {
+                        println!("[WARN] 检测时钟偏差失败: {}", e);
+                    }
+                });
+            }
+
+            // 启动后延迟校验所有账号（默认关闭），应用退出时随后台任务一起中止
+            if settings.validate_on_startup && !settings.offline_mode {
+                let app_handle = app.handle().clone();
+                let manager_for_validation = account_manager.clone();
+                let delay = std::time::Duration::from_secs(settings.startup_validation_delay_secs);
+                tasks::spawn(tasks::STARTUP_VALIDATION, async move {
+                    tokio::time::sleep(delay).await;
+                    run_startup_validation(&app_handle, &manager_for_validation).await;
+                });
+            }
+
+            // 按设置启动定时备份（默认关闭）
+            let app_handle = app.handle().clone();
+            let manager_for_backup = account_manager.clone();
+            let backup_settings = settings.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = backup::start(app_handle, manager_for_backup, &backup_settings) {
+                    println!("[WARN] 启动定时备份失败: {}", e);
+                }
+            });
+
+            // 按设置启动本地控制 API（默认关闭）
+            tauri::async_runtime::spawn(async move {
+                match control_api::start(account_manager, &settings) {
+                    Ok(handle) => *control_api.lock().await = handle,
+                    Err(e) => println!("[WARN] {}", e),
+                }
+            });
+            Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            is_store_read_only,
+            check_storage_writable,
+            check_clock_skew,
+            is_safe_mode,
             add_account_by_token,
             remove_account,
             get_accounts,
             get_account,
+            list_unvalidated,
+            validate_stale,
+            cancel_validation,
+            reassign_account_id,
+            find_duplicate_groups,
+            find_linked_accounts,
+            replay_capture,
+            predict_login,
+            account_stats,
+            check_store,
+            diagnose_all,
+            compact_store,
+            verify_store_checksum,
+            account_sizes,
+            normalize_cookies,
+            export_diagnostics,
+            set_account_status,
+            set_account_color,
+            set_account_environment,
+            get_grouped_accounts,
+            set_account_group,
+            reorder_group,
+            reorder_groups,
+            set_group_collapsed,
+            suggested_environments,
+            set_account_proxy,
+            account_health_history,
+            set_account_enabled,
+            set_account_auto_refresh,
+            tag_where,
+            set_proxy_where,
+            auto_tag_by_domain,
+            remove_tag,
+            find_by_claim,
+            purge_expired,
+            reset_statuses,
+            drain_pending_events,
+            set_primary,
+            get_primary,
+            prewarm_clients,
+            restore_from_quarantine,
+            empty_quarantine,
+            reveal_store_in_explorer,
+            copy_cookie_header,
+            export_browser_session,
+            run_backup,
+            set_backup_passphrase,
+            has_backup_passphrase,
+            set_offline_mode,
+            import_backup,
+            export_age,
+            import_age,
+            reencrypt_backup,
+            export_token_only,
+            open_billing,
+            preview_activation,
+            verify_active,
+            report_cookie_conflicts,
+            remap_cookie_domain,
+            dedup_account_cookies,
+            cookie_capture_report,
             switch_account,
+            activation_diagnosis,
             get_account_usage,
+            refresh_account_email,
+            import_current_session,
+            list_draft_captures,
+            commit_draft,
+            discard_draft,
             update_account_token,
+            set_account_user_agent,
+            set_account_extra_headers,
+            captured_header_names,
+            clear_captured_headers,
             export_accounts,
+            export_by_tag,
             import_accounts,
+            validate_imported,
+            import_foreign_json,
+            export_manifest,
+            import_manifest,
+            find_import_duplicates,
+            snapshot,
+            list_snapshots,
+            restore_snapshot,
             get_usage_events,
             read_trae_account,
             get_machine_id,
@@ -245,7 +1439,38 @@ pub fn run() {
             refresh_token,
             refresh_all_tokens,
             start_browser_login,
+            test_capture,
+            refresh_account_cookies,
+            smart_relogin_account,
+            retry_login,
+            request_manual_capture,
+            open_login_devtools,
+            get_injection_script,
+            render_injection_for_port,
+            get_metrics,
+            last_capture_timing,
+            finish_browser_login,
+            test_proxy,
+            validate_account_via,
+            get_settings,
+            get_effective_config,
+            update_settings,
+            regenerate_control_api_token,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                // 退出时关闭登录窗口、停止回调服务和本地控制 API
+                login::shutdown(app);
+                if let Ok(mut control_api) = app.state::<AppState>().control_api.try_lock() {
+                    control_api.take();
+                }
+                // 退出时释放存储文件锁
+                if let Ok(mut manager) = app.state::<AppState>().account_manager.try_lock() {
+                    manager.release_lock();
+                }
+                tasks::abort_all();
+            }
+        });
 }
@@ -1,146 +1,838 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
 use tokio::sync::{oneshot, Mutex};
 use warp::Filter;
 
-use crate::account::AccountManager;
+use crate::account::{cookies, AccountError, AccountManager, CookieCapture};
+use crate::api::{classify_network_error, TraeApiClient};
+use crate::callback_token::{self, CallbackSigner, TokenError};
+use crate::metrics::{self, LoginEvent, LoginPhase};
+use crate::tasks;
+use crate::pending_events;
+use crate::window_accounts;
+use crate::settings::{DEFAULT_LOGIN_CALLBACK_PATH, DEFAULT_LOGOUT_URL_PATTERNS, DEFAULT_MIN_TOKEN_LENGTH};
 
-pub async fn start_login_flow(
-    app: AppHandle,
-    state: Arc<Mutex<AccountManager>>,
-) -> Result<(), String> {
-    // 如果已有登录窗口，聚焦它
-    if let Some(win) = app.get_webview_window("trae-login") {
-        let _ = win.set_focus();
-        return Ok(());
+/// 登录窗口标签
+const LOGIN_WINDOW_LABEL: &str = "trae-login";
+
+/// 手动捕获时在登录窗口中执行的脚本（调用注入脚本暴露的入口）
+const MANUAL_CAPTURE_SCRIPT: &str =
+    "if (window.__traeAutoCapture) { window.__traeAutoCapture(); }";
+
+/// 登录页地址
+const LOGIN_START_URL: &str = "https://www.trae.ai";
+
+/// 捕获到 Token 后等待的毫秒数；期间 trae.ai 再次下发的 Token 会替换前一个，只提交最后一个
+const TOKEN_SETTLE_MS: u64 = 1500;
+
+/// 登录成功后显示的本地页面：短暂显示成功标记后请求关闭窗口
+const DONE_PAGE_HTML: &str = r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+<meta charset="utf-8">
+<title>登录成功</title>
+<style>
+  body { margin: 0; height: 100vh; display: flex; flex-direction: column; align-items: center; justify-content: center;
+         font-family: -apple-system, "Segoe UI", "Microsoft YaHei", sans-serif; background: #f6f7f9; color: #1f2329; }
+  .check { width: 72px; height: 72px; border-radius: 50%; background: #32a645; color: #fff; font-size: 44px;
+           display: flex; align-items: center; justify-content: center; }
+  p { margin-top: 16px; font-size: 16px; }
+</style>
+</head>
+<body>
+<div class="check">&#10003;</div>
+<p>账号已添加，窗口即将关闭</p>
+<script>
+  // 回调令牌由注入脚本通过地址的 # 部分传入
+  var auth = decodeURIComponent(location.hash.slice(1));
+  setTimeout(function() {
+    fetch("/action", {
+      method: "POST",
+      headers: { "Content-Type": "application/json", "X-Login-Auth": auth },
+      body: JSON.stringify({ action: "close" })
+    });
+  }, 1000);
+</script>
+</body>
+</html>
+"#;
+
+/// 登录回调服务绑定端口的最大尝试次数
+const CALLBACK_BIND_ATTEMPTS: usize = 3;
+
+/// 探测回调服务是否可连接的超时
+const CALLBACK_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 主窗口标签（tauri.conf.json 未指定 label 时的默认值）
+pub(crate) const MAIN_WINDOW_LABEL: &str = "main";
+
+/// 登录窗口最近访问的 trae.ai 页面，用于失败后从原处重试
+static LAST_LOGIN_URL: Lazy<std::sync::Mutex<Option<String>>> = Lazy::new(|| std::sync::Mutex::new(None));
+
+/// 无痕模式下读取不到登录窗口 Cookies 时置位，之后的登录窗口回退为普通模式
+static INCOGNITO_COOKIES_UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// 当前登录流程的回调服务停止信号，应用退出时用于清理
+type ShutdownSlot = Arc<Mutex<Option<oneshot::Sender<()>>>>;
+static ACTIVE_LOGIN: Lazy<std::sync::Mutex<Option<ShutdownSlot>>> = Lazy::new(|| std::sync::Mutex::new(None));
+
+/// 打开登录窗口的选项
+#[derive(Debug, Clone, Default)]
+pub struct LoginOptions {
+    /// 预填的邮箱
+    pub email_hint: Option<String>,
+    /// 连续添加模式：登录成功后不关闭窗口
+    pub multi_add: bool,
+    /// 起始页面，为空时打开登录首页
+    pub start_url: Option<String>,
+    /// 登录成功时发送桌面通知
+    pub notify_success: bool,
+    /// 添加失败时把捕获的凭证保存为草稿
+    pub save_drafts: bool,
+    /// 登录窗口使用无痕模式，避免连续登录之间共享会话
+    pub incognito: bool,
+    /// 接受的最短 Token 长度，None 时使用默认值
+    pub min_token_length: Option<usize>,
+    /// 重新登录的目标账号：捕获到该账号时原地更新凭证，保留其余信息
+    pub target_account_id: Option<String>,
+    /// 关闭窗口前先显示成功页面
+    pub success_page: bool,
+    /// 登录窗口置顶显示
+    pub always_on_top: bool,
+    /// 测试捕获：只上报捕获结果（Token 长度、Cookie 名称）后关闭窗口，不添加账号、不记录统计
+    pub dry_run: bool,
+    /// 只刷新 target_account_id 的 Cookies：复用登录窗口中已有的会话，保留账号现有的 Token
+    pub cookies_only: bool,
+    /// 继续使用登录窗口中已有的 trae.ai 会话；默认先清空会话，避免误捕获之前已登录的账号
+    pub keep_session: bool,
+    /// 详细捕获日志：注入脚本把捕获过程逐步上报，以 login-debug 事件转发给前端（只影响本次登录）
+    pub verbose: bool,
+    /// 接收 Token 的回调路径，None 时使用默认的 callback
+    pub callback_path: Option<String>,
+    /// 登出页面地址片段，None 时使用默认值
+    pub logout_patterns: Option<Vec<String>>,
+    /// 不保存 Cookies：忽略脚本上报的 Cookies，也不从 webview 读取，账号只保存 Token
+    pub skip_cookies: bool,
+    /// 重新登录 target_account_id 时只替换已失效或有更新的凭证，仍然有效的保持不变
+    pub selective: bool,
+}
+
+/// 记录登录窗口当前所在页面（仅记录 trae.ai 页面）
+fn record_login_url(url: &str) {
+    if !is_trae_url(url) {
+        return;
+    }
+    if let Ok(mut last) = LAST_LOGIN_URL.lock() {
+        *last = Some(url.to_string());
     }
+}
 
-    // 创建 oneshot channel 用于通知 warp 服务停止
-    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
-    let shutdown_tx = Arc::new(Mutex::new(Some(shutdown_tx)));
+/// 取出可用于重试的页面地址（再次校验属于 trae.ai）
+fn last_login_url() -> Option<String> {
+    LAST_LOGIN_URL
+        .lock()
+        .ok()
+        .and_then(|last| last.clone())
+        .filter(|url| is_trae_url(url))
+}
 
-    let app_clone = app.clone();
-    let state_clone = state.clone();
+fn clear_last_login_url() {
+    if let Ok(mut last) = LAST_LOGIN_URL.lock() {
+        *last = None;
+    }
+}
 
-    // POST /callback — 接收 token 和 cookies
-    let callback = warp::post()
-        .and(warp::path("callback"))
-        .and(warp::body::json())
-        .and_then(move |body: serde_json::Value| {
-            let app = app_clone.clone();
-            let state = state_clone.clone();
-            async move {
-                let token = body["token"].as_str().unwrap_or("");
-                if token.is_empty() {
-                    return Ok::<_, warp::Rejection>(warp::reply::json(
-                        &serde_json::json!({"status": "waiting"}),
-                    ));
-                }
+/// 判断 URL 是否属于 trae.ai（包括 www、accounts 等所有子域名）
+pub fn is_trae_url(url: &str) -> bool {
+    let Ok(parsed) = tauri::Url::parse(url) else {
+        return false;
+    };
+    if parsed.scheme() != "https" {
+        return false;
+    }
+    match parsed.host_str() {
+        Some(host) => host == "trae.ai" || host.ends_with(".trae.ai"),
+        None => false,
+    }
+}
 
-                // 提取 cookies（如果有）
-                let cookies = body["cookies"].as_str().map(|s| s.to_string());
+/// 构建邮箱预填脚本：在 trae.ai 登录页出现邮箱输入框时自动填入，找不到输入框则静默放弃
+fn build_email_hint_script(email: &str) -> String {
+    // 通过 JSON 序列化得到安全的 JS 字符串字面量
+    let hint = serde_json::to_string(email).unwrap_or_else(|_| "\"\"".to_string());
+    format!(
+        r#"
+        (function() {{
+            if (!/(^|\.)trae\.ai$/.test(location.hostname)) return;
+            var hint = {hint};
 
-                let mut manager = state.lock().await;
-                match manager.add_account_by_token(token.to_string(), cookies).await {
-                    Ok(account) => {
-                        let _ = app.emit("login-success", &account.email);
-                        // 延迟关闭窗口，让 warp 先返回响应
-                        let app2 = app.clone();
-                        tokio::spawn(async move {
-                            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-                            if let Some(win) = app2.get_webview_window("trae-login") {
-                                let _ = win.close();
-                            }
-                        });
-                        Ok(warp::reply::json(&serde_json::json!({"status": "ok"})))
-                    }
+            function fill() {{
+                var input = document.querySelector('input[type="email"], input[name*="email" i]');
+                if (!input) return false;
+                if (input.value) return true;
+                // 使用原生 setter，确保 React 等框架能感知到值变化
+                var setter = Object.getOwnPropertyDescriptor(HTMLInputElement.prototype, "value").set;
+                setter.call(input, hint);
+                input.dispatchEvent(new Event("input", {{ bubbles: true }}));
+                input.dispatchEvent(new Event("change", {{ bubbles: true }}));
+                return true;
+            }}
+
+            function start() {{
+                if (fill()) return;
+                var observer = new MutationObserver(function() {{
+                    if (fill()) observer.disconnect();
+                }});
+                observer.observe(document.documentElement, {{ childList: true, subtree: true }});
+                setTimeout(function() {{ observer.disconnect(); }}, 30000);
+            }}
+
+            if (document.readyState === "loading") {{
+                document.addEventListener("DOMContentLoaded", start);
+            }} else {{
+                start();
+            }}
+        }})();
+    "#,
+        hint = hint
+    )
+}
+
+/// 回调响应中告诉注入脚本的后续动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum CallbackAction {
+    /// 关闭登录窗口
+    Close,
+    /// 清空会话并回到登录页（连续添加）
+    Reset,
+    /// 保持当前页面，允许再次捕获
+    Keep,
+}
+
+/// 登录成功（或账号已存在）后的动作：连续添加模式下重置窗口，否则关闭
+fn success_action(multi_add: bool) -> CallbackAction {
+    if multi_add {
+        CallbackAction::Reset
+    } else {
+        CallbackAction::Close
+    }
+}
+
+/// 登录成功的回调响应；需要关闭窗口且开启了成功页面时，让脚本先跳转到 /done
+fn success_reply(multi_add: bool, success_page: bool) -> warp::reply::Json {
+    let action = success_action(multi_add);
+    let mut body = serde_json::json!({ "status": "ok", "action": action });
+    if success_page && action == CallbackAction::Close {
+        body["done_page"] = serde_json::Value::Bool(true);
+    }
+    warp::reply::json(&body)
+}
+
+fn callback_reply(status: &str, action: CallbackAction, message: Option<&str>) -> warp::reply::Json {
+    let mut body = serde_json::json!({ "status": status, "action": action });
+    if let Some(message) = message {
+        body["message"] = serde_json::Value::from(message);
+    }
+    warp::reply::json(&body)
+}
+
+/// 回调请求没有携带有效的回调令牌
+#[derive(Debug)]
+struct CallbackUnauthorized(TokenError);
+
+impl warp::reject::Reject for CallbackUnauthorized {}
+
+/// 校验注入脚本携带的回调令牌，拒绝缺失、篡改、过期或属于之前登录流程的令牌
+fn with_callback_auth(signer: Arc<CallbackSigner>) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>(callback_token::HEADER)
+        .and_then(move |token: Option<String>| {
+            let signer = signer.clone();
+            async move {
+                match signer.verify(token.as_deref(), chrono::Utc::now().timestamp()) {
+                    Ok(()) => Ok(()),
                     Err(e) => {
-                        let msg = e.to_string();
-                        if msg.contains("已存在") {
-                            let _ = app.emit("login-failed", "该账号已存在");
-                            let app2 = app.clone();
-                            tokio::spawn(async move {
-                                tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-                                if let Some(win) = app2.get_webview_window("trae-login") {
-                                    let _ = win.close();
-                                }
-                            });
-                        }
-                        Ok(warp::reply::json(
-                            &serde_json::json!({"status": "error", "message": msg}),
-                        ))
+                        println!("[WARN] 拒绝登录回调请求: {}", e);
+                        Err(warp::reject::custom(CallbackUnauthorized(e)))
                     }
                 }
             }
-        });
+        })
+        .untuple_one()
+}
 
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_methods(vec!["POST"])
-        .allow_headers(vec!["content-type"]);
+async fn handle_callback_rejection(rejection: warp::Rejection) -> Result<warp::reply::Response, warp::Rejection> {
+    use warp::Reply;
+    match rejection.find::<CallbackUnauthorized>() {
+        Some(CallbackUnauthorized(e)) => Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({ "status": "unauthorized", "message": e.to_string() })),
+            warp::http::StatusCode::FORBIDDEN,
+        )
+        .into_response()),
+        None => Err(rejection),
+    }
+}
 
-    let routes = callback.with(cors);
+/// 捕获到 Token 但保存账号失败时的错误分类，供前端展示
+fn store_error_code(error: &anyhow::Error) -> &'static str {
+    match error.downcast_ref::<AccountError>() {
+        Some(AccountError::LimitReached(_)) => "limit_reached",
+        Some(AccountError::ReadOnly(_)) => "read_only",
+        Some(AccountError::Duplicate { .. }) => "duplicate",
+        Some(AccountError::CredentialMismatch { .. }) => "credential_mismatch",
+        None if classify_network_error(error).is_some() => "network",
+        None if error.downcast_ref::<std::io::Error>().is_some() => "storage",
+        None => "invalid_token",
+    }
+}
 
-    let (addr, server) =
-        warp::serve(routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async {
-            let _ = shutdown_rx.await;
-        });
-    let port = addr.port();
+/// 捕获到的账号正在其他窗口（如用量页面）中使用时提醒：激活或刷新凭证可能使该窗口的会话失效
+fn warn_if_in_use(app: &AppHandle, account_id: &str, email: &str) {
+    let windows = window_accounts::windows_using(account_id);
+    if windows.is_empty() {
+        return;
+    }
+    println!("[WARN] 账号 {} 正在窗口 {:?} 中使用", email, windows);
+    let _ = app.emit(
+        "account-in-use",
+        serde_json::json!({ "id": account_id, "email": email, "windows": windows }),
+    );
+}
+
+/// 登录成功后按设置通知前端弹出桌面通知
+fn notify_login_success(app: &AppHandle, enabled: bool, email: &str) {
+    if enabled {
+        let _ = app.emit("login-notify", email);
+    }
+}
+
+fn close_login_window(app: &AppHandle) {
+    if let Some(win) = app.get_webview_window(LOGIN_WINDOW_LABEL) {
+        let _ = win.close();
+    }
+}
+
+/// 清空登录窗口的会话并回到登录页，准备登录下一个账号
+fn reset_login_window(app: &AppHandle) {
+    let Some(win) = app.get_webview_window(LOGIN_WINDOW_LABEL) else {
+        return;
+    };
+    if let Err(e) = win.clear_all_browsing_data() {
+        println!("[WARN] 清空登录窗口会话失败: {}", e);
+    }
+    let _ = win.navigate(LOGIN_START_URL.parse().unwrap());
+    let _ = app.emit("ready-for-next", ());
+}
+
+/// 测试捕获的结果：只包含长度和 Cookie 名称，不包含任何凭证值
+fn capture_test_result(token: &str, cookies: Option<&str>, refresh_token: Option<&str>) -> serde_json::Value {
+    let cookie_names: Vec<String> = cookies
+        .map(|c| cookies::parse_cookie_pairs(c).into_iter().map(|(name, _)| name).collect())
+        .unwrap_or_default();
+    let missing_critical = cookies.map(|c| cookies::critical_cookie_presence(c).1).unwrap_or_else(|| {
+        cookies::CRITICAL_COOKIES.iter().map(|name| name.to_string()).collect()
+    });
+    serde_json::json!({
+        "token_length": token.len(),
+        "has_refresh_token": refresh_token.is_some_and(|t| !t.is_empty()),
+        "cookie_names": cookie_names,
+        "missing_critical_cookies": missing_critical,
+    })
+}
 
-    tokio::spawn(server);
+/// 保存捕获的 Cookie 分类（失败只记录日志，不影响登录结果）
+fn record_cookie_capture(manager: &mut AccountManager, account_id: &str, capture: Option<CookieCapture>) {
+    let Some(capture) = capture else {
+        return;
+    };
+    if capture.http_only.is_empty() {
+        println!("[WARN] 账号 {} 捕获时没有读到 HttpOnly Cookie，登录态可能不完整", account_id);
+    }
+    if let Err(e) = manager.record_cookie_capture(account_id, capture) {
+        println!("[WARN] 保存 Cookie 捕获记录失败: {}", e);
+    }
+}
+
+/// 注入脚本回传的 GetUserToken 请求头（值可能包含凭证，只保存、不打印）
+fn reported_request_headers(body: &serde_json::Value) -> HashMap<String, String> {
+    body["request_headers"]
+        .as_object()
+        .map(|headers| {
+            headers
+                .iter()
+                .filter_map(|(name, value)| value.as_str().map(|v| (name.clone(), v.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 保存登录时捕获的请求头，供刷新 Token 时复用；没有捕获到时保留账号原有的记录
+fn record_request_headers(manager: &mut AccountManager, account_id: &str, headers: &HashMap<String, String>) {
+    if headers.is_empty() {
+        return;
+    }
+    match manager.set_captured_headers(account_id, headers) {
+        Ok(names) => println!("[INFO] 已保存账号 {} 登录时的 {} 个请求头", account_id, names.len()),
+        Err(e) => println!("[WARN] 保存登录请求头失败: {}", e),
+    }
+}
+
+/// 只刷新 Cookies：确认登录窗口中的会话属于目标账号后合并完整 Cookies（含 HttpOnly），Token 保持不变
+fn refresh_cookies_only(
+    app: &AppHandle,
+    manager: &mut AccountManager,
+    account_id: &str,
+    token: &str,
+    cookies: Option<&str>,
+    cookie_capture: Option<CookieCapture>,
+) -> warp::reply::Json {
+    let account = match manager.get_account(account_id) {
+        Ok(account) => account,
+        Err(e) => return callback_reply("error", CallbackAction::Close, Some(&e.to_string())),
+    };
+    let session_user = TraeApiClient::parse_jwt_token(token).map(|payload| payload.user_id).ok();
+    if session_user.as_deref() != Some(account.user_id.as_str()) {
+        let msg = format!("登录窗口中的账号不是 {}，请切换到该账号后重试", account.email);
+        let _ = app.emit("login-failed", &msg);
+        return callback_reply("error", CallbackAction::Keep, Some(&msg));
+    }
+    let Some(cookies) = cookies.filter(|c| !c.trim().is_empty()) else {
+        return callback_reply("error", CallbackAction::Keep, Some("未读取到 Cookies"));
+    };
+    if let Err(e) = manager.merge_cookies(account_id, cookies) {
+        return callback_reply("error", CallbackAction::Keep, Some(&e.to_string()));
+    }
+    record_cookie_capture(manager, account_id, cookie_capture);
+
+    let merged = manager.get_account(account_id).map(|a| a.cookies).unwrap_or_default();
+    let (present, missing) = cookies::critical_cookie_presence(&merged);
+    println!("[INFO] 已刷新账号 {} 的 Cookies，缺少关键 Cookie: {:?}", account.email, missing);
+    let _ = app.emit(
+        "cookies-refreshed",
+        serde_json::json!({ "id": account.id, "email": account.email, "present": present, "missing": missing }),
+    );
+    callback_reply("ok", CallbackAction::Close, None)
+}
+
+/// 回调服务绑定后尝试建立一次 TCP 连接，确认端口确实可用
+async fn probe_callback_server(addr: std::net::SocketAddr) -> bool {
+    matches!(
+        tokio::time::timeout(CALLBACK_PROBE_TIMEOUT, tokio::net::TcpStream::connect(addr)).await,
+        Ok(Ok(_))
+    )
+}
+
+/// 规范化登出页面地址片段：去掉首尾空白、转为小写并丢弃空项；未设置时使用默认值
+fn normalize_logout_patterns(patterns: Option<Vec<String>>) -> Vec<String> {
+    let patterns = patterns.unwrap_or_else(|| DEFAULT_LOGOUT_URL_PATTERNS.iter().map(|p| p.to_string()).collect());
+    patterns
+        .iter()
+        .map(|p| p.trim().to_lowercase())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// 地址是否为登出页面（路径包含任一登出地址片段，不区分大小写；查询参数和域名不参与匹配）
+pub fn is_logout_url(url: &str, patterns: &[String]) -> bool {
+    let Ok(parsed) = tauri::Url::parse(url) else {
+        return false;
+    };
+    let path = parsed.path().to_lowercase();
+    patterns.iter().any(|p| !p.is_empty() && path.contains(p.as_str()))
+}
+
+/// 回调服务的其他路由，自定义回调路径不能与它们重名
+const RESERVED_CALLBACK_PATHS: &[&str] = &["action", "status", "cookies", "done", "debug"];
+
+/// 校验并规范化自定义回调路径：去掉首尾斜杠，只允许单段的字母、数字、`-` 和 `_`
+pub fn normalize_callback_path(path: &str) -> Result<String, String> {
+    let path = path.trim().trim_matches('/');
+    if path.is_empty() {
+        return Err("回调路径不能为空".to_string());
+    }
+    if !path.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("回调路径只能包含字母、数字、- 和 _: {}", path));
+    }
+    if RESERVED_CALLBACK_PATHS.contains(&path) {
+        return Err(format!("回调路径不能使用保留名称: {}", path));
+    }
+    Ok(path.to_string())
+}
 
-    // 注入 JS：Hook fetch/XHR 拦截 trae.ai 前端自身的 GetUserToken 请求响应
-    // 注意：document.cookie 无法获取 HttpOnly cookies，所以这里只发送 token
-    // 完整的 cookies 需要在 Rust 端通过 webview API 获取
-    let init_script = format!(
+/// 构建注入登录页的脚本：Hook fetch/XHR 拦截 trae.ai 前端自身的 GetUserToken 请求响应
+/// 注意：document.cookie 无法获取 HttpOnly cookies，所以这里只发送 token，
+/// 完整的 cookies 需要在 Rust 端通过 webview API 获取
+fn build_init_script(
+    port: impl std::fmt::Display,
+    callback_path: &str,
+    min_token_length: usize,
+    auth: &str,
+    verbose: bool,
+    logout_patterns: &[String],
+) -> String {
+    let logout_patterns = serde_json::to_string(logout_patterns).unwrap_or_else(|_| "[]".to_string());
+    format!(
         r#"
         (function() {{
             var __sent = false;
-            var __callbackUrl = "http://127.0.0.1:{port}/callback";
+            var __pending = null;
+            var __settleTimer = null;
+            var __settleMs = {settle_ms};
+            var __shortReported = false;
+            var __minTokenLength = {min_token_length};
+            var __callbackUrl = "http://127.0.0.1:{port}/{callback_path}";
+            var __actionUrl = "http://127.0.0.1:{port}/action";
+            var __statusUrl = "http://127.0.0.1:{port}/status";
+            var __cookiesUrl = "http://127.0.0.1:{port}/cookies";
+            var __doneUrl = "http://127.0.0.1:{port}/done";
+            var __debugUrl = "http://127.0.0.1:{port}/debug";
+            var __auth = "{auth}";
+            var __verbose = {verbose};
+            var __captcha = false;
+            var __afterCaptcha = [];
+            var __logoutPatterns = {logout_patterns};
+
+            // 回调令牌只能使用一次：每个回调响应的 X-Login-Auth 头中带有下一个令牌，
+            // trae.ai 页面加载完成后应用也会下发新令牌（令牌被拒绝时等待新令牌再重发）
+            var __queue = [];
+            var __inflight = false;
+            var __waitingAuth = false;
+            window.__traeSetAuth = function(auth) {{
+                if (typeof auth !== "string" || !auth) return;
+                __auth = auth;
+                if (__waitingAuth) {{
+                    __waitingAuth = false;
+                    pumpQueue();
+                }}
+            }};
+
+            // 人机验证（验证码）出现时暂停提交和关闭窗口，并丢弃验证前捕获的 Token；验证完成后恢复
+            var __captchaSelector = [
+                'iframe[src*="captcha" i]',
+                'iframe[src*="recaptcha" i]',
+                'iframe[src*="hcaptcha" i]',
+                'iframe[src*="turnstile" i]',
+                '[id*="captcha" i]',
+                '[class*="captcha" i]'
+            ].join(",");
+
+            function isVisible(el) {{
+                var rect = el.getBoundingClientRect();
+                return rect.width > 0 && rect.height > 0;
+            }}
+
+            // Token 已提交且验证不在进行中时停止轮询，之后允许再次捕获时（登出、回调要求继续）重新开始
+            var __captchaTimer = null;
+            function watchCaptcha() {{
+                if (!__captchaTimer) __captchaTimer = setInterval(checkCaptcha, 1000);
+            }}
+
+            function checkCaptcha() {{
+                if (__sent && !__captcha) {{
+                    clearInterval(__captchaTimer);
+                    __captchaTimer = null;
+                    return;
+                }}
+                var active = false;
+                try {{
+                    var nodes = document.querySelectorAll(__captchaSelector);
+                    for (var i = 0; i < nodes.length; i++) {{
+                        if (isVisible(nodes[i])) {{ active = true; break; }}
+                    }}
+                }} catch(e) {{}}
+                if (active === __captcha) return;
+                __captcha = active;
+                postJson(__statusUrl, {{ url: location.href, captcha: active }});
+                if (active) {{
+                    debugLog("检测到人机验证，暂停捕获");
+                    if (__settleTimer) clearTimeout(__settleTimer);
+                    __settleTimer = null;
+                    __pending = null;
+                }} else {{
+                    debugLog("人机验证已完成，恢复捕获");
+                    var queued = __afterCaptcha;
+                    __afterCaptcha = [];
+                    queued.forEach(function(fn) {{ fn(); }});
+                }}
+            }}
+            watchCaptcha();
+
+            // 经过登出页面（如全新会话登录时先登出旧账号）之前捕获的 Token 属于旧会话：丢弃并允许再次捕获
+            // 只匹配路径，与 Rust 端的 is_logout_url 一致
+            function isLogoutUrl(path) {{
+                path = String(path || "").toLowerCase();
+                for (var i = 0; i < __logoutPatterns.length; i++) {{
+                    if (path.indexOf(__logoutPatterns[i]) !== -1) return true;
+                }}
+                return false;
+            }}
+
+            function checkLogout() {{
+                if (!isLogoutUrl(location.pathname)) return;
+                debugLog("经过登出页面，丢弃之前捕获的 Token");
+                if (__settleTimer) clearTimeout(__settleTimer);
+                __settleTimer = null;
+                __pending = null;
+                __sent = false;
+                watchCaptcha();
+            }}
+
+            ["pushState", "replaceState"].forEach(function(name) {{
+                var orig = history[name];
+                history[name] = function() {{
+                    var result = orig.apply(this, arguments);
+                    checkLogout();
+                    scanStorage();
+                    return result;
+                }};
+            }});
+            window.addEventListener("popstate", function() {{
+                checkLogout();
+                scanStorage();
+            }});
+
+            // 人机验证进行中时把动作推迟到验证完成之后
+            function whenNoCaptcha(fn) {{
+                if (__captcha) {{
+                    __afterCaptcha.push(fn);
+                }} else {{
+                    fn();
+                }}
+            }}
+
+            // Token 发送后继续观察约 2 秒，把新写入的 Cookies 补发给应用，之后再执行后续动作
+            function watchCookies(done) {{
+                var last = document.cookie;
+                var elapsed = 0;
+                var timer = setInterval(function() {{
+                    elapsed += 500;
+                    var current = document.cookie;
+                    if (current !== last) {{
+                        last = current;
+                        postJson(__cookiesUrl, {{ cookies: current, url: location.href }});
+                    }}
+                    if (elapsed >= 2000) {{
+                        clearInterval(timer);
+                        whenNoCaptcha(done);
+                    }}
+                }}, 500);
+            }}
+
+            // 输出到控制台；开启详细日志时同时上报给应用
+            function debugLog(message) {{
+                console.log("[Trae Auto] " + message);
+                if (__verbose) postJson(__debugUrl, {{ message: message, url: location.href }});
+            }}
+
+            // 回调请求逐个发送，保证每个请求使用上一个响应下发的令牌
+            function pumpQueue() {{
+                if (__inflight || __waitingAuth || !__queue.length) return;
+                var item = __queue.shift();
+                var xhr = new XMLHttpRequest();
+                __inflight = true;
+                xhr.open("POST", item.url, true);
+                xhr.setRequestHeader("Content-Type", "application/json");
+                xhr.setRequestHeader("X-Login-Auth", __auth);
+                xhr.onload = function() {{
+                    __inflight = false;
+                    var next = xhr.getResponseHeader("X-Login-Auth");
+                    if (next) __auth = next;
+                    if (xhr.status === 403) {{
+                        __queue.unshift(item);
+                        __waitingAuth = true;
+                        return;
+                    }}
+                    if (item.onload) item.onload(xhr.responseText);
+                    pumpQueue();
+                }};
+                xhr.onerror = function() {{
+                    __inflight = false;
+                    pumpQueue();
+                }};
+                xhr.send(JSON.stringify(item.data));
+            }}
+
+            function postJson(url, data, onload) {{
+                // 状态上报只需保留最新的一条
+                if (url === __statusUrl) {{
+                    __queue = __queue.filter(function(item) {{ return item.url !== __statusUrl; }});
+                }}
+                __queue.push({{ url: url, data: data, onload: onload }});
+                pumpQueue();
+            }}
+
+            // 按回调响应中的 action 决定后续：close/reset 交给应用处理，keep 则允许再次捕获
+            function handleReply(text) {{
+                var reply = {{}};
+                try {{ reply = JSON.parse(text); }} catch(e) {{}}
+                if (reply.status !== "ok") {{
+                    debugLog("回调返回: " + reply.status + " " + (reply.message || ""));
+                }}
+                if (reply.action === "close" || reply.action === "reset") {{
+                    var applyAction = function() {{ postJson(__actionUrl, {{ action: reply.action }}); }};
+                    if (reply.status === "ok" && reply.done_page) {{
+                        watchCookies(function() {{ location.href = __doneUrl + '#' + encodeURIComponent(__auth); }});
+                    }} else if (reply.status === "ok") {{
+                        watchCookies(applyAction);
+                    }} else {{
+                        applyAction();
+                    }}
+                }} else {{
+                    __sent = false;
+                    watchCaptcha();
+                }}
+            }}
+
+            // 登录后 trae.ai 可能很快再下发一次新 Token：等待一小段时间，只提交最后看到的 Token
+            function sendToken(token, refreshToken, requestHeaders) {{
+                if (__sent || !token) return;
+                if (isLogoutUrl(location.pathname)) {{
+                    debugLog("登出页面上的 Token 属于之前的会话，已忽略");
+                    return;
+                }}
+                // 验证进行中下发的 Token 可能对应验证前的会话，不提交，等待验证完成后的新 Token
+                if (__captcha) {{
+                    debugLog("人机验证进行中，忽略 Token");
+                    return;
+                }}
+                // 过短的 Token 不提交，但上报一次，避免捕获静默失败
+                if (token.length < __minTokenLength) {{
+                    debugLog("Token 过短，已忽略，长度: " + token.length);
+                    if (!__shortReported) {{
+                        __shortReported = true;
+                        postJson(__statusUrl, {{ url: location.href, token_too_short: token.length }});
+                    }}
+                    return;
+                }}
+                debugLog("收到 Token，等待 " + __settleMs + "ms 后提交");
+                __pending = {{ token: token, refreshToken: refreshToken, requestHeaders: requestHeaders || {{}} }};
+                if (__settleTimer) clearTimeout(__settleTimer);
+                __settleTimer = setTimeout(flushToken, __settleMs);
+            }}
 
-            function sendToken(token) {{
-                if (__sent || !token || token.length < 50) return;
+            function flushToken() {{
+                __settleTimer = null;
+                if (__sent || !__pending) return;
+                var token = __pending.token;
+                var refreshToken = __pending.refreshToken;
+                var requestHeaders = __pending.requestHeaders;
+                __pending = null;
                 __sent = true;
 
                 // 注意：document.cookie 只能获取非 HttpOnly cookies
                 // 大部分认证 cookies（如 sessionid, sid_guard 等）是 HttpOnly 的，无法通过 JS 访问
                 var cookies = document.cookie;
 
-                console.log("[Trae Auto] 捕获到 Token，长度:", token.length);
-                console.log("[Trae Auto] document.cookie 长度:", cookies.length);
-                console.log("[Trae Auto] 注意：HttpOnly cookies 无法通过 JS 获取");
+                debugLog("捕获到 Token，长度: " + token.length);
+                debugLog("document.cookie 长度: " + cookies.length + "（HttpOnly cookies 无法通过 JS 获取）");
 
-                var xhr = new XMLHttpRequest();
-                xhr.open("POST", __callbackUrl, true);
-                xhr.setRequestHeader("Content-Type", "application/json");
-                xhr.send(JSON.stringify({{
+                postJson(__callbackUrl, {{
                     token: token,
-                    cookies: cookies || ""
-                }}));
+                    cookies: cookies || "",
+                    refresh_token: refreshToken || "",
+                    request_headers: requestHeaders || {{}},
+                    url: location.href
+                }}, handleReply);
             }}
 
+            // 从 GetUserToken 响应中提取访问 Token 和刷新凭证
             function tryExtractToken(text) {{
                 try {{
                     var data = typeof text === "string" ? JSON.parse(text) : text;
                     if (data && data.Result && data.Result.Token) {{
-                        return data.Result.Token;
+                        return {{
+                            token: data.Result.Token,
+                            refreshToken: data.Result.RefreshToken || ""
+                        }};
                     }}
                 }} catch(e) {{}}
                 return null;
             }}
 
+            // 形如 trae.ai 访问 Token 的 JWT：三段 base64url，payload 中带有 data.id
+            function isTraeJwt(value) {{
+                if (typeof value !== "string" || !/^[\w-]+\.[\w-]+\.[\w-]+$/.test(value)) return false;
+                try {{
+                    var payload = value.split(".")[1].replace(/-/g, "+").replace(/_/g, "/");
+                    var claims = JSON.parse(atob(payload + "===".slice((payload.length + 3) % 4)));
+                    return !!(claims && claims.data && claims.data.id);
+                }} catch(e) {{}}
+                return false;
+            }}
+
+            // 从 Web Storage 的值中提取 Token：GetUserToken 响应、带 Token 字段的对象或裸 JWT
+            function tokenFromStorageValue(raw) {{
+                var result = tryExtractToken(raw);
+                if (result) return result;
+                var data = null;
+                try {{ data = JSON.parse(raw); }} catch(e) {{}}
+                if (data && typeof data === "object") {{
+                    var token = data.Token || data.token;
+                    if (isTraeJwt(token)) {{
+                        return {{ token: token, refreshToken: data.RefreshToken || data.refreshToken || "" }};
+                    }}
+                }}
+                if (typeof data === "string") raw = data;
+                return isTraeJwt(raw) ? {{ token: raw, refreshToken: "" }} : null;
+            }}
+
+            // 扫描 localStorage/sessionStorage：trae.ai 可能把 Token 存在这里而不经过可 Hook 的网络响应
+            var __storageSeen = {{}};
+            function scanStorage() {{
+                [["localStorage", window.localStorage], ["sessionStorage", window.sessionStorage]].forEach(function(entry) {{
+                    var storage = entry[1];
+                    if (!storage) return;
+                    try {{
+                        for (var i = 0; i < storage.length; i++) {{
+                            var raw = storage.getItem(storage.key(i));
+                            if (!raw || __storageSeen[raw]) continue;
+                            var result = tokenFromStorageValue(raw);
+                            if (!result) continue;
+                            __storageSeen[raw] = true;
+                            debugLog("在 " + entry[0] + " 中发现 Token");
+                            sendToken(result.token, result.refreshToken);
+                        }}
+                    }} catch(e) {{}}
+                }});
+            }}
+            scanStorage();
+            window.addEventListener("load", scanStorage);
+
+            // GetUserToken 请求携带的请求头（客户端版本等），随 Token 一起回传供无界面刷新时复用；不写入调试日志
+            function headersToObject(headers) {{
+                var out = {{}};
+                if (!headers) return out;
+                try {{
+                    if (typeof Headers !== "undefined" && headers instanceof Headers) {{
+                        headers.forEach(function(value, name) {{ out[name] = value; }});
+                    }} else if (Array.isArray(headers)) {{
+                        headers.forEach(function(pair) {{ out[pair[0]] = String(pair[1]); }});
+                    }} else {{
+                        for (var name in headers) out[name] = String(headers[name]);
+                    }}
+                }} catch(e) {{}}
+                return out;
+            }}
+
             // Hook fetch
             var origFetch = window.fetch;
             window.fetch = function() {{
                 var url = arguments[0];
+                var init = arguments[1];
+                var requestHeaders = headersToObject((init && init.headers) || (typeof url === "object" && url.headers));
                 if (typeof url === "object" && url.url) url = url.url;
                 var p = origFetch.apply(this, arguments);
                 if (typeof url === "string" && url.indexOf("GetUserToken") !== -1) {{
+                    debugLog("fetch 请求 GetUserToken");
                     p.then(function(resp) {{
                         return resp.clone().text();
                     }}).then(function(text) {{
-                        var token = tryExtractToken(text);
-                        if (token) sendToken(token);
+                        var result = tryExtractToken(text);
+                        if (result) sendToken(result.token, result.refreshToken, requestHeaders);
                     }}).catch(function() {{}});
                 }}
                 return p;
@@ -149,38 +841,603 @@ pub async fn start_login_flow(
             // Hook XMLHttpRequest
             var origOpen = XMLHttpRequest.prototype.open;
             var origSend = XMLHttpRequest.prototype.send;
+            var origSetRequestHeader = XMLHttpRequest.prototype.setRequestHeader;
             XMLHttpRequest.prototype.open = function(method, url) {{
                 this.__url = url;
+                this.__headers = {{}};
                 return origOpen.apply(this, arguments);
             }};
+            XMLHttpRequest.prototype.setRequestHeader = function(name, value) {{
+                if (this.__headers) this.__headers[name] = String(value);
+                return origSetRequestHeader.apply(this, arguments);
+            }};
             XMLHttpRequest.prototype.send = function() {{
                 var self = this;
                 if (self.__url && self.__url.indexOf("GetUserToken") !== -1) {{
+                    debugLog("XHR 请求 GetUserToken");
                     self.addEventListener("load", function() {{
-                        var token = tryExtractToken(self.responseText);
-                        if (token) sendToken(token);
+                        var result = tryExtractToken(self.responseText);
+                        if (result) sendToken(result.token, result.refreshToken, self.__headers);
                     }});
                 }}
                 return origSend.apply(this, arguments);
             }};
+
+            // Hook WebSocket：检查收到的文本消息中是否包含 Token
+            var OrigWebSocket = window.WebSocket;
+            if (OrigWebSocket) {{
+                var WrappedWebSocket = function(url, protocols) {{
+                    var ws = protocols === undefined ? new OrigWebSocket(url) : new OrigWebSocket(url, protocols);
+                    ws.addEventListener("message", function(event) {{
+                        if (typeof event.data !== "string") return;
+                        var result = tryExtractToken(event.data);
+                        if (result) sendToken(result.token, result.refreshToken);
+                    }});
+                    return ws;
+                }};
+                WrappedWebSocket.prototype = OrigWebSocket.prototype;
+                ["CONNECTING", "OPEN", "CLOSING", "CLOSED"].forEach(function(key) {{
+                    WrappedWebSocket[key] = OrigWebSocket[key];
+                }});
+                window.WebSocket = WrappedWebSocket;
+            }}
+
+            // 定期上报当前页面，登录失败时可从该页面重试
+            function reportStatus() {{
+                try {{
+                    postJson(__statusUrl, {{ url: location.href }});
+                }} catch(e) {{}}
+            }}
+            reportStatus();
+            setInterval(reportStatus, 3000);
+
+            // 手动捕获入口：重新请求 GetUserToken，响应会经过上面的 fetch Hook
+            window.__traeAutoCapture = function() {{
+                ["https://api-sg-central.trae.ai", "https://api-us-east.trae.ai"].forEach(function(base) {{
+                    window.fetch(base + "/cloudide/api/v3/common/GetUserToken", {{
+                        method: "POST",
+                        credentials: "include"
+                    }}).catch(function() {{}});
+                }});
+            }};
         }})();
     "#,
-        port = port
-    );
+        port = port,
+        min_token_length = min_token_length,
+        settle_ms = TOKEN_SETTLE_MS,
+        auth = auth,
+        verbose = verbose,
+        callback_path = callback_path,
+        logout_patterns = logout_patterns
+    )
+}
 
-    // 不使用 incognito 模式，以便能访问所有 cookies
-    let window = WebviewWindowBuilder::new(
-        &app,
-        "trae-login",
-        WebviewUrl::External("https://www.trae.ai".parse().unwrap()),
+/// 供用户审阅的注入脚本（端口和回调令牌以占位符显示）
+pub fn injection_script(port_placeholder: Option<&str>) -> String {
+    build_init_script(
+        port_placeholder.unwrap_or("{PORT}"),
+        DEFAULT_LOGIN_CALLBACK_PATH,
+        DEFAULT_MIN_TOKEN_LENGTH,
+        "{AUTH}",
+        false,
+        &normalize_logout_patterns(None),
     )
+}
+
+/// 拼出实际注入登录窗口的完整脚本（start_login_flow 与 render_injection_for_port 共用）
+fn render_login_script(
+    port: impl std::fmt::Display,
+    callback_path: &str,
+    min_token_length: usize,
+    auth: &str,
+    verbose: bool,
+    logout_patterns: &[String],
+    email_hint: Option<&str>,
+) -> String {
+    let init_script = build_init_script(port, callback_path, min_token_length, auth, verbose, logout_patterns);
+    // 预填邮箱（重新登录已知账号时可跳过账号选择步骤）
+    match email_hint.map(str::trim).filter(|e| !e.is_empty()) {
+        Some(email) => format!("{}\n{}", init_script, build_email_hint_script(email)),
+        None => init_script,
+    }
+}
+
+/// 按登录选项渲染指定端口下实际会注入的脚本，回调令牌以占位符代替（供集成测试和安全审阅对比）
+pub fn render_injection_for_port(port: u16, options: &LoginOptions, nonce_placeholder: Option<&str>) -> Result<String, String> {
+    let callback_path = normalize_callback_path(options.callback_path.as_deref().unwrap_or(DEFAULT_LOGIN_CALLBACK_PATH))?;
+    let logout_patterns = normalize_logout_patterns(options.logout_patterns.clone());
+    Ok(render_login_script(
+        port,
+        &callback_path,
+        options.min_token_length.unwrap_or(DEFAULT_MIN_TOKEN_LENGTH),
+        nonce_placeholder.unwrap_or("{AUTH}"),
+        options.verbose,
+        &logout_patterns,
+        options.email_hint.as_deref(),
+    ))
+}
+
+pub async fn start_login_flow(
+    app: AppHandle,
+    state: Arc<Mutex<AccountManager>>,
+    options: LoginOptions,
+) -> Result<(), String> {
+    let LoginOptions {
+        email_hint,
+        multi_add,
+        start_url,
+        notify_success,
+        save_drafts,
+        incognito,
+        min_token_length,
+        target_account_id,
+        success_page,
+        always_on_top,
+        dry_run,
+        cookies_only,
+        keep_session,
+        verbose,
+        callback_path,
+        logout_patterns,
+        skip_cookies,
+        selective,
+    } = options;
+    crate::offline::ensure_online().map_err(|e| e.to_string())?;
+    let min_token_length = min_token_length.unwrap_or(DEFAULT_MIN_TOKEN_LENGTH);
+    // 回调路由和注入脚本共用同一个路径
+    let callback_path = normalize_callback_path(callback_path.as_deref().unwrap_or(DEFAULT_LOGIN_CALLBACK_PATH))?;
+    // 注入脚本和回调服务按同一组登出地址片段判断
+    let logout_patterns = normalize_logout_patterns(logout_patterns);
+    if cookies_only && skip_cookies {
+        return Err("已关闭 Cookie 捕获，无法只刷新 Cookies".to_string());
+    }
+    if cookies_only && target_account_id.is_none() {
+        return Err("只刷新 Cookies 时必须指定账号".to_string());
+    }
+    if selective && target_account_id.is_none() {
+        return Err("原地重新登录时必须指定账号".to_string());
+    }
+    // 只刷新 Cookies 需要复用已登录的会话，不能使用无痕窗口
+    let incognito = !cookies_only && use_incognito(incognito);
+    // 测试捕获和只刷新 Cookies 都不是一次新的登录，不记录统计
+    let record_metrics = !dry_run && !cookies_only;
+    let clear_session = should_clear_session(keep_session, cookies_only, incognito);
+    let start_url = match start_url {
+        Some(url) if is_trae_url(&url) => url,
+        _ => LOGIN_START_URL.to_string(),
+    };
+
+    // 如果已有登录窗口，聚焦它
+    if let Some(win) = app.get_webview_window(LOGIN_WINDOW_LABEL) {
+        let _ = win.set_focus();
+        return Ok(());
+    }
+
+    // 通知 warp 服务停止的 oneshot channel，回调服务启动成功后写入
+    let shutdown_tx: ShutdownSlot = Arc::new(Mutex::new(None));
+    if let Ok(mut active) = ACTIVE_LOGIN.lock() {
+        *active = Some(shutdown_tx.clone());
+    }
+    // 本次窗口最近捕获的账号 ID（用于合并后到的 Cookies，以及区分取消和登录后关闭）
+    let captured: Arc<std::sync::Mutex<Option<String>>> = Arc::new(std::sync::Mutex::new(None));
+    let captured_in_callback = captured.clone();
+    let captured_for_cookies = captured.clone();
+
+    // 本次登录的回调令牌签发器：四个 POST 接口都要求携带有效令牌
+    let signer = Arc::new(CallbackSigner::new()?);
+
+    let app_clone = app.clone();
+    let state_clone = state.clone();
+    let logout_patterns_in_callback = logout_patterns.clone();
+
+    // POST /<callback_path>（默认 /callback）— 接收 token 和 cookies
+    let callback = warp::post()
+        .and(warp::path(callback_path.clone()))
+        .and(with_callback_auth(signer.clone()))
+        .and(warp::body::json())
+        .and_then(move |body: serde_json::Value| {
+            let app = app_clone.clone();
+            let state = state_clone.clone();
+            let captured = captured_in_callback.clone();
+            let target_account_id = target_account_id.clone();
+            let logout_patterns = logout_patterns_in_callback.clone();
+            async move {
+                let token = body["token"].as_str().unwrap_or("");
+                if token.is_empty() {
+                    return Ok::<_, warp::Rejection>(callback_reply(
+                        "waiting",
+                        CallbackAction::Keep,
+                        None,
+                    ));
+                }
+
+                // 只接受 trae.ai 页面上捕获的 Token
+                let page_url = body["url"].as_str().unwrap_or("");
+                if !is_trae_url(page_url) {
+                    println!("[WARN] 忽略非 trae.ai 页面回传的 Token: {}", page_url);
+                    return Ok(callback_reply(
+                        "error",
+                        CallbackAction::Keep,
+                        Some("来源页面不是 trae.ai"),
+                    ));
+                }
+                // 登出页面上捕获的 Token 属于之前的会话：不添加，脚本收到 keep 后会重新等待登录后的 Token
+                if is_logout_url(page_url, &logout_patterns) {
+                    println!("[INFO] 忽略登出页面回传的 Token: {}", page_url);
+                    return Ok(callback_reply(
+                        "waiting",
+                        CallbackAction::Keep,
+                        Some("登出页面的 Token 已忽略"),
+                    ));
+                }
+
+                metrics::mark(LoginPhase::TokenSeen);
+
+                // 提取 cookies 和刷新凭证（如果有），并补充脚本读不到的 HttpOnly Cookies；关闭 Cookie 捕获时只保存 Token
+                let (cookies, cookie_capture) = if skip_cookies {
+                    (None, None)
+                } else {
+                    (
+                        capture_login_cookies(&app, body["cookies"].as_str(), incognito),
+                        classify_login_cookies(&app, body["cookies"].as_str()),
+                    )
+                };
+                metrics::mark(LoginPhase::CookiesCollected);
+                let refresh_token = body["refresh_token"].as_str().map(|s| s.to_string());
+                let request_headers = reported_request_headers(&body);
+
+                // 测试捕获：只上报结果，不接触账号数据
+                if dry_run {
+                    let _ = app.emit("capture-test-result", capture_test_result(token, cookies.as_deref(), refresh_token.as_deref()));
+                    return Ok(callback_reply("ok", CallbackAction::Close, None));
+                }
+
+                let mut manager = state.lock().await;
+                if let (true, Some(id)) = (cookies_only, target_account_id.as_deref()) {
+                    let reply = refresh_cookies_only(&app, &mut manager, id, token, cookies.as_deref(), cookie_capture);
+                    if let Ok(mut captured) = captured.lock() {
+                        *captured = Some(id.to_string());
+                    }
+                    return Ok(reply);
+                }
+
+                match manager.add_account_by_token(token.to_string(), cookies.clone()).await {
+                    Ok(account) => {
+                        metrics::record(LoginEvent::Capture);
+                        metrics::mark(LoginPhase::Stored);
+                        if let Ok(mut captured) = captured.lock() {
+                            *captured = Some(account.id.clone());
+                        }
+                        clear_last_login_url();
+                        if let Err(e) = manager.set_refresh_token(&account.id, refresh_token) {
+                            println!("[WARN] 保存刷新凭证失败: {}", e);
+                        }
+                        record_cookie_capture(&mut manager, &account.id, cookie_capture);
+                        record_request_headers(&mut manager, &account.id, &request_headers);
+                        pending_events::emit_or_persist(&app, "login-success", &account.email);
+                        notify_login_success(&app, notify_success, &account.email);
+                        Ok(success_reply(multi_add, success_page))
+                    }
+                    Err(e) => {
+                        // 重新登录的是目标账号或 Trae IDE 当前使用的账号：原地更新凭证，保留名称、标签等信息
+                        // 本窗口刚添加的账号又收到新 Token 时视为 Token 轮换，同样原地更新，不重复提示
+                        let just_captured = captured.lock().ok().and_then(|c| c.clone());
+                        let (refresh_id, event) = match e.downcast_ref::<AccountError>() {
+                            Some(AccountError::Duplicate { id, .. }) if just_captured.as_deref() == Some(id.as_str()) => {
+                                (Some(id.clone()), "login-token-rotated")
+                            }
+                            Some(AccountError::Duplicate { id, .. }) if target_account_id.as_deref() == Some(id.as_str()) => {
+                                (Some(id.clone()), "account-relogin-success")
+                            }
+                            Some(AccountError::Duplicate { id, .. }) if manager.is_current_account(id) => {
+                                (Some(id.clone()), "active-account-refreshed")
+                            }
+                            _ => (None, ""),
+                        };
+                        if let Some(id) = refresh_id {
+                            if let Ok(account) = manager.get_account(&id) {
+                                warn_if_in_use(&app, &account.id, &account.email);
+                            }
+                            let mut cookies_applied = true;
+                            let result = if selective && event == "account-relogin-success" {
+                                manager.refresh_selective(&id, token.to_string(), cookies, refresh_token).map(|(account, update)| {
+                                    println!(
+                                        "[INFO] 账号 {} 原地重新登录：Token {}，Cookies {}",
+                                        account.email,
+                                        if update.token_updated { "已更新" } else { "保持不变" },
+                                        if update.cookies_updated { "已更新" } else { "保持不变" },
+                                    );
+                                    cookies_applied = update.cookies_updated;
+                                    let _ = app.emit("relogin-selective-update", &update);
+                                    account
+                                })
+                            } else {
+                                manager.refresh_captured_credentials(&id, token.to_string(), cookies, refresh_token)
+                            };
+                            if result.is_ok() {
+                                if cookies_applied {
+                                    record_cookie_capture(&mut manager, &id, cookie_capture);
+                                }
+                                record_request_headers(&mut manager, &id, &request_headers);
+                            }
+                            return match result {
+                                Ok(account) if event == "login-token-rotated" => {
+                                    println!("[INFO] 账号 {} 的 Token 已轮换，已更新为最新 Token", account.email);
+                                    let _ = app.emit(
+                                        event,
+                                        serde_json::json!({ "id": account.id, "email": account.email }),
+                                    );
+                                    Ok(success_reply(multi_add, success_page))
+                                }
+                                Ok(account) => {
+                                    metrics::record(LoginEvent::Capture);
+                                    metrics::mark(LoginPhase::Stored);
+                                    if let Ok(mut captured) = captured.lock() {
+                                        *captured = Some(account.id.clone());
+                                    }
+                                    clear_last_login_url();
+                                    pending_events::emit_or_persist(
+                                        &app,
+                                        event,
+                                        serde_json::json!({ "id": account.id, "email": account.email }),
+                                    );
+                                    notify_login_success(&app, notify_success, &account.email);
+                                    Ok(success_reply(multi_add, success_page))
+                                }
+                                Err(e) => {
+                                    metrics::record(LoginEvent::Failure);
+                                    let msg = e.to_string();
+                                    let _ = app.emit(
+                                        "login-store-error",
+                                        serde_json::json!({ "code": store_error_code(&e), "network_error": classify_network_error(&e), "message": msg }),
+                                    );
+                                    Ok(callback_reply("error", CallbackAction::Keep, Some(&msg)))
+                                }
+                            };
+                        }
+
+                        let msg = e.to_string();
+                        let action = if let Some(AccountError::Duplicate { id, email }) = e.downcast_ref::<AccountError>() {
+                            let _ = app.emit("login-failed", "该账号已存在");
+                            warn_if_in_use(&app, id, email);
+                            let _ = app.emit(
+                                "account-duplicate",
+                                serde_json::json!({ "id": id, "email": email }),
+                            );
+                            success_action(multi_add)
+                        } else {
+                            metrics::record(LoginEvent::Failure);
+                            let _ = app.emit(
+                                "login-store-error",
+                                serde_json::json!({ "code": store_error_code(&e), "network_error": classify_network_error(&e), "message": msg }),
+                            );
+                            // 凭证来自两次登录交错时不保存，避免留下错配的草稿
+                            let mismatched = matches!(e.downcast_ref::<AccountError>(), Some(AccountError::CredentialMismatch { .. }));
+                            if save_drafts && !mismatched {
+                                match manager.save_draft(token.to_string(), cookies, refresh_token, msg.clone()) {
+                                    Ok(draft) => {
+                                        let _ = app.emit("login-draft-saved", &draft);
+                                    }
+                                    Err(e) => println!("[WARN] 保存登录草稿失败: {}", e),
+                                }
+                            }
+                            CallbackAction::Keep
+                        };
+                        Ok(callback_reply("error", action, Some(&msg)))
+                    }
+                }
+            }
+        });
+
+    // POST /action — 注入脚本收到回调响应后，按其中的 action 请求关闭或重置窗口
+    let app_for_action = app.clone();
+    let action = warp::post()
+        .and(warp::path("action"))
+        .and(with_callback_auth(signer.clone()))
+        .and(warp::body::json())
+        .map(move |body: serde_json::Value| {
+            match serde_json::from_value::<CallbackAction>(body["action"].clone()) {
+                Ok(CallbackAction::Close) => close_login_window(&app_for_action),
+                Ok(CallbackAction::Reset) => reset_login_window(&app_for_action),
+                Ok(CallbackAction::Keep) | Err(_) => {}
+            }
+            warp::reply::json(&serde_json::json!({"status": "ok"}))
+        });
+
+    // POST /cookies — 捕获 Token 后短时间内新出现的 Cookies，合并到刚添加的账号
+    let state_for_cookies = state.clone();
+    let cookies_route = warp::post()
+        .and(warp::path("cookies"))
+        .and(with_callback_auth(signer.clone()))
+        .and(warp::body::json())
+        .and_then(move |body: serde_json::Value| {
+            let state = state_for_cookies.clone();
+            let account_id = captured_for_cookies.lock().ok().and_then(|c| c.clone());
+            async move {
+                let cookies = body["cookies"].as_str().unwrap_or("");
+                let page_url = body["url"].as_str().unwrap_or("");
+                let reply = match account_id {
+                    Some(id) if !skip_cookies && is_trae_url(page_url) && !cookies.trim().is_empty() => {
+                        match state.lock().await.merge_cookies(&id, cookies) {
+                            Ok(()) => serde_json::json!({"status": "ok"}),
+                            Err(e) => serde_json::json!({"status": "error", "message": e.to_string()}),
+                        }
+                    }
+                    _ => serde_json::json!({"status": "ignored"}),
+                };
+                Ok::<_, warp::Rejection>(warp::reply::json(&reply))
+            }
+        });
+
+    // POST /status — 注入脚本定期上报当前页面地址，捕获到过短的 Token 时也经此上报
+    let app_for_status = app.clone();
+    let status = warp::post()
+        .and(warp::path("status"))
+        .and(with_callback_auth(signer.clone()))
+        .and(warp::body::json())
+        .map(move |body: serde_json::Value| {
+            record_login_url(body["url"].as_str().unwrap_or(""));
+            if let Some(active) = body["captcha"].as_bool() {
+                println!("[INFO] 登录页{}人机验证", if active { "出现" } else { "已完成" });
+                let _ = app_for_status.emit("login-captcha", serde_json::json!({ "active": active }));
+            }
+            if let Some(length) = body["token_too_short"].as_u64() {
+                println!("[WARN] 捕获到的 Token 过短（{} < {}），已忽略", length, min_token_length);
+                let _ = app_for_status.emit(
+                    "login-token-too-short",
+                    serde_json::json!({ "length": length, "min_length": min_token_length }),
+                );
+            }
+            warp::reply::json(&serde_json::json!({"status": "ok"}))
+        });
+
+    // POST /debug — 详细捕获日志（只在本次登录开启了 verbose 时转发）
+    let app_for_debug = app.clone();
+    let debug = warp::post()
+        .and(warp::path("debug"))
+        .and(with_callback_auth(signer.clone()))
+        .and(warp::body::json())
+        .map(move |body: serde_json::Value| {
+            if verbose {
+                let message = body["message"].as_str().unwrap_or("");
+                let _ = app_for_debug.emit(
+                    "login-debug",
+                    serde_json::json!({ "message": message, "url": body["url"].as_str().unwrap_or("") }),
+                );
+            }
+            warp::reply::json(&serde_json::json!({"status": "ok"}))
+        });
+
+    let cors = warp::cors()
+        .allow_any_origin()
+        .allow_methods(vec!["POST"])
+        .allow_headers(vec!["content-type", callback_token::HEADER])
+        .expose_headers(vec![callback_token::HEADER]);
+
+    // GET /done — 登录成功页面
+    let done = warp::get()
+        .and(warp::path("done"))
+        .and(warp::path::end())
+        .map(|| warp::reply::html(DONE_PAGE_HTML));
+
+    // 令牌只能使用一次：通过校验的请求在响应头中带回下一个令牌
+    let signer_for_reply = signer.clone();
+    let authorized = callback
+        .or(action)
+        .or(cookies_route)
+        .or(status)
+        .or(debug)
+        .map(move |reply| {
+            warp::reply::with_header(reply, callback_token::HEADER, signer_for_reply.issue(chrono::Utc::now().timestamp()))
+        });
+
+    let routes = authorized
+        .or(done)
+        .recover(handle_callback_rejection)
+        .with(cors);
+
+    // 上一次登录遗留的回调服务（窗口已不存在）直接中止
+    let orphaned = tasks::abort(tasks::LOGIN);
+    if orphaned > 0 {
+        println!("[WARN] 已中止 {} 个遗留的登录后台任务", orphaned);
+    }
+
+    // 极少数情况下系统分配的端口无法立即使用：绑定后先探测能否连接，失败则换端口重试
+    let mut bound = None;
+    for attempt in 1..=CALLBACK_BIND_ATTEMPTS {
+        let (tx, rx) = oneshot::channel::<()>();
+        let (addr, server) = match warp::serve(routes.clone())
+            .try_bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async {
+                let _ = rx.await;
+            }) {
+            Ok(bound) => bound,
+            Err(e) => {
+                println!("[WARN] 登录回调服务绑定端口失败（第 {} 次）: {}", attempt, e);
+                continue;
+            }
+        };
+        let server_handle = tasks::spawn(tasks::LOGIN, server);
+        if probe_callback_server(addr).await {
+            bound = Some((addr.port(), tx, server_handle));
+            break;
+        }
+        println!("[WARN] 登录回调服务端口 {} 无法连接（第 {} 次）", addr.port(), attempt);
+        let _ = tx.send(());
+        server_handle.abort();
+    }
+    let Some((port, tx, server_handle)) = bound else {
+        metrics::record(LoginEvent::Failure);
+        let _ = app.emit("login-failed", "登录回调服务启动失败");
+        return Err("登录回调服务启动失败，请稍后重试".to_string());
+    };
+    *shutdown_tx.lock().await = Some(tx);
+
+    let init_script = render_login_script(
+        port,
+        &callback_path,
+        min_token_length,
+        &signer.issue(chrono::Utc::now().timestamp()),
+        verbose,
+        &logout_patterns,
+        email_hint.as_deref(),
+    );
+
+    // 需要全新会话时先打开空白页，清空会话后再跳转，保证登录页显示账号选择
+    let start_url: tauri::Url = start_url.parse().map_err(|e| format!("登录地址无效: {}", e))?;
+    let initial_url = if clear_session {
+        tauri::Url::parse("about:blank").map_err(|e| e.to_string())?
+    } else {
+        start_url.clone()
+    };
+
+    // Cookies 由 Rust 侧从 webview 读取，无痕模式下同样可以拿到 HttpOnly Cookies
+    let window = WebviewWindowBuilder::new(&app, LOGIN_WINDOW_LABEL, WebviewUrl::External(initial_url))
     .title("登录 Trae 账号")
     .inner_size(500.0, 700.0)
     .center()
-    .incognito(false)  // 改为 false，允许访问完整 cookies
+    .incognito(incognito)
+    .always_on_top(always_on_top)
     .initialization_script(&init_script)
+    // trae.ai 页面加载完成后下发新的回调令牌：初始化脚本中写入的令牌只能用一次，每个页面都需要新令牌
+    // 其他站点的页面（如第三方登录页）不下发，避免令牌泄露给无关页面
+    .on_page_load(move |window, payload| {
+        if matches!(payload.event(), tauri::webview::PageLoadEvent::Finished) && is_trae_url(payload.url().as_str()) {
+            metrics::mark(LoginPhase::PageLoaded);
+            let auth = signer.issue(chrono::Utc::now().timestamp());
+            let _ = window.eval(format!("window.__traeSetAuth && window.__traeSetAuth(\"{}\");", auth));
+            // 只刷新 Cookies 时页面可能已处于登录状态，不会再请求 Token：主动触发一次捕获
+            if cookies_only {
+                let _ = window.eval(MANUAL_CAPTURE_SCRIPT);
+            }
+        }
+    })
     .build()
     .map_err(|e| e.to_string())?;
+    if clear_session {
+        if let Err(e) = window.clear_all_browsing_data() {
+            println!("[WARN] 清空登录窗口会话失败: {}", e);
+        }
+        window.navigate(start_url).map_err(|e| e.to_string())?;
+    }
+    if record_metrics {
+        metrics::record(LoginEvent::Attempt);
+    }
+    metrics::mark(LoginPhase::WindowOpened);
+
+    // 监控回调服务：若在请求停止前意外退出，关闭登录窗口并通知前端，避免窗口无响应
+    let shutdown_on_exit = shutdown_tx.clone();
+    let app_for_monitor = app.clone();
+    tasks::spawn(tasks::LOGIN, async move {
+        let _ = server_handle.await;
+        if shutdown_on_exit.lock().await.take().is_some() {
+            println!("[WARN] 登录回调服务意外退出");
+            metrics::record(LoginEvent::Failure);
+            let _ = app_for_monitor.emit("login-failed", "回调服务异常");
+            if let Some(win) = app_for_monitor.get_webview_window(LOGIN_WINDOW_LABEL) {
+                let _ = win.close();
+            }
+        }
+    });
 
     // 监听窗口关闭，停止 warp 服务并通知前端
     let shutdown_on_close = shutdown_tx.clone();
@@ -189,9 +1446,13 @@ pub async fn start_login_flow(
         if let tauri::WindowEvent::Destroyed = event {
             let shutdown = shutdown_on_close.clone();
             let app = app_for_close.clone();
+            let captured = captured.clone();
             tauri::async_runtime::spawn(async move {
                 if let Some(tx) = shutdown.lock().await.take() {
                     // shutdown 还在说明不是登录成功后关的窗口，是用户手动关的
+                    if record_metrics && captured.lock().map(|c| c.is_none()).unwrap_or(true) {
+                        metrics::record(LoginEvent::Cancellation);
+                    }
                     let _ = app.emit("login-cancelled", ());
                     let _ = tx.send(());
                 }
@@ -201,3 +1462,281 @@ pub async fn start_login_flow(
 
     Ok(())
 }
+
+/// 应用退出时清理：关闭登录窗口并停止回调服务（同步执行，不依赖异步任务）
+pub fn shutdown(app: &AppHandle) {
+    let slot = ACTIVE_LOGIN.lock().ok().and_then(|mut active| active.take());
+    if let Some(slot) = slot {
+        // 先取出停止信号，窗口关闭事件就不会再当作用户取消处理
+        let tx = slot.try_lock().ok().and_then(|mut tx| tx.take());
+        if let Some(tx) = tx {
+            let _ = tx.send(());
+            println!("[INFO] 已停止登录回调服务");
+        }
+    }
+    if let Some(win) = app.get_webview_window(LOGIN_WINDOW_LABEL) {
+        let _ = win.close();
+    }
+}
+
+/// 手动触发 Token 捕获（自动捕获错过时的补救手段）
+pub fn request_manual_capture(app: &AppHandle) -> Result<(), String> {
+    let window = app
+        .get_webview_window(LOGIN_WINDOW_LABEL)
+        .ok_or_else(|| "登录窗口未打开".to_string())?;
+    window.eval(MANUAL_CAPTURE_SCRIPT).map_err(|e| e.to_string())
+}
+
+/// 打开登录窗口前是否需要清空已有会话：只刷新 Cookies 必须复用会话，无痕窗口本身就是全新会话
+fn should_clear_session(keep_session: bool, cookies_only: bool, incognito: bool) -> bool {
+    !keep_session && !cookies_only && !incognito
+}
+
+/// 决定登录窗口是否使用无痕模式：此前发现无痕模式读不到 Cookies 时回退为普通模式
+fn use_incognito(requested: bool) -> bool {
+    if requested && INCOGNITO_COOKIES_UNAVAILABLE.load(Ordering::Relaxed) {
+        println!("[WARN] 无痕模式下无法读取登录窗口的 Cookies，本次改用普通模式");
+        return false;
+    }
+    requested
+}
+
+/// 合并脚本上报的 Cookies 与登录窗口 webview 中的 Cookies（后者包含 HttpOnly，同名时优先）
+fn capture_login_cookies(app: &AppHandle, reported: Option<&str>, incognito: bool) -> Option<String> {
+    // 登录过程中同一个 Cookie 可能被多次写入，只保留最后的值
+    let (reported, removed) = cookies::dedup_cookie_string(reported.unwrap_or(""));
+    if removed > 0 {
+        println!("[INFO] 捕获的 Cookies 中去除了 {} 个重复项", removed);
+    }
+    let reported = reported.as_str();
+    match webview_trae_cookies(app, LOGIN_WINDOW_LABEL) {
+        Ok(store) if !store.is_empty() => Some(cookies::dedup_cookie_string(&cookies::merge_cookie_strings(reported, &store)).0),
+        result => {
+            if let Err(e) = result {
+                println!("[WARN] {}", e);
+            }
+            if incognito {
+                INCOGNITO_COOKIES_UNAVAILABLE.store(true, Ordering::Relaxed);
+            }
+            (!reported.is_empty()).then(|| reported.to_string())
+        }
+    }
+}
+
+/// 把登录窗口中的 Cookies 按是否 HttpOnly 分类（只取名称）；脚本上报的 Cookie 都算作脚本可读
+fn classify_login_cookies(app: &AppHandle, reported: Option<&str>) -> Option<CookieCapture> {
+    let window = app.get_webview_window(LOGIN_WINDOW_LABEL)?;
+    let url = tauri::Url::parse(LOGIN_START_URL).ok()?;
+    let store = window.cookies_for_url(url).ok()?;
+
+    let mut capture = CookieCapture { captured_at: chrono::Utc::now().timestamp(), ..Default::default() };
+    let push = |list: &mut Vec<String>, name: &str| {
+        if !list.iter().any(|n| n == name) {
+            list.push(name.to_string());
+        }
+    };
+    for (name, _) in cookies::parse_cookie_pairs(reported.unwrap_or("")) {
+        push(&mut capture.js_accessible, &name);
+    }
+    for cookie in store.iter().filter(|cookie| !cookie.value().is_empty()) {
+        if cookie.http_only() == Some(true) {
+            push(&mut capture.http_only, cookie.name());
+        } else {
+            push(&mut capture.js_accessible, cookie.name());
+        }
+    }
+    Some(capture)
+}
+
+/// 读取主窗口 webview 中 trae.ai 的 Cookies，拼接为 Cookie 字符串（没有会话时为空）
+pub fn main_session_cookies(app: &AppHandle) -> Result<String, String> {
+    webview_trae_cookies(app, MAIN_WINDOW_LABEL)
+}
+
+/// 读取指定窗口 webview 中 trae.ai 的 Cookies（包含 HttpOnly）
+fn webview_trae_cookies(app: &AppHandle, label: &str) -> Result<String, String> {
+    let window = app
+        .get_webview_window(label)
+        .ok_or_else(|| format!("窗口不存在: {}", label))?;
+    let url = tauri::Url::parse(LOGIN_START_URL).map_err(|e| e.to_string())?;
+    let store = window
+        .cookies_for_url(url)
+        .map_err(|e| format!("读取 {} 窗口的 Cookies 失败: {}", label, e))?;
+    let pairs: Vec<(String, String)> = store
+        .iter()
+        .filter(|cookie| !cookie.value().is_empty())
+        .map(|cookie| (cookie.name().to_string(), cookie.value().to_string()))
+        .collect();
+    // 不同域名下的同名 Cookie 会被合并为一个值，记录下来便于排查切换后登录态不对的问题
+    for conflict in cookies::find_conflicts(&cookies::join_cookie_pairs(&pairs)) {
+        let domains: Vec<&str> = store
+            .iter()
+            .filter(|cookie| cookie.name() == conflict.name)
+            .filter_map(|cookie| cookie.domain())
+            .collect();
+        println!("[WARN] Cookie {} 在多个域名下取值不同: {}", conflict.name, domains.join(", "));
+    }
+    Ok(cookies::join_cookie_pairs(&pairs))
+}
+
+/// 为登录窗口打开开发者工具
+pub fn open_login_devtools(app: &AppHandle) -> Result<(), String> {
+    let window = app
+        .get_webview_window(LOGIN_WINDOW_LABEL)
+        .ok_or_else(|| "登录窗口未打开".to_string())?;
+    window.open_devtools();
+    Ok(())
+}
+
+/// 重新打开登录窗口，从上次访问的 trae.ai 页面继续（保留会话，否则会丢失登录进度）
+pub async fn retry_login_flow(
+    app: AppHandle,
+    state: Arc<Mutex<AccountManager>>,
+    options: LoginOptions,
+) -> Result<(), String> {
+    let options = LoginOptions {
+        start_url: last_login_url(),
+        keep_session: true,
+        ..options
+    };
+    start_login_flow(app, state, options).await
+}
+
+/// 结束连续添加，关闭登录窗口
+pub fn finish_login_flow(app: &AppHandle) -> Result<(), String> {
+    match app.get_webview_window(LOGIN_WINDOW_LABEL) {
+        Some(window) => window.close().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trae_urls_include_subdomains() {
+        assert!(is_trae_url("https://trae.ai/"));
+        assert!(is_trae_url("https://www.trae.ai/login?next=/"));
+        assert!(is_trae_url("https://accounts.trae.ai/path"));
+        assert!(is_trae_url("https://WWW.TRAE.AI/"));
+    }
+
+    #[test]
+    fn lookalike_and_non_https_urls_are_rejected() {
+        assert!(!is_trae_url("https://trae.ai.evil.com/"));
+        assert!(!is_trae_url("https://eviltrae.ai/"));
+        assert!(!is_trae_url("https://evil-trae.ai/"));
+        assert!(!is_trae_url("https://evil.com/trae.ai"));
+        assert!(!is_trae_url("https://evil.com/?host=www.trae.ai"));
+        assert!(!is_trae_url("https://www.trae.ai@evil.com/"));
+        assert!(!is_trae_url("http://www.trae.ai/"));
+        assert!(!is_trae_url("www.trae.ai"));
+        assert!(!is_trae_url(""));
+    }
+
+    #[test]
+    fn callback_path_is_trimmed_and_validated() {
+        assert_eq!(normalize_callback_path(" /my-callback_1/ "), Ok("my-callback_1".to_string()));
+        assert!(normalize_callback_path("/").is_err());
+        assert!(normalize_callback_path("a/b").is_err());
+        assert!(normalize_callback_path("call back").is_err());
+        assert!(normalize_callback_path("../x").is_err());
+        assert!(normalize_callback_path("status").is_err());
+        assert!(normalize_callback_path("/done/").is_err());
+    }
+
+    #[test]
+    fn logout_url_matches_path_only() {
+        let patterns = normalize_logout_patterns(None);
+        assert!(is_logout_url("https://www.trae.ai/logout", &patterns));
+        assert!(is_logout_url("https://www.trae.ai/account/SignOut?x=1", &patterns));
+        assert!(!is_logout_url("https://www.trae.ai/login?next=/logout", &patterns));
+        assert!(!is_logout_url("https://www.trae.ai/login#/sign-out", &patterns));
+        assert!(!is_logout_url("https://www.trae.ai/", &patterns));
+        assert!(!is_logout_url("not a url /logout", &patterns));
+    }
+
+    #[test]
+    fn logout_patterns_are_trimmed_and_lowercased() {
+        let patterns = normalize_logout_patterns(Some(vec![" /Bye ".to_string(), "  ".to_string()]));
+        assert_eq!(patterns, vec!["/bye"]);
+        assert!(is_logout_url("https://www.trae.ai/BYE", &patterns));
+        assert!(!is_logout_url("https://www.trae.ai/logout", &[]));
+    }
+
+
+    fn guarded_route(signer: Arc<CallbackSigner>) -> impl Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible> + Clone {
+        use warp::Reply;
+        warp::post()
+            .and(warp::path("status"))
+            .and(with_callback_auth(signer))
+            .map(|| warp::reply::json(&serde_json::json!({ "status": "ok" })).into_response())
+            .recover(|rejection| async move {
+                handle_callback_rejection(rejection)
+                    .await
+                    .or_else(|_| Ok::<_, std::convert::Infallible>(warp::http::StatusCode::NOT_FOUND.into_response()))
+            })
+            .unify()
+    }
+
+    #[tokio::test]
+    async fn callback_auth_consumes_valid_token_and_rejects_replay() {
+        let signer = Arc::new(CallbackSigner::new().unwrap());
+        let route = guarded_route(signer.clone());
+        let token = signer.issue(chrono::Utc::now().timestamp());
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/status")
+            .header(callback_token::HEADER, &token)
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), 200);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/status")
+            .header(callback_token::HEADER, &token)
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), 403);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["status"], "unauthorized");
+        assert_eq!(body["message"], TokenError::Replayed.to_string());
+    }
+
+    #[tokio::test]
+    async fn callback_auth_rejects_missing_and_foreign_tokens() {
+        let signer = Arc::new(CallbackSigner::new().unwrap());
+        let route = guarded_route(signer);
+
+        let response = warp::test::request().method("POST").path("/status").reply(&route).await;
+        assert_eq!(response.status(), 403);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["message"], TokenError::Missing.to_string());
+
+        // 之前登录流程签发的令牌
+        let earlier = CallbackSigner::new().unwrap().issue(chrono::Utc::now().timestamp());
+        let response = warp::test::request()
+            .method("POST")
+            .path("/status")
+            .header(callback_token::HEADER, &earlier)
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), 403);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["message"], TokenError::BadSignature.to_string());
+    }
+
+
+    #[test]
+    fn captcha_poll_stops_once_token_is_sent() {
+        let script = injection_script(None);
+        assert_eq!(script.matches("setInterval(checkCaptcha").count(), 1);
+        assert!(script.contains("if (__sent && !__captcha) {\n                    clearInterval(__captchaTimer);"));
+        // 每处允许再次捕获的地方都重新开始轮询（初始一次加两处重置）
+        assert_eq!(script.matches("  __sent = false;").count(), 2);
+        assert_eq!(script.matches("watchCaptcha();").count(), 3);
+    }
+}
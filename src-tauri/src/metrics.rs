@@ -0,0 +1,198 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// 浏览器登录捕获的本地统计（仅保存在本机，不上报）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoginMetrics {
+    /// 打开登录窗口的次数
+    #[serde(default)]
+    pub attempts: u64,
+    /// 成功捕获并保存账号的次数
+    #[serde(default)]
+    pub captures: u64,
+    /// 捕获到 Token 但添加失败、或回调服务异常的次数
+    #[serde(default)]
+    pub failures: u64,
+    /// 用户未完成登录就关闭窗口的次数
+    #[serde(default)]
+    pub cancellations: u64,
+    /// 最近一次成功捕获时各阶段的耗时
+    #[serde(default)]
+    pub last_capture_timing: Option<CaptureTiming>,
+}
+
+/// 登录流程的阶段，按发生顺序排列
+#[derive(Debug, Clone, Copy)]
+pub enum LoginPhase {
+    /// 登录窗口已打开
+    WindowOpened,
+    /// 第一个 trae.ai 页面加载完成
+    PageLoaded,
+    /// 回调服务收到 Token
+    TokenSeen,
+    /// Cookies 已收集（含 webview 中的 HttpOnly Cookies）
+    CookiesCollected,
+    /// 账号已保存
+    Stored,
+}
+
+/// 一次登录流程中各阶段的时间点（Unix 毫秒）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaptureTimeline {
+    pub window_opened: Option<i64>,
+    pub page_loaded: Option<i64>,
+    pub token_seen: Option<i64>,
+    pub cookies_collected: Option<i64>,
+    pub stored: Option<i64>,
+}
+
+/// 一次成功捕获中相邻阶段之间的耗时（毫秒），缺少某个阶段时对应的耗时为空
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureTiming {
+    /// 打开窗口到页面加载完成
+    pub page_load_ms: Option<i64>,
+    /// 页面加载完成到收到 Token（主要是用户登录所用的时间）
+    pub token_ms: Option<i64>,
+    /// 收到 Token 到收集完 Cookies
+    pub cookies_ms: Option<i64>,
+    /// 收集完 Cookies 到账号保存完成（含校验 Token 的网络请求）
+    pub store_ms: Option<i64>,
+    /// 打开窗口到账号保存完成
+    pub total_ms: Option<i64>,
+    /// 捕获完成的时间（Unix 秒）
+    pub captured_at: i64,
+}
+
+impl CaptureTimeline {
+    /// 由各阶段时间点计算耗时；时间点倒退时（如系统时间被调整）视为 0
+    pub fn timing(&self) -> CaptureTiming {
+        let span = |from: Option<i64>, to: Option<i64>| match (from, to) {
+            (Some(from), Some(to)) => Some((to - from).max(0)),
+            _ => None,
+        };
+        CaptureTiming {
+            page_load_ms: span(self.window_opened, self.page_loaded),
+            token_ms: span(self.page_loaded, self.token_seen),
+            cookies_ms: span(self.token_seen, self.cookies_collected),
+            store_ms: span(self.cookies_collected, self.stored),
+            total_ms: span(self.window_opened, self.stored),
+            captured_at: self.stored.map_or_else(|| chrono::Utc::now().timestamp(), |ms| ms / 1000),
+        }
+    }
+}
+
+impl LoginMetrics {
+    /// 捕获成功率（尚无登录记录时为 None）
+    pub fn success_rate(&self) -> Option<f64> {
+        if self.attempts == 0 {
+            None
+        } else {
+            Some(self.captures as f64 / self.attempts as f64)
+        }
+    }
+}
+
+/// 登录流程中需要统计的事件
+#[derive(Debug, Clone, Copy)]
+pub enum LoginEvent {
+    Attempt,
+    Capture,
+    Failure,
+    Cancellation,
+}
+
+static METRICS: Lazy<Mutex<LoginMetrics>> = Lazy::new(|| Mutex::new(load_metrics()));
+
+/// 当前登录流程的阶段时间点（只保存在内存中）
+static TIMELINE: Lazy<Mutex<CaptureTimeline>> = Lazy::new(|| Mutex::new(CaptureTimeline::default()));
+
+/// 获取统计文件路径
+fn get_metrics_path() -> Result<PathBuf> {
+    let proj_dirs = directories::ProjectDirs::from("com", "sauce", "trae-auto")
+        .ok_or_else(|| anyhow!("无法获取应用数据目录"))?;
+
+    let data_dir = proj_dirs.data_dir();
+    fs::create_dir_all(data_dir)?;
+
+    Ok(data_dir.join("metrics.json"))
+}
+
+/// 加载统计数据，文件不存在或损坏时从零开始
+fn load_metrics() -> LoginMetrics {
+    get_metrics_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_metrics(metrics: &LoginMetrics) -> Result<()> {
+    let content = serde_json::to_string_pretty(metrics)?;
+    fs::write(get_metrics_path()?, content)?;
+    Ok(())
+}
+
+/// 记录一次登录事件并持久化
+pub fn record(event: LoginEvent) {
+    let Ok(mut metrics) = METRICS.lock() else {
+        return;
+    };
+    match event {
+        LoginEvent::Attempt => metrics.attempts += 1,
+        LoginEvent::Capture => metrics.captures += 1,
+        LoginEvent::Failure => metrics.failures += 1,
+        LoginEvent::Cancellation => metrics.cancellations += 1,
+    }
+    if let Err(e) = save_metrics(&metrics) {
+        println!("[WARN] 保存登录统计失败: {}", e);
+    }
+}
+
+/// 记录登录流程到达某个阶段：打开窗口时重新开始计时，账号保存后计算并持久化各阶段耗时
+///
+/// 页面加载只记录第一次；收到 Token 之后的阶段记录最近一次，添加失败后再次捕获时按最后一次计算
+pub fn mark(phase: LoginPhase) {
+    let now = chrono::Utc::now().timestamp_millis();
+    let Ok(mut timeline) = TIMELINE.lock() else {
+        return;
+    };
+    match phase {
+        LoginPhase::WindowOpened => *timeline = CaptureTimeline { window_opened: Some(now), ..Default::default() },
+        LoginPhase::PageLoaded => {
+            timeline.page_loaded.get_or_insert(now);
+        }
+        LoginPhase::TokenSeen => timeline.token_seen = Some(now),
+        LoginPhase::CookiesCollected => timeline.cookies_collected = Some(now),
+        LoginPhase::Stored => timeline.stored = Some(now),
+    }
+    if !matches!(phase, LoginPhase::Stored) {
+        return;
+    }
+
+    let timing = timeline.timing();
+    timeline.token_seen = None;
+    timeline.cookies_collected = None;
+    timeline.stored = None;
+    drop(timeline);
+    let Ok(mut metrics) = METRICS.lock() else {
+        return;
+    };
+    metrics.last_capture_timing = Some(timing);
+    if let Err(e) = save_metrics(&metrics) {
+        println!("[WARN] 保存登录统计失败: {}", e);
+    }
+}
+
+/// 最近一次成功捕获时各阶段的耗时
+pub fn last_capture_timing() -> Option<CaptureTiming> {
+    METRICS.lock().ok().and_then(|m| m.last_capture_timing.clone())
+}
+
+/// 获取当前统计数据
+pub fn snapshot() -> LoginMetrics {
+    METRICS.lock().map(|m| m.clone()).unwrap_or_default()
+}
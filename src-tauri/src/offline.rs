@@ -0,0 +1,26 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use thiserror::Error;
+
+/// 离线模式：开启后所有访问网络的操作直接失败，定时任务暂停
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// 离线模式下拒绝网络请求的错误
+#[derive(Debug, Error)]
+#[error("已开启离线模式，网络请求已暂停")]
+pub struct OfflineMode;
+
+pub fn set_enabled(enabled: bool) {
+    OFFLINE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// 发起网络请求前调用：离线模式下返回 OfflineMode 错误
+pub fn ensure_online() -> Result<(), OfflineMode> {
+    if is_enabled() {
+        return Err(OfflineMode);
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::login::MAIN_WINDOW_LABEL;
+
+/// 最多保留的待处理事件数量，超出时丢弃最旧的
+const MAX_PENDING: usize = 50;
+
+/// 发送时没有前端接收的重要事件，下次启动时由前端取回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingEvent {
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub created_at: i64,
+}
+
+static PENDING: Lazy<Mutex<Vec<PendingEvent>>> = Lazy::new(|| Mutex::new(load_pending()));
+
+/// 获取待处理事件文件路径
+fn get_pending_path() -> Result<PathBuf> {
+    let proj_dirs = directories::ProjectDirs::from("com", "sauce", "trae-auto")
+        .ok_or_else(|| anyhow!("无法获取应用数据目录"))?;
+
+    let data_dir = proj_dirs.data_dir();
+    fs::create_dir_all(data_dir)?;
+
+    Ok(data_dir.join("pending_events.json"))
+}
+
+/// 加载待处理事件，文件不存在或损坏时为空
+fn load_pending() -> Vec<PendingEvent> {
+    get_pending_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_pending(events: &[PendingEvent]) -> Result<()> {
+    let content = serde_json::to_string_pretty(events)?;
+    fs::write(get_pending_path()?, content)?;
+    Ok(())
+}
+
+/// 发送重要事件；主窗口已关闭或发送失败时保存下来，避免丢失（如登录成功的通知）
+pub fn emit_or_persist<S: Serialize>(app: &AppHandle, event: &str, payload: S) {
+    let payload = match serde_json::to_value(payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            println!("[WARN] 序列化事件 {} 失败: {}", event, e);
+            return;
+        }
+    };
+    // 主窗口不存在时没有前端在监听，直接保存
+    if app.get_webview_window(MAIN_WINDOW_LABEL).is_some() {
+        match app.emit(event, &payload) {
+            Ok(()) => return,
+            Err(e) => println!("[WARN] 发送事件 {} 失败，已保存待下次启动时处理: {}", event, e),
+        }
+    }
+    persist(PendingEvent {
+        event: event.to_string(),
+        payload,
+        created_at: chrono::Utc::now().timestamp(),
+    });
+}
+
+fn persist(pending: PendingEvent) {
+    let Ok(mut events) = PENDING.lock() else {
+        return;
+    };
+    events.push(pending);
+    let excess = events.len().saturating_sub(MAX_PENDING);
+    events.drain(..excess);
+    if let Err(e) = save_pending(&events) {
+        println!("[WARN] 保存待处理事件失败: {}", e);
+    }
+}
+
+/// 取出并清空所有待处理事件（按发生顺序）
+pub fn drain() -> Vec<PendingEvent> {
+    let Ok(mut events) = PENDING.lock() else {
+        return Vec::new();
+    };
+    if events.is_empty() {
+        return Vec::new();
+    }
+    let drained = std::mem::take(&mut *events);
+    if let Err(e) = save_pending(&events) {
+        println!("[WARN] 清空待处理事件失败: {}", e);
+    }
+    drained
+}
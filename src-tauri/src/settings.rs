@@ -0,0 +1,268 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// 本地控制 API 默认端口
+pub const DEFAULT_CONTROL_API_PORT: u16 = 17923;
+
+/// 凭证状态默认有效期（秒）
+pub const DEFAULT_STATUS_TTL_SECS: u64 = 24 * 3600;
+
+/// 批量刷新 Token 的默认并发数
+pub const DEFAULT_REFRESH_CONCURRENCY: usize = 4;
+
+/// 批量刷新时单个请求的默认超时（秒）
+pub const DEFAULT_REFRESH_TIMEOUT_SECS: u64 = 15;
+
+/// 登录窗口接受的默认最短 Token 长度
+pub const DEFAULT_MIN_TOKEN_LENGTH: usize = 50;
+
+/// 定时备份的默认间隔（小时）
+pub const DEFAULT_BACKUP_INTERVAL_HOURS: u64 = 24;
+
+/// 定时备份默认保留的数量
+pub const DEFAULT_BACKUP_RETENTION: usize = 7;
+
+/// 启动后延迟校验账号的默认等待时间（秒）
+pub const DEFAULT_STARTUP_VALIDATION_DELAY_SECS: u64 = 10;
+
+/// 登录回调服务接收 Token 的默认路径（不含斜杠）
+pub const DEFAULT_LOGIN_CALLBACK_PATH: &str = "callback";
+
+/// 登录窗口默认识别的登出页面地址片段（不区分大小写）
+pub const DEFAULT_LOGOUT_URL_PATTERNS: &[&str] = &["/logout", "/signout", "/sign-out"];
+
+/// trae.ai 站点默认地址
+pub const DEFAULT_WEB_BASE_URL: &str = "https://www.trae.ai";
+
+/// 由站点地址推断环境名称：默认站点为 prod，主机名含 staging 时为 staging，其他情况直接使用主机名
+pub fn environment_for_base_url(web_base_url: &str) -> Option<String> {
+    let rest = web_base_url.trim().split_once("://").map_or(web_base_url.trim(), |(_, rest)| rest);
+    let host = rest.split(['/', ':', '?', '#']).next().unwrap_or("").to_ascii_lowercase();
+    let host = host.trim_start_matches("www.");
+    if host.is_empty() {
+        return None;
+    }
+    let environment = match host {
+        "trae.ai" => "prod".to_string(),
+        h if h.contains("staging") => "staging".to_string(),
+        h => h.to_string(),
+    };
+    Some(environment)
+}
+
+/// 应用设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// 是否启用本地控制 API（仅监听 127.0.0.1）
+    pub control_api_enabled: bool,
+    /// 本地控制 API 端口
+    pub control_api_port: u16,
+    /// 本地控制 API 访问令牌（Bearer Token）的 SHA-256 摘要，令牌本身只在生成时显示一次
+    pub control_api_token_hash: String,
+    /// 下次启动时以安全模式（只读）加载账号数据
+    pub safe_mode: bool,
+    /// 账号数量上限，None 表示不限制
+    pub max_accounts: Option<usize>,
+    /// 凭证状态有效期（秒），超过后显示为未知；None 表示不过期
+    pub status_ttl_secs: Option<u64>,
+    /// 允许为登录窗口打开开发者工具（排查捕获失败时使用）
+    pub login_devtools_enabled: bool,
+    /// 批量刷新 Token 的最大并发数
+    pub refresh_concurrency: usize,
+    /// 批量刷新时单个账号的请求超时（秒）
+    pub refresh_timeout_secs: u64,
+    /// 浏览器登录成功时发送桌面通知
+    pub login_notify_enabled: bool,
+    /// 浏览器登录添加失败时保存捕获的凭证，稍后可重试添加
+    pub save_failed_captures: bool,
+    /// 登录窗口使用无痕模式（无法读取 Cookies 时自动回退）
+    pub login_incognito: bool,
+    /// trae.ai 站点地址，用于打开用量等网页
+    pub web_base_url: String,
+    /// 登录窗口接受的最短 Token 长度，更短的 Token 会被忽略并提示
+    pub min_token_length: usize,
+    /// 新账号默认使用的代理（创建时复制到账号上，之后可单独修改）
+    pub default_proxy: Option<String>,
+    /// 登录成功后先显示成功页面再关闭登录窗口
+    pub login_success_page: bool,
+    /// 登录窗口置顶显示，避免被主窗口遮挡
+    pub login_always_on_top: bool,
+    /// 登出页面地址片段：登录窗口经过这些页面时丢弃之前捕获的 Token，只提交重新登录后的 Token
+    pub login_logout_patterns: Vec<String>,
+    /// 是否定时把账号数据加密备份到 backup_dir
+    pub backup_enabled: bool,
+    /// 备份目录（建议放在应用数据目录以外，例如同步盘）
+    pub backup_dir: Option<String>,
+    /// 备份间隔（小时）
+    pub backup_interval_hours: u64,
+    /// 保留最新的备份数量，更早的备份会被删除
+    pub backup_retention: usize,
+    /// 启动后在后台校验所有账号
+    pub validate_on_startup: bool,
+    /// 启动后等待多少秒再开始校验，让界面先加载完成
+    pub startup_validation_delay_secs: u64,
+    /// 登录回调服务接收 Token 的路径（单段，不含斜杠）；回调路由和注入脚本都使用它
+    pub login_callback_path: String,
+    /// 离线模式：暂停所有网络请求和定时任务（按流量计费或隔离网络时使用）
+    pub offline_mode: bool,
+    /// 连续切换账号时只执行最后一次请求，跳过排队中被取代的请求
+    pub coalesce_activations: bool,
+    /// 浏览器登录时是否保存 Cookies；关闭后新账号只保存 Token（无法切换 Trae IDE，也无法用 Cookies 刷新 Token）
+    pub capture_cookies: bool,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            control_api_enabled: false,
+            control_api_port: DEFAULT_CONTROL_API_PORT,
+            control_api_token_hash: String::new(),
+            safe_mode: false,
+            max_accounts: None,
+            status_ttl_secs: Some(DEFAULT_STATUS_TTL_SECS),
+            login_devtools_enabled: false,
+            refresh_concurrency: DEFAULT_REFRESH_CONCURRENCY,
+            refresh_timeout_secs: DEFAULT_REFRESH_TIMEOUT_SECS,
+            login_notify_enabled: false,
+            save_failed_captures: false,
+            login_incognito: false,
+            web_base_url: DEFAULT_WEB_BASE_URL.to_string(),
+            min_token_length: DEFAULT_MIN_TOKEN_LENGTH,
+            default_proxy: None,
+            login_success_page: false,
+            login_always_on_top: false,
+            login_logout_patterns: DEFAULT_LOGOUT_URL_PATTERNS.iter().map(|p| p.to_string()).collect(),
+            backup_enabled: false,
+            backup_dir: None,
+            backup_interval_hours: DEFAULT_BACKUP_INTERVAL_HOURS,
+            backup_retention: DEFAULT_BACKUP_RETENTION,
+            validate_on_startup: false,
+            startup_validation_delay_secs: DEFAULT_STARTUP_VALIDATION_DELAY_SECS,
+            login_callback_path: DEFAULT_LOGIN_CALLBACK_PATH.to_string(),
+            offline_mode: false,
+            coalesce_activations: false,
+            capture_cookies: true,
+        }
+    }
+}
+
+/// 配置项的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// 内置默认值
+    Default,
+    /// 设置文件 settings.json
+    File,
+    /// 命令行参数
+    CommandLine,
+}
+
+/// 实际生效的配置项及其来源
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigValue {
+    pub value: serde_json::Value,
+    pub source: ConfigSource,
+}
+
+/// 获取设置文件路径
+fn get_settings_path() -> Result<PathBuf> {
+    let proj_dirs = directories::ProjectDirs::from("com", "sauce", "trae-auto")
+        .ok_or_else(|| anyhow!("无法获取应用数据目录"))?;
+    let config_dir = proj_dirs.config_dir();
+    fs::create_dir_all(config_dir)?;
+    Ok(config_dir.join("settings.json"))
+}
+
+/// 加载设置，文件不存在或无法解析时使用默认值
+pub fn load_settings() -> AppSettings {
+    let Ok(path) = get_settings_path() else {
+        return AppSettings::default();
+    };
+    if !path.exists() {
+        return AppSettings::default();
+    }
+    match fs::read_to_string(&path).map(|content| (serde_json::from_str(&content), content)) {
+        Ok((Ok(mut settings), content)) => {
+            remove_plaintext_secrets(&content, &mut settings);
+            settings
+        }
+        Ok((Err(e), _)) => {
+            println!("[WARN] 解析设置文件失败，使用默认设置: {}", e);
+            AppSettings::default()
+        }
+        Err(e) => {
+            println!("[WARN] 读取设置文件失败，使用默认设置: {}", e);
+            AppSettings::default()
+        }
+    }
+}
+
+/// 旧版本明文保存在设置文件中的密钥，加载时从文件中移除
+const LEGACY_SECRET_KEYS: &[&str] = &["backup_passphrase", "control_api_token"];
+
+/// 设置文件中仍有旧版本明文保存的密钥时，按当前设置重写文件
+/// （备份密码改为只保存在内存中，控制 API 令牌改为只保存摘要，原令牌仍然可用）
+fn remove_plaintext_secrets(content: &str, settings: &mut AppSettings) {
+    let Ok(serde_json::Value::Object(map)) = serde_json::from_str::<serde_json::Value>(content) else {
+        return;
+    };
+    if !LEGACY_SECRET_KEYS.iter().any(|key| map.contains_key(*key)) {
+        return;
+    }
+    if settings.control_api_token_hash.is_empty() {
+        if let Some(token) = map.get("control_api_token").and_then(|v| v.as_str()).map(str::trim).filter(|t| !t.is_empty()) {
+            settings.control_api_token_hash = crate::control_api::token_hash(token);
+        }
+    }
+    match save_settings(settings) {
+        Ok(()) => println!("[INFO] 已从设置文件中移除明文保存的密钥"),
+        Err(e) => println!("[WARN] 移除设置文件中的明文密钥失败: {}", e),
+    }
+}
+
+/// 保存设置
+pub fn save_settings(settings: &AppSettings) -> Result<()> {
+    let content = serde_json::to_string_pretty(settings)?;
+    fs::write(get_settings_path()?, content)?;
+    Ok(())
+}
+
+/// 设置文件中显式写出的配置项（文件不存在或无法解析时为空，此时全部使用默认值）
+fn file_keys() -> Vec<String> {
+    let Some(content) = get_settings_path().ok().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    if serde_json::from_str::<AppSettings>(&content).is_err() {
+        return Vec::new();
+    }
+    match serde_json::from_str::<serde_json::Value>(&content) {
+        Ok(serde_json::Value::Object(map)) => map.into_iter().map(|(key, _)| key).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 合并后实际生效的配置，标明每一项来自默认值还是设置文件（控制 API 令牌会被替换）
+pub fn effective_config(settings: &AppSettings) -> BTreeMap<String, ConfigValue> {
+    let file_keys = file_keys();
+    let serde_json::Value::Object(values) = serde_json::to_value(settings).unwrap_or_default() else {
+        return BTreeMap::new();
+    };
+    values
+        .into_iter()
+        .map(|(key, value)| {
+            let source = if file_keys.contains(&key) { ConfigSource::File } else { ConfigSource::Default };
+            let value = match key.as_str() {
+                "control_api_token_hash" if value.as_str().is_some_and(|t| !t.is_empty()) => {
+                    serde_json::Value::String("<redacted>".to_string())
+                }
+                _ => value,
+            };
+            (key, ConfigValue { value, source })
+        })
+        .collect()
+}
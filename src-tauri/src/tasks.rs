@@ -0,0 +1,74 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::task::{AbortHandle, JoinHandle};
+
+/// 浏览器登录流程的回调服务及其监控任务
+pub const LOGIN: &str = "login";
+
+/// 本地控制 API 服务
+pub const CONTROL_API: &str = "control-api";
+
+/// 定时加密备份
+pub const BACKUP: &str = "backup";
+
+/// 启动后延迟执行的账号校验
+pub const STARTUP_VALIDATION: &str = "startup-validation";
+
+/// 按用途登记的后台任务，功能关闭或应用退出时统一中止，避免遗留孤立任务
+static REGISTRY: Lazy<Mutex<HashMap<&'static str, Vec<AbortHandle>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 启动后台任务并登记到指定用途下（顺带清理该用途下已结束的任务）；
+/// 任务运行在 tauri 的异步运行时上，可以在 setup 等非异步上下文中直接调用
+pub fn spawn<F>(purpose: &'static str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let handle = tauri::async_runtime::handle().inner().spawn(future);
+    if let Ok(mut registry) = REGISTRY.lock() {
+        let handles = registry.entry(purpose).or_default();
+        handles.retain(|h| !h.is_finished());
+        handles.push(handle.abort_handle());
+    }
+    handle
+}
+
+/// 中止指定用途下的所有后台任务，返回中止的数量
+pub fn abort(purpose: &str) -> usize {
+    let handles = REGISTRY
+        .lock()
+        .ok()
+        .and_then(|mut registry| registry.remove(purpose))
+        .unwrap_or_default();
+    let running = handles.iter().filter(|h| !h.is_finished()).count();
+    for handle in handles {
+        handle.abort();
+    }
+    running
+}
+
+/// 各用途下仍在运行的后台任务数量
+pub fn running() -> HashMap<&'static str, usize> {
+    let Ok(registry) = REGISTRY.lock() else {
+        return HashMap::new();
+    };
+    registry
+        .iter()
+        .map(|(purpose, handles)| (*purpose, handles.iter().filter(|h| !h.is_finished()).count()))
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// 应用退出时中止所有后台任务
+pub fn abort_all() {
+    let all: Vec<AbortHandle> = match REGISTRY.lock() {
+        Ok(mut registry) => registry.drain().flat_map(|(_, handles)| handles).collect(),
+        Err(_) => return,
+    };
+    for handle in all {
+        handle.abort();
+    }
+}
@@ -0,0 +1,39 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// 非登录窗口（如用量页面）当前以哪个账号的会话打开：窗口标签 -> 账号 ID
+static WINDOW_ACCOUNTS: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 记录窗口正在使用的账号（同一窗口换账号时覆盖）
+pub fn assign(label: &str, account_id: &str) {
+    if let Ok(mut windows) = WINDOW_ACCOUNTS.lock() {
+        windows.insert(label.to_string(), account_id.to_string());
+    }
+}
+
+/// 窗口关闭后移除记录
+pub fn release(label: &str) {
+    if let Ok(mut windows) = WINDOW_ACCOUNTS.lock() {
+        windows.remove(label);
+    }
+}
+
+/// 窗口当前使用的账号 ID
+pub fn account_of(label: &str) -> Option<String> {
+    WINDOW_ACCOUNTS.lock().ok()?.get(label).cloned()
+}
+
+/// 正在使用指定账号的窗口标签（按标签排序）
+pub fn windows_using(account_id: &str) -> Vec<String> {
+    let Ok(windows) = WINDOW_ACCOUNTS.lock() else {
+        return Vec::new();
+    };
+    let mut labels: Vec<String> = windows
+        .iter()
+        .filter(|(_, id)| id.as_str() == account_id)
+        .map(|(label, _)| label.clone())
+        .collect();
+    labels.sort();
+    labels
+}
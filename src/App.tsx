@@ -1,4 +1,6 @@
 import { useState, useEffect, useCallback } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { save } from "@tauri-apps/plugin-dialog";
 import { Sidebar } from "./components/Sidebar";
 import { AccountCard } from "./components/AccountCard";
 import { AccountListItem } from "./components/AccountListItem";
@@ -14,7 +16,7 @@ import { Settings } from "./pages/Settings";
 import { About } from "./pages/About";
 import { useToast } from "./hooks/useToast";
 import * as api from "./api";
-import type { AccountBrief, UsageSummary } from "./types";
+import type { AccountBrief, AccountSort, BrowserCookieFormat, SelectiveUpdate, StorageCheck, UsageSummary, ValidationReport } from "./types";
 import "./App.css";
 
 interface AccountWithUsage extends AccountBrief {
@@ -31,6 +33,7 @@ function App() {
   const [loading, setLoading] = useState(true);
   const [currentPage, setCurrentPage] = useState("dashboard");
   const [viewMode, setViewMode] = useState<ViewMode>("grid");
+  const [sortMode, setSortMode] = useState<AccountSort>("default");
 
   // 使用自定义 Toast hook
   const { toasts, addToast, removeToast } = useToast();
@@ -81,7 +84,7 @@ function App() {
   const loadAccounts = useCallback(async () => {
     setLoading(true);
     try {
-      const list = await api.getAccounts();
+      const list = await api.getAccounts(false, sortMode);
 
       // 先立即显示账号列表（不等待使用量加载）
       setAccounts(list.map((account) => ({ ...account, usage: undefined })));
@@ -89,8 +92,11 @@ function App() {
 
       // 后台并行加载使用量
       if (list.length > 0) {
+        // 停用的账号不自动刷新
         const usageResults = await Promise.allSettled(
-          list.map((account) => api.getAccountUsage(account.id))
+          list.map((account) =>
+            account.is_active ? api.getAccountUsage(account.id) : Promise.reject(new Error("账号已停用"))
+          )
         );
 
         setAccounts((prev) =>
@@ -107,13 +113,199 @@ function App() {
       setError(err.message || "加载账号失败");
       setLoading(false);
     }
-  }, []);
+  }, [sortMode]);
 
   // 初始加载
   useEffect(() => {
     loadAccounts();
   }, [loadAccounts]);
 
+  // 补发主窗口关闭期间完成的登录（不会重复发送）
+  useEffect(() => {
+    api.drainPendingEvents().then((events) => {
+      for (const pending of events) {
+        if (pending.event === "login-success") {
+          addToast("success", `浏览器登录成功: ${pending.payload}`);
+        } else if (pending.event === "account-relogin-success") {
+          addToast("success", `账号凭证已更新: ${pending.payload.email}`);
+        }
+      }
+    }).catch(console.error);
+  }, []);
+
+  // 检查是否有其他实例正在使用账号数据
+  useEffect(() => {
+    Promise.all([api.isSafeMode(), api.isStoreReadOnly()]).then(([safeMode, readOnly]) => {
+      if (safeMode) {
+        addToast("warning", "当前为安全模式，账号数据只读加载，可导出后再恢复正常模式");
+      } else if (readOnly) {
+        addToast("warning", "另一个窗口正在使用账号数据，当前为只读模式，修改不会被保存");
+      }
+    }).catch(console.error);
+  }, []);
+
+  // 数据目录不可写时提醒（启动时和主动检查时都可能发送 storage-readonly，只提示一次）
+  useEffect(() => {
+    let warned = false;
+    const unlisten = listen<StorageCheck>("storage-readonly", (event) => {
+      if (warned) return;
+      warned = true;
+      addToast("error", `数据目录不可写，修改将无法保存: ${event.payload.path}`);
+    });
+    unlisten.then(() => api.checkStorageWritable()).catch(console.error);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 账号数据文件与校验和不一致时提醒（启动时的事件可能早于监听，挂载后再主动检查一次，只提示一次）
+  useEffect(() => {
+    let warned = false;
+    const warn = () => {
+      if (warned) return;
+      warned = true;
+      addToast("warning", "账号数据文件可能在应用之外被修改或上次保存不完整，建议检查账号并从快照或备份恢复");
+    };
+    const unlisten = listen("store-checksum-mismatch", warn);
+    unlisten
+      .then(() => api.verifyStoreChecksum())
+      .then((status) => status === "mismatch" && warn())
+      .catch(console.error);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 本机时钟与服务器偏差较大时提醒（会影响 Token 过期判断）
+  useEffect(() => {
+    const unlisten = listen<{ offset_secs: number }>("clock-skew-detected", (event) => {
+      const minutes = Math.round(Math.abs(event.payload.offset_secs) / 60);
+      addToast("warning", `本机时钟与服务器相差约 ${minutes} 分钟，Token 过期时间可能显示不准确，请校准系统时间`);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 登录捕获的账号正在用量页面等窗口中使用时提醒
+  useEffect(() => {
+    const unlisten = listen<{ id: string; email: string; windows: string[] }>("account-in-use", (event) => {
+      addToast("warning", `账号 ${event.payload.email} 正在其他窗口中使用，更新凭证后该窗口可能需要重新打开`);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 登录页出现人机验证：捕获和自动关闭已暂停，提示用户完成验证
+  useEffect(() => {
+    const unlisten = listen<{ active: boolean }>("login-captcha", (event) => {
+      if (event.payload.active) {
+        addToast("info", "登录页需要人机验证，请在登录窗口中完成验证后继续");
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 只刷新 Cookies 完成
+  useEffect(() => {
+    const unlisten = listen<{ id: string; email: string; missing: string[] }>("cookies-refreshed", (event) => {
+      const { email, missing } = event.payload;
+      if (missing.length > 0) {
+        addToast("warning", `已刷新 ${email} 的 Cookies，仍缺少: ${missing.join(", ")}`);
+      } else {
+        addToast("success", `已刷新 ${email} 的 Cookies`);
+      }
+      loadAccounts();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 定时备份失败时提醒（备份在后台进行，成功时不打扰）
+  useEffect(() => {
+    const unlisten = listen<string>("backup-failed", (event) => {
+      addToast("error", `定时备份失败：${event.payload}`);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 批量校验被取消时提示已完成的数量
+  useEffect(() => {
+    const unlisten = listen<{ completed: number }>("validation-cancelled", (event) => {
+      addToast("info", `校验已取消，已完成 ${event.payload.completed} 个账号`);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 启动时的后台校验完成后刷新列表
+  useEffect(() => {
+    const unlisten = listen<ValidationReport>("startup-validate-finished", (event) => {
+      const { valid, expired, failed } = event.payload;
+      addToast(expired > 0 || failed > 0 ? "warning" : "success", `启动校验完成：有效 ${valid}，失效 ${expired}，失败 ${failed}`);
+      loadAccounts();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [loadAccounts]);
+
+  // 浏览器登录成功时发送桌面通知（需在设置中开启）
+  useEffect(() => {
+    const unlisten = listen<string>("login-notify", async (event) => {
+      if (!("Notification" in window)) return;
+      if (Notification.permission === "default") {
+        await Notification.requestPermission();
+      }
+      if (Notification.permission === "granted") {
+        new Notification("账号添加成功", { body: event.payload });
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 首次运行时导入主窗口中已登录的 trae.ai 会话
+  useEffect(() => {
+    api.importCurrentSession().then((account) => {
+      if (account) {
+        addToast("success", `已导入当前登录的账号: ${account.email}`);
+        loadAccounts();
+      }
+    }).catch(console.error);
+  }, []);
+
+  // 重新登录已有账号后刷新列表（名称、标签等信息保持不变）
+  useEffect(() => {
+    const unlisten = listen<{ id: string; email: string }>("account-relogin-success", (event) => {
+      addToast("success", `账号凭证已更新: ${event.payload.email}`);
+      loadAccounts();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [loadAccounts]);
+
+  // 原地重新登录：提示哪些凭证被替换
+  useEffect(() => {
+    const unlisten = listen<SelectiveUpdate>("relogin-selective-update", (event) => {
+      const { email, token_updated, cookies_updated } = event.payload;
+      const describe = (updated: boolean) => (updated ? "已更新" : "仍有效，保持不变");
+      addToast("info", `${email}：Token ${describe(token_updated)}，Cookies ${describe(cookies_updated)}`);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   // 自动刷新即将过期的 Token
   useEffect(() => {
     // 启动时刷新
@@ -238,6 +430,122 @@ function App() {
     }
   };
 
+  // 重新登录已有账号，只更新凭证
+  const handleRelogin = async (accountId: string) => {
+    try {
+      await api.startBrowserLogin(undefined, false, accountId);
+    } catch (err: any) {
+      addToast("error", err.message || "打开登录窗口失败");
+    }
+  };
+
+  // 只刷新 Cookies（Token 仍有效但 Cookies 不完整时使用）
+  const handleRefreshCookies = async (accountId: string) => {
+    try {
+      await api.refreshAccountCookies(accountId);
+    } catch (err: any) {
+      addToast("error", err.message || "打开登录窗口失败");
+    }
+  };
+
+  // 重新登录，只替换已失效或有更新的凭证
+  const handleSmartRelogin = async (accountId: string) => {
+    try {
+      await api.smartReloginAccount(accountId);
+    } catch (err: any) {
+      addToast("error", err.message || "打开登录窗口失败");
+    }
+  };
+
+  // 以该账号打开 trae.ai 用量页面
+  const handleOpenBilling = async (accountId: string) => {
+    try {
+      await api.openBilling(accountId);
+      // 读回 Cookies，部分 Cookie 被 webview 丢弃时页面可能显示未登录
+      const result = await api.verifyActive(accountId);
+      const failed = [...result.missing, ...result.mismatched];
+      if (failed.length > 0) {
+        addToast("warning", `用量页面有 ${failed.length} 个 Cookie 未生效：${failed.join(", ")}`);
+      }
+    } catch (err: any) {
+      addToast("error", err.message || "打开用量页面失败");
+    }
+  };
+
+  // 复制 Cookie 请求头（包含敏感信息，需确认）
+  const handleCopyCookieHeader = (accountId: string) => {
+    setConfirmModal({
+      isOpen: true,
+      title: "复制 Cookie 请求头",
+      message: "Cookie 可直接用于登录该账号，请勿泄露给他人。确定要复制吗？",
+      type: "warning",
+      onConfirm: async () => {
+        try {
+          const header = await api.copyCookieHeader(accountId);
+          await navigator.clipboard.writeText(header);
+          addToast("success", "Cookie 请求头已复制到剪贴板");
+        } catch (err: any) {
+          addToast("error", err.message || "获取 Cookies 失败");
+        }
+        setConfirmModal(null);
+      },
+    });
+  };
+
+  // 导出浏览器会话（包含敏感信息，需确认）
+  // 只分享访问 Token（加密文件），对方在 Token 过期前可临时使用，无法刷新
+  const handleExportTokenOnly = async (accountId: string) => {
+    const account = accounts.find((a) => a.id === accountId);
+    if (!account) return;
+    const passphrase = prompt("请设置分享文件的密码（对方导入时需要）");
+    if (!passphrase) return;
+    try {
+      const name = (account.email || account.name || account.id).replace(/[^\w.@-]/g, "_");
+      const path = await save({
+        defaultPath: `trae-token-${name}.trbak`,
+        filters: [{ name: "账号备份", extensions: ["trbak"] }],
+        title: "保存临时 Token 文件",
+      });
+      if (!path) return;
+      await api.exportTokenOnly(accountId, passphrase, path);
+      addToast("success", "已导出临时 Token，对方可在「设置 - 从备份恢复」中导入，Token 过期后失效");
+    } catch (err: any) {
+      addToast("error", err.message || "导出失败");
+    }
+  };
+
+  const handleExportBrowserSession = (accountId: string, format: BrowserCookieFormat) => {
+    const account = accounts.find((a) => a.id === accountId);
+    if (!account) return;
+    setConfirmModal({
+      isOpen: true,
+      title: "导出浏览器会话",
+      message: "导出的 Cookies 可直接用于登录该账号，导入浏览器后请妥善保管文件，勿泄露给他人。确定要导出吗？",
+      type: "warning",
+      onConfirm: async () => {
+        try {
+          const data = await api.exportBrowserSession(accountId, format);
+          const isJson = format === "json";
+          const blob = new Blob([data], { type: isJson ? "application/json" : "text/plain" });
+          const url = URL.createObjectURL(blob);
+          const a = document.createElement("a");
+          a.href = url;
+          const name = (account.email || account.name || account.id).replace(/[^\w.@-]/g, "_");
+          const fileName = isJson ? `trae-cookies-${name}.json` : `trae-cookies-${name}.txt`;
+          a.download = fileName;
+          document.body.appendChild(a);
+          a.click();
+          document.body.removeChild(a);
+          URL.revokeObjectURL(url);
+          addToast("success", `已导出到下载文件夹：${fileName}`);
+        } catch (err: any) {
+          addToast("error", err.message || "导出失败");
+        }
+        setConfirmModal(null);
+      },
+    });
+  };
+
   // 切换账号
   const handleSwitchAccount = async (accountId: string) => {
     const account = accounts.find((a) => a.id === accountId);
@@ -252,11 +560,17 @@ function App() {
         setConfirmModal(null);
         addToast("info", "正在切换账号，请稍候...");
         try {
-          await api.switchAccount(accountId);
+          const applied = await api.switchAccount(accountId);
           await loadAccounts();
+          if (!applied) {
+            addToast("info", "已有更新的切换请求，本次切换已跳过");
+            return;
+          }
           addToast("success", "账号切换成功，请重新打开 Trae IDE");
         } catch (err: any) {
-          addToast("error", err.message || "切换账号失败");
+          // 把笼统的失败原因换成可操作的建议
+          const diagnosis = await api.activationDiagnosis(accountId).catch(() => null);
+          addToast("error", diagnosis?.guidance || err.message || "切换账号失败");
         }
       },
     });
@@ -302,6 +616,51 @@ function App() {
   };
 
   // 获取礼包
+  // 启用/停用账号
+  const handleToggleEnabled = async (accountId: string) => {
+    const account = accounts.find((a) => a.id === accountId);
+    if (!account) return;
+    try {
+      await api.setAccountEnabled(accountId, !account.is_active);
+      setAccounts((prev) =>
+        prev.map((a) => (a.id === accountId ? { ...a, is_active: !account.is_active } : a))
+      );
+      addToast("success", account.is_active ? "账号已停用，批量刷新将跳过该账号" : "账号已启用");
+    } catch (err: any) {
+      addToast("error", err.message || "操作失败");
+    }
+  };
+
+  // 开启/关闭 Token 自动刷新
+  const handleToggleAutoRefresh = async (accountId: string) => {
+    const account = accounts.find((a) => a.id === accountId);
+    if (!account) return;
+    try {
+      await api.setAccountAutoRefresh(accountId, !account.auto_refresh);
+      setAccounts((prev) =>
+        prev.map((a) => (a.id === accountId ? { ...a, auto_refresh: !account.auto_refresh } : a))
+      );
+      addToast("success", account.auto_refresh ? "已关闭自动刷新，该账号的 Token 不会被自动轮换" : "已开启自动刷新");
+    } catch (err: any) {
+      addToast("error", err.message || "操作失败");
+    }
+  };
+
+  // 设为/取消主账号（同一时间只有一个主账号）
+  const handleTogglePrimary = async (accountId: string) => {
+    const account = accounts.find((a) => a.id === accountId);
+    if (!account) return;
+    try {
+      await api.setPrimary(account.is_primary ? null : accountId);
+      setAccounts((prev) =>
+        prev.map((a) => ({ ...a, is_primary: !account.is_primary && a.id === accountId }))
+      );
+      addToast("success", account.is_primary ? "已取消主账号" : `已将 ${account.email} 设为主账号`);
+    } catch (err: any) {
+      addToast("error", err.message || "操作失败");
+    }
+  };
+
   const handleClaimGift = async (accountId: string) => {
     const account = accounts.find((a) => a.id === accountId);
     if (!account) return;
@@ -457,7 +816,7 @@ function App() {
           title: "⚠️ 注意事项",
           content: `<ul>
 <li>仅支持本应用导出的格式</li>
-<li>导入会自动跳过重复账号</li>
+<li>已存在的账号会保留 Token 较新的一方</li>
 <li>建议定期备份账号数据</li>
 </ul>`,
           type: "list"
@@ -482,9 +841,23 @@ function App() {
 
       try {
         const text = await file.text();
-        const count = await api.importAccounts(text);
-        addToast("success", `成功导入 ${count} 个账号`);
+        const records = await api.importAccounts(text);
+        const added = records.filter((r) => r.action === "added").length;
+        const overwritten = records.filter((r) => r.action === "overwritten").length;
+        const duplicates = records.filter((r) => r.action === "duplicate").length;
+        addToast("success", `成功导入 ${added} 个账号，更新 ${overwritten} 个已存在账号`);
+        if (duplicates > 0) {
+          addToast("info", `导入文件中有 ${duplicates} 个同一用户的重复条目，已合并`);
+        }
         await loadAccounts();
+
+        // 校验刚导入的账号，提示哪些账号已失效
+        if (added + overwritten > 0) {
+          const report = await api.validateImported(records);
+          const type = report.expired > 0 || report.failed > 0 ? "warning" : "success";
+          addToast(type, `导入账号校验完成：有效 ${report.valid} 个，失效 ${report.expired} 个，无法校验 ${report.failed} 个`);
+          await loadAccounts();
+        }
       } catch (err: any) {
         addToast("error", err.message || "导入失败");
       }
@@ -499,7 +872,14 @@ function App() {
       return;
     }
 
-    const ids = Array.from(selectedIds);
+    // 跳过已停用的账号
+    const ids = Array.from(selectedIds).filter(
+      (id) => accounts.find((a) => a.id === id)?.is_active !== false
+    );
+    if (ids.length === 0) {
+      addToast("warning", "选中的账号均已停用");
+      return;
+    }
     addToast("info", `正在刷新 ${ids.length} 个账号...`);
 
     // 并行刷新所有选中的账号
@@ -708,6 +1088,15 @@ function App() {
                     )}
                   </div>
                   <div className="toolbar-right">
+                    <select
+                      className="setting-select"
+                      value={sortMode}
+                      onChange={(e) => setSortMode(e.target.value as AccountSort)}
+                      title="排序方式"
+                    >
+                      <option value="default">默认顺序</option>
+                      <option value="by_expiry">按过期时间</option>
+                    </select>
                     <div className="view-toggle">
                       <button
                         className={`view-btn ${viewMode === "grid" ? "active" : ""}`}
@@ -877,6 +1266,34 @@ function App() {
             handleCopyToken(contextMenu.accountId);
             setContextMenu(null);
           }}
+          onCopyCookieHeader={() => {
+            handleCopyCookieHeader(contextMenu.accountId);
+            setContextMenu(null);
+          }}
+          onExportBrowserSession={(format) => {
+            handleExportBrowserSession(contextMenu.accountId, format);
+            setContextMenu(null);
+          }}
+          onExportTokenOnly={() => {
+            handleExportTokenOnly(contextMenu.accountId);
+            setContextMenu(null);
+          }}
+          onRelogin={() => {
+            handleRelogin(contextMenu.accountId);
+            setContextMenu(null);
+          }}
+          onRefreshCookies={() => {
+            handleRefreshCookies(contextMenu.accountId);
+            setContextMenu(null);
+          }}
+          onSmartRelogin={() => {
+            handleSmartRelogin(contextMenu.accountId);
+            setContextMenu(null);
+          }}
+          onOpenBilling={() => {
+            handleOpenBilling(contextMenu.accountId);
+            setContextMenu(null);
+          }}
           onSwitchAccount={() => {
             handleSwitchAccount(contextMenu.accountId);
             setContextMenu(null);
@@ -885,11 +1302,26 @@ function App() {
             handleClaimGift(contextMenu.accountId);
             setContextMenu(null);
           }}
+          onToggleEnabled={() => {
+            handleToggleEnabled(contextMenu.accountId);
+            setContextMenu(null);
+          }}
+          onToggleAutoRefresh={() => {
+            handleToggleAutoRefresh(contextMenu.accountId);
+            setContextMenu(null);
+          }}
+          onTogglePrimary={() => {
+            handleTogglePrimary(contextMenu.accountId);
+            setContextMenu(null);
+          }}
           onDelete={() => {
             handleDeleteAccount(contextMenu.accountId);
             setContextMenu(null);
           }}
           isCurrent={accounts.find(a => a.id === contextMenu.accountId)?.is_current || false}
+          isEnabled={accounts.find(a => a.id === contextMenu.accountId)?.is_active !== false}
+          isAutoRefresh={accounts.find(a => a.id === contextMenu.accountId)?.auto_refresh !== false}
+          isPrimary={accounts.find(a => a.id === contextMenu.accountId)?.is_primary || false}
         />
       )}
 
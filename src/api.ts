@@ -1,5 +1,66 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { Account, AccountBrief, UsageSummary, UsageEventsResponse } from "./types";
+import type {
+  Account,
+  AccountBrief,
+  AccountProblem,
+  AccountSection,
+  CaptureTiming,
+  ActivationDiagnosis,
+  ActivationPreview,
+  UsageSummary,
+  UsageEventsResponse,
+  ImportStrategy,
+  ImportDuplicate,
+  ImportRecord,
+  SnapshotInfo,
+  AccountStats,
+  StoreIssue,
+  CompactReport,
+  AccountSize,
+  AccountSort,
+  PendingEvent,
+  CaptureReplay,
+  ChecksumStatus,
+  CookieCaptureReport,
+  CookieConflict,
+  CookieRemap,
+  LinkedAccountGroup,
+  LoginPrediction,
+  CookieVerification,
+  StorageCheck,
+  ValidationReport,
+  DraftBrief,
+  AccountStatus,
+  AccountFilter,
+  BrowserCookieFormat,
+  LoginMetrics,
+  AppSettings,
+  ConfigValue,
+  ProxyTestResult,
+  ProxyValidation,
+  ForeignMapping,
+  HealthRecord,
+} from "./types";
+
+// 是否以安全模式运行（账号数据只读加载）
+export async function isSafeMode(): Promise<boolean> {
+  return invoke("is_safe_mode");
+}
+
+// 检查数据目录是否可写（不可写时同时发送 storage-readonly 事件）
+export async function checkStorageWritable(): Promise<StorageCheck> {
+  return invoke("check_storage_writable");
+}
+
+// 检测本机时钟与 trae.ai 服务器的偏差（秒），偏差较大时同时发送 clock-skew-detected 事件
+export async function checkClockSkew(): Promise<number> {
+  return invoke("check_clock_skew");
+}
+
+// 账号数据是否处于只读模式（另一个实例正在使用）
+export async function isStoreReadOnly(): Promise<boolean> {
+  return invoke("is_store_read_only");
+}
 
 // 添加账号（通过 Cookies）
 export async function addAccount(cookies: string): Promise<Account> {
@@ -16,9 +77,33 @@ export async function removeAccount(accountId: string): Promise<void> {
   return invoke("remove_account", { accountId });
 }
 
-// 获取所有账号
-export async function getAccounts(): Promise<AccountBrief[]> {
-  return invoke("get_accounts");
+// 获取所有账号（默认不包含已隔离的账号；sort 为 by_expiry 时最需要续期的在前）
+export async function getAccounts(
+  includeQuarantined = false,
+  sort: AccountSort = "default",
+  environment?: string
+): Promise<AccountBrief[]> {
+  return invoke("get_accounts", { includeQuarantined, sort, environment });
+}
+
+// 修改账号 ID（修复导入造成的 ID 冲突），新 ID 已被占用时报错
+export async function reassignAccountId(oldId: string, newId: string): Promise<void> {
+  return invoke("reassign_account_id", { oldId, newId });
+}
+
+// 取消进行中的批量校验，校验命令返回已完成的部分结果
+export async function cancelValidation(): Promise<void> {
+  return invoke("cancel_validation");
+}
+
+// 只校验超过 ttlSecs 秒未校验的账号，返回各状态的数量
+export async function validateStale(ttlSecs: number): Promise<ValidationReport> {
+  return invoke("validate_stale", { ttlSecs });
+}
+
+// 获取从未校验过凭证的账号（如刚导入的账号）
+export async function listUnvalidated(): Promise<AccountBrief[]> {
+  return invoke("list_unvalidated");
 }
 
 // 获取单个账号详情（包含 token）
@@ -26,26 +111,321 @@ export async function getAccount(accountId: string): Promise<Account> {
   return invoke("get_account", { accountId });
 }
 
+// 查找底层用户 ID 相同的重复账号分组
+export async function findDuplicateGroups(): Promise<string[][]> {
+  return invoke("find_duplicate_groups");
+}
+
+// 预测用该 Token 登录会新增账号还是更新已有账号
+export async function predictLogin(token: string): Promise<LoginPrediction> {
+  return invoke("predict_login", { token });
+}
+
+// 离线回放一次记录下的 GetUserToken 响应体，报告将会添加的账号
+export async function replayCapture(body: string): Promise<CaptureReplay> {
+  return invoke("replay_capture", { body });
+}
+
+// 获取账号最近几次校验的结果（从旧到新）
+export async function accountHealthHistory(accountId: string): Promise<HealthRecord[]> {
+  return invoke("account_health_history", { accountId });
+}
+
+// 查找同一用户绑定不同邮箱的账号分组
+export async function findLinkedAccounts(): Promise<LinkedAccountGroup[]> {
+  return invoke("find_linked_accounts");
+}
+
+// 在系统文件管理器中打开账号数据目录
+export async function revealStoreInExplorer(): Promise<void> {
+  return invoke("reveal_store_in_explorer");
+}
+
+// 导出用于问题反馈的诊断信息（JSON，不含 Token 与 Cookies）
+export async function exportDiagnostics(): Promise<string> {
+  return invoke("export_diagnostics");
+}
+
+// 启用或停用账号（停用的账号不参与批量刷新）
+export async function setAccountEnabled(accountId: string, enabled: boolean): Promise<void> {
+  return invoke("set_account_enabled", { accountId, enabled });
+}
+
+// 设置主账号（替换原来的主账号），accountId 为 null 时取消
+export async function setPrimary(accountId: string | null): Promise<void> {
+  return invoke("set_primary", { accountId });
+}
+
+// 获取主账号，未设置时为 null
+export async function getPrimary(): Promise<AccountBrief | null> {
+  return invoke("get_primary");
+}
+
+// 设置账号是否参与 Token 自动刷新（共享账号可关闭，避免轮换他人正在使用的凭证）
+export async function setAccountAutoRefresh(accountId: string, autoRefresh: boolean): Promise<void> {
+  return invoke("set_account_auto_refresh", { accountId, autoRefresh });
+}
+
+// 设置账号使用的代理，传 null 表示直连
+export async function setAccountProxy(accountId: string, proxy: string | null): Promise<void> {
+  return invoke("set_account_proxy", { accountId, proxy });
+}
+
+// 账号保存的登录请求头名称（不含值），刷新 Token 时复用
+export async function capturedHeaderNames(accountId: string): Promise<string[]> {
+  return invoke("captured_header_names", { accountId });
+}
+
+// 清除账号保存的登录请求头
+export async function clearCapturedHeaders(accountId: string): Promise<void> {
+  return invoke("clear_captured_headers", { accountId });
+}
+
+// 设置账号所属的环境（如 prod/staging），传 null 清除
+export async function setAccountEnvironment(accountId: string, environment: string | null): Promise<void> {
+  return invoke("set_account_environment", { accountId, environment });
+}
+
+// 按分组获取账号列表（未分组的账号在最后一节）
+export async function getGroupedAccounts(includeQuarantined = false): Promise<AccountSection[]> {
+  return invoke("get_grouped_accounts", { includeQuarantined });
+}
+
+// 把账号移到指定分组（null 表示移出分组）
+export async function setAccountGroup(accountId: string, group: string | null): Promise<void> {
+  return invoke("set_account_group", { accountId, group });
+}
+
+// 设置分组内账号的顺序
+export async function reorderGroup(group: string, accountIds: string[]): Promise<void> {
+  return invoke("reorder_group", { group, accountIds });
+}
+
+// 设置分组的显示顺序
+export async function reorderGroups(groups: string[]): Promise<void> {
+  return invoke("reorder_groups", { groups });
+}
+
+// 折叠或展开分组
+export async function setGroupCollapsed(group: string, collapsed: boolean): Promise<void> {
+  return invoke("set_group_collapsed", { group, collapsed });
+}
+
+// 账号环境的建议取值
+export async function suggestedEnvironments(): Promise<string[]> {
+  return invoke("suggested_environments");
+}
+
+// 设置账号的颜色标记（#rrggbb），传 null 清除
+export async function setAccountColor(accountId: string, color: string | null): Promise<void> {
+  return invoke("set_account_color", { accountId, color });
+}
+
+// 手动设置账号状态（设为 unknown 取消手动设置）
+export async function setAccountStatus(accountId: string, status: AccountStatus): Promise<void> {
+  return invoke("set_account_status", { accountId, status });
+}
+
+// 为符合条件的账号批量设置代理（proxy 为 null 时清除），返回匹配的账号数量
+export async function setProxyWhere(filter: AccountFilter, proxy: string | null): Promise<number> {
+  return invoke("set_proxy_where", { filter, proxy });
+}
+
+// 给符合条件的账号批量添加标签，返回匹配的账号数量
+export async function tagWhere(filter: AccountFilter, tag: string): Promise<number> {
+  return invoke("tag_where", { filter, tag });
+}
+
+// 给每个账号添加其邮箱域名标签（已删除的自动标签不会再加回），返回新添加标签的账号数量
+export async function autoTagByDomain(): Promise<number> {
+  return invoke("auto_tag_by_domain");
+}
+
+// 删除账号的一个标签
+export async function removeTag(accountId: string, tag: string): Promise<void> {
+  return invoke("remove_tag", { accountId, tag });
+}
+
+// 预先创建账号用到的 HTTP 客户端，返回客户端数量
+export async function prewarmClients(): Promise<number> {
+  return invoke("prewarm_clients");
+}
+
+// 取出主窗口关闭期间未送达的重要事件，取出后清空
+export async function drainPendingEvents(): Promise<PendingEvent[]> {
+  return invoke("drain_pending_events");
+}
+
+// 把所有账号的状态重置为未知（不修改凭证），返回有变化的数量
+export async function resetStatuses(): Promise<number> {
+  return invoke("reset_statuses");
+}
+
+// 清理失效账号，quarantine 为 true 时只隔离不删除，返回处理的数量
+export async function purgeExpired(quarantine: boolean): Promise<number> {
+  return invoke("purge_expired", { quarantine });
+}
+
+// 将账号移出隔离区
+export async function restoreFromQuarantine(accountId: string): Promise<void> {
+  return invoke("restore_from_quarantine", { accountId });
+}
+
+// 删除所有已隔离的账号，返回删除的数量
+export async function emptyQuarantine(): Promise<number> {
+  return invoke("empty_quarantine");
+}
+
+// 按 Token 声明查找账号，key 支持点号路径
+export async function findByClaim(key: string, value: string): Promise<AccountBrief[]> {
+  return invoke("find_by_claim", { key, value });
+}
+
+// 按凭证状态统计账号数量
+export async function accountStats(): Promise<AccountStats> {
+  return invoke("account_stats");
+}
+
+// 规范化所有账号的 Cookies，返回被修改的账号数量
+export async function normalizeCookies(): Promise<number> {
+  return invoke("normalize_cookies");
+}
+
+// 用上次保存时记录的校验和检查账号数据文件的完整性
+export async function verifyStoreChecksum(): Promise<ChecksumStatus> {
+  return invoke("verify_store_checksum");
+}
+
+// 重写账号数据文件，清理旧版本遗留的字段
+export async function compactStore(): Promise<CompactReport> {
+  return invoke("compact_store");
+}
+
+// 估算每个账号在数据文件中的占用，按占用从大到小排列
+export async function accountSizes(): Promise<AccountSize[]> {
+  return invoke("account_sizes");
+}
+
+// 一次检查所有账号的问题，按严重程度排序（已失效 > 缺少 Cookies > 即将过期 > 状态未知）
+export async function diagnoseAll(): Promise<AccountProblem[]> {
+  return invoke("diagnose_all");
+}
+
+// 检查账号数据，列出缺少关键 Cookie 的账号
+export async function checkStore(): Promise<StoreIssue[]> {
+  return invoke("check_store");
+}
+
+// 获取账号 Cookies 格式化后的 `Cookie:` 请求头
+export async function copyCookieHeader(accountId: string): Promise<string> {
+  return invoke("copy_cookie_header", { accountId });
+}
+
+// 把账号的访问 Token 加密导出到文件（不含 Cookies 和刷新凭证），对方可通过「从备份恢复」导入
+export async function exportTokenOnly(accountId: string, passphrase: string, path: string): Promise<void> {
+  return invoke("export_token_only", { accountId, passphrase, path });
+}
+
+// 导出账号 Cookies 为浏览器可导入的格式（包含敏感信息）
+export async function exportBrowserSession(accountId: string, format: BrowserCookieFormat): Promise<string> {
+  return invoke("export_browser_session", { accountId, format });
+}
+
+// 以指定账号打开 trae.ai 用量页面
+export async function openBilling(accountId: string): Promise<void> {
+  return invoke("open_billing", { accountId });
+}
+
+// 列出账号 Cookies 中同名但值不同的 Cookie（通常来自不同域名）
+export async function reportCookieConflicts(accountId: string): Promise<CookieConflict[]> {
+  return invoke("report_cookie_conflicts", { accountId });
+}
+
+// trae.ai 迁移域名后，把账号 Cookies 从旧域名改写到新域名（host-only Cookie 不改写），返回受影响的账号
+export async function remapCookieDomain(oldDomain: string, newDomain: string): Promise<CookieRemap[]> {
+  return invoke("remap_cookie_domain", { oldDomain, newDomain });
+}
+
+// 列出账号 Cookies 中哪些是 HttpOnly、哪些脚本可读
+export async function cookieCaptureReport(accountId: string): Promise<CookieCaptureReport> {
+  return invoke("cookie_capture_report", { accountId });
+}
+
+// 去除账号 Cookies 中重复的同名 Cookie，返回去掉的个数
+export async function dedupAccountCookies(accountId: string): Promise<number> {
+  return invoke("dedup_account_cookies", { accountId });
+}
+
+// 预览以账号打开用量页面时将写入、覆盖或清除的 Cookies（只含名称和域名）
+export async function previewActivation(accountId: string): Promise<ActivationPreview> {
+  return invoke("preview_activation", { accountId });
+}
+
+// 读回用量窗口中的 Cookies，报告写入后未生效的 Cookie
+export async function verifyActive(accountId: string): Promise<CookieVerification> {
+  return invoke("verify_active", { accountId });
+}
+
 // 设置活跃账号
 export async function setActiveAccount(accountId: string): Promise<void> {
   return invoke("switch_account", { accountId });
 }
 
-// 切换账号（设置活跃账号并更新机器码）
-export async function switchAccount(accountId: string): Promise<void> {
+// 切换账号（设置活跃账号并更新机器码）；返回 false 表示该请求已被之后的切换请求取代
+export async function switchAccount(accountId: string): Promise<boolean> {
   return invoke("switch_account", { accountId });
 }
 
+// 诊断账号为什么无法切换，返回第一个阻塞原因及处理建议
+export async function activationDiagnosis(accountId: string): Promise<ActivationDiagnosis> {
+  return invoke("activation_diagnosis", { accountId });
+}
+
 // 获取账号使用量
 export async function getAccountUsage(accountId: string): Promise<UsageSummary> {
   return invoke("get_account_usage", { accountId });
 }
 
+// 从 trae.ai 重新获取账号邮箱，返回新邮箱（无变化时为 null）
+export async function refreshAccountEmail(accountId: string): Promise<string | null> {
+  return invoke("refresh_account_email", { accountId });
+}
+
+// 列出浏览器登录添加失败时保存的草稿
+export async function listDraftCaptures(): Promise<DraftBrief[]> {
+  return invoke("list_draft_captures");
+}
+
+// 用草稿中的凭证重新添加账号
+export async function commitDraft(draftId: string): Promise<Account> {
+  return invoke("commit_draft", { draftId });
+}
+
+// 删除草稿
+export async function discardDraft(draftId: string): Promise<void> {
+  return invoke("discard_draft", { draftId });
+}
+
+// 首次运行时从主窗口已有的 trae.ai 会话导入账号（只执行一次）
+export async function importCurrentSession(): Promise<Account | null> {
+  return invoke("import_current_session");
+}
+
 // 更新账号 Token
 export async function updateAccountToken(accountId: string, token: string): Promise<UsageSummary> {
   return invoke("update_account_token", { accountId, token });
 }
 
+// 设置账号的自定义 User-Agent（传空值恢复默认）
+export async function setAccountUserAgent(accountId: string, userAgent: string | null): Promise<void> {
+  return invoke("set_account_user_agent", { accountId, userAgent });
+}
+
+// 设置账号请求 Trae API 时附加的请求头（传空对象清除）
+export async function setAccountExtraHeaders(accountId: string, extraHeaders: Record<string, string>): Promise<void> {
+  return invoke("set_account_extra_headers", { accountId, extraHeaders });
+}
+
 // 刷新 Token
 export async function refreshToken(accountId: string): Promise<void> {
   return invoke("refresh_token", { accountId });
@@ -61,9 +441,89 @@ export async function exportAccounts(): Promise<string> {
   return invoke("export_accounts");
 }
 
-// 导入账号
-export async function importAccounts(data: string): Promise<number> {
-  return invoke("import_accounts", { data });
+// 只导出带有指定标签的账号（格式与导出全部相同）
+export async function exportByTag(tag: string): Promise<string> {
+  return invoke("export_by_tag", { tag });
+}
+
+// 校验刚导入的账号（校验过程中发送 import-validate-progress 事件）
+export async function validateImported(records: ImportRecord[]): Promise<ValidationReport> {
+  return invoke("validate_imported", { records });
+}
+
+// 开启或关闭离线模式，返回更新后的设置
+export async function setOfflineMode(enabled: boolean): Promise<AppSettings> {
+  return invoke("set_offline_mode", { enabled });
+}
+
+// 设置本次运行使用的备份密码（只保存在内存中，null 表示清除）
+export async function setBackupPassphrase(passphrase: string | null): Promise<void> {
+  return invoke("set_backup_passphrase", { passphrase });
+}
+
+// 本次运行是否已输入备份密码
+export async function hasBackupPassphrase(): Promise<boolean> {
+  return invoke("has_backup_passphrase");
+}
+
+// 立即按当前设置创建一个加密备份，返回备份文件路径
+export async function runBackup(): Promise<string> {
+  return invoke("run_backup");
+}
+
+// 用新密码重新加密备份文件（不导入账号），返回新文件路径
+export async function reencryptBackup(path: string, oldPassphrase: string, newPassphrase: string): Promise<string> {
+  return invoke("reencrypt_backup", { path, oldPassphrase, newPassphrase });
+}
+
+// 用备份密码解密备份文件并导入其中的账号（preserveIds 同 importAccounts）
+export async function importBackup(
+  path: string,
+  passphrase: string,
+  strategy?: ImportStrategy,
+  preserveIds = false
+): Promise<ImportRecord[]> {
+  return invoke("import_backup", { path, passphrase, strategy, preserveIds });
+}
+
+// 把全部账号导出为标准 age 文件（recipient 为 age1 开头的公钥或密码）
+export async function exportAge(path: string, recipient: string): Promise<void> {
+  return invoke("export_age", { path, recipient });
+}
+
+// 解密 age 文件并导入其中的账号（secret 为密码或 AGE-SECRET-KEY-1 身份密钥，preserveIds 同 importAccounts）
+export async function importAge(
+  path: string,
+  secret: string,
+  strategy?: ImportStrategy,
+  preserveIds = false
+): Promise<ImportRecord[]> {
+  return invoke("import_age", { path, secret, strategy, preserveIds });
+}
+
+// 导入账号（默认保留 Token 较新的一方）；preserveIds 为 true 时新增账号沿用源数据中的 ID，冲突时记录在 id_remap 中
+export async function importAccounts(data: string, strategy?: ImportStrategy, preserveIds = false): Promise<ImportRecord[]> {
+  return invoke("import_accounts", { data, strategy, preserveIds });
+}
+
+// 导入前预检导入数据中同一用户的重复条目
+export async function findImportDuplicates(data: string): Promise<ImportDuplicate[]> {
+  return invoke("find_import_duplicates", { data });
+}
+
+// 从其他工具导出的 JSON 文件导入账号（不传 mapping 时使用内置映射）
+export async function importForeignJson(path: string, mapping?: ForeignMapping): Promise<ImportRecord[]> {
+  return invoke("import_foreign_json", { path, mapping });
+}
+
+// 导出账号清单（不含 Token / Cookies）
+export async function exportManifest(): Promise<string> {
+  return invoke("export_manifest");
+}
+
+// 导入账号清单（为缺失的账号创建需要登录的占位条目）
+export async function importManifest(data: string): Promise<number> {
+  return invoke("import_manifest", { data });
 }
 
 // 获取使用事件
@@ -160,7 +620,122 @@ export async function claimGift(accountId: string): Promise<void> {
 
 // ============ 浏览器登录 ============
 
-// 打开浏览器登录窗口
-export async function startBrowserLogin(): Promise<void> {
-  return invoke("start_browser_login");
+// 只刷新账号的 Cookies：复用登录窗口中的会话捕获完整 Cookies，完成后发送 cookies-refreshed 事件
+export async function refreshAccountCookies(accountId: string): Promise<void> {
+  return invoke("refresh_account_cookies", { accountId });
+}
+
+// 原地重新登录账号，只替换已失效或有更新的凭证，结果通过 relogin-selective-update 事件返回
+export async function smartReloginAccount(accountId: string): Promise<void> {
+  return invoke("smart_relogin_account", { accountId });
+}
+
+// 打开登录窗口测试 Token 捕获，结果通过 capture-test-result 事件返回，不保存任何数据
+export async function testCapture(): Promise<void> {
+  return invoke("test_capture");
+}
+
+// 打开浏览器登录窗口（可选预填邮箱；multiAdd 为 true 时可连续添加多个账号；accountId 为重新登录的目标账号；
+// keepSession 为 true 时沿用已有会话，默认先清空会话以显示账号选择；verbose 为 true 时本次登录发送 login-debug 日志）
+export async function startBrowserLogin(
+  emailHint?: string,
+  multiAdd?: boolean,
+  accountId?: string,
+  keepSession?: boolean,
+  verbose?: boolean
+): Promise<void> {
+  return invoke("start_browser_login", { emailHint, multiAdd, accountId, keepSession, verbose });
+}
+
+// 重新打开登录窗口，从上次访问的页面继续
+export async function retryLogin(): Promise<void> {
+  return invoke("retry_login");
+}
+
+// 最近一次成功捕获时各阶段的耗时，尚无记录时为 null
+export async function lastCaptureTiming(): Promise<CaptureTiming | null> {
+  return invoke("last_capture_timing");
+}
+
+// 获取浏览器登录捕获统计（仅本地）
+export async function getMetrics(): Promise<LoginMetrics> {
+  return invoke("get_metrics");
+}
+
+// 获取注入登录页的脚本（端口默认显示为 {PORT} 占位符）
+export async function getInjectionScript(portPlaceholder?: string): Promise<string> {
+  return invoke("get_injection_script", { portPlaceholder });
+}
+
+// 按当前设置渲染指定端口下实际会注入的脚本（回调令牌以占位符显示）
+export async function renderInjectionForPort(
+  port: number,
+  callbackPath?: string,
+  noncePlaceholder?: string
+): Promise<string> {
+  return invoke("render_injection_for_port", { port, callbackPath, noncePlaceholder });
+}
+
+// 在登录窗口中手动触发 Token 捕获
+export async function requestManualCapture(): Promise<void> {
+  return invoke("request_manual_capture");
+}
+
+// 为登录窗口打开开发者工具（需在设置中开启）
+export async function openLoginDevtools(): Promise<void> {
+  return invoke("open_login_devtools");
+}
+
+// 结束连续添加，关闭登录窗口
+export async function finishBrowserLogin(): Promise<void> {
+  return invoke("finish_browser_login");
+}
+
+// ============ 快照相关 API ============
+
+// 创建账号存储快照
+export async function snapshot(): Promise<string> {
+  return invoke("snapshot");
+}
+
+// 列出所有快照（最新的在前）
+export async function listSnapshots(): Promise<SnapshotInfo[]> {
+  return invoke("list_snapshots");
+}
+
+// 从快照恢复账号存储（会先自动创建一次快照）
+export async function restoreSnapshot(snapshotId: string): Promise<void> {
+  return invoke("restore_snapshot", { snapshotId });
+}
+
+// ============ 设置相关 API ============
+
+// 测试代理是否可以访问 trae.ai（仅支持 http/https 代理）
+export async function testProxy(proxy: string): Promise<ProxyTestResult> {
+  return invoke("test_proxy", { proxy });
+}
+
+// 通过指定代理校验账号，不保存代理到账号上
+export async function validateAccountVia(accountId: string, proxy: string): Promise<ProxyValidation> {
+  return invoke("validate_account_via", { accountId, proxy });
+}
+
+// 获取应用设置
+export async function getSettings(): Promise<AppSettings> {
+  return invoke("get_settings");
+}
+
+// 获取实际生效的配置及每一项的来源
+export async function getEffectiveConfig(): Promise<Record<string, ConfigValue>> {
+  return invoke("get_effective_config");
+}
+
+// 更新应用设置（返回保存后的设置，控制 API 令牌需通过 regenerateControlApiToken 生成）
+export async function updateSettings(settings: AppSettings): Promise<AppSettings> {
+  return invoke("update_settings", { settings });
+}
+
+// 生成新的控制 API 访问令牌，返回的令牌只显示这一次
+export async function regenerateControlApiToken(): Promise<string> {
+  return invoke("regenerate_control_api_token");
 }
@@ -69,9 +69,10 @@ export function AccountCard({ account, usage, selected, onSelect, onContextMenu
 
   return (
     <div
-      className={`account-card ${selected ? "selected" : ""} ${account.is_current ? "current" : ""}`}
+      className={`account-card ${selected ? "selected" : ""} ${account.is_current ? "current" : ""} ${account.is_active ? "" : "disabled"}`}
       onClick={() => onSelect(account.id)}
       onContextMenu={(e) => onContextMenu(e, account.id)}
+      style={account.color ? { borderLeft: `4px solid ${account.color}` } : undefined}
     >
       <div className="card-header">
         <div className="card-checkbox" onClick={(e) => e.stopPropagation()}>
@@ -125,6 +126,8 @@ export function AccountCard({ account, usage, selected, onSelect, onContextMenu
             礼包
           </span>
         )}
+        {account.needs_login && <span className="tag extra">需要登录</span>}
+        {!account.is_active && <span className="tag extra">已停用</span>}
         {account.is_current && (
           <span className="tag current">
             <svg width="12" height="12" viewBox="0 0 24 24" fill="currentColor">
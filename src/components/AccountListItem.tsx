@@ -60,6 +60,7 @@ export function AccountListItem({ account, usage, selected, onSelect, onContextM
       className={`account-list-item ${selected ? "selected" : ""}`}
       onClick={() => onSelect(account.id)}
       onContextMenu={(e) => onContextMenu(e, account.id)}
+      style={account.color ? { borderLeft: `4px solid ${account.color}` } : undefined}
     >
       <div className="list-item-checkbox" onClick={(e) => e.stopPropagation()}>
         <input
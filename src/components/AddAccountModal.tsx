@@ -1,6 +1,7 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useRef } from "react";
 import { listen } from "@tauri-apps/api/event";
 import * as api from "../api";
+import type { DraftBrief, NetworkErrorKind } from "../types";
 
 interface AddAccountModalProps {
   isOpen: boolean;
@@ -19,32 +20,115 @@ export function AddAccountModal({ isOpen, onClose, onAdd, onToast, onAccountAdde
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState("");
   const [browserLoginStarted, setBrowserLoginStarted] = useState(false);
+  const [multiAdd, setMultiAdd] = useState(false);
+  const [keepSession, setKeepSession] = useState(false);
+  const [verbose, setVerbose] = useState(false);
+  const [debugLines, setDebugLines] = useState<string[]>([]);
+  const [addedCount, setAddedCount] = useState(0);
+  const [drafts, setDrafts] = useState<DraftBrief[]>([]);
+  // 事件监听只注册一次，用 ref 读取最新的连续添加开关
+  const multiAddRef = useRef(false);
+  multiAddRef.current = multiAdd;
 
   // 监听浏览器登录事件
   useEffect(() => {
     const unlistenSuccess = listen<string>("login-success", (event) => {
       onToast?.("success", `浏览器登录成功: ${event.payload}`);
       onAccountAdded?.();
+      if (multiAddRef.current) {
+        // 连续添加模式：窗口会自动回到登录页，保持等待状态
+        setAddedCount((count) => count + 1);
+        return;
+      }
+      setBrowserLoginStarted(false);
+      handleCloseInternal();
+    });
+
+    // 详细捕获日志（只在开启了详细日志的登录中发送），保留最近 20 条
+    const unlistenDebug = listen<{ message: string; url: string }>("login-debug", (event) => {
+      setDebugLines((lines) => [...lines, event.payload.message].slice(-20));
+    });
+
+    // 重新登录的是 Trae IDE 当前使用的账号，凭证已原地更新
+    const unlistenRefreshed = listen<{ id: string; email: string }>("active-account-refreshed", (event) => {
+      onToast?.("success", `当前账号凭证已更新: ${event.payload.email}`);
+      onAccountAdded?.();
+      if (multiAddRef.current) return;
       setBrowserLoginStarted(false);
       handleCloseInternal();
     });
 
     const unlistenFailed = listen<string>("login-failed", (event) => {
       setError(event.payload || "登录失败");
-      setBrowserLoginStarted(false);
+      if (!multiAddRef.current) {
+        setBrowserLoginStarted(false);
+      }
+    });
+
+    const unlistenDuplicate = listen<{ id: string; email: string }>("account-duplicate", (event) => {
+      setError(`该账号已存在: ${event.payload.email || event.payload.id}`);
+    });
+
+    // 捕获到 Token 但保存失败：窗口保持打开，可重试
+    const unlistenStoreError = listen<{ code: string; network_error: NetworkErrorKind | null; message: string }>("login-store-error", (event) => {
+      const hints: Record<string, string> = {
+        limit_reached: "账号数量已达上限",
+        read_only: "账号数据为只读模式",
+        storage: "账号数据保存失败",
+        invalid_token: "Token 无效或验证失败",
+        credential_mismatch: "Token 与 Cookies 不属于同一账号，请重新登录",
+      };
+      const networkHints: Record<NetworkErrorKind, string> = {
+        dns: "无法解析 trae.ai 域名，请检查网络连接",
+        tls: "安全连接失败，请检查代理或抓包软件设置",
+        timeout: "请求超时，请检查网络或代理",
+        connection_refused: "连接被拒绝，请检查代理是否已启动",
+        other: "网络连接失败",
+        offline: "已开启离线模式，请先在设置中关闭",
+      };
+      const networkError = event.payload.network_error;
+      const hint = (networkError && networkHints[networkError]) || hints[event.payload.code] || "添加账号失败";
+      setError(`${hint}: ${event.payload.message}`);
+    });
+
+    const unlistenReady = listen("ready-for-next", () => {
+      setError("");
+      onToast?.("info", "登录窗口已重置，可以登录下一个账号");
     });
 
     const unlistenCancelled = listen("login-cancelled", () => {
       setBrowserLoginStarted(false);
     });
 
+    const unlistenTooShort = listen<{ length: number; min_length: number }>("login-token-too-short", (event) => {
+      setError(`捕获到的 Token 过短（${event.payload.length} < ${event.payload.min_length}），已忽略；如确认有效，可在设置中调低最短 Token 长度`);
+    });
+
+    const unlistenDraft = listen<DraftBrief>("login-draft-saved", (event) => {
+      setDrafts((list) => [...list, event.payload]);
+    });
+
     return () => {
       unlistenSuccess.then((fn) => fn());
       unlistenFailed.then((fn) => fn());
       unlistenCancelled.then((fn) => fn());
+      unlistenReady.then((fn) => fn());
+      unlistenDuplicate.then((fn) => fn());
+      unlistenStoreError.then((fn) => fn());
+      unlistenRefreshed.then((fn) => fn());
+      unlistenDraft.then((fn) => fn());
+      unlistenTooShort.then((fn) => fn());
+      unlistenDebug.then((fn) => fn());
     };
   }, []);
 
+  // 打开时加载添加失败保存的草稿
+  useEffect(() => {
+    if (isOpen) {
+      api.listDraftCaptures().then(setDrafts).catch(console.error);
+    }
+  }, [isOpen]);
+
   if (!isOpen) return null;
 
   // 从输入中提取 Token（优化：增强验证和清理）
@@ -155,6 +239,25 @@ export function AddAccountModal({ isOpen, onClose, onAdd, onToast, onAccountAdde
         return;
       }
 
+      // Token 属于已有账号时改为更新该账号的 Token，避免添加时报重复
+      const prediction = await api.predictLogin(token).catch(() => null);
+      if (prediction?.kind === "would_update") {
+        if (!confirm(`该 Token 属于已有账号 ${prediction.email || prediction.existing_id}，是否用它更新该账号的 Token？`)) {
+          setLoading(false);
+          return;
+        }
+        await api.updateAccountToken(prediction.existing_id, token);
+        if (cookiesInput.trim()) {
+          await api.updateCookies(prediction.existing_id, cookiesInput.trim());
+        }
+        onToast?.("success", "已更新账号 Token");
+        onAccountAdded?.();
+        setTokenInput("");
+        setCookiesInput("");
+        onClose();
+        return;
+      }
+
       // 清理 Cookies（如果有）
       const cookies = cookiesInput.trim() || undefined;
       await onAdd(token, cookies);
@@ -173,9 +276,11 @@ export function AddAccountModal({ isOpen, onClose, onAdd, onToast, onAccountAdde
     setLoading(true);
     setError("");
     setBrowserLoginStarted(true);
+    setAddedCount(0);
+    setDebugLines([]);
 
     try {
-      await api.startBrowserLogin();
+      await api.startBrowserLogin(undefined, multiAdd, undefined, keepSession, verbose);
     } catch (err: any) {
       setError(err.message || "打开登录窗口失败");
       setBrowserLoginStarted(false);
@@ -184,6 +289,67 @@ export function AddAccountModal({ isOpen, onClose, onAdd, onToast, onAccountAdde
     }
   };
 
+  // 登录失败后，从上次访问的页面重新打开登录窗口
+  const handleRetryLogin = async () => {
+    setError("");
+    setBrowserLoginStarted(true);
+    try {
+      await api.retryLogin();
+    } catch (err: any) {
+      setError(err.message || "打开登录窗口失败");
+      setBrowserLoginStarted(false);
+    }
+  };
+
+  // 自动捕获未触发时，手动触发一次捕获
+  const handleManualCapture = async () => {
+    try {
+      await api.requestManualCapture();
+    } catch (err: any) {
+      setError(err.message || "手动捕获失败");
+    }
+  };
+
+  // 用草稿中的凭证重试添加
+  const handleCommitDraft = async (draftId: string) => {
+    setError("");
+    try {
+      const account = await api.commitDraft(draftId);
+      setDrafts((list) => list.filter((d) => d.id !== draftId));
+      onToast?.("success", `账号添加成功: ${account.email}`);
+      onAccountAdded?.();
+    } catch (err: any) {
+      setError(err.message || "重试添加失败");
+    }
+  };
+
+  const handleDiscardDraft = async (draftId: string) => {
+    try {
+      await api.discardDraft(draftId);
+      setDrafts((list) => list.filter((d) => d.id !== draftId));
+    } catch (err: any) {
+      setError(err.message || "删除草稿失败");
+    }
+  };
+
+  // 打开登录窗口的开发者工具，便于排查捕获失败
+  const handleOpenDevtools = async () => {
+    try {
+      await api.openLoginDevtools();
+    } catch (err: any) {
+      setError(err.message || "打开开发者工具失败");
+    }
+  };
+
+  // 结束连续添加
+  const handleFinishMultiAdd = async () => {
+    try {
+      await api.finishBrowserLogin();
+    } catch (err: any) {
+      setError(err.message || "关闭登录窗口失败");
+    }
+  };
+
   const handleCloseInternal = () => {
     setError("");
     setTokenInput("");
@@ -274,11 +440,78 @@ export function AddAccountModal({ isOpen, onClose, onAdd, onToast, onAccountAdde
                 </div>
                 <h3>浏览器授权登录</h3>
                 <p>将打开一个登录窗口，在其中登录 trae.ai 账号，系统将自动提取 Cookies 并添加账号</p>
+                <label style={{ display: "inline-flex", alignItems: "center", gap: "6px", marginTop: "8px" }}>
+                  <input
+                    type="checkbox"
+                    checked={multiAdd}
+                    onChange={(e) => setMultiAdd(e.target.checked)}
+                    disabled={browserLoginStarted}
+                  />
+                  连续添加多个账号（登录成功后不关闭窗口）
+                </label>
+                <label style={{ display: "inline-flex", alignItems: "center", gap: "6px", marginTop: "8px" }}>
+                  <input
+                    type="checkbox"
+                    checked={keepSession}
+                    onChange={(e) => setKeepSession(e.target.checked)}
+                    disabled={browserLoginStarted}
+                  />
+                  继续使用已登录的会话（默认先退出，以便选择账号）
+                </label>
+                <label style={{ display: "inline-flex", alignItems: "center", gap: "6px", marginTop: "8px" }}>
+                  <input
+                    type="checkbox"
+                    checked={verbose}
+                    onChange={(e) => setVerbose(e.target.checked)}
+                    disabled={browserLoginStarted}
+                  />
+                  显示详细捕获日志（仅本次登录）
+                </label>
+                {verbose && debugLines.length > 0 && (
+                  <pre style={{ marginTop: "8px", maxHeight: "120px", overflow: "auto", fontSize: "12px" }}>
+                    {debugLines.join("\n")}
+                  </pre>
+                )}
                 {browserLoginStarted && (
                   <p style={{ color: "var(--color-warning, #f0a030)", marginTop: "8px" }}>
                     登录窗口已打开，请在窗口中完成登录...
+                    {multiAdd && addedCount > 0 && ` 已添加 ${addedCount} 个账号`}
+                  </p>
+                )}
+                {browserLoginStarted && (
+                  <p style={{ marginTop: "8px" }}>
+                    已登录但没有反应？
+                    <button type="button" className="link-btn" onClick={handleManualCapture}>
+                      手动捕获
+                    </button>
+                    <button type="button" className="link-btn" onClick={handleOpenDevtools}>
+                      打开调试工具
+                    </button>
                   </p>
                 )}
+                {error && !browserLoginStarted && (
+                  <p style={{ marginTop: "8px" }}>
+                    <button type="button" className="link-btn" onClick={handleRetryLogin}>
+                      从上次的页面重试
+                    </button>
+                  </p>
+                )}
+                {drafts.length > 0 && (
+                  <div style={{ marginTop: "8px" }}>
+                    <p>以下登录已捕获凭证但添加失败，可直接重试：</p>
+                    {drafts.map((draft) => (
+                      <p key={draft.id} style={{ marginTop: "4px" }}>
+                        {new Date(draft.created_at * 1000).toLocaleString()}（{draft.error}）
+                        <button type="button" className="link-btn" onClick={() => handleCommitDraft(draft.id)}>
+                          重试添加
+                        </button>
+                        <button type="button" className="link-btn" onClick={() => handleDiscardDraft(draft.id)}>
+                          删除
+                        </button>
+                      </p>
+                    ))}
+                  </div>
+                )}
               </div>
 
               {error && <div className="error-message">{error}</div>}
@@ -354,6 +587,10 @@ export function AddAccountModal({ isOpen, onClose, onAdd, onToast, onAccountAdde
             >
               {loading ? "读取中..." : "读取本地账号"}
             </button>
+          ) : mode === "browser" && multiAdd && browserLoginStarted ? (
+            <button type="button" className="primary" onClick={handleFinishMultiAdd}>
+              完成添加
+            </button>
           ) : mode === "browser" ? (
             <button
               type="button"
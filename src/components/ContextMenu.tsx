@@ -1,4 +1,5 @@
 import { useEffect, useRef } from "react";
+import type { BrowserCookieFormat } from "../types";
 
 interface ContextMenuProps {
   x: number;
@@ -8,10 +9,23 @@ interface ContextMenuProps {
   onRefresh: () => void;
   onUpdateToken: () => void;
   onCopyToken: () => void;
+  onCopyCookieHeader: () => void;
+  onExportBrowserSession: (format: BrowserCookieFormat) => void;
+  onExportTokenOnly: () => void;
+  onOpenBilling: () => void;
+  onRelogin: () => void;
+  onRefreshCookies: () => void;
+  onSmartRelogin: () => void;
   onSwitchAccount: () => void;
   onClaimGift: () => void;
+  onToggleEnabled: () => void;
+  onToggleAutoRefresh: () => void;
+  onTogglePrimary: () => void;
   onDelete: () => void;
   isCurrent?: boolean; // 是否是当前使用的账号
+  isEnabled?: boolean; // 账号是否启用
+  isAutoRefresh?: boolean; // 账号是否参与 Token 自动刷新
+  isPrimary?: boolean; // 是否为主账号
 }
 
 export function ContextMenu({
@@ -22,10 +36,23 @@ export function ContextMenu({
   onRefresh,
   onUpdateToken,
   onCopyToken,
+  onCopyCookieHeader,
+  onExportBrowserSession,
+  onExportTokenOnly,
+  onOpenBilling,
+  onRelogin,
+  onRefreshCookies,
+  onSmartRelogin,
   onSwitchAccount,
   onClaimGift,
+  onToggleEnabled,
+  onToggleAutoRefresh,
+  onTogglePrimary,
   onDelete,
   isCurrent = false,
+  isEnabled = true,
+  isAutoRefresh = true,
+  isPrimary = false,
 }: ContextMenuProps) {
   const menuRef = useRef<HTMLDivElement>(null);
 
@@ -68,6 +95,38 @@ export function ContextMenu({
           <span className="icon">🔑</span>
           复制 Token
         </div>
+        <div className="context-menu-item" onClick={onCopyCookieHeader}>
+          <span className="icon">🍪</span>
+          复制 Cookie 请求头
+        </div>
+        <div className="context-menu-item" onClick={() => onExportBrowserSession("netscape")}>
+          <span className="icon">📄</span>
+          导出 cookies.txt
+        </div>
+        <div className="context-menu-item" onClick={() => onExportBrowserSession("json")}>
+          <span className="icon">🧩</span>
+          导出浏览器扩展 Cookies JSON
+        </div>
+        <div className="context-menu-item" onClick={onExportTokenOnly}>
+          <span className="icon">🎫</span>
+          分享临时 Token
+        </div>
+        <div className="context-menu-item" onClick={onRelogin}>
+          <span className="icon">🔄</span>
+          重新登录
+        </div>
+        <div className="context-menu-item" onClick={onRefreshCookies}>
+          <span className="icon">🍪</span>
+          只刷新 Cookies
+        </div>
+        <div className="context-menu-item" onClick={onSmartRelogin}>
+          <span className="icon">🧩</span>
+          重新登录（只更新失效凭证）
+        </div>
+        <div className="context-menu-item" onClick={onOpenBilling}>
+          <span className="icon">📊</span>
+          打开用量页面
+        </div>
         <div
           className={`context-menu-item ${isCurrent ? "disabled" : ""}`}
           onClick={isCurrent ? undefined : onSwitchAccount}
@@ -80,6 +139,18 @@ export function ContextMenu({
           <span className="icon">🎁</span>
           获取礼包
         </div>
+        <div className="context-menu-item" onClick={onToggleEnabled}>
+          <span className="icon">{isEnabled ? "⏸" : "▶"}</span>
+          {isEnabled ? "停用账号" : "启用账号"}
+        </div>
+        <div className="context-menu-item" onClick={onToggleAutoRefresh}>
+          <span className="icon">{isAutoRefresh ? "🔒" : "🔄"}</span>
+          {isAutoRefresh ? "关闭自动刷新" : "开启自动刷新"}
+        </div>
+        <div className="context-menu-item" onClick={onTogglePrimary}>
+          <span className="icon">⭐</span>
+          {isPrimary ? "取消主账号" : "设为主账号"}
+        </div>
         <div className="context-menu-divider" />
         <div className="context-menu-item danger" onClick={onDelete}>
           <span className="icon">🗑</span>
@@ -1,5 +1,5 @@
 import { useState } from "react";
-import type { UsageSummary } from "../types";
+import type { HealthRecord, UsageSummary } from "../types";
 
 interface DetailModalProps {
   isOpen: boolean;
@@ -12,6 +12,7 @@ interface DetailModalProps {
     plan_type: string;
     cookies?: string;
     jwt_token?: string | null;
+    health_history?: HealthRecord[];
   } | null;
   usage: UsageSummary | null;
 }
@@ -72,6 +73,20 @@ export function DetailModal({ isOpen, onClose, account, usage }: DetailModalProp
               <span className="detail-label">重置时间</span>
               <span className="detail-value">{usage ? formatDate(usage.reset_time) : "-"}</span>
             </div>
+            {account.health_history && account.health_history.length > 0 && (
+              <div className="detail-row">
+                <span className="detail-label">校验记录</span>
+                <span className="detail-value health-history">
+                  {account.health_history.map((record) => (
+                    <span
+                      key={record.checked_at}
+                      className={`health-tick ${record.status}`}
+                      title={`${formatDate(record.checked_at)} ${record.status === "valid" ? "有效" : record.status === "expired" ? "已过期" : "未知"}`}
+                    />
+                  ))}
+                </span>
+              </div>
+            )}
           </div>
 
           {/* Token 信息 */}
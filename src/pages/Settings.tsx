@@ -1,6 +1,8 @@
 import { useState, useEffect } from "react";
-import { open } from "@tauri-apps/plugin-dialog";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { listen } from "@tauri-apps/api/event";
 import * as api from "../api";
+import type { AppSettings, CaptureTestResult } from "../types";
 
 interface SettingsProps {
   onToast?: (type: "success" | "error" | "warning" | "info", message: string) => void;
@@ -13,6 +15,9 @@ export function Settings({ onToast }: SettingsProps) {
   const [traePath, setTraePath] = useState<string>("");
   const [traePathLoading, setTraePathLoading] = useState(false);
   const [scanning, setScanning] = useState(false);
+  const [appSettings, setAppSettings] = useState<AppSettings | null>(null);
+  const [hasPassphrase, setHasPassphrase] = useState(false);
+  const [newControlApiToken, setNewControlApiToken] = useState<string>("");
 
   // 加载 Trae IDE 机器码
   const loadTraeMachineId = async () => {
@@ -45,8 +50,35 @@ export function Settings({ onToast }: SettingsProps) {
   useEffect(() => {
     loadTraeMachineId();
     loadTraePath();
+    loadAppSettings();
   }, []);
 
+  // 测试捕获结果（只包含 Token 长度和 Cookie 名称）
+  useEffect(() => {
+    const unlisten = listen<CaptureTestResult>("capture-test-result", (event) => {
+      const result = event.payload;
+      const missing = result.missing_critical_cookies.length > 0
+        ? `，缺少关键 Cookie: ${result.missing_critical_cookies.join(", ")}`
+        : "";
+      onToast?.(
+        missing ? "warning" : "success",
+        `捕获正常：Token 长度 ${result.token_length}，Cookies ${result.cookie_names.length} 个${missing}`
+      );
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 打开登录窗口测试 Token 捕获，不保存任何数据
+  const handleTestCapture = async () => {
+    try {
+      await api.testCapture();
+    } catch (err: any) {
+      onToast?.("error", err.message || "打开登录窗口失败");
+    }
+  };
+
   // 复制 Trae IDE 机器码
   const handleCopyTraeMachineId = async () => {
     try {
@@ -75,6 +107,302 @@ export function Settings({ onToast }: SettingsProps) {
     }
   };
 
+  // 加载应用设置
+  const loadAppSettings = async () => {
+    try {
+      setAppSettings(await api.getSettings());
+      setHasPassphrase(await api.hasBackupPassphrase());
+    } catch (err: any) {
+      console.error("加载设置失败:", err);
+    }
+  };
+
+  // 保存应用设置
+  const saveAppSettings = async (next: AppSettings) => {
+    try {
+      setAppSettings(await api.updateSettings(next));
+      onToast?.("success", "设置已保存");
+    } catch (err: any) {
+      onToast?.("error", err.message || "保存设置失败");
+      loadAppSettings();
+    }
+  };
+
+  // 生成新的控制 API 令牌（设置中只保存摘要，令牌只在这里显示一次）
+  const handleRegenerateControlApiToken = async () => {
+    try {
+      const token = await api.regenerateControlApiToken();
+      setNewControlApiToken(token);
+      setAppSettings(await api.getSettings());
+      try {
+        await navigator.clipboard.writeText(token);
+        onToast?.("success", "新令牌已复制到剪贴板，离开此页后不再显示");
+      } catch {
+        onToast?.("warning", "新令牌已生成，请立即复制保存，离开此页后不再显示");
+      }
+      return true;
+    } catch (err: any) {
+      onToast?.("error", err.message || "生成令牌失败");
+      return false;
+    }
+  };
+
+  // 开启或关闭控制 API，首次开启时先生成令牌
+  const handleToggleControlApi = async (enabled: boolean) => {
+    if (!appSettings) return;
+    if (enabled && !appSettings.control_api_token_hash && !(await handleRegenerateControlApiToken())) {
+      return;
+    }
+    saveAppSettings({ ...appSettings, control_api_enabled: enabled });
+  };
+
+  // 开启或关闭离线模式
+  const handleToggleOffline = async (enabled: boolean) => {
+    try {
+      setAppSettings(await api.setOfflineMode(enabled));
+      onToast?.("success", enabled ? "已开启离线模式" : "已关闭离线模式");
+    } catch (err: any) {
+      onToast?.("error", err.message || "切换离线模式失败");
+      loadAppSettings();
+    }
+  };
+
+  // 选择备份目录
+  const handleChooseBackupDir = async () => {
+    if (!appSettings) return;
+    try {
+      const selected = await open({ directory: true, multiple: false, title: "选择备份目录" });
+      if (selected) {
+        saveAppSettings({ ...appSettings, backup_dir: selected as string });
+      }
+    } catch (err: any) {
+      onToast?.("error", err.message || "选择目录失败");
+    }
+  };
+
+  // 立即创建一个备份
+  const handleRunBackup = async () => {
+    try {
+      const path = await api.runBackup();
+      onToast?.("success", `备份已创建：${path}`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "创建备份失败");
+    }
+  };
+
+  // 从加密备份恢复账号（每次输入该备份的密码）
+  const handleImportBackup = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        defaultPath: appSettings?.backup_dir ?? undefined,
+        filters: [{ name: "账号备份", extensions: ["trbak"] }],
+        title: "选择备份文件",
+      });
+      if (!selected) return;
+      const passphrase = prompt("请输入该备份的密码");
+      if (!passphrase) return;
+      const records = await api.importBackup(selected as string, passphrase);
+      const added = records.filter((r) => r.action === "added").length;
+      const overwritten = records.filter((r) => r.action === "overwritten").length;
+      onToast?.("success", `已从备份导入 ${added} 个账号，更新 ${overwritten} 个已存在账号`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "恢复备份失败");
+    }
+  };
+
+  // 用新密码重新加密备份，便于分享给不知道原密码的人
+  const handleReencryptBackup = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        defaultPath: appSettings?.backup_dir ?? undefined,
+        filters: [{ name: "账号备份", extensions: ["trbak"] }],
+        title: "选择要重新加密的备份文件",
+      });
+      if (!selected) return;
+      const oldPassphrase = prompt("请输入该备份的原密码") || "";
+      if (!oldPassphrase) return;
+      const newPassphrase = prompt("请输入分享用的新密码");
+      if (!newPassphrase) return;
+      const path = await api.reencryptBackup(selected as string, oldPassphrase, newPassphrase);
+      onToast?.("success", `已生成分享用的备份：${path}`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "重新加密失败");
+    }
+  };
+
+  // 导出为 age 文件，便于用 age 工具管理
+  const handleExportAge = async () => {
+    try {
+      const recipient = prompt("请输入 age 公钥（age1 开头）或加密密码");
+      if (!recipient) return;
+      const path = await save({
+        defaultPath: "trae-accounts.age",
+        filters: [{ name: "age 加密文件", extensions: ["age"] }],
+        title: "导出为 age 文件",
+      });
+      if (!path) return;
+      await api.exportAge(path, recipient);
+      onToast?.("success", `已导出到 ${path}`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "导出 age 文件失败");
+    }
+  };
+
+  // 导入 age 文件（密码或身份密钥解密）
+  const handleImportAge = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        filters: [{ name: "age 加密文件", extensions: ["age"] }],
+        title: "选择 age 文件",
+      });
+      if (!selected) return;
+      const secret = prompt("请输入该文件的密码或 age 身份密钥（AGE-SECRET-KEY-1 开头）");
+      if (!secret) return;
+      const records = await api.importAge(selected as string, secret);
+      const added = records.filter((r) => r.action === "added").length;
+      const overwritten = records.filter((r) => r.action === "overwritten").length;
+      onToast?.("success", `已导入 ${added} 个账号，更新 ${overwritten} 个已存在账号`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "导入 age 文件失败");
+    }
+  };
+
+  // 打开账号数据目录
+  const handleRevealStore = async () => {
+    try {
+      await api.revealStoreInExplorer();
+    } catch (err: any) {
+      onToast?.("error", err.message || "打开数据目录失败");
+    }
+  };
+
+  // 重写账号数据文件
+  const handleCompactStore = async () => {
+    try {
+      const report = await api.compactStore();
+      onToast?.("success", `账号数据已整理，节省 ${report.reclaimed_bytes} 字节`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "整理账号数据失败");
+    }
+  };
+
+  // 显示占用空间最大的几个账号
+  const handleShowAccountSizes = async () => {
+    try {
+      const sizes = await api.accountSizes();
+      if (sizes.length === 0) {
+        onToast?.("info", "暂无账号");
+        return;
+      }
+      const total = sizes.reduce((sum, s) => sum + s.total_bytes, 0);
+      const top = sizes
+        .slice(0, 3)
+        .map((s) => `${s.email || s.account_id} ${(s.total_bytes / 1024).toFixed(1)} KB（Cookies ${(s.cookies_bytes / 1024).toFixed(1)} KB）`)
+        .join("；");
+      onToast?.("info", `共 ${(total / 1024).toFixed(1)} KB，占用最大：${top}`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "统计占用失败");
+    }
+  };
+
+  // 列出同一用户绑定不同邮箱的账号，只提示不合并
+  const handleFindLinkedAccounts = async () => {
+    try {
+      const groups = await api.findLinkedAccounts();
+      if (groups.length === 0) {
+        onToast?.("info", "没有发现绑定多个邮箱的用户");
+        return;
+      }
+      const summary = groups.map((g) => g.emails.join(" / ")).join("；");
+      onToast?.("info", `发现 ${groups.length} 组同一用户的账号：${summary}`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "查找关联账号失败");
+    }
+  };
+
+  // 一次检查所有账号的问题，按严重程度汇总
+  const handleDiagnoseAll = async () => {
+    try {
+      const problems = await api.diagnoseAll();
+      if (problems.length === 0) {
+        onToast?.("success", "所有账号均未发现问题");
+        return;
+      }
+      const labels: Record<string, string> = {
+        expired: "已失效",
+        missing_cookies: "缺少 Cookies",
+        expiry_mismatch: "Token 与 Cookie 过期时间不一致",
+        expiring_soon: "即将过期",
+        unknown: "状态未知",
+      };
+      const summary = Object.entries(labels)
+        .map(([kind, label]) => {
+          const emails = problems.filter((p) => p.kind === kind).map((p) => p.email);
+          return emails.length > 0 ? `${label} ${emails.length} 个（${emails.slice(0, 3).join("、")}${emails.length > 3 ? " 等" : ""}）` : "";
+        })
+        .filter(Boolean)
+        .join("；");
+      const severe = problems.some((p) => p.kind === "expired" || p.kind === "missing_cookies" || p.kind === "expiry_mismatch");
+      onToast?.(severe ? "warning" : "info", `发现 ${problems.length} 个问题：${summary}`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "检查账号问题失败");
+    }
+  };
+
+  // 规范化所有账号的 Cookies 编码
+  const handleNormalizeCookies = async () => {
+    try {
+      const changed = await api.normalizeCookies();
+      onToast?.("success", changed > 0 ? `已修复 ${changed} 个账号的 Cookies` : "所有账号的 Cookies 均已是规范格式");
+    } catch (err: any) {
+      onToast?.("error", err.message || "修复 Cookies 失败");
+    }
+  };
+
+  // 按邮箱域名给账号打标签
+  const handleAutoTagByDomain = async () => {
+    try {
+      const tagged = await api.autoTagByDomain();
+      onToast?.("success", tagged > 0 ? `已为 ${tagged} 个账号添加邮箱域名标签` : "没有需要添加标签的账号");
+    } catch (err: any) {
+      onToast?.("error", err.message || "添加标签失败");
+    }
+  };
+
+  // 重置所有账号的状态（如服务异常导致误判为失效时）
+  const handleResetStatuses = async () => {
+    if (!confirm("确定要把所有账号的状态重置为未知吗？账号凭证不会被修改。")) return;
+    try {
+      const reset = await api.resetStatuses();
+      onToast?.("success", reset > 0 ? `已重置 ${reset} 个账号的状态` : "所有账号的状态已是未知");
+    } catch (err: any) {
+      onToast?.("error", err.message || "重置状态失败");
+    }
+  };
+
+  // 导出诊断信息，用于反馈问题
+  const handleExportDiagnostics = async () => {
+    try {
+      const data = await api.exportDiagnostics();
+      const blob = new Blob([data], { type: "application/json" });
+      const url = URL.createObjectURL(blob);
+      const a = document.createElement("a");
+      a.href = url;
+      const fileName = `trae-diagnostics-${new Date().toISOString().split("T")[0]}.json`;
+      a.download = fileName;
+      document.body.appendChild(a);
+      a.click();
+      document.body.removeChild(a);
+      URL.revokeObjectURL(url);
+      onToast?.("success", `诊断信息已导出到下载文件夹：${fileName}`);
+    } catch (err: any) {
+      onToast?.("error", err.message || "导出诊断信息失败");
+    }
+  };
+
   // 自动扫描 Trae IDE 路径
   const handleScanTraePath = async () => {
     setScanning(true);
@@ -264,8 +592,248 @@ export function Settings({ onToast }: SettingsProps) {
         </div>
       </div>
 
+      <div className="settings-section">
+        <h3>本地控制 API</h3>
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">启用控制 API</div>
+            <div className="setting-desc">
+              仅监听 127.0.0.1，供脚本等外部工具查询、切换、校验账号（需携带 Bearer Token）
+            </div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.control_api_enabled ?? false}
+              disabled={!appSettings}
+              onChange={(e) => handleToggleControlApi(e.target.checked)}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        {appSettings?.control_api_enabled && (
+          <div className="setting-item">
+            <div className="setting-info">
+              <div className="setting-label">访问地址与令牌</div>
+              <div className="setting-desc">
+                http://127.0.0.1:{appSettings.control_api_port} ·{" "}
+                {newControlApiToken || "令牌只在生成时显示一次，遗失后请重新生成"}
+              </div>
+            </div>
+            <button className="setting-btn" onClick={handleRegenerateControlApiToken}>
+              重新生成
+            </button>
+          </div>
+        )}
+      </div>
+
+      <div className="settings-section">
+        <h3>定时备份</h3>
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">启用定时备份</div>
+            <div className="setting-desc">按间隔把全部账号加密备份到指定目录，只保留最新的若干个</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.backup_enabled ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, backup_enabled: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">备份目录</div>
+            <div className="setting-desc">{appSettings?.backup_dir || "未设置，建议选择应用数据目录以外的位置"}</div>
+          </div>
+          <button className="setting-btn" onClick={handleChooseBackupDir} disabled={!appSettings}>选择</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">备份密码</div>
+            <div className="setting-desc">
+              用于加密备份文件，恢复时需要；忘记密码将无法恢复。密码只保存在内存中，每次启动应用后需要重新输入，
+              {hasPassphrase ? "本次运行已输入" : "本次运行尚未输入，定时备份会失败"}
+            </div>
+          </div>
+          <input
+            type="password"
+            className="setting-select"
+            placeholder={hasPassphrase ? "已输入，留空保持不变" : "输入备份密码"}
+            disabled={!appSettings}
+            onBlur={async (e) => {
+              const value = e.target.value;
+              if (!value) return;
+              try {
+                await api.setBackupPassphrase(value);
+                setHasPassphrase(true);
+                e.target.value = "";
+                onToast?.("success", "已设置本次运行的备份密码");
+              } catch (err: any) {
+                onToast?.("error", err.message || "设置备份密码失败");
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">备份间隔</div>
+            <div className="setting-desc">每隔多少小时创建一个备份</div>
+          </div>
+          <input
+            type="number"
+            min={1}
+            className="setting-select"
+            defaultValue={appSettings?.backup_interval_hours ?? ""}
+            key={`backup-interval-${appSettings?.backup_interval_hours}`}
+            disabled={!appSettings}
+            onBlur={(e) => {
+              if (!appSettings) return;
+              const value = parseInt(e.target.value, 10);
+              if (Number.isFinite(value) && value > 0 && value !== appSettings.backup_interval_hours) {
+                saveAppSettings({ ...appSettings, backup_interval_hours: value });
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">保留数量</div>
+            <div className="setting-desc">只保留最新的若干个备份，更早的备份会被删除</div>
+          </div>
+          <input
+            type="number"
+            min={1}
+            className="setting-select"
+            defaultValue={appSettings?.backup_retention ?? ""}
+            key={`backup-retention-${appSettings?.backup_retention}`}
+            disabled={!appSettings}
+            onBlur={(e) => {
+              if (!appSettings) return;
+              const value = parseInt(e.target.value, 10);
+              if (Number.isFinite(value) && value > 0 && value !== appSettings.backup_retention) {
+                saveAppSettings({ ...appSettings, backup_retention: value });
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">立即备份</div>
+            <div className="setting-desc">按上面的设置马上创建一个备份</div>
+          </div>
+          <button className="setting-btn" onClick={handleRunBackup}>备份</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">从备份恢复</div>
+            <div className="setting-desc">选择备份文件，用当前备份密码解密后导入其中的账号</div>
+          </div>
+          <button className="setting-btn" onClick={handleImportBackup}>恢复</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">分享备份</div>
+            <div className="setting-desc">用新密码重新加密一个备份文件供他人导入，不会暴露原备份密码</div>
+          </div>
+          <button className="setting-btn" onClick={handleReencryptBackup}>重新加密</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">age 文件</div>
+            <div className="setting-desc">导出为标准 age 文件（公钥或密码加密），或用密码、身份密钥导入 age 文件</div>
+          </div>
+          <button className="setting-btn" onClick={handleExportAge}>导出</button>
+          <button className="setting-btn" onClick={handleImportAge}>导入</button>
+        </div>
+      </div>
+
       <div className="settings-section">
         <h3>数据管理</h3>
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">数据目录</div>
+            <div className="setting-desc">在文件管理器中打开账号数据所在目录</div>
+          </div>
+          <button className="setting-btn" onClick={handleRevealStore}>打开</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">整理数据文件</div>
+            <div className="setting-desc">重写账号数据文件，清理旧版本遗留的字段</div>
+          </div>
+          <button className="setting-btn" onClick={handleCompactStore}>整理</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">账号占用空间</div>
+            <div className="setting-desc">估算每个账号在数据文件中的大小，找出 Cookies 过大的账号</div>
+          </div>
+          <button className="setting-btn" onClick={handleShowAccountSizes}>统计</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">关联账号</div>
+            <div className="setting-desc">找出用户 ID 相同但邮箱不同的账号（同一用户绑定了多个邮箱），仅供核对</div>
+          </div>
+          <button className="setting-btn" onClick={handleFindLinkedAccounts}>查找</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">检查账号问题</div>
+            <div className="setting-desc">一次列出所有账号的问题：已失效、缺少 Cookies、即将过期、状态未知，按严重程度排序；只读取本地数据，不发起网络请求</div>
+          </div>
+          <button className="setting-btn" onClick={handleDiagnoseAll}>检查</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">修复 Cookies 编码</div>
+            <div className="setting-desc">将旧版本保存的 Cookies 统一为规范格式，已规范的账号不受影响</div>
+          </div>
+          <button className="setting-btn" onClick={handleNormalizeCookies}>修复</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">按邮箱域名打标签</div>
+            <div className="setting-desc">给每个账号添加邮箱域名标签（如 example.com），便于筛选同一组织的账号；手动删除的标签不会再加回</div>
+          </div>
+          <button className="setting-btn" onClick={handleAutoTagByDomain}>添加</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">重置账号状态</div>
+            <div className="setting-desc">清除所有账号缓存的状态、校验时间和手动设置的状态，下次校验时重新判断；不修改凭证</div>
+          </div>
+          <button className="setting-btn" onClick={handleResetStatuses}>重置</button>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">诊断信息</div>
+            <div className="setting-desc">导出设置、账号清单与登录统计用于反馈问题，不包含 Token 和 Cookies</div>
+          </div>
+          <button className="setting-btn" onClick={handleExportDiagnostics}>导出</button>
+        </div>
+
         <div className="setting-item">
           <div className="setting-info">
             <div className="setting-label">导出数据</div>
@@ -282,6 +850,391 @@ export function Settings({ onToast }: SettingsProps) {
           <button className="setting-btn">导入</button>
         </div>
 
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">状态有效期</div>
+            <div className="setting-desc">超过该时间未校验的账号状态显示为未知，提示重新校验</div>
+          </div>
+          <select
+            className="setting-select"
+            value={appSettings?.status_ttl_secs ?? ""}
+            disabled={!appSettings}
+            onChange={(e) =>
+              appSettings &&
+              saveAppSettings({
+                ...appSettings,
+                status_ttl_secs: e.target.value ? Number(e.target.value) : null,
+              })
+            }
+          >
+            <option value="3600">1 小时</option>
+            <option value="21600">6 小时</option>
+            <option value="86400">24 小时</option>
+            <option value="604800">7 天</option>
+            <option value="">不过期</option>
+          </select>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">离线模式</div>
+            <div className="setting-desc">暂停所有网络请求、定时备份和启动校验，适用于按流量计费或隔离的网络</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.offline_mode ?? false}
+              disabled={!appSettings}
+              onChange={(e) => handleToggleOffline(e.target.checked)}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">合并连续切换</div>
+            <div className="setting-desc">连续快速切换账号时只执行最后一次请求，跳过排队中已被取代的切换</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.coalesce_activations ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, coalesce_activations: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">启动时校验账号</div>
+            <div className="setting-desc">启动后在后台校验所有账号的凭证状态，下次启动生效</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.validate_on_startup ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, validate_on_startup: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        {appSettings?.validate_on_startup && (
+          <div className="setting-item">
+            <div className="setting-info">
+              <div className="setting-label">校验延迟</div>
+              <div className="setting-desc">启动后等待多少秒再开始校验，让界面先加载完成</div>
+            </div>
+            <input
+              type="number"
+              min={0}
+              className="setting-select"
+              defaultValue={appSettings.startup_validation_delay_secs}
+              key={`startup-delay-${appSettings.startup_validation_delay_secs}`}
+              onBlur={(e) => {
+                const value = parseInt(e.target.value, 10);
+                if (Number.isFinite(value) && value >= 0 && value !== appSettings.startup_validation_delay_secs) {
+                  saveAppSettings({ ...appSettings, startup_validation_delay_secs: value });
+                }
+              }}
+            />
+          </div>
+        )}
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">账号数量上限</div>
+            <div className="setting-desc">超过上限后不能再添加新账号，留空表示不限制</div>
+          </div>
+          <input
+            type="number"
+            min={1}
+            className="setting-select"
+            placeholder="不限制"
+            defaultValue={appSettings?.max_accounts ?? ""}
+            key={appSettings?.max_accounts ?? "unlimited"}
+            disabled={!appSettings}
+            onBlur={(e) => {
+              if (!appSettings) return;
+              const value = parseInt(e.target.value, 10);
+              const maxAccounts = Number.isFinite(value) && value > 0 ? value : null;
+              if (maxAccounts !== appSettings.max_accounts) {
+                saveAppSettings({ ...appSettings, max_accounts: maxAccounts });
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">批量刷新并发数</div>
+            <div className="setting-desc">批量刷新 Token 时同时请求的账号数量</div>
+          </div>
+          <input
+            type="number"
+            min={1}
+            className="setting-select"
+            defaultValue={appSettings?.refresh_concurrency ?? ""}
+            key={`concurrency-${appSettings?.refresh_concurrency}`}
+            disabled={!appSettings}
+            onBlur={(e) => {
+              if (!appSettings) return;
+              const value = parseInt(e.target.value, 10);
+              if (Number.isFinite(value) && value > 0 && value !== appSettings.refresh_concurrency) {
+                saveAppSettings({ ...appSettings, refresh_concurrency: value });
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">刷新请求超时</div>
+            <div className="setting-desc">批量刷新时单个账号超过该时间（秒）未响应则跳过</div>
+          </div>
+          <input
+            type="number"
+            min={1}
+            className="setting-select"
+            defaultValue={appSettings?.refresh_timeout_secs ?? ""}
+            key={`timeout-${appSettings?.refresh_timeout_secs}`}
+            disabled={!appSettings}
+            onBlur={(e) => {
+              if (!appSettings) return;
+              const value = parseInt(e.target.value, 10);
+              if (Number.isFinite(value) && value > 0 && value !== appSettings.refresh_timeout_secs) {
+                saveAppSettings({ ...appSettings, refresh_timeout_secs: value });
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">新账号默认代理</div>
+            <div className="setting-desc">新添加的账号默认使用的 http/https 代理，添加后可单独修改；留空表示直连</div>
+          </div>
+          <input
+            type="text"
+            className="setting-select"
+            placeholder="http://127.0.0.1:7890"
+            defaultValue={appSettings?.default_proxy ?? ""}
+            key={`proxy-${appSettings?.default_proxy}`}
+            disabled={!appSettings}
+            onBlur={(e) => {
+              if (!appSettings) return;
+              const value = e.target.value.trim() || null;
+              if (value !== appSettings.default_proxy) {
+                saveAppSettings({ ...appSettings, default_proxy: value });
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">安全模式</div>
+            <div className="setting-desc">下次启动时只读加载账号数据并跳过损坏的条目，用于数据损坏时导出恢复</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.safe_mode ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, safe_mode: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">登录成功通知</div>
+            <div className="setting-desc">浏览器登录添加账号成功时发送桌面通知，连续添加时无需一直盯着窗口</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.login_notify_enabled ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, login_notify_enabled: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">登录成功页面</div>
+            <div className="setting-desc">添加成功后先在登录窗口显示成功提示，约一秒后再关闭窗口</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.login_success_page ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, login_success_page: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">登录窗口置顶</div>
+            <div className="setting-desc">浏览器登录窗口始终显示在最前面，避免被主窗口遮挡</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.login_always_on_top ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, login_always_on_top: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">保存 Cookies</div>
+            <div className="setting-desc">浏览器登录时保存会话 Cookies；关闭后新账号只保存 Token，不能用 Cookies 刷新 Token，Token 过期后需要重新登录</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.capture_cookies ?? true}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, capture_cookies: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">保存添加失败的登录</div>
+            <div className="setting-desc">浏览器登录后添加失败（如网络异常）时保留捕获的凭证，稍后可直接重试而无需重新登录。凭证用本次运行的备份密码加密保存，需先在定时备份中输入备份密码</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.save_failed_captures ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, save_failed_captures: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">无痕登录窗口</div>
+            <div className="setting-desc">登录窗口使用无痕模式，连续登录多个账号时互不影响；无法读取 Cookies 时自动改用普通模式</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.login_incognito ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, login_incognito: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">最短 Token 长度</div>
+            <div className="setting-desc">浏览器登录时短于该长度的 Token 会被忽略并提示</div>
+          </div>
+          <input
+            type="number"
+            min={1}
+            className="setting-select"
+            defaultValue={appSettings?.min_token_length ?? ""}
+            key={`min-token-${appSettings?.min_token_length}`}
+            disabled={!appSettings}
+            onBlur={(e) => {
+              if (!appSettings) return;
+              const value = parseInt(e.target.value, 10);
+              if (Number.isFinite(value) && value > 0 && value !== appSettings.min_token_length) {
+                saveAppSettings({ ...appSettings, min_token_length: value });
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">登出页面地址</div>
+            <div className="setting-desc">登录窗口经过包含这些片段的地址时丢弃之前捕获的 Token，多个用逗号分隔</div>
+          </div>
+          <input
+            type="text"
+            className="setting-select"
+            defaultValue={appSettings?.login_logout_patterns.join(", ") ?? ""}
+            key={`logout-patterns-${appSettings?.login_logout_patterns.join(",")}`}
+            disabled={!appSettings}
+            onBlur={(e) => {
+              if (!appSettings) return;
+              const patterns = e.target.value.split(",").map((p) => p.trim()).filter(Boolean);
+              if (patterns.join(",") !== appSettings.login_logout_patterns.join(",")) {
+                saveAppSettings({ ...appSettings, login_logout_patterns: patterns });
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">登录回调路径</div>
+            <div className="setting-desc">登录窗口回传 Token 使用的本地路径，默认 callback；该路径被拦截时可改为其他名称</div>
+          </div>
+          <input
+            type="text"
+            className="setting-select"
+            defaultValue={appSettings?.login_callback_path ?? ""}
+            key={`callback-path-${appSettings?.login_callback_path}`}
+            disabled={!appSettings}
+            onBlur={(e) => {
+              if (!appSettings) return;
+              const value = e.target.value.trim();
+              if (value && value !== appSettings.login_callback_path) {
+                saveAppSettings({ ...appSettings, login_callback_path: value });
+              }
+            }}
+          />
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">登录窗口调试</div>
+            <div className="setting-desc">允许为浏览器登录窗口打开开发者工具，用于排查 Token 捕获失败</div>
+          </div>
+          <label className="toggle">
+            <input
+              type="checkbox"
+              checked={appSettings?.login_devtools_enabled ?? false}
+              disabled={!appSettings}
+              onChange={(e) => appSettings && saveAppSettings({ ...appSettings, login_devtools_enabled: e.target.checked })}
+            />
+            <span className="toggle-slider"></span>
+          </label>
+        </div>
+
+        <div className="setting-item">
+          <div className="setting-info">
+            <div className="setting-label">测试登录捕获</div>
+            <div className="setting-desc">打开登录窗口，捕获到 Token 后只显示捕获结果并关闭窗口，不会添加账号</div>
+          </div>
+          <button className="setting-btn" onClick={handleTestCapture}>测试</button>
+        </div>
+
         <div className="setting-item danger">
           <div className="setting-info">
             <div className="setting-label">清空数据</div>
@@ -5,11 +5,23 @@ export interface AccountBrief {
   email: string;
   avatar_url: string;
   plan_type: string;
-  is_active: boolean;
+  is_active: boolean; // 是否启用，停用的账号不参与批量刷新
   created_at: number;
   machine_id: string | null;
   is_current: boolean; // 是否是当前 Trae IDE 正在使用的账号
   token_expired_at: string | null; // Token 过期时间
+  needs_login: boolean; // 没有任何凭证，需要重新登录
+  status: AccountStatus; // 凭证状态（超过有效期未校验时为 unknown）
+  status_manual: boolean; // 状态是否为手动设置
+  last_checked: number | null; // 最近一次校验凭证的时间
+  tags: string[]; // 账号标签
+  quarantined: boolean; // 是否已隔离
+  color: string | null; // 颜色标记（#rrggbb）
+  environment: string | null; // 所属 trae.ai 环境（如 prod/staging）
+  auto_refresh: boolean; // 是否参与 Token 自动刷新
+  expires_at: number | null; // Token 与会话 Cookie 中较早的过期时间（Unix 秒）
+  expired: boolean; // 是否已过期
+  is_primary: boolean; // 是否为主账号
 }
 
 // 完整账号信息
@@ -21,6 +33,7 @@ export interface Account {
   cookies: string;
   jwt_token: string | null;
   token_expired_at: string | null;
+  refresh_token: string | null; // 刷新凭证
   user_id: string;
   tenant_id: string;
   region: string;
@@ -29,6 +42,184 @@ export interface Account {
   updated_at: number;
   is_active: boolean;
   machine_id: string | null;
+  user_agent: string | null;
+  extra_headers: Record<string, string>; // 请求 Trae API 时附加的请求头
+  captured_headers: Record<string, string>; // 登录时捕获的请求头，刷新 Token 时复用
+  status: AccountStatus;
+  last_checked: number | null; // 最近一次校验凭证的时间
+  status_override_until: number | null; // 手动设置状态的保护截止时间
+  tags: string[];
+  cookie_diagnostic: CookieDiagnostic | null;
+  last_import_hash: string | null; // 最近一次导入时凭证的内容哈希
+  quarantined: boolean; // 是否已隔离
+  color: string | null; // 颜色标记（#rrggbb）
+  environment: string | null; // 所属 trae.ai 环境（如 prod/staging）
+  proxy: string | null; // 请求 Trae API 时使用的代理
+  auto_refresh: boolean; // 是否参与 Token 自动刷新
+  auto_tags: string[]; // 曾经自动添加过的标签
+  token_only: boolean; // 从只含 Token 的分享文件导入，不能刷新
+  health_history: HealthRecord[]; // 最近几次校验的结果（从旧到新）
+  cookie_capture: CookieCapture | null; // 最近一次浏览器登录捕获的 Cookie 分类
+  cookie_domains: Record<string, string>; // 不使用站点域名的 Cookie（名称 -> 域名）
+}
+
+// Cookies 写入时关键 Cookie 的齐全情况（只记录名称）
+export interface CookieDiagnostic {
+  present: string[];
+  missing: string[];
+  captured_at: number;
+}
+
+// 浏览器登录添加失败时保存的草稿（不含凭证）
+export interface DraftBrief {
+  id: string;
+  user_id: string;
+  has_cookies: boolean;
+  error: string;
+  created_at: number;
+}
+
+// 单个账号在数据文件中的占用（字节）
+export interface AccountSize {
+  account_id: string;
+  email: string;
+  total_bytes: number; // 整个账号记录
+  token_bytes: number; // 访问 Token 和刷新凭证
+  cookies_bytes: number; // Cookies
+}
+
+// 用量窗口中账号 Cookies 的读回校验结果（只含 Cookie 名称）
+export interface CookieVerification {
+  account_id: string;
+  applied: number; // 写入的 Cookie 数量
+  missing: string[]; // 写入后读不回来的 Cookie
+  mismatched: string[]; // 读回值与写入值不一致的 Cookie
+}
+
+// 写入用量窗口的单个 Cookie 操作（不含值）
+export interface CookieOperation {
+  name: string;
+  domain: string;
+  action: "set" | "overwrite" | "unchanged" | "clear"; // 写入 / 覆盖 / 值相同 / 随会话清除
+}
+
+// 以账号打开用量窗口前的预览（只含 Cookie 名称和域名）
+export interface ActivationPreview {
+  account_id: string;
+  operations: CookieOperation[];
+  overwrites_existing: boolean; // 是否会覆盖或清除当前会话中的 Cookie
+}
+
+// 批量校验的结果
+export interface ValidationReport {
+  valid: number;
+  expired: number;
+  failed: number; // 因网络等原因无法判断
+  skipped: number; // 最近已校验或没有凭证
+  failures: ValidationFailure[];
+  results: ValidationResult[]; // 每个已校验账号的结果
+  cancelled: boolean; // 是否被中途取消（结果只包含已完成的账号）
+}
+
+// 单个账号的校验结果
+export interface ValidationResult {
+  account_id: string;
+  email: string;
+  status: AccountStatus | null; // 无法判断时为 null
+}
+
+// 单个账号校验失败的原因
+export interface ValidationFailure {
+  account_id: string;
+  message: string;
+  network_error: NetworkErrorKind | null;
+}
+
+// 测试捕获的结果（不包含任何凭证值）
+export interface CaptureTestResult {
+  token_length: number;
+  has_refresh_token: boolean;
+  cookie_names: string[];
+  missing_critical_cookies: string[];
+}
+
+// 数据目录可写性检查结果
+export interface StorageCheck {
+  path: string;
+  writable: boolean;
+  error: string | null; // 不可写的原因
+}
+
+// 数据文件与上次保存时记录的校验和的比对结果（missing 表示旧版本数据还没有校验和）
+export type ChecksumStatus = "match" | "mismatch" | "missing";
+
+// 重写账号数据文件的结果
+export interface CompactReport {
+  before_bytes: number;
+  after_bytes: number;
+  reclaimed_bytes: number;
+}
+
+// 账号数据检查发现的问题
+export interface StoreIssue {
+  account_id: string;
+  email: string;
+  missing_cookies: string[];
+}
+
+// 账号凭证状态：unknown 表示从未校验过
+export type AccountStatus = "unknown" | "valid" | "expired";
+
+// 浏览器登录捕获的 Cookie 按来源分类（只含名称）
+export interface CookieCapture {
+  http_only: string[]; // 脚本读不到、由应用从 webview 读取的 Cookie
+  js_accessible: string[];
+  captured_at: number;
+}
+
+// 账号 Cookies 的捕获情况
+export interface CookieCaptureReport {
+  account_id: string;
+  http_only: string[];
+  js_accessible: string[];
+  captured_at: number | null; // 从未通过浏览器登录捕获时为 null
+  likely_incomplete: boolean; // 没有读到任何 HttpOnly Cookie，建议重新登录捕获
+}
+
+// 一次校验的结果
+export interface HealthRecord {
+  checked_at: number; // Unix 秒
+  status: AccountStatus;
+}
+
+// 导出浏览器会话的 Cookies 格式：Netscape cookies.txt 或 Chrome 扩展 JSON
+export type BrowserCookieFormat = "netscape" | "json";
+
+// 主窗口关闭期间未送达的重要事件（如登录成功）
+export interface PendingEvent {
+  event: string;
+  payload: any;
+  created_at: number; // Unix 秒
+}
+
+// 账号列表的排序方式：by_expiry 按最早过期时间排序，已过期的在最前
+export type AccountSort = "default" | "by_expiry";
+
+// 批量操作的账号筛选条件
+export type AccountFilter =
+  | { kind: "status"; value: AccountStatus }
+  | { kind: "email_domain"; value: string }
+  | { kind: "missing_cookies" }
+  | { kind: "tag"; value: string }
+  | { kind: "environment"; value: string };
+
+// 按状态统计的账号数量
+export interface AccountStats {
+  total: number;
+  valid: number;
+  expired: number;
+  unknown: number;
+  needs_login: number;
 }
 
 // 使用量汇总
@@ -88,7 +279,210 @@ export interface UsageEventsResponse {
   user_usage_group_by_sessions: UsageEvent[];
 }
 
+// 账号问题类型（按严重程度从高到低）
+export type ProblemKind = "expired" | "missing_cookies" | "expiry_mismatch" | "expiring_soon" | "unknown";
+
+// 原地重新登录时各项凭证是否被替换
+export interface SelectiveUpdate {
+  account_id: string;
+  email: string;
+  token_updated: boolean;
+  cookies_updated: boolean;
+}
+
+// 分组显示的账号列表中的一节，group 为 null 的是未分组的账号
+export interface AccountSection {
+  group: string | null;
+  collapsed: boolean;
+  accounts: AccountBrief[];
+}
+
+// 一键检查得到的单个账号问题
+export interface AccountProblem {
+  account_id: string;
+  email: string;
+  kind: ProblemKind;
+  detail: string;
+}
+
+// 导入冲突处理策略
+export type ImportStrategy = "skip" | "overwrite" | "keep_newest";
+
+// 单个账号的导入结果
+export interface ImportRecord {
+  user_id: string;
+  email: string;
+  action: "added" | "skipped" | "overwritten" | "failed" | "duplicate";
+  message: string | null;
+  id_remap: IdRemap | null; // 保留源 ID 导入时，源 ID 被占用而改用的 ID
+}
+
+// 导入时源 ID 与实际使用的 ID
+export interface IdRemap {
+  source_id: string;
+  new_id: string;
+}
+
+// 导入文件内指向同一用户的多个条目
+export interface ImportDuplicate {
+  user_id: string;
+  indices: number[]; // 条目在导入文件中的下标
+  kept: number; // 实际导入的条目下标（Token 最新，相同时取靠后的条目）
+  emails: string[];
+}
+
+// 其他工具导出 JSON 的字段映射（字段为候选路径列表，支持 a.b.c）
+export interface ForeignMapping {
+  accounts?: string | null; // 账号数组所在路径
+  token?: string[];
+  cookies?: string[];
+  email?: string[];
+}
+
+// 账号存储快照
+export interface SnapshotInfo {
+  id: string;
+  created_at: number;
+  account_count: number;
+  size: number;
+}
+
+// 浏览器登录捕获统计（仅本地）
+export interface LoginMetrics {
+  attempts: number;
+  captures: number;
+  failures: number;
+  cancellations: number;
+  last_capture_timing: CaptureTiming | null; // 最近一次成功捕获时各阶段的耗时
+  success_rate: number | null; // 捕获成功率，尚无记录时为 null
+}
+
+// 一次成功捕获中各阶段的耗时（毫秒），缺少某个阶段时为 null
+export interface CaptureTiming {
+  page_load_ms: number | null; // 打开窗口到页面加载完成
+  token_ms: number | null; // 页面加载完成到收到 Token
+  cookies_ms: number | null; // 收到 Token 到收集完 Cookies
+  store_ms: number | null; // 收集完 Cookies 到账号保存完成
+  total_ms: number | null; // 打开窗口到账号保存完成
+  captured_at: number; // 捕获完成的时间（Unix 秒）
+}
+
+// 代理测试结果
+export interface ProxyTestResult {
+  status: "reachable" | "auth_required" | "failed";
+  latency_ms: number | null; // 请求耗时（毫秒）
+  message: string | null;
+}
+
+// 账号无法切换时的阻塞原因（按检查顺序）
+export type ActivationBlocker =
+  | "store_read_only"
+  | "not_found"
+  | "quarantined"
+  | "no_token"
+  | "token_expired";
+
+// 账号能否切换的诊断结果，没有阻塞时 blocker 为 null
+export interface ActivationDiagnosis {
+  account_id: string;
+  blocker: ActivationBlocker | null;
+  guidance: string | null; // 给用户的处理建议
+}
+
+// 用一个 Token 登录时的预期结果
+export type LoginPrediction =
+  | { kind: "would_create" }
+  | { kind: "would_update"; existing_id: string; email: string };
+
+// 回放 GetUserToken 响应的结果（不含任何凭证值）
+export interface CaptureReplay {
+  user_id: string;
+  tenant_id: string;
+  token_length: number;
+  has_refresh_token: boolean;
+  issued_at: number; // Unix 秒
+  expires_at: number | null;
+  duplicate_of: string | null; // 与已有账号重复时为该账号的 ID
+  duplicate_email: string | null;
+}
+
+// 同一用户绑定不同邮箱的账号分组（仅供核对，不会自动合并）
+export interface LinkedAccountGroup {
+  user_id: string;
+  account_ids: string[];
+  emails: string[]; // 组内出现的不同邮箱
+}
+
+// 同名但值不同的 Cookie（只含名称，不含值）
+export interface CookieConflict {
+  name: string;
+  distinct_values: number; // 不同值的数量
+}
+
+// 域名迁移时受影响的账号（只含 Cookie 名称）
+export interface CookieRemap {
+  account_id: string;
+  email: string;
+  remapped: string[]; // 改写到新域名的 Cookie
+  kept_host_only: string[]; // 因 host-only 保留在旧域名的 Cookie
+}
+
+// 通过临时代理校验账号的结果（代理不会保存到账号上）
+export interface ProxyValidation {
+  account_id: string;
+  proxy: string;
+  status: AccountStatus | null; // 请求失败时为 null
+  latency_ms: number; // 请求耗时（毫秒）
+  message: string | null;
+  network_error: NetworkErrorKind | null;
+}
+
+// 应用设置
+export interface AppSettings {
+  control_api_enabled: boolean; // 是否启用本地控制 API
+  control_api_port: number;
+  control_api_token_hash: string; // Bearer Token 的 SHA-256 摘要，令牌本身只在生成时显示一次
+  safe_mode: boolean; // 下次启动时以安全模式加载账号数据
+  max_accounts: number | null; // 账号数量上限，null 表示不限制
+  status_ttl_secs: number | null; // 凭证状态有效期（秒），null 表示不过期
+  login_devtools_enabled: boolean; // 允许为登录窗口打开开发者工具
+  refresh_concurrency: number; // 批量刷新 Token 的最大并发数
+  refresh_timeout_secs: number; // 批量刷新时单个账号的请求超时（秒）
+  login_notify_enabled: boolean; // 浏览器登录成功时发送桌面通知
+  save_failed_captures: boolean; // 添加失败时保存捕获的凭证
+  login_incognito: boolean; // 登录窗口使用无痕模式
+  web_base_url: string; // trae.ai 站点地址
+  min_token_length: number; // 登录窗口接受的最短 Token 长度
+  default_proxy: string | null; // 新账号默认使用的代理
+  login_success_page: boolean; // 登录成功后先显示成功页面再关闭窗口
+  login_always_on_top: boolean; // 登录窗口置顶显示
+  login_logout_patterns: string[]; // 登出页面地址片段，经过时丢弃之前捕获的 Token
+  backup_enabled: boolean; // 是否定时加密备份账号数据
+  backup_dir: string | null; // 备份目录
+  backup_interval_hours: number; // 备份间隔（小时）
+  backup_retention: number; // 保留最新的备份数量
+  validate_on_startup: boolean; // 启动后在后台校验所有账号
+  startup_validation_delay_secs: number; // 启动后等待多少秒再开始校验
+  login_callback_path: string; // 登录回调服务接收 Token 的路径（单段，不含斜杠）
+  offline_mode: boolean; // 离线模式：暂停所有网络请求和定时任务
+  coalesce_activations: boolean; // 连续切换账号时只执行最后一次请求
+  capture_cookies: boolean; // 浏览器登录时是否保存 Cookies（关闭后只保存 Token）
+}
+
+// 配置项来源
+export type ConfigSource = "default" | "file" | "command_line";
+
+// 实际生效的配置项
+export interface ConfigValue {
+  value: unknown;
+  source: ConfigSource;
+}
+
+// 网络错误的具体类型
+export type NetworkErrorKind = "dns" | "tls" | "timeout" | "connection_refused" | "other" | "offline";
+
 // API 错误
 export interface ApiError {
   message: string;
+  network_error?: NetworkErrorKind; // 网络错误时的具体类型
 }